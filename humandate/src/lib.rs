@@ -1,42 +1,183 @@
-use chrono::{DateTime, Local, Utc};
+use chrono::{
+  format::{Item, StrftimeItems},
+  DateTime, Days, Local, TimeDelta, Utc,
+};
+use std::{
+  error::Error,
+  fmt::{self, Display, Write},
+  time::Duration,
+};
 
 /// Format a date as I like
 pub trait HumanDate {
   fn format_from_now(&self) -> String;
+  /// Time elapsed until now: "3 minutes ago", "yesterday", "2 weeks ago"…
+  fn format_relative(&self) -> String;
 }
 
+/// Format a duration like a clock
+pub trait HumanDuration {
+  /// `m:ss`, or `h:mm:ss` from an hour: "3:42", "1:02:15". The fractions of
+  /// seconds are dropped.
+  fn format_compact(&self) -> String;
+}
+
+impl HumanDuration for Duration {
+  fn format_compact(&self) -> String {
+    let seconds = self.as_secs();
+    let (hours, minutes, seconds) = (seconds / 3600, seconds / 60 % 60, seconds % 60);
+    if hours > 0 {
+      format!("{hours}:{minutes:02}:{seconds:02}")
+    } else {
+      format!("{minutes}:{seconds:02}")
+    }
+  }
+}
+
+/// Formats a date with a strftime pattern chosen from its age: the pattern
+/// of today, the one of the first step younger than the date, otherwise the
+/// pattern of the older dates.
+#[derive(Clone, Debug)]
+pub struct HumanDateFormatter {
+  today: String,
+  // Sorted by age
+  steps: Vec<(TimeDelta, String)>,
+  older: String,
+}
+
+impl Default for HumanDateFormatter {
+  /// The formats of `format_from_now`.
+  fn default() -> Self {
+    HumanDateFormatter::new("Today %R", "%e %b %Y")
+      .step(TimeDelta::weeks(1), "%a %R")
+      .step(TimeDelta::weeks(26), "%d %h %R")
+  }
+}
+
+impl HumanDateFormatter {
+  /// Formatter of the dates of today and of the older dates, without steps.
+  pub fn new(today: impl Into<String>, older: impl Into<String>) -> Self {
+    HumanDateFormatter {
+      today: today.into(),
+      steps: vec![],
+      older: older.into(),
+    }
+  }
+
+  /// Pattern of the dates of today.
+  pub fn today(mut self, pattern: impl Into<String>) -> Self {
+    self.today = pattern.into();
+    self
+  }
+
+  /// Pattern of the dates older than the steps.
+  pub fn older(mut self, pattern: impl Into<String>) -> Self {
+    self.older = pattern.into();
+    self
+  }
+
+  /// Remove the steps, to replace them.
+  pub fn without_steps(mut self) -> Self {
+    self.steps.clear();
+    self
+  }
+
+  /// Pattern of the dates younger than `until`, before today.
+  pub fn step(mut self, until: TimeDelta, pattern: impl Into<String>) -> Self {
+    self.steps.push((until, pattern.into()));
+    self.steps.sort_by_key(|(until, _)| *until);
+    self
+  }
+
+  /// Check the patterns: an invalid one is formatted as an empty string.
+  pub fn validate(&self) -> Result<(), InvalidPattern> {
+    let patterns = [&self.today, &self.older]
+      .into_iter()
+      .chain(self.steps.iter().map(|(_, pattern)| pattern));
+    for pattern in patterns {
+      if StrftimeItems::new(pattern).any(|item| item == Item::Error) {
+        return Err(InvalidPattern(pattern.clone()));
+      }
+    }
+    Ok(())
+  }
+
+  pub fn format(&self, date: &DateTime<Local>) -> String {
+    let now = Local::now();
+    let delta = now - date;
+    let pattern = if date.date_naive() >= now.date_naive() {
+      &self.today
+    } else {
+      self
+        .steps
+        .iter()
+        .find(|(until, _)| delta < *until)
+        .map_or(&self.older, |(_, pattern)| pattern)
+    };
+    let mut text = String::new();
+    // chrono fails on an invalid pattern
+    let _ = write!(text, "{}", date.format(pattern));
+    text
+  }
+}
+
+/// A strftime pattern chrono can't format.
+#[derive(Clone, Debug, PartialEq)]
+pub struct InvalidPattern(pub String);
+
+impl Display for InvalidPattern {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    write!(f, "invalid date pattern `{}`", self.0)
+  }
+}
+
+impl Error for InvalidPattern {}
+
 impl HumanDate for DateTime<Local> {
   fn format_from_now(&self) -> String {
+    HumanDateFormatter::default().format(self)
+  }
+
+  fn format_relative(&self) -> String {
     let now = Local::now();
     let delta = now - self;
+    let yesterday = now.date_naive().checked_sub_days(Days::new(1));
 
-    if self.date_naive() >= now.date_naive() {
-      self.format("Today %R").to_string()
-    } else if delta.num_weeks() < 1 {
-      self.format("%a %R").to_string()
-    } else if delta.num_weeks() < 26 {
-      self.format("%d %h %R").to_string()
+    if delta.num_minutes() < 1 {
+      "just now".to_string()
+    } else if delta.num_hours() < 1 {
+      ago(delta.num_minutes(), "minute")
+    } else if self.date_naive() >= now.date_naive() {
+      ago(delta.num_hours(), "hour")
+    } else if Some(self.date_naive()) == yesterday {
+      "yesterday".to_string()
+    } else if delta.num_days() < 7 {
+      ago(delta.num_days(), "day")
+    } else if delta.num_days() < 30 {
+      ago(delta.num_weeks(), "week")
+    } else if delta.num_days() < 365 {
+      ago(delta.num_days() / 30, "month")
     } else {
-      self.format("%e %b %Y").to_string()
+      ago(delta.num_days() / 365, "year")
     }
   }
 }
 
 impl HumanDate for DateTime<Utc> {
   fn format_from_now(&self) -> String {
-    let now = Local::now();
-    let date = self.with_timezone(&Local);
-    let delta = now - date;
+    self.with_timezone(&Local).format_from_now()
+  }
 
-    if date.date_naive() >= now.date_naive() {
-      date.format("Today %R").to_string()
-    } else if delta.num_weeks() < 1 {
-      date.format("%a %R").to_string()
-    } else if delta.num_weeks() < 26 {
-      date.format("%d %h %R").to_string()
-    } else {
-      date.format("%e %b %Y").to_string()
-    }
+  fn format_relative(&self) -> String {
+    self.with_timezone(&Local).format_relative()
+  }
+}
+
+fn ago(count: i64, unit: &str) -> String {
+  if count == 1 {
+    format!("1 {unit} ago")
+  } else {
+    format!("{count} {unit}s ago")
   }
 }
 
@@ -80,4 +221,106 @@ mod tests {
 
     assert_eq!(date.format_from_now(), date.format("%e %b %Y").to_string());
   }
+
+  #[test]
+  fn custom_steps() {
+    let formatter = HumanDateFormatter::new("%R", "%Y")
+      .step(TimeDelta::days(60), "%d/%m")
+      .step(TimeDelta::days(2), "%a");
+    let date = Local::now() - TimeDelta::days(30);
+
+    assert_eq!(formatter.format(&date), date.format("%d/%m").to_string());
+  }
+
+  #[test]
+  fn custom_older() {
+    let formatter = HumanDateFormatter::new("%R", "%Y").step(TimeDelta::days(60), "%d/%m");
+    let date = Local::now() - TimeDelta::days(90);
+
+    assert_eq!(formatter.format(&date), date.format("%Y").to_string());
+  }
+
+  #[test]
+  fn default_overridden() {
+    let formatter = HumanDateFormatter::default().today("%H:%M");
+    let date = Local::now() - TimeDelta::weeks(2);
+
+    assert_eq!(
+      formatter.format(&Local::now()),
+      Local::now().format("%H:%M").to_string()
+    );
+    assert_eq!(formatter.format(&date), date.format("%d %h %R").to_string());
+  }
+
+  #[test]
+  fn invalid_pattern() {
+    let formatter = HumanDateFormatter::new("%R", "%Q");
+
+    assert_eq!(formatter.validate(), Err(InvalidPattern("%Q".to_string())));
+    assert_eq!(formatter.format(&(Local::now() - TimeDelta::weeks(60))), "");
+  }
+
+  #[test]
+  fn compact_seconds() {
+    assert_eq!(Duration::from_millis(5_900).format_compact(), "0:05");
+  }
+
+  #[test]
+  fn compact_minutes() {
+    assert_eq!(Duration::from_secs(222).format_compact(), "3:42");
+  }
+
+  #[test]
+  fn compact_hours() {
+    assert_eq!(Duration::from_secs(3735).format_compact(), "1:02:15");
+  }
+
+  #[test]
+  fn relative_just_now() {
+    let date = Local::now() - TimeDelta::seconds(20);
+
+    assert_eq!(date.format_relative(), "just now");
+  }
+
+  #[test]
+  fn relative_3_minutes() {
+    let date = Local::now() - TimeDelta::minutes(3);
+
+    assert_eq!(date.format_relative(), "3 minutes ago");
+  }
+
+  #[test]
+  fn relative_yesterday() {
+    let date = Local::now() - TimeDelta::days(1);
+
+    assert_eq!(date.format_relative(), "yesterday");
+  }
+
+  #[test]
+  fn relative_3_days() {
+    let date = Local::now() - TimeDelta::days(3);
+
+    assert_eq!(date.format_relative(), "3 days ago");
+  }
+
+  #[test]
+  fn relative_2_weeks() {
+    let date = Local::now() - TimeDelta::weeks(2);
+
+    assert_eq!(date.format_relative(), "2 weeks ago");
+  }
+
+  #[test]
+  fn relative_1_month() {
+    let date = Local::now() - TimeDelta::days(45);
+
+    assert_eq!(date.format_relative(), "1 month ago");
+  }
+
+  #[test]
+  fn relative_2_years() {
+    let date = Utc::now() - TimeDelta::weeks(110);
+
+    assert_eq!(date.format_relative(), "2 years ago");
+  }
 }
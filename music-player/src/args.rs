@@ -1,4 +1,5 @@
-use clap::{Parser, Subcommand};
+use crate::player_state::{Repeat, Shuffle};
+use clap::{Parser, Subcommand, ValueEnum};
 use clap_complete::Shell;
 
 #[derive(Subcommand)]
@@ -6,6 +7,157 @@ pub(crate) enum Commands {
   /// Config related commands
   #[command(subcommand)]
   Config(Config),
+  /// Search the library and print the tracks found
+  Search(Search),
+  /// Print the statistics of the library
+  Stats(Stats),
+  /// Time the load, the save and the searches of the library
+  Bench(Bench),
+  /// Export or rotate the history of the plays
+  #[command(subcommand)]
+  History(History),
+  /// Control the running player
+  #[command(subcommand)]
+  Ctl(Ctl),
+  /// Maintain the tracks of the library
+  #[command(subcommand)]
+  Library(Library),
+  /// Manage the podcast feeds
+  #[cfg(feature = "podcast")]
+  #[command(subcommand)]
+  Podcast(Podcast),
+}
+
+#[cfg(feature = "podcast")]
+#[derive(Subcommand, Debug)]
+pub(crate) enum Podcast {
+  /// Add the feed and its episodes to the library
+  Add { url: url::Url },
+  /// List the feeds of the library
+  List,
+  /// Add the new episodes of the feeds
+  Refresh {
+    /// Only the feeds whose title or url contains this text
+    feed: Option<String>,
+    /// Don't wait for an unmetered connection
+    #[arg(long)]
+    allow_metered: bool,
+  },
+  /// Download the episodes whose title or feed contains the text
+  Download {
+    episode: String,
+    /// Only the most recent episodes
+    #[arg(long)]
+    latest: Option<usize>,
+    /// Don't wait for an unmetered connection
+    #[arg(long)]
+    allow_metered: bool,
+  },
+}
+
+#[derive(Subcommand, Debug)]
+pub(crate) enum History {
+  /// Print the plays of the history
+  Export {
+    /// Output format
+    #[arg(long, value_enum, default_value_t)]
+    format: ExportFormat,
+    /// Only the plays of this period, like `30d` or `1week`
+    #[arg(long)]
+    since: Option<humantime::Duration>,
+    /// Write to this file instead of the standard output
+    #[arg(long, short)]
+    output: Option<std::path::PathBuf>,
+  },
+  /// Move the history aside, the next plays start a new one
+  Rotate,
+  /// Print the path of the history file
+  Path,
+}
+
+#[derive(Subcommand, Debug)]
+pub(crate) enum Library {
+  /// Compute the ReplayGain of the tracks missing it
+  ScanGain {
+    /// Scan again the tracks which already have a gain
+    #[arg(long)]
+    force: bool,
+    /// Also write the gains in the tags of the mp3 files
+    #[arg(long)]
+    write_tags: bool,
+  },
+  /// Detect the tempo of the tracks missing it
+  ScanBpm {
+    /// Detect again the tracks which already have a tempo
+    #[arg(long)]
+    force: bool,
+  },
+  /// Fill the first-seen and modification dates missing from the files
+  BackfillDates,
+  /// Add the playlists of MPD to the static playlists of Rhythmbox
+  ImportMpd {
+    /// Directory of the playlists, `playlist_directory` of mpd.conf by default
+    #[arg(long)]
+    playlists: Option<std::path::PathBuf>,
+    /// Directory of the music, `music_directory` of mpd.conf by default
+    #[arg(long)]
+    music_directory: Option<std::path::PathBuf>,
+    /// Database of MPD, `db_file` of mpd.conf by default
+    #[arg(long)]
+    database: Option<std::path::PathBuf>,
+  },
+  /// Update the tracks with the metadata of beets, and add the missing ones
+  #[cfg(feature = "beets")]
+  SyncBeets {
+    /// Database of beets, `~/.config/beets/library.db` by default
+    #[arg(long)]
+    database: Option<std::path::PathBuf>,
+  },
+}
+
+#[derive(Clone, Copy, Debug, Default, ValueEnum)]
+pub(crate) enum ExportFormat {
+  #[default]
+  Csv,
+  Jsonl,
+}
+
+#[derive(Subcommand, Debug)]
+pub(crate) enum Ctl {
+  /// Print the track being played, for the status bars
+  NowPlaying(NowPlaying),
+  /// Print the queued tracks, in JSON
+  Queue,
+  /// Play or pause the track
+  PlayPause,
+  /// Pause the track
+  Pause,
+  /// Stop the playback
+  Stop,
+  /// Play the next track
+  Next,
+  /// Play the previous track
+  Previous,
+  /// Go to a position of the track
+  Seek {
+    /// In seconds
+    position: u64,
+  },
+  /// Enqueue the tracks listed by another MPRIS player, to hand off a session
+  ImportQueue {
+    /// Bus name of the player, like `firefox` or `org.mpris.MediaPlayer2.vlc`
+    player: String,
+  },
+}
+
+#[derive(Parser, Debug)]
+pub(crate) struct NowPlaying {
+  /// Fields: {title}, {artist}, {album}, {status}, {elapsed}, {duration}
+  #[arg(long, default_value = "{artist} – {title} [{elapsed}/{duration}]")]
+  pub(crate) format: String,
+  /// Print a line on every change until interrupted
+  #[arg(long)]
+  pub(crate) follow: bool,
 }
 
 #[derive(Subcommand)]
@@ -16,6 +168,17 @@ pub(crate) enum Config {
   Show,
   /// Show ignored entries in DB
   ShowIgnoredEntries,
+  /// Print a setting of `settings.toml`
+  Get {
+    /// Dotted key, like `theme.preset`
+    key: String,
+  },
+  /// Change a setting of `settings.toml`
+  Set {
+    /// Dotted key, like `theme.preset`
+    key: String,
+    value: String,
+  },
 }
 
 #[derive(Parser, Debug)]
@@ -24,26 +187,101 @@ pub(crate) struct ConfigClean {
   /// Some obsolete podcast entries may remains in the playlist after an update.
   #[arg(long, required_unless_present_any(["main","ignored_entries"]))]
   pub(crate) playlist: bool,
-  /// Delete the main config file. It contains the current track and the current play position.
+  /// Delete the main config file and the ui session. The main config file contains the current track and the current play position.
   #[arg(long, required_unless_present_any(["playlist","ignored_entries"]))]
   pub(crate) main: bool,
   #[arg(long, required_unless_present_any(["main","playlist"]))]
   pub(crate) ignored_entries: bool,
 }
 
+#[derive(Parser, Debug)]
+pub(crate) struct Search {
+  /// Searched in the titles, the artists and the albums
+  #[arg(required = true)]
+  pub(crate) query: Vec<String>,
+  /// Search the podcasts instead of the music
+  #[arg(long)]
+  pub(crate) podcasts: bool,
+  /// Output format
+  #[arg(long, value_enum, default_value_t)]
+  pub(crate) format: OutputFormat,
+  /// Maximum number of tracks printed
+  #[arg(long, short)]
+  pub(crate) limit: Option<usize>,
+}
+
+#[derive(Parser, Debug)]
+pub(crate) struct Stats {
+  /// Only the tracks played during this period, like `30d` or `1week`
+  #[arg(long)]
+  pub(crate) since: Option<humantime::Duration>,
+  /// Output format
+  #[arg(long, value_enum, default_value_t)]
+  pub(crate) format: OutputFormat,
+  /// Number of top artists and albums
+  #[arg(long, default_value_t = 10)]
+  pub(crate) top: usize,
+}
+
+#[derive(Parser, Debug)]
+pub(crate) struct Bench {
+  /// Number of runs of each operation
+  #[arg(long, default_value_t = 5)]
+  pub(crate) runs: usize,
+  /// Searched text, the artist of the first track when none
+  #[arg(long)]
+  pub(crate) query: Option<String>,
+}
+
+#[derive(Clone, Copy, Debug, Default, ValueEnum)]
+pub(crate) enum OutputFormat {
+  #[default]
+  Table,
+  Json,
+}
+
 #[derive(Parser)]
 #[command(version, about, long_about = None)]
 pub(crate) struct App {
-  /// File to play
-  pub(crate) file: Option<String>,
+  /// Files or directories to play
+  pub(crate) files: Vec<String>,
+
+  /// Read the files or the urls to play from stdin, one per line
+  #[arg(long)]
+  pub(crate) stdin: bool,
+
+  /// Enqueue the files in the running player instead of playing them
+  #[arg(long, short)]
+  pub(crate) enqueue: bool,
+
+  /// Shuffle mode, instead of the saved one
+  #[arg(long, value_enum)]
+  pub(crate) shuffle: Option<Shuffle>,
+
+  /// Repeat mode, instead of the saved one
+  #[arg(long, value_enum)]
+  pub(crate) repeat: Option<Repeat>,
+
+  /// Start with the playback paused
+  #[arg(long)]
+  pub(crate) start_paused: bool,
+
+  /// Run without the tui, controlled by MPRIS and the `ctl` commands
+  #[arg(long)]
+  pub(crate) daemon: bool,
 
   /// Profile name
   #[arg(long, short)]
   profile: Option<String>,
 
-  /// Path to the playlist
-  #[arg(long)]
-  playlist_path: Option<String>,
+  /// Path to the rhythmbox db. Repeated, the dbs are merged for the session
+  #[arg(long, visible_alias = "db")]
+  playlist_path: Vec<String>,
+
+  /// Level of the logs, like `debug` or `music_player=trace`, instead of
+  /// `RUST_LOG`
+  #[arg(long, global = true)]
+  pub(crate) log_level: Option<String>,
 
   /// Generate shell completions
   #[arg(long, value_enum)]
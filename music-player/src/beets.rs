@@ -0,0 +1,285 @@
+use crate::{
+  rhythmdb::{check_writable, Entry, Rhythmdb, SongEntry},
+  settings::Settings,
+  ui::{Order, OrderDir},
+};
+use chrono::{Datelike, NaiveDate};
+use directories::BaseDirs;
+use miette::{miette, IntoDiagnostic, Result};
+use rusqlite::{Connection, OpenFlags, Row};
+use std::{
+  collections::HashMap,
+  ffi::OsStr,
+  fs,
+  os::unix::ffi::OsStrExt,
+  path::{Path, PathBuf},
+  sync::Arc,
+};
+use tracing::instrument;
+use url::Url;
+
+const QUERY: &str = "SELECT path, title, artist, album, albumartist, genre, composer, year, \
+                     month, day, track, tracktotal, disc, disctotal, length, bitrate, bpm, \
+                     format, comments, mb_trackid, mb_albumid, mb_artistid, mb_albumartistid, \
+                     rg_track_gain, rg_track_peak, rg_album_gain, rg_album_peak, added, mtime \
+                     FROM items";
+
+/// Track of the beets library. Beets writes empty texts and zeros for the
+/// unknown values.
+#[derive(Debug)]
+struct Item {
+  path: PathBuf,
+  title: String,
+  artist: String,
+  album: String,
+  album_artist: String,
+  genre: String,
+  composer: String,
+  year: i32,
+  month: u32,
+  day: u32,
+  track: u64,
+  track_total: u64,
+  disc: u64,
+  disc_total: u64,
+  // In seconds
+  length: f64,
+  bitrate: u64,
+  bpm: u64,
+  format: String,
+  comments: String,
+  mb_trackid: String,
+  mb_albumid: String,
+  mb_artistid: String,
+  mb_albumartistid: String,
+  track_gain: Option<f64>,
+  track_peak: Option<f64>,
+  album_gain: Option<f64>,
+  album_peak: Option<f64>,
+  added: f64,
+  mtime: f64,
+}
+
+/// Update the songs of the Rhythmbox db with the metadata of beets, found by
+/// path or else by MusicBrainz track id, and add the tracks it is missing.
+/// The values unknown to beets are left as they are.
+#[instrument(skip(config))]
+pub(crate) fn sync_beets(config: &Settings, database: Option<&Path>) -> Result<()> {
+  check_writable()?;
+  let database = match database {
+    Some(database) => database.to_path_buf(),
+    None => BaseDirs::new()
+      .ok_or(miette!("Can't get the home directory"))?
+      .config_dir()
+      .join("beets/library.db"),
+  };
+  let items = read_items(&database)?;
+  let mut db = Rhythmdb::load(config)?;
+
+  let songs: Vec<SongEntry> = db
+    .filter_by_song("", Order::Default, OrderDir::Desc)
+    .iter()
+    .filter_map(|entry| match entry.as_ref() {
+      Entry::Song(song) => Some(song.clone()),
+      _ => None,
+    })
+    .collect();
+  let by_path: HashMap<PathBuf, &SongEntry> = songs
+    .iter()
+    .filter_map(|song| Some((song.location.to_file_path().ok()?, song)))
+    .collect();
+  let by_mbid: HashMap<&str, &SongEntry> = songs
+    .iter()
+    .filter_map(|song| {
+      Some((
+        song.mb_trackid.as_deref().filter(|id| !id.is_empty())?,
+        song,
+      ))
+    })
+    .collect();
+
+  let (mut updated, mut added, mut failed) = (0, 0, 0);
+  for item in items {
+    let Ok(location) = Url::from_file_path(&item.path) else {
+      eprintln!("{}: not an absolute path", item.path.display());
+      failed += 1;
+      continue;
+    };
+    let found = by_path
+      .get(&item.path)
+      .or_else(|| by_mbid.get(item.mb_trackid.as_str()));
+    match found {
+      Some(song) => {
+        let mut song = (*song).clone();
+        // Moved since Rhythmbox saw it
+        if song.location != location && item.path.exists() {
+          song.location = location;
+        }
+        item.apply(&mut song);
+        db.update_entry(Arc::new(Entry::Song(song)));
+        updated += 1;
+      }
+      None if item.path.exists() => {
+        let mut song = SongEntry::default();
+        item.apply(&mut song);
+        song.location = location;
+        song.first_seen = item.added as u64;
+        song.file_size = fs::metadata(&item.path)
+          .map(|metadata| metadata.len().to_string())
+          .unwrap_or_default();
+        db.add_entry(Entry::Song(song));
+        added += 1;
+      }
+      None => {
+        eprintln!("{}: not found", item.path.display());
+        failed += 1;
+      }
+    }
+  }
+  db.save(config)?;
+  println!("{updated} tracks updated, {added} added, {failed} failed");
+  Ok(())
+}
+
+/// Read the tracks of the beets library, without locking it.
+#[instrument]
+fn read_items(database: &Path) -> Result<Vec<Item>> {
+  let connection = Connection::open_with_flags(database, OpenFlags::SQLITE_OPEN_READ_ONLY)
+    .map_err(|e| miette!("Can't open `{}`: {e}", database.display()))?;
+  let mut statement = connection.prepare(QUERY).into_diagnostic()?;
+  let items = statement
+    .query_map([], Item::from_row)
+    .into_diagnostic()?
+    .collect::<rusqlite::Result<Vec<Item>>>()
+    .into_diagnostic()?;
+  Ok(items)
+}
+
+impl Item {
+  fn from_row(row: &Row<'_>) -> rusqlite::Result<Item> {
+    // The paths are stored as bytes, in the encoding of the file system
+    let path: Vec<u8> = row.get("path")?;
+    let text = |name: &str| -> rusqlite::Result<String> {
+      Ok(row.get::<_, Option<String>>(name)?.unwrap_or_default())
+    };
+    let number = |name: &str| -> rusqlite::Result<u64> {
+      Ok(row.get::<_, Option<i64>>(name)?.unwrap_or_default().max(0) as u64)
+    };
+    let real = |name: &str| -> rusqlite::Result<f64> {
+      Ok(row.get::<_, Option<f64>>(name)?.unwrap_or_default())
+    };
+    Ok(Item {
+      path: PathBuf::from(OsStr::from_bytes(&path)),
+      title: text("title")?,
+      artist: text("artist")?,
+      album: text("album")?,
+      album_artist: text("albumartist")?,
+      genre: text("genre")?,
+      composer: text("composer")?,
+      year: number("year")? as i32,
+      month: number("month")? as u32,
+      day: number("day")? as u32,
+      track: number("track")?,
+      track_total: number("tracktotal")?,
+      disc: number("disc")?,
+      disc_total: number("disctotal")?,
+      length: real("length")?,
+      bitrate: number("bitrate")?,
+      bpm: number("bpm")?,
+      format: text("format")?,
+      comments: text("comments")?,
+      mb_trackid: text("mb_trackid")?,
+      mb_albumid: text("mb_albumid")?,
+      mb_artistid: text("mb_artistid")?,
+      mb_albumartistid: text("mb_albumartistid")?,
+      track_gain: row.get("rg_track_gain")?,
+      track_peak: row.get("rg_track_peak")?,
+      album_gain: row.get("rg_album_gain")?,
+      album_peak: row.get("rg_album_peak")?,
+      added: real("added")?,
+      mtime: real("mtime")?,
+    })
+  }
+
+  /// Copy the values known to beets in the song.
+  fn apply(&self, song: &mut SongEntry) {
+    let texts = [
+      (&mut song.title, &self.title),
+      (&mut song.artist, &self.artist),
+      (&mut song.album, &self.album),
+      (&mut song.genre, &self.genre),
+      (&mut song.composer, &self.composer),
+    ];
+    for (field, value) in texts {
+      if !value.is_empty() {
+        value.clone_into(field);
+      }
+    }
+    let optional_texts = [
+      (&mut song.album_artist, &self.album_artist),
+      (&mut song.comment, &self.comments),
+      (&mut song.mb_trackid, &self.mb_trackid),
+      (&mut song.mb_albumid, &self.mb_albumid),
+      (&mut song.mb_artistid, &self.mb_artistid),
+      (&mut song.mb_albumartistid, &self.mb_albumartistid),
+    ];
+    for (field, value) in optional_texts {
+      if !value.is_empty() {
+        *field = Some(value.clone());
+      }
+    }
+    let numbers = [
+      (&mut song.track_number, self.track),
+      (&mut song.track_total, self.track_total),
+      (&mut song.disc_number, self.disc),
+      (&mut song.disc_total, self.disc_total),
+      (&mut song.bitrate, self.bitrate / 1000),
+      (&mut song.duration, self.length.round() as u64),
+    ];
+    for (field, value) in numbers {
+      if value > 0 {
+        *field = Some(value);
+      }
+    }
+    let gains = [
+      (&mut song.track_gain, self.track_gain),
+      (&mut song.track_peak, self.track_peak),
+      (&mut song.album_gain, self.album_gain),
+      (&mut song.album_peak, self.album_peak),
+    ];
+    for (field, value) in gains {
+      if value.is_some() {
+        *field = value;
+      }
+    }
+
+    if self.bpm > 0 {
+      song.beats_per_minute = Some(self.bpm.to_string());
+    }
+    // Rhythmbox stores the release date as a julian day
+    if let Some(date) = NaiveDate::from_ymd_opt(self.year, self.month.max(1), self.day.max(1)) {
+      if self.year > 0 {
+        song.date = date.num_days_from_ce() as u64;
+      }
+    }
+    if self.mtime > 0.0 {
+      song.mtime = self.mtime as u64;
+    }
+    if let Some(media_type) = media_type(&self.format) {
+      song.media_type = media_type.to_string();
+    }
+  }
+}
+
+/// Media type of Rhythmbox for the format names of beets.
+fn media_type(format: &str) -> Option<&'static str> {
+  match format {
+    "MP3" => Some("audio/mpeg"),
+    "FLAC" => Some("audio/x-flac"),
+    "OGG" => Some("audio/x-vorbis"),
+    "Opus" => Some("audio/x-opus"),
+    "AAC" | "ALAC" => Some("audio/x-aac"),
+    "WAVE" => Some("audio/x-wav"),
+    _ => None,
+  }
+}
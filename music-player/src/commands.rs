@@ -0,0 +1,438 @@
+use crate::{
+  args::{Bench, Ctl, NowPlaying, OutputFormat, Search, Stats},
+  instance::{self, Request},
+  playlists::Playlist,
+  rhythmdb::{Entry, Rating, Rhythmdb},
+  settings::Settings,
+  ui::{Order, OrderDir},
+};
+use humandate::HumanDuration;
+use miette::{miette, IntoDiagnostic, Result};
+use mpris_server::zbus::{
+  zvariant::{OwnedObjectPath, OwnedValue},
+  Connection, Proxy,
+};
+use serde::Serialize;
+use std::{
+  collections::HashMap,
+  env, fs, io,
+  time::{Duration, Instant},
+};
+use tokio::time::sleep;
+use tracing::instrument;
+use url::Url;
+
+// Longest text printed in a column of the table
+const MAX_COLUMN_WIDTH: usize = 40;
+
+/// Track printed by the commands.
+#[derive(Debug, Serialize)]
+pub(crate) struct Track<'a> {
+  title: &'a str,
+  artist: &'a str,
+  album: &'a str,
+  duration: u64,
+  rating: Rating,
+  location: Url,
+}
+
+impl<'a> From<&'a Entry> for Track<'a> {
+  fn from(entry: &'a Entry) -> Self {
+    Track {
+      title: entry.get_title(),
+      artist: entry.get_artist(),
+      album: entry.get_album(),
+      duration: entry.get_duration(),
+      rating: entry.get_rating(),
+      location: entry.get_location(),
+    }
+  }
+}
+
+/// Print the tracks found with the search of the tui.
+#[instrument(skip(config))]
+pub(crate) fn search(config: &Settings, args: &Search) -> Result<()> {
+  let db = Rhythmdb::load(config)?;
+  let query = args.query.join(" ");
+  let entries = if args.podcasts {
+    db.filter_by_podcast(&query, Order::Default, OrderDir::Desc)
+  } else {
+    db.filter_by_song(&query, Order::Default, OrderDir::Desc)
+  };
+  let tracks: Vec<Track> = entries
+    .iter()
+    .take(args.limit.unwrap_or(usize::MAX))
+    .map(|entry| Track::from(entry.as_ref()))
+    .collect();
+
+  match args.format {
+    OutputFormat::Json => {
+      println!(
+        "{}",
+        serde_json::to_string_pretty(&tracks).into_diagnostic()?
+      );
+    }
+    OutputFormat::Table => print_table(&tracks),
+  }
+  Ok(())
+}
+
+/// Statistics of the library.
+#[derive(Debug, Serialize)]
+struct LibraryStats<'a> {
+  tracks: usize,
+  played_tracks: usize,
+  plays: u64,
+  hours_listened: f64,
+  top_artists: Vec<PlayCount<'a>>,
+  top_albums: Vec<PlayCount<'a>>,
+}
+
+#[derive(Debug, Serialize)]
+struct PlayCount<'a> {
+  name: &'a str,
+  plays: u64,
+}
+
+/// Print the totals of the library computed from the play counts. With
+/// `--since`, only the tracks last played during the period are counted.
+#[instrument(skip(config))]
+pub(crate) fn stats(config: &Settings, args: &Stats) -> Result<()> {
+  let db = Rhythmdb::load(config)?;
+  let entries = db.filter_by_song("", Order::Default, OrderDir::Desc);
+  let since = args
+    .since
+    .map(|since| (chrono::Local::now().timestamp() as u64).saturating_sub(since.as_secs()));
+  let played: Vec<&Entry> = entries
+    .iter()
+    .map(|entry| entry.as_ref())
+    .filter(|entry| entry.get_play_count() > 0)
+    .filter(|entry| match since {
+      Some(since) => entry.get_last_played().is_some_and(|last| last >= since),
+      None => true,
+    })
+    .collect();
+
+  let mut artists: HashMap<&str, u64> = HashMap::new();
+  let mut albums: HashMap<&str, u64> = HashMap::new();
+  for entry in &played {
+    *artists.entry(entry.get_artist()).or_default() += entry.get_play_count();
+    *albums.entry(entry.get_album()).or_default() += entry.get_play_count();
+  }
+  let seconds: u64 = played
+    .iter()
+    .map(|entry| entry.get_play_count() * entry.get_duration())
+    .sum();
+  let stats = LibraryStats {
+    tracks: entries.len(),
+    played_tracks: played.len(),
+    plays: played.iter().map(|entry| entry.get_play_count()).sum(),
+    hours_listened: (seconds as f64 / 360.0).round() / 10.0,
+    top_artists: top(artists, args.top),
+    top_albums: top(albums, args.top),
+  };
+
+  match args.format {
+    OutputFormat::Json => {
+      println!(
+        "{}",
+        serde_json::to_string_pretty(&stats).into_diagnostic()?
+      );
+    }
+    OutputFormat::Table => {
+      println!("Tracks          {}", stats.tracks);
+      println!("Played tracks   {}", stats.played_tracks);
+      println!("Plays           {}", stats.plays);
+      println!("Hours listened  {}", stats.hours_listened);
+      for (title, counts) in [
+        ("Top artists", &stats.top_artists),
+        ("Top albums", &stats.top_albums),
+      ] {
+        println!("\n{title}");
+        for count in counts {
+          println!("{:>6}  {}", count.plays, count.name);
+        }
+      }
+    }
+  }
+  Ok(())
+}
+
+/// The most played names, the unknown ones left out.
+fn top<'a>(counts: HashMap<&'a str, u64>, size: usize) -> Vec<PlayCount<'a>> {
+  let mut counts: Vec<PlayCount> = counts
+    .into_iter()
+    .filter(|(name, _)| !name.is_empty())
+    .map(|(name, plays)| PlayCount { name, plays })
+    .collect();
+  counts.sort_by(|a, b| b.plays.cmp(&a.plays).then(a.name.cmp(b.name)));
+  counts.truncate(size);
+  counts
+}
+
+/// Time the db operations on the library of the user, to compare the
+/// performances between two versions. The library is saved in a temporary file.
+#[instrument(skip(config))]
+pub(crate) fn bench(config: &Settings, args: &Bench) -> Result<()> {
+  let runs = args.runs.max(1);
+  let db = Rhythmdb::load(config)?;
+  let songs = db.filter_by_song("", Order::Default, OrderDir::Desc);
+  let query = match &args.query {
+    Some(query) => query.clone(),
+    None => songs
+      .first()
+      .map(|song| song.get_artist().to_string())
+      .unwrap_or_default(),
+  };
+  println!("{} songs, {runs} runs, query `{query}`\n", songs.len());
+  let path = env::temp_dir().join("music-player-bench.xml");
+  fs::File::create(&path).into_diagnostic()?;
+
+  println!("{:24}{:>10}{:>10}{:>10}", "Operation", "Min", "Mean", "Max");
+  let operations: [(&str, &dyn Fn() -> Result<()>); 7] = [
+    ("Load", &|| Rhythmdb::load(config).map(drop)),
+    // The xml alone, without the writing of the file
+    ("Serialize", &|| db.write(io::sink())),
+    ("Save", &|| db.save_to(&path)),
+    ("List songs", &|| {
+      db.filter_by_song("", Order::Default, OrderDir::Desc);
+      Ok(())
+    }),
+    ("List songs by title", &|| {
+      db.filter_by_song("", Order::Title, OrderDir::Asc);
+      Ok(())
+    }),
+    ("Search songs", &|| {
+      db.filter_by_song(&query, Order::Default, OrderDir::Desc);
+      Ok(())
+    }),
+    ("List podcasts", &|| {
+      db.filter_by_podcast("", Order::Default, OrderDir::Desc);
+      Ok(())
+    }),
+  ];
+  let result = operations.iter().try_for_each(|(name, operation)| {
+    let mut times = Vec::with_capacity(runs);
+    for _ in 0..runs {
+      let start = Instant::now();
+      operation()?;
+      times.push(start.elapsed());
+    }
+    let mean = times.iter().sum::<Duration>() / runs as u32;
+    let (min, max) = (times.iter().min(), times.iter().max());
+    println!(
+      "{name:24}{:>10}{:>10}{:>10}",
+      milliseconds(min.copied().unwrap_or_default()),
+      milliseconds(mean),
+      milliseconds(max.copied().unwrap_or_default()),
+    );
+    Ok(())
+  });
+  let _ = fs::remove_file(&path);
+  result
+}
+
+fn milliseconds(duration: Duration) -> String {
+  format!("{:.1}ms", duration.as_secs_f64() * 1000.0)
+}
+
+/// Print the track played by the running instance. In follow mode, poll the
+/// instance and print a line when it changes.
+/// Run a command in the running player, through its socket.
+#[instrument(skip(config))]
+pub(crate) async fn ctl(config: &Settings, command: &Ctl) -> Result<()> {
+  let request = match command {
+    Ctl::NowPlaying(args) => return now_playing(args).await,
+    Ctl::ImportQueue { player } => return import_queue(config, player).await,
+    Ctl::Queue => {
+      let tracks = instance::call(Request::Queue).await?;
+      println!(
+        "{}",
+        serde_json::to_string_pretty(&tracks).into_diagnostic()?
+      );
+      return Ok(());
+    }
+    Ctl::PlayPause => Request::PlayPause,
+    Ctl::Pause => Request::Pause,
+    Ctl::Stop => Request::Stop,
+    Ctl::Next => Request::Next,
+    Ctl::Previous => Request::Previous,
+    Ctl::Seek { position } => Request::Seek(*position),
+  };
+  instance::call(request).await?;
+  Ok(())
+}
+
+#[instrument]
+async fn now_playing(args: &NowPlaying) -> Result<()> {
+  if !args.follow {
+    let now_playing = instance::now_playing()
+      .await?
+      .ok_or_else(|| miette!("Music player is not running"))?;
+    println!("{}", format_now_playing(&args.format, &now_playing));
+    return Ok(());
+  }
+
+  let mut last_line = None;
+  loop {
+    // An empty line when the player is not running
+    let line = match instance::now_playing().await {
+      Ok(Some(now_playing)) => format_now_playing(&args.format, &now_playing),
+      _ => String::new(),
+    };
+    if last_line.as_ref() != Some(&line) {
+      println!("{line}");
+      last_line = Some(line);
+    }
+    sleep(Duration::from_secs(1)).await;
+  }
+}
+
+/// Enqueue the tracks of the TrackList of another MPRIS player. Only the
+/// tracks of the library can be enqueued: the other ones are counted as
+/// skipped.
+#[instrument(skip(config))]
+async fn import_queue(config: &Settings, player: &str) -> Result<()> {
+  let locations = tracklist_locations(player).await?;
+  let db = Rhythmdb::load(config)?;
+  let (known, unknown): (Vec<Url>, Vec<Url>) = locations
+    .into_iter()
+    .partition(|location| db.find_url(location).is_some());
+  for location in &unknown {
+    eprintln!("{location}: not in the library");
+  }
+
+  let requests = known.iter().cloned().map(Request::Enqueue).collect();
+  if !instance::hand_off(requests).await? {
+    // Enqueued in the saved queue, played at the next start
+    let mut queue = Playlist::load()?;
+    for location in &known {
+      if !queue.enqueue(location.clone(), config.playback.skip_queued) {
+        eprintln!("{location}: already queued");
+      }
+    }
+    queue.save()?;
+  }
+  println!("{} tracks enqueued, {} skipped", known.len(), unknown.len());
+  Ok(())
+}
+
+/// Urls of the tracks listed by the player, in their order.
+async fn tracklist_locations(player: &str) -> Result<Vec<Url>> {
+  const PREFIX: &str = "org.mpris.MediaPlayer2.";
+  let short_name = player.strip_prefix(PREFIX).unwrap_or(player);
+  let connection = Connection::session().await.into_diagnostic()?;
+
+  // The browsers suffix their name with the instance
+  let names: Vec<String> = connection
+    .call_method(
+      Some("org.freedesktop.DBus"),
+      "/org/freedesktop/DBus",
+      Some("org.freedesktop.DBus"),
+      "ListNames",
+      &(),
+    )
+    .await
+    .into_diagnostic()?
+    .body()
+    .deserialize()
+    .into_diagnostic()?;
+  let name = names
+    .into_iter()
+    .find(|name| {
+      name
+        .strip_prefix(PREFIX)
+        .is_some_and(|name| name == short_name || name.starts_with(&format!("{short_name}.")))
+    })
+    .ok_or_else(|| miette!("No MPRIS player named '{player}'"))?;
+
+  let proxy = Proxy::new(
+    &connection,
+    name.as_str(),
+    "/org/mpris/MediaPlayer2",
+    "org.mpris.MediaPlayer2.TrackList",
+  )
+  .await
+  .into_diagnostic()?;
+  let tracks: Vec<OwnedObjectPath> = proxy
+    .get_property("Tracks")
+    .await
+    .map_err(|e| miette!("'{name}' has no track list: {e}"))?;
+  let metadata: Vec<HashMap<String, OwnedValue>> = proxy
+    .call("GetTracksMetadata", &(tracks,))
+    .await
+    .into_diagnostic()?;
+  Ok(
+    metadata
+      .into_iter()
+      .filter_map(|mut metadata| {
+        let url = String::try_from(metadata.remove("xesam:url")?).ok()?;
+        Url::parse(&url).ok()
+      })
+      .collect(),
+  )
+}
+
+fn format_now_playing(format: &str, now_playing: &instance::NowPlaying) -> String {
+  if now_playing.title.is_empty() {
+    return String::new();
+  }
+  format
+    .replace("{title}", &now_playing.title)
+    .replace("{artist}", &now_playing.artist)
+    .replace("{album}", &now_playing.album)
+    .replace("{status}", &now_playing.status)
+    .replace(
+      "{elapsed}",
+      &Duration::from_secs(now_playing.elapsed).format_compact(),
+    )
+    .replace(
+      "{duration}",
+      &Duration::from_secs(now_playing.duration).format_compact(),
+    )
+}
+
+fn print_table(tracks: &[Track]) {
+  let rows: Vec<[String; 5]> = tracks
+    .iter()
+    .map(|track| {
+      [
+        track.title.to_string(),
+        track.artist.to_string(),
+        track.album.to_string(),
+        Duration::from_secs(track.duration).format_compact(),
+        format!(
+          "{}{}",
+          "★".repeat(track.rating.stars() as usize),
+          if track.rating.has_half() { "½" } else { "" }
+        ),
+      ]
+    })
+    .collect();
+  let mut widths = [0; 5];
+  for row in &rows {
+    for (width, cell) in widths.iter_mut().zip(row) {
+      *width = (*width).max(cell.chars().count().min(MAX_COLUMN_WIDTH));
+    }
+  }
+  for row in rows {
+    let line = row
+      .iter()
+      .zip(widths)
+      .map(|(cell, width)| format!("{:width$}", truncate(cell, width)))
+      .collect::<Vec<_>>()
+      .join("  ");
+    println!("{}", line.trim_end());
+  }
+}
+
+/// Cut the text to `width` chars, with an ellipsis.
+fn truncate(text: &str, width: usize) -> String {
+  if text.chars().count() > width {
+    let mut text: String = text.chars().take(width.saturating_sub(1)).collect();
+    text.push('…');
+    text
+  } else {
+    text.to_string()
+  }
+}
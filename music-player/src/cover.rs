@@ -0,0 +1,212 @@
+use crate::{rhythmdb::Entry, settings::cache_dir};
+use std::{fs, path::PathBuf};
+use tracing::warn;
+use url::Url;
+
+// Side of the placeholder image, in pixels
+const PLACEHOLDER_SIZE: usize = 128;
+
+/// Cover of the album of the entry, when it is in the art cache.
+pub(crate) fn cached(entry: &Entry) -> Option<PathBuf> {
+  let path = cover_path(entry.get_mb_albumid()?)?;
+  path.is_file().then_some(path)
+}
+
+/// Url of the cached cover, for the MPRIS metadata. A placeholder is given
+/// when there is no cover, so that the OSDs don't show a broken image.
+pub(crate) fn art_url(entry: &Entry) -> Option<Url> {
+  Url::from_file_path(cached(entry).or_else(|| placeholder(entry))?).ok()
+}
+
+/// A gradient with colors picked from the location of the track, written
+/// once in the art cache.
+fn placeholder(entry: &Entry) -> Option<PathBuf> {
+  let hash = fnv1a(entry.get_location().as_str().as_bytes());
+  let path = cache_dir()?
+    .join("covers")
+    .join(format!("placeholder-{hash:016x}.png"));
+  if path.is_file() {
+    return Some(path);
+  }
+  let from = hue_to_rgb((hash % 360) as u32);
+  let to = hue_to_rgb(((hash >> 16) % 360) as u32);
+  let written = path
+    .parent()
+    .map_or(Ok(()), fs::create_dir_all)
+    .and_then(|_| fs::write(&path, gradient_png(from, to)));
+  match written {
+    Ok(()) => Some(path),
+    Err(e) => {
+      warn!("Can't write the placeholder cover: {e}");
+      None
+    }
+  }
+}
+
+fn fnv1a(bytes: &[u8]) -> u64 {
+  bytes.iter().fold(0xcbf29ce484222325, |hash, byte| {
+    (hash ^ *byte as u64).wrapping_mul(0x100000001b3)
+  })
+}
+
+/// Saturated color of the hue, in degrees.
+fn hue_to_rgb(hue: u32) -> [u8; 3] {
+  let x = (255 * (60 - (hue % 120).abs_diff(60)) / 60) as u8;
+  match hue / 60 {
+    0 => [255, x, 0],
+    1 => [x, 255, 0],
+    2 => [0, 255, x],
+    3 => [0, x, 255],
+    4 => [x, 0, 255],
+    _ => [255, 0, x],
+  }
+}
+
+/// Diagonal gradient encoded as a png. The deflate stream is made of stored
+/// blocks: the image is small and no compression crate is needed.
+fn gradient_png(from: [u8; 3], to: [u8; 3]) -> Vec<u8> {
+  let last = 2 * (PLACEHOLDER_SIZE - 1);
+  let mut pixels = Vec::with_capacity(PLACEHOLDER_SIZE * (1 + 3 * PLACEHOLDER_SIZE));
+  for y in 0..PLACEHOLDER_SIZE {
+    // No filter on the row
+    pixels.push(0);
+    for x in 0..PLACEHOLDER_SIZE {
+      for channel in 0..3 {
+        let (from, to) = (from[channel] as usize, to[channel] as usize);
+        pixels.push(((from * (last - x - y) + to * (x + y)) / last) as u8);
+      }
+    }
+  }
+
+  let mut zlib = vec![0x78, 0x01];
+  let mut blocks = pixels.chunks(u16::MAX as usize).peekable();
+  while let Some(block) = blocks.next() {
+    let len = block.len() as u16;
+    zlib.push(u8::from(blocks.peek().is_none()));
+    zlib.extend(len.to_le_bytes());
+    zlib.extend((!len).to_le_bytes());
+    zlib.extend(block);
+  }
+  zlib.extend(adler32(&pixels).to_be_bytes());
+
+  let mut header = Vec::with_capacity(13);
+  header.extend((PLACEHOLDER_SIZE as u32).to_be_bytes());
+  header.extend((PLACEHOLDER_SIZE as u32).to_be_bytes());
+  // 8 bits rgb, no interlace
+  header.extend([8, 2, 0, 0, 0]);
+
+  let mut png = b"\x89PNG\r\n\x1a\n".to_vec();
+  png_chunk(&mut png, b"IHDR", &header);
+  png_chunk(&mut png, b"IDAT", &zlib);
+  png_chunk(&mut png, b"IEND", &[]);
+  png
+}
+
+fn png_chunk(png: &mut Vec<u8>, kind: &[u8; 4], data: &[u8]) {
+  png.extend((data.len() as u32).to_be_bytes());
+  png.extend(kind);
+  png.extend(data);
+  png.extend(crc32(&[kind.as_slice(), data].concat()).to_be_bytes());
+}
+
+fn crc32(bytes: &[u8]) -> u32 {
+  !bytes.iter().fold(!0, |crc: u32, byte| {
+    (0..8).fold(crc ^ *byte as u32, |crc, _| {
+      if crc & 1 == 1 {
+        (crc >> 1) ^ 0xedb88320
+      } else {
+        crc >> 1
+      }
+    })
+  })
+}
+
+fn adler32(bytes: &[u8]) -> u32 {
+  let (a, b) = bytes.iter().fold((1u32, 0u32), |(a, b), byte| {
+    let a = (a + *byte as u32) % 65521;
+    (a, (b + a) % 65521)
+  });
+  (b << 16) | a
+}
+
+/// `~/.cache/music-player/covers/<mb-albumid>.jpg`. The id comes from the
+/// tags of the files: anything but an uuid is refused.
+fn cover_path(mbid: &str) -> Option<PathBuf> {
+  if !mbid.chars().all(|c| c.is_ascii_hexdigit() || c == '-') {
+    return None;
+  }
+  cache_dir().map(|directory| directory.join("covers").join(format!("{mbid}.jpg")))
+}
+
+/// Download the cover of the track when it is not cached, then update the
+/// MPRIS metadata if the track is still played.
+#[cfg(feature = "coverart")]
+pub(crate) fn fetch_in_background(track: crate::rhythmdb::SharedEntry) {
+  use mpris_server::{Metadata, Property};
+
+  if track.get_mb_albumid().is_none() || cached(&track).is_some() {
+    return;
+  }
+  tokio::spawn(async move {
+    match fetch(&track).await {
+      Ok(true) => {
+        let Ok(server) = crate::get_mpris_server().await else {
+          return;
+        };
+        let still_played = server
+          .imp()
+          .get_track()
+          .await
+          .as_ref()
+          .is_some_and(|played| played.get_location() == track.get_location());
+        if still_played {
+          let metadata = Property::Metadata(Metadata::from(track.as_ref()));
+          let _ = server.properties_changed(vec![metadata]).await;
+        }
+      }
+      Ok(false) => {}
+      Err(e) => tracing::warn!("Can't fetch the cover: {e}"),
+    }
+  });
+}
+
+/// Download the front cover of the release from the
+/// [Cover Art Archive](https://coverartarchive.org) into the art cache.
+/// Returns false when the release has no cover.
+#[cfg(feature = "coverart")]
+#[tracing::instrument(skip(entry))]
+async fn fetch(entry: &Entry) -> miette::Result<bool> {
+  use miette::{miette, IntoDiagnostic};
+
+  let Some(mbid) = entry.get_mb_albumid() else {
+    return Ok(false);
+  };
+  let path = cover_path(mbid).ok_or(miette!("Can't get the cache directory"))?;
+  let response = reqwest::Client::new()
+    .get(format!(
+      "https://coverartarchive.org/release/{mbid}/front-500"
+    ))
+    .header(
+      "User-Agent",
+      concat!("music-player/", env!("CARGO_PKG_VERSION")),
+    )
+    .send()
+    .await
+    .into_diagnostic()?;
+  if response.status() == reqwest::StatusCode::NOT_FOUND {
+    return Ok(false);
+  }
+  let image = response
+    .error_for_status()
+    .into_diagnostic()?
+    .bytes()
+    .await
+    .into_diagnostic()?;
+  if let Some(directory) = path.parent() {
+    tokio::fs::create_dir_all(directory)
+      .await
+      .into_diagnostic()?;
+  }
+  tokio::fs::write(&path, image).await.into_diagnostic()?;
+  Ok(true)
+}
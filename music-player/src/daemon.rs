@@ -0,0 +1,199 @@
+use crate::{
+  get_mpris_server, media_keys, metrics, mplayer,
+  player_state::{PlayerState, UiNotification},
+  rhythmdb::Saved,
+  settings::{PlayerStateSetting, Settings},
+  suggestions,
+  ui::{
+    i18n::init_messages, is_played, record_play, spawn_saver, update_last_played, watch_settings,
+  },
+};
+use clap::ArgMatches;
+use futures::StreamExt;
+use gstreamer::{prelude::ElementExt, MessageView, State};
+use miette::{IntoDiagnostic, Result};
+use std::{
+  env,
+  os::{fd::FromRawFd, unix::net::UnixListener},
+  process,
+  sync::{
+    atomic::{AtomicBool, Ordering},
+    Mutex,
+  },
+  time::{Duration, Instant},
+};
+use tokio::{
+  select,
+  signal::unix::{signal, SignalKind},
+  sync::mpsc::channel,
+  time::interval,
+};
+use tracing::{instrument, warn};
+
+// First fd passed by systemd, see sd_listen_fds(3)
+const LISTEN_FDS_START: i32 = 3;
+const BUS_NAME: &str = "org.mpris.MediaPlayer2.org.djedi.music-player";
+
+// The control socket was passed by systemd
+static ACTIVATED: AtomicBool = AtomicBool::new(false);
+// The MPRIS name is released until the first playback
+static NAME_DEFERRED: AtomicBool = AtomicBool::new(false);
+static LAST_CALL: Mutex<Option<Instant>> = Mutex::new(None);
+
+/// Control socket passed by systemd on socket activation, none when the
+/// player was started otherwise.
+pub(crate) fn activated_listener() -> Option<UnixListener> {
+  let pid: u32 = env::var("LISTEN_PID").ok()?.parse().ok()?;
+  let fds: i32 = env::var("LISTEN_FDS").ok()?.parse().ok()?;
+  if pid != process::id() || fds < 1 {
+    return None;
+  }
+  // The hooks run by the player don't inherit the socket
+  env::remove_var("LISTEN_PID");
+  env::remove_var("LISTEN_FDS");
+  env::remove_var("LISTEN_FDNAMES");
+  // SAFETY: systemd hands the fd over to this process, nothing else owns it
+  let listener = unsafe { UnixListener::from_raw_fd(LISTEN_FDS_START) };
+  listener.set_nonblocking(true).ok()?;
+  ACTIVATED.store(true, Ordering::Relaxed);
+  Some(listener)
+}
+
+/// Whether the control socket belongs to systemd.
+pub(crate) fn is_activated() -> bool {
+  ACTIVATED.load(Ordering::Relaxed)
+}
+
+/// Keep the daemon alive: a call of the control socket or of an MPRIS
+/// client was answered.
+pub(crate) fn touch() {
+  if let Ok(mut last_call) = LAST_CALL.lock() {
+    *last_call = Some(Instant::now());
+  }
+}
+
+/// Release the MPRIS name of a socket-activated daemon until it plays: the
+/// desktop doesn't list a player started by a `ctl` command.
+pub(crate) async fn defer_name() {
+  if !is_activated() {
+    return;
+  }
+  let Ok(server) = get_mpris_server().await else {
+    return;
+  };
+  match server.connection().release_name(BUS_NAME).await {
+    Ok(_) => NAME_DEFERRED.store(true, Ordering::Relaxed),
+    Err(e) => warn!("Can't release the MPRIS name: {e}"),
+  }
+}
+
+/// Claim the MPRIS name released by `defer_name`, on the first playback.
+pub(crate) fn claim_name() {
+  if !NAME_DEFERRED.swap(false, Ordering::Relaxed) {
+    return;
+  }
+  tokio::spawn(async {
+    if let Ok(server) = get_mpris_server().await {
+      if let Err(e) = server.connection().request_name(BUS_NAME).await {
+        warn!("Can't claim the MPRIS name: {e}");
+      }
+    }
+  });
+}
+
+/// Play without the tui, controlled through MPRIS and the control socket.
+/// The player exits on SIGTERM, or once idle for `daemon.idle_timeout`, and
+/// saves its session for the next activation. The settings of the playback
+/// are reloaded when `settings.toml` is modified.
+#[instrument(skip(player, settings, matches))]
+pub(crate) async fn run(
+  player: &'static PlayerState,
+  settings: &Settings,
+  matches: ArgMatches,
+) -> Result<()> {
+  init_messages(settings.language);
+  let (tx, mut rx) = channel(16);
+  player.set_sender(tx).await;
+  let saves = spawn_saver(player, settings.playlist_path.clone());
+  watch_settings(player, matches);
+  mplayer::announce_position(player);
+  if settings.playback.media_keys {
+    media_keys::grab_media_keys(player);
+  }
+  if let Some(address) = &settings.daemon.metrics {
+    metrics::serve(address.clone());
+  }
+
+  let idle_timeout = Duration::from_secs(settings.daemon.idle_timeout * 60);
+  let mut terminate = signal(SignalKind::terminate()).into_diagnostic()?;
+  let mut tick = interval(Duration::from_secs(1));
+  let mut idle_since = Instant::now();
+  // Track played again by the `retry` policy and its attempts
+  let mut retried = None;
+  loop {
+    let pipeline = player.get_pipeline().await;
+    let playing = pipeline
+      .as_ref()
+      .is_some_and(|pipeline| pipeline.current_state() == State::Playing);
+    if playing {
+      idle_since = Instant::now();
+    }
+    let last_call = LAST_CALL.lock().ok().and_then(|last_call| *last_call);
+    let active = last_call.map_or(idle_since, |last_call| last_call.max(idle_since));
+    if !idle_timeout.is_zero() && active.elapsed() >= idle_timeout {
+      break;
+    }
+
+    let message = async move {
+      match pipeline.and_then(|pipeline| pipeline.bus()) {
+        Some(bus) => bus.stream().next().await,
+        None => std::future::pending().await,
+      }
+    };
+    select! {
+      _ = tick.tick() => {}
+      _ = terminate.recv() => break,
+      _ = tokio::signal::ctrl_c() => break,
+      Some(message) = message => match message.view() {
+        MessageView::Eos(_) => {
+          player.next_track().await?;
+        }
+        MessageView::Error(err) => {
+          metrics::playback_error();
+          warn!("Can't play the track: {}", err.error());
+          player.apply_error_policy(&mut retried).await?;
+        }
+        _ => {}
+      },
+      Some(notification) = rx.recv() => match notification {
+        UiNotification::Played(track, listened) => {
+          if is_played(&track, listened) {
+            record_play(&track, listened);
+            update_last_played(player, &track).await;
+            // A full channel already holds a request
+            let _ = saves.try_send(());
+          } else {
+            suggestions::record_skip(&player.get_suggestions().await, &track);
+          }
+        }
+        UiNotification::Saved(Ok(Saved::Deferred)) => {
+          warn!("Rhythmbox is running: the db is saved once it is closed");
+        }
+        UiNotification::Saved(Err(e)) => warn!("Can't save the db: {e}"),
+        UiNotification::Settings(settings) => player.apply_settings(&settings).await,
+        UiNotification::Error(text) => warn!("{text}"),
+        _ => {}
+      }
+    }
+  }
+
+  let layout = PlayerStateSetting::load()?
+    .map(|saved| saved.layout)
+    .unwrap_or_default();
+  player.save_session(layout).await?;
+  let mut db = player.get_mut_db().await;
+  if db.has_pending_changes() {
+    db.save(settings)?;
+  }
+  Ok(())
+}
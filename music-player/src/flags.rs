@@ -0,0 +1,99 @@
+use crate::settings::{state_dir, write_state};
+use miette::{miette, IntoDiagnostic, Result};
+use serde::{Deserialize, Serialize};
+use std::{collections::BTreeSet, fs, path::PathBuf, sync::RwLock};
+use toml::{from_str, to_string_pretty};
+use tracing::{debug, instrument};
+use url::Url;
+
+// Weight of a loved track in the shuffle, the others weigh 1
+const LOVED_WEIGHT: u64 = 3;
+
+/// Loved and banned tracks, by location. They are kept in the state
+/// directory: the Rhythmbox db has no field for them.
+#[derive(Debug, Default, Deserialize, Serialize)]
+#[serde(default)]
+struct Flags {
+  loved: BTreeSet<String>,
+  banned: BTreeSet<String>,
+}
+
+static FLAGS: RwLock<Flags> = RwLock::new(Flags {
+  loved: BTreeSet::new(),
+  banned: BTreeSet::new(),
+});
+
+/// Read the flags saved in `flags.toml`, none when it can't be read.
+#[instrument]
+pub(crate) fn load_flags() {
+  let flags = get_path()
+    .and_then(|path| fs::read_to_string(path).ok())
+    .and_then(|text| {
+      from_str(&text)
+        .inspect_err(|e| debug!("Can't read the flags: {e}"))
+        .ok()
+    })
+    .unwrap_or_default();
+  if let Ok(mut lock) = FLAGS.write() {
+    *lock = flags;
+  }
+}
+
+pub(crate) fn is_loved(location: &Url) -> bool {
+  FLAGS
+    .read()
+    .is_ok_and(|flags| flags.loved.contains(location.as_str()))
+}
+
+pub(crate) fn is_banned(location: &Url) -> bool {
+  FLAGS
+    .read()
+    .is_ok_and(|flags| flags.banned.contains(location.as_str()))
+}
+
+/// Love the track, or unlove it: a loved track is no longer banned. True
+/// when it is loved.
+pub(crate) fn toggle_loved(location: &Url) -> Result<bool> {
+  toggle(location, true)
+}
+
+/// Ban the track, or unban it: a banned track is no longer loved. True when
+/// it is banned.
+pub(crate) fn toggle_banned(location: &Url) -> Result<bool> {
+  toggle(location, false)
+}
+
+/// Weight of the track in the shuffle: the loved ones come more often.
+pub(crate) fn shuffle_weight(location: &Url) -> u64 {
+  if is_loved(location) {
+    LOVED_WEIGHT
+  } else {
+    1
+  }
+}
+
+#[instrument]
+fn toggle(location: &Url, love: bool) -> Result<bool> {
+  let mut flags = FLAGS.write().map_err(|e| miette!("{e}"))?;
+  let Flags { loved, banned } = &mut *flags;
+  let (set, other) = if love {
+    (loved, banned)
+  } else {
+    (banned, loved)
+  };
+  let key = location.to_string();
+  let flagged = if set.remove(&key) {
+    false
+  } else {
+    other.remove(&key);
+    set.insert(key)
+  };
+  if let Some(path) = get_path() {
+    write_state(&path, &to_string_pretty(&*flags).into_diagnostic()?)?;
+  }
+  Ok(flagged)
+}
+
+fn get_path() -> Option<PathBuf> {
+  state_dir().map(|directory| directory.join("flags.toml"))
+}
@@ -1,5 +1,12 @@
-use gstreamer::{parse::launch, prelude::ElementExt, Element, State, StateChangeSuccess};
-use miette::{IntoDiagnostic, Result, WrapErr};
+use gstreamer::{
+  glib,
+  parse::launch,
+  prelude::{DeviceMonitorExt, DeviceMonitorExtManual, ElementExt},
+  tags, Bin, ClockTime, DeviceMonitor, Element, MessageView, ResourceError, State,
+  StateChangeSuccess, TagList,
+};
+use miette::{miette, IntoDiagnostic, Result, WrapErr};
+use std::fmt::Display;
 use tracing::instrument;
 use url::Url;
 
@@ -17,6 +24,15 @@ pub(crate) fn start_playing(url: &Url) -> Result<Element> {
   Ok(pipeline)
 }
 
+/// Pipeline of the url, prerolled in pause.
+#[instrument]
+pub(crate) fn preroll(url: &Url) -> Result<Element> {
+  let pipeline = launch(&format!("playbin3 uri={url}")).into_diagnostic()?;
+
+  pause(&pipeline).with_context(|| format!("Can preroll {url}"))?;
+  Ok(pipeline)
+}
+
 #[instrument]
 pub(crate) fn stop(pipeline: &Element) -> Result<StateChangeSuccess> {
   // Shutdown pipeline
@@ -41,3 +57,166 @@ pub(crate) fn play(pipeline: &Element) -> Result<StateChangeSuccess> {
     .into_diagnostic()
     .context("Unable to set the pipeline to the `Playing` state")
 }
+
+/// Watch the audio outputs: the removed ones are announced on the bus of the
+/// monitor.
+#[instrument]
+pub(crate) fn monitor_audio_outputs() -> Result<DeviceMonitor> {
+  let monitor = DeviceMonitor::new();
+  monitor.add_filter(Some("Audio/Sink"), None);
+  monitor
+    .start()
+    .into_diagnostic()
+    .context("Can't monitor the audio outputs")?;
+  Ok(monitor)
+}
+
+/// The error comes from the audio output: it is lost, busy or can't be
+/// opened.
+pub(crate) fn is_output_error(error: &glib::Error) -> bool {
+  matches!(
+    error.kind::<ResourceError>(),
+    Some(
+      ResourceError::Write
+        | ResourceError::OpenWrite
+        | ResourceError::OpenReadWrite
+        | ResourceError::Busy
+    )
+  )
+}
+
+/// Decode the whole track through `rganalysis` and return its ReplayGain
+/// track gain (dB) and peak.
+#[instrument]
+pub(crate) fn analyze_gain(url: &Url) -> Result<(f64, f64)> {
+  let (mut gain, mut peak) = (None, None);
+  analyze(url, "rganalysis", |tags| {
+    gain = tags.get::<tags::TrackGain>().map(|v| v.get()).or(gain);
+    peak = tags.get::<tags::TrackPeak>().map(|v| v.get()).or(peak);
+  })?;
+  gain
+    .zip(peak)
+    .ok_or_else(|| miette!("No gain computed for {url}"))
+}
+
+/// Decode the whole track through `bpmdetect` (gst-plugins-bad) and return
+/// its tempo. The estimate is refined along the track, the last one is kept.
+#[instrument]
+pub(crate) fn analyze_bpm(url: &Url) -> Result<f64> {
+  let mut bpm = None;
+  analyze(url, "bpmdetect", |tags| {
+    bpm = tags.get::<tags::BeatsPerMinute>().map(|v| v.get()).or(bpm);
+  })?;
+  bpm.ok_or_else(|| miette!("No tempo detected for {url}"))
+}
+
+/// Decode the track as fast as possible through the analysis element, the
+/// tags it posts are read until the end of the stream.
+fn analyze(url: &Url, element: &str, mut read_tags: impl FnMut(&TagList)) -> Result<()> {
+  let pipeline = launch(&format!(
+    "uridecodebin uri={url} ! audioconvert ! audioresample ! {element} ! fakesink sync=false"
+  ))
+  .into_diagnostic()?;
+  let bus = pipeline
+    .bus()
+    .ok_or_else(|| miette!("The pipeline has no bus"))?;
+  play(&pipeline).with_context(|| format!("Can analyze {url}"))?;
+
+  let result = loop {
+    let Some(message) = bus.timed_pop(ClockTime::NONE) else {
+      break Err(miette!("The analysis of {url} stopped"));
+    };
+    match message.view() {
+      MessageView::Tag(tag) => read_tags(&tag.tags()),
+      MessageView::Eos(_) => break Ok(()),
+      MessageView::Error(err) => break Err(miette!("Can't analyze {url}: {}", err.error())),
+      _ => {}
+    }
+  };
+  stop(&pipeline)?;
+  result
+}
+
+/// Format of the played stream: codec and bitrate come from the tags, the
+/// sample format from the caps negotiated by the audio sink.
+#[derive(Debug, Default)]
+pub(crate) struct StreamInfo {
+  pub(crate) location: Option<Url>,
+  codec: Option<String>,
+  bitrate: Option<u32>,
+  rate: Option<i32>,
+  channels: Option<i32>,
+  format: Option<String>,
+}
+
+impl StreamInfo {
+  pub(crate) fn new(location: Url) -> StreamInfo {
+    StreamInfo {
+      location: Some(location),
+      ..Default::default()
+    }
+  }
+
+  #[instrument(skip(self, tags))]
+  pub(crate) fn update_tags(&mut self, tags: &TagList) {
+    if let Some(codec) = tags.get::<tags::AudioCodec>() {
+      self.codec = Some(codec.get().to_string());
+    }
+    if let Some(bitrate) = tags
+      .get::<tags::Bitrate>()
+      .or_else(|| tags.get::<tags::NominalBitrate>())
+    {
+      self.bitrate = Some(bitrate.get());
+    }
+  }
+
+  /// Read the caps once the audio sink has negotiated them.
+  #[instrument(skip(self))]
+  pub(crate) fn update_caps(&mut self, pipeline: &Element) {
+    use gstreamer::prelude::*;
+    if self.rate.is_some() {
+      return;
+    }
+    let Some(bin) = pipeline.downcast_ref::<Bin>() else {
+      return;
+    };
+    let caps = bin
+      .iterate_sinks()
+      .into_iter()
+      .flatten()
+      .flat_map(|sink| sink.sink_pads())
+      .find_map(|pad| pad.current_caps());
+    if let Some(structure) = caps.as_ref().and_then(|caps| caps.structure(0)) {
+      if structure.name().starts_with("audio/") {
+        self.rate = structure.get::<i32>("rate").ok();
+        self.channels = structure.get::<i32>("channels").ok();
+        self.format = structure
+          .get::<&str>("format")
+          .ok()
+          .map(|format| format.to_string());
+      }
+    }
+  }
+}
+
+impl Display for StreamInfo {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    let mut parts = vec![];
+    if let Some(codec) = &self.codec {
+      parts.push(codec.clone());
+    }
+    if let Some(rate) = self.rate {
+      parts.push(format!("{:.1} kHz", rate as f64 / 1000.0));
+    }
+    if let Some(format) = &self.format {
+      parts.push(format.clone());
+    }
+    if let Some(channels) = self.channels {
+      parts.push(format!("{channels} ch"));
+    }
+    if let Some(bitrate) = self.bitrate {
+      parts.push(format!("{} kbps", bitrate / 1000));
+    }
+    f.write_str(&parts.join(" · "))
+  }
+}
@@ -0,0 +1,155 @@
+use crate::{
+  args::{ExportFormat, History},
+  rhythmdb::Entry,
+  settings::state_dir,
+};
+use chrono::{DateTime, Local};
+use miette::{miette, Context, IntoDiagnostic, Result};
+use serde::{Deserialize, Serialize};
+use std::{
+  fs::{self, OpenOptions},
+  io::{self, BufRead, BufReader, Write},
+  path::{Path, PathBuf},
+  time::Duration,
+};
+use tracing::instrument;
+
+/// A completed play: a line of the history file.
+#[derive(Debug, Deserialize, Serialize)]
+struct Play {
+  // Rfc 3339 date of the end of the play
+  timestamp: DateTime<Local>,
+  title: String,
+  artist: String,
+  album: String,
+  location: String,
+  // In seconds
+  duration: u64,
+  listened: u64,
+}
+
+/// Append the play of the track to the history, apart from the rhythmbox db.
+#[instrument(skip(track))]
+pub(crate) fn record(track: &Entry, listened: Duration) -> Result<()> {
+  let path = get_path().ok_or(miette!("Can't get the history path"))?;
+  let play = Play {
+    timestamp: Local::now(),
+    title: track.get_title().to_string(),
+    artist: track.get_artist().to_string(),
+    album: track.get_album().to_string(),
+    location: track.get_location().to_string(),
+    duration: track.get_duration(),
+    listened: listened.as_secs(),
+  };
+  if let Some(directory) = path.parent() {
+    fs::create_dir_all(directory).into_diagnostic()?;
+  }
+  let mut file = OpenOptions::new()
+    .create(true)
+    .append(true)
+    .open(&path)
+    .into_diagnostic()
+    .with_context(|| format!("Trying to open `{}`", path.display()))?;
+  writeln!(file, "{}", serde_json::to_string(&play).into_diagnostic()?).into_diagnostic()
+}
+
+/// Run a history command.
+#[instrument]
+pub(crate) fn history(command: &History) -> Result<()> {
+  let path = get_path().ok_or(miette!("Can't get the history path"))?;
+  match command {
+    History::Export {
+      format,
+      since,
+      output,
+    } => {
+      let since = since.map(|since| Local::now().timestamp() - since.as_secs() as i64);
+      let plays = load(&path)?
+        .into_iter()
+        .filter(|play| since.is_none_or(|since| play.timestamp.timestamp() >= since));
+      let mut out: Box<dyn Write> = match output {
+        Some(output) => Box::new(
+          fs::File::create(output)
+            .into_diagnostic()
+            .with_context(|| format!("Trying to create `{}`", output.display()))?,
+        ),
+        None => Box::new(io::stdout().lock()),
+      };
+      match format {
+        ExportFormat::Csv => {
+          writeln!(
+            out,
+            "timestamp,title,artist,album,location,duration,listened"
+          )
+          .into_diagnostic()?;
+          for play in plays {
+            writeln!(
+              out,
+              "{},{},{},{},{},{},{}",
+              play.timestamp.to_rfc3339(),
+              csv_field(&play.title),
+              csv_field(&play.artist),
+              csv_field(&play.album),
+              csv_field(&play.location),
+              play.duration,
+              play.listened
+            )
+            .into_diagnostic()?;
+          }
+        }
+        ExportFormat::Jsonl => {
+          for play in plays {
+            let line = serde_json::to_string(&play).into_diagnostic()?;
+            writeln!(out, "{line}").into_diagnostic()?;
+          }
+        }
+      }
+    }
+    History::Rotate => {
+      if !path.exists() {
+        return Err(miette!("No history in `{}`", path.display()));
+      }
+      let rotated = path.with_file_name(format!(
+        "history-{}.jsonl",
+        Local::now().format("%Y%m%d-%H%M%S")
+      ));
+      fs::rename(&path, &rotated).into_diagnostic()?;
+      println!("{}", rotated.display());
+    }
+    History::Path => println!("{}", path.display()),
+  }
+  Ok(())
+}
+
+/// The plays of the history file, the unreadable lines skipped.
+fn load(path: &Path) -> Result<Vec<Play>> {
+  let file = match fs::File::open(path) {
+    Ok(file) => file,
+    Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(vec![]),
+    Err(e) => {
+      return Err(e)
+        .into_diagnostic()
+        .with_context(|| format!("Trying to read `{}`", path.display()))
+    }
+  };
+  Ok(
+    BufReader::new(file)
+      .lines()
+      .map_while(Result::ok)
+      .filter_map(|line| serde_json::from_str(&line).ok())
+      .collect(),
+  )
+}
+
+/// Quote the field when it holds a comma, a quote or a line break.
+fn csv_field(field: &str) -> String {
+  if field.contains([',', '"', '\n', '\r']) {
+    format!("\"{}\"", field.replace('"', "\"\""))
+  } else {
+    field.to_string()
+  }
+}
+
+fn get_path() -> Option<PathBuf> {
+  state_dir().map(|directory| directory.join("history.jsonl"))
+}
@@ -0,0 +1,123 @@
+use crate::{
+  rhythmdb::SharedEntry,
+  settings::{HookEvent, HookSettings},
+};
+use futures::future::join_all;
+use miette::{miette, IntoDiagnostic, Result};
+use serde::Serialize;
+use std::{process::Stdio, sync::OnceLock};
+use tokio::{io::AsyncWriteExt, process::Command};
+use tracing::{instrument, warn};
+
+static HOOKS: OnceLock<Vec<HookSettings>> = OnceLock::new();
+
+/// Payload of the hooks: sent as json to the urls and on the stdin of the
+/// commands.
+#[derive(Debug, Serialize)]
+struct Payload {
+  event: HookEvent,
+  title: String,
+  artist: String,
+  album: String,
+  location: String,
+  // In seconds
+  duration: u64,
+}
+
+impl Payload {
+  fn new(event: HookEvent, track: Option<&SharedEntry>) -> Payload {
+    Payload {
+      event,
+      title: track.map(|t| t.get_title().to_string()).unwrap_or_default(),
+      artist: track
+        .map(|t| t.get_artist().to_string())
+        .unwrap_or_default(),
+      album: track.map(|t| t.get_album().to_string()).unwrap_or_default(),
+      location: track
+        .map(|t| t.get_location().to_string())
+        .unwrap_or_default(),
+      duration: track.map(|t| t.get_duration()).unwrap_or_default(),
+    }
+  }
+}
+
+#[instrument(skip(hooks))]
+pub(crate) fn init_hooks(hooks: &[HookSettings]) {
+  #[cfg(not(feature = "webhooks"))]
+  if hooks.iter().any(|hook| hook.url.is_some()) {
+    warn!("The url hooks are ignored: build with the `webhooks` feature");
+  }
+  // The hooks are only set once at startup: ignore a second call.
+  let _ = HOOKS.set(hooks.to_vec());
+}
+
+/// Run the hooks of the event in the background.
+pub(crate) fn fire(event: HookEvent, track: Option<SharedEntry>) {
+  if hooks_of(event).next().is_some() {
+    tokio::spawn(run(event, track));
+  }
+}
+
+/// Run the hooks of the event and wait for them.
+#[instrument(skip(track))]
+pub(crate) async fn run(event: HookEvent, track: Option<SharedEntry>) {
+  let payload = Payload::new(event, track.as_ref());
+  join_all(hooks_of(event).map(|hook| async {
+    if let Err(e) = run_hook(hook, &payload).await {
+      warn!("Hook failed on {event:?}: {e}");
+    }
+  }))
+  .await;
+}
+
+fn hooks_of(event: HookEvent) -> impl Iterator<Item = &'static HookSettings> {
+  HOOKS
+    .get()
+    .into_iter()
+    .flatten()
+    .filter(move |hook| hook.events.contains(&event))
+}
+
+async fn run_hook(hook: &HookSettings, payload: &Payload) -> Result<()> {
+  if let Some(command) = &hook.command {
+    run_command(command, payload).await?;
+  }
+  #[cfg(feature = "webhooks")]
+  if let Some(url) = &hook.url {
+    reqwest::Client::new()
+      .post(url.clone())
+      .json(payload)
+      .send()
+      .await
+      .into_diagnostic()?
+      .error_for_status()
+      .into_diagnostic()?;
+  }
+  Ok(())
+}
+
+/// Run the command with `sh`, the payload in its environment and on its stdin.
+async fn run_command(command: &str, payload: &Payload) -> Result<()> {
+  let json = serde_json::to_string(payload).into_diagnostic()?;
+  let mut child = Command::new("sh")
+    .arg("-c")
+    .arg(command)
+    .env("MUSIC_PLAYER_EVENT", payload.event.name())
+    .env("MUSIC_PLAYER_TITLE", &payload.title)
+    .env("MUSIC_PLAYER_ARTIST", &payload.artist)
+    .env("MUSIC_PLAYER_ALBUM", &payload.album)
+    .env("MUSIC_PLAYER_LOCATION", &payload.location)
+    .stdin(Stdio::piped())
+    .stdout(Stdio::null())
+    .spawn()
+    .into_diagnostic()?;
+  if let Some(mut stdin) = child.stdin.take() {
+    // The command may not read its stdin
+    let _ = stdin.write_all(json.as_bytes()).await;
+  }
+  let status = child.wait().await.into_diagnostic()?;
+  if !status.success() {
+    return Err(miette!("`{command}` exited with {status}"));
+  }
+  Ok(())
+}
@@ -0,0 +1,51 @@
+use mpris_server::zbus::{self, zvariant::OwnedFd, Connection};
+use std::sync::atomic::{AtomicBool, Ordering};
+use tokio::sync::Mutex;
+use tracing::{instrument, warn};
+
+static ENABLED: AtomicBool = AtomicBool::new(true);
+static PLAYING: AtomicBool = AtomicBool::new(false);
+// Lock of logind: the suspend is inhibited until the fd is closed
+static INHIBITOR: Mutex<Option<OwnedFd>> = Mutex::const_new(None);
+
+/// Inhibit the suspend while playing, from `playback.inhibit_suspend`.
+pub(crate) fn init_inhibit(enabled: bool) {
+  ENABLED.store(enabled, Ordering::Relaxed);
+  tokio::spawn(apply());
+}
+
+/// Take the inhibitor when the playback starts, release it on pause and
+/// stop. The D-Bus calls run in the background.
+pub(crate) fn set_playing(playing: bool) {
+  PLAYING.store(playing, Ordering::Relaxed);
+  tokio::spawn(apply());
+}
+
+/// Take or release the inhibitor to follow the last state.
+async fn apply() {
+  let mut inhibitor = INHIBITOR.lock().await;
+  let wanted = ENABLED.load(Ordering::Relaxed) && PLAYING.load(Ordering::Relaxed);
+  match (wanted, inhibitor.is_some()) {
+    (true, false) => match inhibit().await {
+      Ok(fd) => *inhibitor = Some(fd),
+      Err(e) => warn!("Can't inhibit the suspend: {e}"),
+    },
+    (false, true) => *inhibitor = None,
+    _ => {}
+  }
+}
+
+#[instrument]
+async fn inhibit() -> zbus::Result<OwnedFd> {
+  let connection = Connection::system().await?;
+  let reply = connection
+    .call_method(
+      Some("org.freedesktop.login1"),
+      "/org/freedesktop/login1",
+      Some("org.freedesktop.login1.Manager"),
+      "Inhibit",
+      &("sleep:idle", "music-player", "Playing music", "block"),
+    )
+    .await?;
+  reply.body().deserialize()
+}
@@ -0,0 +1,385 @@
+use crate::{
+  commands::Track,
+  daemon,
+  player_state::{PlayerState, UiNotification},
+  rhythmdb::Entry,
+  settings::state_dir,
+  sources::track_from_location,
+};
+use directories::BaseDirs;
+use miette::{miette, IntoDiagnostic, Result};
+use mpris_server::PlayerInterface;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::{
+  fs,
+  ops::Deref,
+  os::unix::{fs::FileTypeExt, net},
+  path::PathBuf,
+};
+use tokio::{
+  io::{AsyncBufReadExt, AsyncWriteExt, BufReader},
+  net::{UnixListener, UnixStream},
+};
+use tracing::{error, instrument};
+use url::Url;
+
+// Error codes of JSON-RPC 2.0
+const PARSE_ERROR: i64 = -32700;
+const INVALID_REQUEST: i64 = -32600;
+const METHOD_NOT_FOUND: i64 = -32601;
+const INVALID_PARAMS: i64 = -32602;
+const SERVER_ERROR: i64 = -32000;
+
+/// Request sent to the running instance, by a new instance or a `ctl`
+/// command.
+#[derive(Debug)]
+pub(crate) enum Request {
+  Play(Url),
+  Enqueue(Url),
+  NowPlaying,
+  Queue,
+  PlayPause,
+  Pause,
+  Stop,
+  Next,
+  Previous,
+  // In seconds
+  Seek(u64),
+}
+
+/// JSON-RPC call, one per line of the socket.
+#[derive(Debug, Deserialize)]
+struct Call {
+  jsonrpc: String,
+  // The notifications have no id: they get no response
+  id: Option<Value>,
+  method: String,
+  #[serde(default)]
+  params: Value,
+}
+
+/// Error of a call, with its JSON-RPC code.
+type CallError = (i64, String);
+
+/// Track played by the running instance.
+#[derive(Debug, Default, Deserialize, Serialize)]
+pub(crate) struct NowPlaying {
+  pub(crate) title: String,
+  pub(crate) artist: String,
+  pub(crate) album: String,
+  pub(crate) status: String,
+  // In seconds
+  pub(crate) elapsed: u64,
+  pub(crate) duration: u64,
+}
+
+impl Request {
+  fn from_call(method: &str, params: &Value) -> std::result::Result<Request, CallError> {
+    let location = || {
+      params
+        .get("location")
+        .and_then(Value::as_str)
+        .and_then(|location| Url::parse(location).ok())
+        .ok_or_else(|| (INVALID_PARAMS, format!("'{method}' expects a location url")))
+    };
+    match method {
+      "play" => Ok(Request::Play(location()?)),
+      "enqueue" => Ok(Request::Enqueue(location()?)),
+      "now-playing" => Ok(Request::NowPlaying),
+      "queue" => Ok(Request::Queue),
+      "play-pause" => Ok(Request::PlayPause),
+      "pause" => Ok(Request::Pause),
+      "stop" => Ok(Request::Stop),
+      "next" => Ok(Request::Next),
+      "previous" => Ok(Request::Previous),
+      "seek" => params
+        .get("position")
+        .and_then(Value::as_u64)
+        .map(Request::Seek)
+        .ok_or_else(|| {
+          (
+            INVALID_PARAMS,
+            "'seek' expects a position in seconds".to_string(),
+          )
+        }),
+      _ => Err((METHOD_NOT_FOUND, format!("Unknown method: '{method}'"))),
+    }
+  }
+
+  fn method(&self) -> &'static str {
+    match self {
+      Request::Play(_) => "play",
+      Request::Enqueue(_) => "enqueue",
+      Request::NowPlaying => "now-playing",
+      Request::Queue => "queue",
+      Request::PlayPause => "play-pause",
+      Request::Pause => "pause",
+      Request::Stop => "stop",
+      Request::Next => "next",
+      Request::Previous => "previous",
+      Request::Seek(_) => "seek",
+    }
+  }
+
+  fn to_line(&self, id: usize) -> String {
+    let mut call = json!({ "jsonrpc": "2.0", "id": id, "method": self.method() });
+    match self {
+      Request::Play(location) | Request::Enqueue(location) => {
+        call["params"] = json!({ "location": location });
+      }
+      Request::Seek(position) => call["params"] = json!({ "position": position }),
+      _ => {}
+    }
+    format!("{call}\n")
+  }
+}
+
+/// Socket of the running instance: in the runtime directory, otherwise in the
+/// state directory, both private to the user.
+fn socket_path() -> Result<PathBuf> {
+  BaseDirs::new()
+    .and_then(|base_dir| base_dir.runtime_dir().map(PathBuf::from))
+    .or_else(state_dir)
+    .map(|directory| directory.join("music-player.sock"))
+    .ok_or_else(|| {
+      miette!(
+        help = "Set XDG_RUNTIME_DIR",
+        "No directory for the socket of the player"
+      )
+    })
+}
+
+/// Hand the requests over to the running instance. Returns false when no
+/// instance is running.
+#[instrument]
+pub(crate) async fn hand_off(requests: Vec<Request>) -> Result<bool> {
+  Ok(send(requests).await?.is_some())
+}
+
+/// The track played by the running instance, none when no instance is running.
+#[instrument]
+pub(crate) async fn now_playing() -> Result<Option<NowPlaying>> {
+  match send(vec![Request::NowPlaying]).await? {
+    Some(mut results) => Ok(Some(
+      serde_json::from_value(results.remove(0)).into_diagnostic()?,
+    )),
+    None => Ok(None),
+  }
+}
+
+/// Run the request in the running instance, with its result.
+#[instrument]
+pub(crate) async fn call(request: Request) -> Result<Value> {
+  let mut results = send(vec![request])
+    .await?
+    .ok_or_else(|| miette!("Music player is not running"))?;
+  Ok(results.remove(0))
+}
+
+/// Send the requests and wait for their results, in the same order. The
+/// first error is returned once all the requests have been run.
+async fn send(requests: Vec<Request>) -> Result<Option<Vec<Value>>> {
+  let Ok(stream) = UnixStream::connect(socket_path()?).await else {
+    return Ok(None);
+  };
+  let (reader, mut writer) = stream.into_split();
+  for (id, request) in requests.iter().enumerate() {
+    writer
+      .write_all(request.to_line(id).as_bytes())
+      .await
+      .into_diagnostic()?;
+  }
+  writer.shutdown().await.into_diagnostic()?;
+
+  let mut lines = BufReader::new(reader).lines();
+  let mut results = vec![Value::Null; requests.len()];
+  let mut first_error = None;
+  while let Some(line) = lines.next_line().await.into_diagnostic()? {
+    let mut response: Value = serde_json::from_str(&line).into_diagnostic()?;
+    if let Some(message) = response["error"]["message"].as_str() {
+      first_error.get_or_insert_with(|| miette!("{message}"));
+    } else if let Some(result) = response["id"]
+      .as_u64()
+      .and_then(|id| results.get_mut(id as usize))
+    {
+      *result = response["result"].take();
+    }
+  }
+  match first_error {
+    Some(e) => Err(e),
+    None => Ok(Some(results)),
+  }
+}
+
+/// Listen to the requests of the next instances, on the socket passed by
+/// systemd when it was activated.
+#[instrument(skip(player, activated))]
+pub(crate) fn listen(
+  player: &'static PlayerState,
+  activated: Option<net::UnixListener>,
+) -> Result<()> {
+  let listener = match activated {
+    Some(listener) => UnixListener::from_std(listener).into_diagnostic()?,
+    None => {
+      let path = socket_path()?;
+      if let Ok(metadata) = fs::symlink_metadata(&path) {
+        if !metadata.file_type().is_socket() {
+          return Err(miette!("{} is not a socket", path.display()));
+        }
+        if net::UnixStream::connect(&path).is_ok() {
+          return Err(miette!("Music player is already running"));
+        }
+        // No instance answered: the socket is left by a crashed player.
+        fs::remove_file(&path).into_diagnostic()?;
+      }
+      if let Some(directory) = path.parent() {
+        fs::create_dir_all(directory).into_diagnostic()?;
+      }
+      UnixListener::bind(&path).into_diagnostic()?
+    }
+  };
+  tokio::spawn(async move {
+    loop {
+      match listener.accept().await {
+        Ok((stream, _)) => {
+          tokio::spawn(serve(player, stream));
+        }
+        Err(e) => error!("Can't accept an instance: {e}"),
+      }
+    }
+  });
+  Ok(())
+}
+
+/// Remove the socket when the player exits, unless systemd listens on it.
+pub(crate) fn release() {
+  if daemon::is_activated() {
+    return;
+  }
+  if let Ok(path) = socket_path() {
+    let _ = fs::remove_file(path);
+  }
+}
+
+/// Answer the JSON-RPC calls of a connection, a response line per call.
+#[instrument(skip(player, stream))]
+async fn serve(player: &'static PlayerState, stream: UnixStream) {
+  let (reader, mut writer) = stream.into_split();
+  let mut lines = BufReader::new(reader).lines();
+  while let Ok(Some(line)) = lines.next_line().await {
+    daemon::touch();
+    let Some(response) = answer(player, &line).await else {
+      continue;
+    };
+    if let Err(e) = writer.write_all(format!("{response}\n").as_bytes()).await {
+      error!("Can't answer the instance: {e}");
+      return;
+    }
+  }
+}
+
+/// Response to a call, none for the notifications.
+async fn answer(player: &'static PlayerState, line: &str) -> Option<Value> {
+  let error = |id: Value, (code, message): CallError| json!({ "jsonrpc": "2.0", "id": id, "error": { "code": code, "message": message } });
+  let call: Call = match serde_json::from_str(line) {
+    Ok(call) => call,
+    Err(e) => return Some(error(Value::Null, (PARSE_ERROR, e.to_string()))),
+  };
+  let id = call.id.clone().unwrap_or_default();
+  if call.jsonrpc != "2.0" {
+    return Some(error(
+      id,
+      (INVALID_REQUEST, "Only JSON-RPC 2.0 is spoken".to_string()),
+    ));
+  }
+  let result = match Request::from_call(&call.method, &call.params) {
+    Ok(request) => handle_request(player, request)
+      .await
+      .map_err(|e| (SERVER_ERROR, e.to_string())),
+    Err(e) => Err(e),
+  };
+  call.id?;
+  Some(match result {
+    Ok(result) => json!({ "jsonrpc": "2.0", "id": id, "result": result }),
+    Err(e) => error(id, e),
+  })
+}
+
+/// Run the request, with its result.
+pub(crate) async fn handle_request(player: &'static PlayerState, request: Request) -> Result<Value> {
+  match request {
+    Request::Play(location) => {
+      // Keep the rating and the play count of the tracks of the library
+      let track = player
+        .get_db()
+        .await
+        .find_url(&location)
+        .unwrap_or_else(|| track_from_location(&location));
+      if !matches!(track.as_ref(), Entry::Song(_) | Entry::PodcastPost(_)) {
+        return Err(miette!(
+          "'{location}' is not a song nor a podcast episode: it can't be played"
+        ));
+      }
+      player.stop_track().await?;
+      player.play_track(track).await?;
+    }
+    Request::Enqueue(location) => {
+      if player.get_db().await.find_url(&location).is_none() {
+        return Err(miette!(
+          "'{location}' is not in the library: it can be played but not enqueued"
+        ));
+      }
+      // False when skipped, already queued
+      let skip_queued = player.get_playback().await.skip_queued;
+      let enqueued = player.get_mut_queue().await.enqueue(location, skip_queued);
+      return Ok(Value::Bool(enqueued));
+    }
+    Request::NowPlaying => {
+      return serde_json::to_value(now_playing_of(player).await?).into_diagnostic();
+    }
+    Request::Queue => {
+      let entries = player
+        .get_db()
+        .await
+        .to_entries(player.get_queue().await.deref());
+      let tracks: Vec<Track> = entries
+        .iter()
+        .map(|entry| Track::from(entry.as_ref()))
+        .collect();
+      return serde_json::to_value(tracks).into_diagnostic();
+    }
+    Request::PlayPause => player.play_pause().await.into_diagnostic()?,
+    Request::Pause => player.pause().await.into_diagnostic()?,
+    Request::Stop => player.stop().await.into_diagnostic()?,
+    Request::Next => player.next().await.into_diagnostic()?,
+    Request::Previous => player.previous().await.into_diagnostic()?,
+    Request::Seek(position) => player.track_seek(position).await?,
+  }
+  player.notify_ui(UiNotification::RebuildTable).await?;
+  Ok(Value::Null)
+}
+
+pub(crate) async fn now_playing_of(player: &'static PlayerState) -> Result<NowPlaying> {
+  use gstreamer::{prelude::ElementExt, State};
+  let Some(track) = player.get_track().await.clone() else {
+    return Ok(NowPlaying::default());
+  };
+  let status = match player
+    .get_pipeline()
+    .await
+    .map(|pipeline| pipeline.state(None).1)
+  {
+    Some(State::Playing) => "playing",
+    Some(State::Paused) => "paused",
+    _ => "stopped",
+  };
+  Ok(NowPlaying {
+    title: track.get_title().to_string(),
+    artist: track.get_artist().to_string(),
+    album: track.get_album().to_string(),
+    status: status.to_string(),
+    elapsed: player.track_position().await? / 1000,
+    duration: track.get_duration(),
+  })
+}
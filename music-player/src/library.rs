@@ -0,0 +1,284 @@
+#[cfg(feature = "beets")]
+use crate::beets;
+use crate::{
+  args::Library,
+  gstreamer::{analyze_bpm, analyze_gain, gstreamer_init},
+  mpd::import_mpd,
+  rhythmdb::{check_writable, Entry, Rhythmdb, SongEntry},
+  settings::Settings,
+  ui::{Order, OrderDir},
+};
+use id3::{frame::ExtendedText, ErrorKind, Tag, TagLike, Version};
+use miette::{IntoDiagnostic, Result};
+use std::{
+  collections::BTreeMap,
+  fs,
+  sync::Arc,
+  time::{SystemTime, UNIX_EPOCH},
+};
+use tracing::instrument;
+
+// Tracks analyzed between two saves of the db, to keep the work done when
+// the scan is interrupted
+const SAVE_EVERY: usize = 50;
+
+/// Run a library command.
+#[instrument(skip(config))]
+pub(crate) fn library(config: &Settings, command: &Library) -> Result<()> {
+  match command {
+    Library::ScanGain { force, write_tags } => scan_gain(config, *force, *write_tags),
+    Library::ScanBpm { force } => scan_bpm(config, *force),
+    Library::BackfillDates => backfill_dates(config),
+    Library::ImportMpd {
+      playlists,
+      music_directory,
+      database,
+    } => import_mpd(
+      config,
+      playlists.as_deref(),
+      music_directory.as_deref(),
+      database.as_deref(),
+    ),
+    #[cfg(feature = "beets")]
+    Library::SyncBeets { database } => beets::sync_beets(config, database.as_deref()),
+  }
+}
+
+/// Compute the track gains missing, then the gains of their albums.
+#[instrument(skip(config))]
+fn scan_gain(config: &Settings, force: bool, write_tags: bool) -> Result<()> {
+  check_writable()?;
+  gstreamer_init()?;
+  let mut db = Rhythmdb::load(config)?;
+
+  // The tracks of an album are in the same directory
+  let mut albums: BTreeMap<(String, String), Vec<SongEntry>> = BTreeMap::new();
+  for entry in db.filter_by_song("", Order::Default, OrderDir::Desc) {
+    if let Entry::Song(song) = entry.as_ref() {
+      if song.location.scheme() == "file" {
+        let mut directory = song.location.clone();
+        if let Ok(mut path) = directory.path_segments_mut() {
+          path.pop();
+        }
+        albums
+          .entry((song.album.clone(), directory.to_string()))
+          .or_default()
+          .push(song.clone());
+      }
+    }
+  }
+
+  let missing = |song: &SongEntry| force || song.track_gain.is_none();
+  let total = albums
+    .values()
+    .flatten()
+    .filter(|song| missing(song))
+    .count();
+  let (mut analyzed, mut failed, mut unsaved) = (0, 0, 0);
+  for ((album, _), mut songs) in albums {
+    let mut changed = false;
+    for song in songs.iter_mut().filter(|song| missing(song)) {
+      analyzed += 1;
+      println!("[{analyzed}/{total}] {} – {}", song.artist, song.title);
+      match analyze_gain(&song.location) {
+        Ok((gain, peak)) => {
+          song.track_gain = Some(gain);
+          song.track_peak = Some(peak);
+          changed = true;
+        }
+        Err(e) => {
+          eprintln!("{e}");
+          failed += 1;
+        }
+      }
+    }
+
+    let album_missing = songs.iter().any(|song| song.album_gain.is_none());
+    if !album.is_empty() && (changed || force || album_missing) {
+      if let Some((gain, peak)) = album_gain(&songs) {
+        for song in &mut songs {
+          song.album_gain = Some(gain);
+          song.album_peak = Some(peak);
+        }
+        changed = true;
+      }
+    }
+    if !changed {
+      continue;
+    }
+
+    for song in songs {
+      if write_tags {
+        if let Err(e) = write_gain_tags(&song) {
+          eprintln!("{}: {e}", song.location);
+        }
+      }
+      db.update_entry(Arc::new(Entry::Song(song)));
+      unsaved += 1;
+    }
+    if unsaved >= SAVE_EVERY {
+      db.save(config)?;
+      unsaved = 0;
+    }
+  }
+  db.save(config)?;
+  println!("{} tracks analyzed, {failed} failed", analyzed - failed);
+  Ok(())
+}
+
+/// Detect the tempo of the songs missing it, stored in `beats-per-minute`.
+#[instrument(skip(config))]
+fn scan_bpm(config: &Settings, force: bool) -> Result<()> {
+  check_writable()?;
+  gstreamer_init()?;
+  let mut db = Rhythmdb::load(config)?;
+
+  let songs: Vec<SongEntry> = db
+    .filter_by_song("", Order::Default, OrderDir::Desc)
+    .iter()
+    .filter_map(|entry| match entry.as_ref() {
+      Entry::Song(song) if song.location.scheme() == "file" && (force || song.bpm().is_none()) => {
+        Some(song.clone())
+      }
+      _ => None,
+    })
+    .collect();
+  let total = songs.len();
+  let (mut failed, mut unsaved) = (0, 0);
+  for (index, mut song) in songs.into_iter().enumerate() {
+    println!("[{}/{total}] {} – {}", index + 1, song.artist, song.title);
+    match analyze_bpm(&song.location) {
+      Ok(bpm) => {
+        song.beats_per_minute = Some(((bpm * 10.0).round() / 10.0).to_string());
+        db.update_entry(Arc::new(Entry::Song(song)));
+        unsaved += 1;
+      }
+      Err(e) => {
+        eprintln!("{e}");
+        failed += 1;
+      }
+    }
+    if unsaved >= SAVE_EVERY {
+      db.save(config)?;
+      unsaved = 0;
+    }
+  }
+  db.save(config)?;
+  println!("{} tracks analyzed, {failed} failed", total - failed);
+  Ok(())
+}
+
+/// Fill the dates left at 0 by the imports, from the files: the tracks
+/// sorted by date would be clustered at the start otherwise. A track never
+/// seen gets the creation date of its file, or its modification date.
+#[instrument(skip(config))]
+fn backfill_dates(config: &Settings) -> Result<()> {
+  check_writable()?;
+  let mut db = Rhythmdb::load(config)?;
+
+  let songs: Vec<SongEntry> = db
+    .filter_by_song("", Order::Default, OrderDir::Desc)
+    .iter()
+    .filter_map(|entry| match entry.as_ref() {
+      Entry::Song(song) if song.first_seen == 0 || song.mtime == 0 => Some(song.clone()),
+      _ => None,
+    })
+    .collect();
+  let mut filled = 0;
+  for mut song in songs {
+    let Some(metadata) = song
+      .location
+      .to_file_path()
+      .ok()
+      .and_then(|path| fs::metadata(path).ok())
+    else {
+      eprintln!("{}: not found", song.location);
+      continue;
+    };
+    let modified = metadata.modified().ok().and_then(unix_time);
+    if song.mtime == 0 {
+      song.mtime = modified.unwrap_or_default();
+    }
+    if song.first_seen == 0 {
+      song.first_seen = metadata
+        .created()
+        .ok()
+        .and_then(unix_time)
+        .or(modified)
+        .unwrap_or_default();
+    }
+    db.update_entry(Arc::new(Entry::Song(song)));
+    filled += 1;
+  }
+  db.save(config)?;
+  println!("{filled} tracks filled");
+  Ok(())
+}
+
+fn unix_time(time: SystemTime) -> Option<u64> {
+  time
+    .duration_since(UNIX_EPOCH)
+    .ok()
+    .map(|since| since.as_secs())
+}
+
+/// Gain and peak of the album, estimated from its tracks: their loudness is
+/// averaged by power, weighted by duration. None while a track has no gain.
+fn album_gain(songs: &[SongEntry]) -> Option<(f64, f64)> {
+  let (mut power, mut duration, mut peak) = (0.0, 0.0, 0.0_f64);
+  for song in songs {
+    let weight = song.duration.unwrap_or_default().max(1) as f64;
+    power += weight * 10_f64.powf(-song.track_gain? / 10.0);
+    duration += weight;
+    peak = peak.max(song.track_peak?);
+  }
+  (duration > 0.0).then(|| (-10.0 * (power / duration).log10(), peak))
+}
+
+/// Write the gains in the `TXXX` frames read by the players applying
+/// ReplayGain. Only the mp3 files are tagged.
+#[instrument(skip(song))]
+fn write_gain_tags(song: &SongEntry) -> Result<()> {
+  let Ok(path) = song.location.to_file_path() else {
+    return Ok(());
+  };
+  let is_mp3 = path
+    .extension()
+    .is_some_and(|extension| extension.eq_ignore_ascii_case("mp3"));
+  if !is_mp3 {
+    return Ok(());
+  }
+
+  let mut tag = match Tag::read_from_path(&path) {
+    Ok(tag) => tag,
+    Err(e) if matches!(e.kind, ErrorKind::NoTag) => Tag::new(),
+    Err(e) => return Err(e).into_diagnostic(),
+  };
+  let frames = [
+    (
+      "REPLAYGAIN_TRACK_GAIN",
+      song.track_gain.map(|gain| format!("{gain:.2} dB")),
+    ),
+    (
+      "REPLAYGAIN_TRACK_PEAK",
+      song.track_peak.map(|peak| format!("{peak:.6}")),
+    ),
+    (
+      "REPLAYGAIN_ALBUM_GAIN",
+      song.album_gain.map(|gain| format!("{gain:.2} dB")),
+    ),
+    (
+      "REPLAYGAIN_ALBUM_PEAK",
+      song.album_peak.map(|peak| format!("{peak:.6}")),
+    ),
+  ];
+  for (description, value) in frames {
+    if let Some(value) = value {
+      tag.add_frame(ExtendedText {
+        description: description.to_string(),
+        value,
+      });
+    }
+  }
+  tag.write_to_path(&path, Version::Id3v24).into_diagnostic()
+}
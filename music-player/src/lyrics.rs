@@ -0,0 +1,136 @@
+use std::{fs, time::Duration};
+use tracing::instrument;
+use url::Url;
+
+/// Lyrics of a track. Synced lyrics come from `.lrc` files and carry the
+/// timestamp of each line.
+#[derive(Debug, Clone)]
+pub(crate) enum Lyrics {
+  Synced(Vec<(Duration, String)>),
+  Plain(Vec<String>),
+}
+
+impl Lyrics {
+  /// Look for a `.lrc` then a `.txt` file next to the track.
+  #[instrument]
+  pub(crate) fn load(location: &Url) -> Option<Lyrics> {
+    let path = location.to_file_path().ok()?;
+    if let Ok(content) = fs::read_to_string(path.with_extension("lrc")) {
+      return Some(Lyrics::parse(&content));
+    }
+    if let Ok(content) = fs::read_to_string(path.with_extension("txt")) {
+      return Some(Lyrics::Plain(content.lines().map(String::from).collect()));
+    }
+    None
+  }
+
+  /// Parse a `.lrc` file. Fallback to plain lyrics if no line is timestamped.
+  pub(crate) fn parse(content: &str) -> Lyrics {
+    let mut synced = vec![];
+    for line in content.lines() {
+      let mut text = line.trim();
+      let mut timestamps = vec![];
+      // A line may have several timestamps: `[00:12.00][01:30.50]Chorus`
+      while let Some(stripped) = text.strip_prefix('[') {
+        let Some((tag, rest)) = stripped.split_once(']') else {
+          break;
+        };
+        // Metadata tags like `[ar:Artist]` are not timestamps.
+        let Some(timestamp) = parse_timestamp(tag) else {
+          break;
+        };
+        timestamps.push(timestamp);
+        text = rest;
+      }
+      for timestamp in timestamps {
+        synced.push((timestamp, text.trim().to_string()));
+      }
+    }
+
+    if synced.is_empty() {
+      Lyrics::Plain(content.lines().map(String::from).collect())
+    } else {
+      synced.sort_by_key(|(timestamp, _)| *timestamp);
+      Lyrics::Synced(synced)
+    }
+  }
+
+  /// Index of the line sung at `position`.
+  pub(crate) fn current_line(&self, position: Duration) -> Option<usize> {
+    match self {
+      Lyrics::Synced(lines) => lines
+        .iter()
+        .rposition(|(timestamp, _)| *timestamp <= position),
+      Lyrics::Plain(_) => None,
+    }
+  }
+
+  pub(crate) fn lines(&self) -> Vec<&str> {
+    match self {
+      Lyrics::Synced(lines) => lines.iter().map(|(_, line)| line.as_str()).collect(),
+      Lyrics::Plain(lines) => lines.iter().map(String::as_str).collect(),
+    }
+  }
+}
+
+/// `mm:ss.xx` to a duration.
+fn parse_timestamp(tag: &str) -> Option<Duration> {
+  let (minutes, seconds) = tag.split_once(':')?;
+  let minutes: u64 = minutes.trim().parse().ok()?;
+  let seconds: f64 = seconds.trim().parse().ok()?;
+  if seconds < 0.0 {
+    return None;
+  }
+  Some(Duration::from_secs(minutes * 60) + Duration::from_secs_f64(seconds))
+}
+
+/// Fetch the lyrics from [LRCLIB](https://lrclib.net).
+#[cfg(feature = "lrclib")]
+#[instrument(skip(entry))]
+pub(crate) async fn fetch(entry: &crate::rhythmdb::Entry) -> miette::Result<Option<Lyrics>> {
+  use crate::rhythmdb::Entry;
+  use miette::IntoDiagnostic;
+  use serde::Deserialize;
+
+  #[derive(Deserialize)]
+  #[serde(rename_all = "camelCase")]
+  struct LrclibLyrics {
+    synced_lyrics: Option<String>,
+    plain_lyrics: Option<String>,
+  }
+
+  let (title, artist, album) = match entry {
+    Entry::Song(song) => (&song.title, &song.artist, &song.album),
+    _ => return Ok(None),
+  };
+  let response = reqwest::Client::new()
+    .get("https://lrclib.net/api/get")
+    .header(
+      "User-Agent",
+      concat!("music-player/", env!("CARGO_PKG_VERSION")),
+    )
+    .query(&[
+      ("track_name", title.as_str()),
+      ("artist_name", artist.as_str()),
+      ("album_name", album.as_str()),
+      ("duration", &entry.get_duration().to_string()),
+    ])
+    .send()
+    .await
+    .into_diagnostic()?;
+  if response.status() == reqwest::StatusCode::NOT_FOUND {
+    return Ok(None);
+  }
+  let lyrics: LrclibLyrics = response
+    .error_for_status()
+    .into_diagnostic()?
+    .json()
+    .await
+    .into_diagnostic()?;
+
+  Ok(match (lyrics.synced_lyrics, lyrics.plain_lyrics) {
+    (Some(synced), _) => Some(Lyrics::parse(&synced)),
+    (None, Some(plain)) => Some(Lyrics::Plain(plain.lines().map(String::from).collect())),
+    (None, None) => None,
+  })
+}
@@ -1,31 +1,57 @@
 mod args;
+#[cfg(feature = "beets")]
+mod beets;
+mod commands;
+mod cover;
+mod daemon;
+mod flags;
 mod gstreamer;
+mod history;
+mod hooks;
+mod inhibit;
+mod instance;
+mod library;
+mod lyrics;
+mod media_keys;
+#[cfg(feature = "podcast")]
+mod metered;
+mod metrics;
+mod mpd;
 mod mplayer;
+mod notifications;
 mod player_state;
 mod playlists;
+#[cfg(feature = "podcast")]
+mod podcast;
+#[cfg(feature = "remote")]
+mod remote;
+mod resume;
 mod rhythmdb;
 mod settings;
+mod sources;
+mod suggestions;
 mod trace;
 mod ui;
 
 use crate::{
   args::{gen_completions, App, Commands},
-  gstreamer::{gstreamer_init, start_playing},
-  player_state::PlayerState,
+  gstreamer::{gstreamer_init, pause, start_playing},
+  player_state::{PlayerState, ScopeKind},
   rhythmdb::Rhythmdb,
 };
 use args::Config;
-use clap::{CommandFactory, Parser};
+use clap::{CommandFactory, FromArgMatches};
 use if_chain::if_chain;
+use instance::Request;
 use miette::{miette, IntoDiagnostic, Result};
 use mpris_server::Server;
 use playlists::Playlist;
-use rhythmdb::{Entry, SongEntry};
-use settings::{settings, PlayerStateSetting};
-use std::sync::Arc;
+use rhythmdb::{Entry, EntryList};
+use settings::{get_setting, init_state_dir, set_setting, settings, PlayerStateSetting, UiSession};
+use sources::{collect_locations, read_stdin, track_from_location};
+use std::{sync::Arc, time::Duration};
 use tokio::sync::OnceCell;
-use trace::init_tracing;
-use url::Url;
+use trace::{init_tracing, shutdown_tracing};
 
 // One singletton to rule them all!
 static MPRIS: OnceCell<Server<PlayerState>> = OnceCell::const_new();
@@ -43,11 +69,52 @@ pub(crate) async fn get_mpris_server() -> Result<&'static Server<PlayerState>> {
 
 #[tokio::main]
 async fn main() -> Result<()> {
-  init_tracing()?;
-  let args = App::parse();
-  let config = settings(&App::command().get_matches())?;
+  // Parsed once: the settings are reloaded from the same arguments
+  let matches = App::command().get_matches();
+  let args = App::from_arg_matches(&matches).unwrap_or_else(|e| e.exit());
+  let config = settings(&matches)?;
+  init_tracing(&config, args.log_level.as_deref())?;
+  init_state_dir(&config)?;
+  flags::load_flags();
+  hooks::init_hooks(&config.hooks);
   gen_completions(&args);
 
+  if let Some(Commands::Search(search)) = &args.command {
+    commands::search(&config, search)?;
+    std::process::exit(0);
+  }
+
+  if let Some(Commands::Stats(stats)) = &args.command {
+    commands::stats(&config, stats)?;
+    std::process::exit(0);
+  }
+
+  if let Some(Commands::Bench(bench)) = &args.command {
+    commands::bench(&config, bench)?;
+    std::process::exit(0);
+  }
+
+  if let Some(Commands::History(command)) = &args.command {
+    history::history(command)?;
+    std::process::exit(0);
+  }
+
+  if let Some(Commands::Library(command)) = &args.command {
+    library::library(&config, command)?;
+    std::process::exit(0);
+  }
+
+  #[cfg(feature = "podcast")]
+  if let Some(Commands::Podcast(command)) = &args.command {
+    podcast::podcast(&config, command).await?;
+    std::process::exit(0);
+  }
+
+  if let Some(Commands::Ctl(command)) = &args.command {
+    commands::ctl(&config, command).await?;
+    std::process::exit(0);
+  }
+
   if let Some(Commands::Config(c)) = &args.command {
     match c {
       Config::Show => {
@@ -58,6 +125,7 @@ async fn main() -> Result<()> {
       Config::Clean(args) => {
         if args.main {
           PlayerStateSetting::clean()?;
+          UiSession::clean()?;
         }
         if args.playlist {
           Playlist::clean()?;
@@ -71,15 +139,52 @@ async fn main() -> Result<()> {
         Rhythmdb::show_ignored_entries(&config)?;
         std::process::exit(0);
       }
+      Config::Get { key } => {
+        get_setting(key)?;
+        std::process::exit(0);
+      }
+      Config::Set { key, value } => {
+        set_setting(key, value)?;
+        std::process::exit(0);
+      }
     }
   }
 
+  let mut sources = args.files.clone();
+  if args.stdin {
+    sources.extend(read_stdin()?);
+  }
+  let locations = collect_locations(&sources, &config.library_roots)?;
+
+  // A single player runs at a time: the tracks are handed over to it. The
+  // first one is played unless they are all enqueued.
+  let requests = locations
+    .iter()
+    .enumerate()
+    .map(|(index, location)| {
+      if args.enqueue || index > 0 {
+        Request::Enqueue(location.clone())
+      } else {
+        Request::Play(location.clone())
+      }
+    })
+    .collect();
+  // Started by systemd on its socket: no other instance to hand over to
+  let activated = daemon::activated_listener();
+  if activated.is_none() && instance::hand_off(requests).await? {
+    if locations.is_empty() {
+      return Err(miette!("Music player is already running"));
+    }
+    std::process::exit(0);
+  }
+
   let db = Rhythmdb::load(&config)?;
 
   // Init the app component: gstreamer and mpris protocol
   gstreamer_init()?;
   let mpris_server = get_mpris_server().await?;
   let player_app = mpris_server.imp();
+  player_app.apply_settings(&config).await;
 
   if let Ok(q) = Playlist::load() {
     player_app.set_queue(q).await;
@@ -94,29 +199,66 @@ async fn main() -> Result<()> {
       player_app.set_repeat_mode(repeat).await;
     }
   }
+  // The flags override the saved modes
+  if let Some(shuffle) = args.shuffle {
+    player_app.set_shuffle_mode(shuffle).await;
+  }
+  if let Some(repeat) = args.repeat {
+    player_app.set_repeat_mode(repeat).await;
+  }
 
   // Find the track to play on startup
   let mut start_index = 0;
   let track_list = db.filter_by_song("", ui::Order::Default, ui::OrderDir::Desc);
-  // Play the track from the cli args
-  if let Some(file) = args.file {
-    let mut track = if let Ok(tag) = id3::Tag::read_from_path(&file) {
-      SongEntry::from(tag)
-    } else {
-      SongEntry::default()
-    };
-    track.location =
-      Url::from_file_path(&file).map_err(|_| miette!("Can't parse file path: '{file}'"))?;
-    player_app.play_track(Arc::new(Entry::Song(track))).await?;
+  // Play the tracks from the cli args as a playlist
+  if !locations.is_empty() {
+    let tracks: EntryList = locations
+      .iter()
+      .map(|location| {
+        db.find_url(location)
+          .unwrap_or_else(|| track_from_location(location))
+      })
+      .collect();
+    player_app.play_track(tracks[0].clone()).await?;
+    player_app
+      .set_scope(ScopeKind::Playlist, tracks.clone())
+      .await;
+    player_app.set_playlist(tracks).await;
   } else if !track_list.is_empty() {
-    // Try to play the saved file or a random one.
-    start_index = player_saved_track(player_app, &db, &track_list).await?;
+    // Try to play the saved file or a random one. The ui opens idle when it
+    // can't be played.
+    start_index = player_saved_track(player_app, &db, &track_list)
+      .await
+      .unwrap_or_else(|e| {
+        tracing::warn!("Can't play the first track: {e}");
+        0
+      });
+  }
+
+  // A daemon waits for a command: the saved track is only loaded
+  if args.start_paused || args.daemon {
+    if let Some(pipeline) = player_app.get_pipeline().await {
+      pause(&pipeline)?;
+      player_app.set_listening(false).await;
+    }
   }
 
   player_app.set_db(db).await;
 
-  ui::ui(start_index, &config).await?;
-  Ok(())
+  instance::listen(player_app, activated)?;
+  #[cfg(feature = "remote")]
+  if let Some(remote) = &config.remote {
+    remote::listen(player_app, remote).await?;
+  }
+  let result = if args.daemon {
+    daemon::defer_name().await;
+    daemon::run(player_app, &config, matches).await
+  } else {
+    ui::ui(start_index, &config, matches).await
+  };
+  instance::release();
+  shutdown_tracing();
+  result
 }
 
 async fn play_saved_file(
@@ -126,14 +268,31 @@ async fn play_saved_file(
   track: Arc<Entry>,
 ) -> Result<usize> {
   player_app.set_playlist(track_list.to_vec()).await;
+  player_app
+    .set_scope(ScopeKind::Library, track_list.to_vec())
+    .await;
   let start_index: usize = player_app.find_track_index(&track).await.unwrap_or(0);
-  player_app.play_track(track).await?;
+  // The track is found at its saved place in the queue, when it is queued
+  // twice
+  *player_app.queue_position.write().await = saved_track_and_position.queue_position;
+  player_app.play_track(track.clone()).await?;
   if let Some(position) = saved_track_and_position.position {
     if let Some(pipeline) = player_app.get_pipeline().await {
       use ::gstreamer::{prelude::ElementExt, State};
       let (_, state, _) = pipeline.state(None);
       if state == State::Playing || state == State::Paused {
-        player_app.track_seek(position / 1000).await?;
+        // Closed since then: a podcast is rewound
+        let closed_for = saved_track_and_position
+          .saved_at
+          .map(|saved_at| (chrono::Local::now().timestamp() as u64).saturating_sub(saved_at))
+          .unwrap_or_default();
+        let rewind = resume::rewind(
+          &player_app.get_playback().await,
+          &track,
+          Duration::from_secs(closed_for),
+        );
+        let position = Duration::from_millis(position).saturating_sub(rewind);
+        player_app.track_seek(position.as_secs()).await?;
       }
     }
   }
@@ -155,8 +314,9 @@ async fn player_saved_track(
           start_index= play_saved_file(player_app, &saved_track_and_position, track_list, track).await?;
       }else {
 	  let (track,_)= PlayerState::choose_track(track_list)?;
-	  player_app.play_track(track).await?;
           player_app.set_playlist(track_list.to_vec()).await;
+          player_app.set_scope(ScopeKind::Library, track_list.to_vec()).await;
+	  player_app.play_track(track).await?;
       }
   }
   Ok(start_index)
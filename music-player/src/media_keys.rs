@@ -0,0 +1,56 @@
+use crate::player_state::PlayerState;
+use futures::StreamExt;
+use mpris_server::{
+  zbus::{self, Connection, Proxy},
+  PlayerInterface,
+};
+use tracing::{debug, instrument, warn};
+
+const APPLICATION: &str = "music-player";
+
+/// Handle the media keys grabbed from gnome-settings-daemon, when no daemon
+/// forwards them to the MPRIS players. From `playback.media_keys`.
+pub(crate) fn grab_media_keys(player: &'static PlayerState) {
+  tokio::spawn(async move {
+    if let Err(e) = listen(player).await {
+      warn!("Can't grab the media keys: {e}");
+    }
+  });
+}
+
+#[instrument(skip(player))]
+async fn listen(player: &'static PlayerState) -> zbus::Result<()> {
+  let connection = Connection::session().await?;
+  let media_keys = Proxy::new(
+    &connection,
+    "org.gnome.SettingsDaemon.MediaKeys",
+    "/org/gnome/SettingsDaemon/MediaKeys",
+    "org.gnome.SettingsDaemon.MediaKeys",
+  )
+  .await?;
+  let mut pressed = media_keys.receive_signal("MediaPlayerKeyPressed").await?;
+  media_keys
+    .call::<_, _, ()>("GrabMediaPlayerKeys", &(APPLICATION, 0_u32))
+    .await?;
+
+  while let Some(message) = pressed.next().await {
+    let (application, key): (String, String) = message.body().deserialize()?;
+    if application != APPLICATION {
+      continue;
+    }
+    let result = match key.as_str() {
+      "Play" => player.play_pause().await,
+      "Pause" => player.pause().await,
+      "Stop" => player.stop().await,
+      "Next" => player.next().await,
+      _ => {
+        debug!("Media key {key} ignored");
+        Ok(())
+      }
+    };
+    if let Err(e) = result {
+      warn!("{e}");
+    }
+  }
+  Ok(())
+}
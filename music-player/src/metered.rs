@@ -0,0 +1,51 @@
+use miette::{IntoDiagnostic, Result};
+use mpris_server::zbus::{Connection, Proxy};
+use std::time::Duration;
+use tokio::time::sleep;
+use tracing::{debug, instrument};
+
+// Period of the checks while waiting for an unmetered network
+const CHECK_EVERY: Duration = Duration::from_secs(60);
+
+// `NMMetered` values of NetworkManager: metered for sure, or guessed
+const METERED_YES: u32 = 1;
+const METERED_GUESS_YES: u32 = 3;
+
+/// The connection of NetworkManager is metered, like a mobile hotspot.
+/// Without NetworkManager, it is not.
+#[instrument]
+pub(crate) async fn is_metered() -> bool {
+  match metered().await {
+    Ok(metered) => matches!(metered, METERED_YES | METERED_GUESS_YES),
+    Err(e) => {
+      debug!("Can't read the metered state of NetworkManager: {e}");
+      false
+    }
+  }
+}
+
+async fn metered() -> Result<u32> {
+  let connection = Connection::system().await.into_diagnostic()?;
+  let proxy = Proxy::new(
+    &connection,
+    "org.freedesktop.NetworkManager",
+    "/org/freedesktop/NetworkManager",
+    "org.freedesktop.NetworkManager",
+  )
+  .await
+  .into_diagnostic()?;
+  proxy.get_property("Metered").await.into_diagnostic()
+}
+
+/// Defer the downloads while the connection is metered, unless
+/// `allow_metered`.
+#[instrument]
+pub(crate) async fn wait_unmetered(allow_metered: bool) {
+  if allow_metered || !is_metered().await {
+    return;
+  }
+  eprintln!("Metered connection: waiting for an unmetered network, --allow-metered to go on");
+  while is_metered().await {
+    sleep(CHECK_EVERY).await;
+  }
+}
@@ -0,0 +1,141 @@
+use std::{
+  sync::atomic::{AtomicU64, Ordering},
+  time::{Duration, Instant},
+};
+use tokio::{
+  io::{AsyncReadExt, AsyncWriteExt},
+  net::{TcpListener, TcpStream},
+};
+use tracing::{instrument, warn};
+
+static TRACKS_PLAYED: AtomicU64 = AtomicU64::new(0);
+static PLAYBACK_ERRORS: AtomicU64 = AtomicU64::new(0);
+// Durations in microseconds
+static SAVES: AtomicU64 = AtomicU64::new(0);
+static SAVE_MICROS: AtomicU64 = AtomicU64::new(0);
+static SEARCHES: AtomicU64 = AtomicU64::new(0);
+static SEARCH_MICROS: AtomicU64 = AtomicU64::new(0);
+
+pub(crate) fn track_played() {
+  TRACKS_PLAYED.fetch_add(1, Ordering::Relaxed);
+}
+
+pub(crate) fn playback_error() {
+  PLAYBACK_ERRORS.fetch_add(1, Ordering::Relaxed);
+}
+
+pub(crate) fn db_saved(elapsed: Duration) {
+  SAVES.fetch_add(1, Ordering::Relaxed);
+  SAVE_MICROS.fetch_add(elapsed.as_micros() as u64, Ordering::Relaxed);
+}
+
+pub(crate) fn searched(elapsed: Duration) {
+  SEARCHES.fetch_add(1, Ordering::Relaxed);
+  SEARCH_MICROS.fetch_add(elapsed.as_micros() as u64, Ordering::Relaxed);
+}
+
+/// Measure until dropped, then pass the duration to `record`.
+pub(crate) struct Timer {
+  started: Instant,
+  record: fn(Duration),
+}
+
+impl Timer {
+  pub(crate) fn start(record: fn(Duration)) -> Timer {
+    Timer {
+      started: Instant::now(),
+      record,
+    }
+  }
+}
+
+impl Drop for Timer {
+  fn drop(&mut self) {
+    (self.record)(self.started.elapsed());
+  }
+}
+
+/// Serve the metrics to Prometheus on `/metrics`, from `daemon.metrics`.
+pub(crate) fn serve(address: String) {
+  tokio::spawn(async move {
+    let listener = match TcpListener::bind(&address).await {
+      Ok(listener) => listener,
+      Err(e) => {
+        warn!("Can't serve the metrics on {address}: {e}");
+        return;
+      }
+    };
+    loop {
+      match listener.accept().await {
+        Ok((stream, _)) => {
+          tokio::spawn(answer(stream));
+        }
+        Err(e) => warn!("Can't accept a metrics request: {e}"),
+      }
+    }
+  });
+}
+
+#[instrument(skip(stream))]
+async fn answer(mut stream: TcpStream) {
+  // Only the request line matters
+  let mut request = [0; 1024];
+  let Ok(read) = stream.read(&mut request).await else {
+    return;
+  };
+  let request = String::from_utf8_lossy(&request[..read]);
+  let response = if request.starts_with("GET /metrics ") {
+    let body = render();
+    format!(
+      "HTTP/1.1 200 OK\r\nContent-Type: text/plain; version=0.0.4\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{body}",
+      body.len()
+    )
+  } else {
+    "HTTP/1.1 404 Not Found\r\nContent-Length: 0\r\nConnection: close\r\n\r\n".to_string()
+  };
+  if let Err(e) = stream.write_all(response.as_bytes()).await {
+    warn!("Can't answer the metrics request: {e}");
+  }
+}
+
+/// Metrics in the text format of Prometheus.
+fn render() -> String {
+  let counter = |name: &str, help: &str, value: &AtomicU64| {
+    format!(
+      "# HELP {name} {help}\n# TYPE {name} counter\n{name} {}\n",
+      value.load(Ordering::Relaxed)
+    )
+  };
+  let summary = |name: &str, help: &str, count: &AtomicU64, micros: &AtomicU64| {
+    format!(
+      "# HELP {name} {help}\n# TYPE {name} summary\n{name}_sum {}\n{name}_count {}\n",
+      micros.load(Ordering::Relaxed) as f64 / 1_000_000.0,
+      count.load(Ordering::Relaxed)
+    )
+  };
+  [
+    counter(
+      "music_player_tracks_played_total",
+      "Tracks listened to long enough to count as a play.",
+      &TRACKS_PLAYED,
+    ),
+    counter(
+      "music_player_playback_errors_total",
+      "Errors of GStreamer while playing.",
+      &PLAYBACK_ERRORS,
+    ),
+    summary(
+      "music_player_db_save_seconds",
+      "Duration of the saves of the db.",
+      &SAVES,
+      &SAVE_MICROS,
+    ),
+    summary(
+      "music_player_search_seconds",
+      "Duration of the searches in the db.",
+      &SEARCHES,
+      &SEARCH_MICROS,
+    ),
+  ]
+  .concat()
+}
@@ -0,0 +1,270 @@
+use crate::{
+  playlists::StaticPlaylist,
+  rhythmdb::{check_writable, Rhythmdb},
+  settings::{expand_path, Settings},
+  ui::{Order, OrderDir},
+};
+use directories::BaseDirs;
+use miette::{miette, IntoDiagnostic, Result};
+use std::{
+  collections::HashMap,
+  fs,
+  path::{Path, PathBuf},
+  process::Command,
+};
+use tracing::{debug, instrument};
+use url::Url;
+
+/// Paths set in `mpd.conf`.
+#[derive(Debug, Default)]
+struct MpdConfig {
+  music_directory: Option<PathBuf>,
+  playlist_directory: Option<PathBuf>,
+  db_file: Option<PathBuf>,
+}
+
+/// Tracks of the Rhythmbox db, by path, file name and tags.
+struct Library {
+  by_path: HashMap<PathBuf, Url>,
+  by_name: HashMap<String, Vec<(PathBuf, Url)>>,
+  by_tags: HashMap<(String, String), Url>,
+}
+
+/// Add the `.m3u` playlists of MPD to the static playlists of Rhythmbox.
+/// Their entries relative to the music directory are found by path, else by
+/// the end of their path, else by their artist and title in the MPD db.
+#[instrument(skip(config))]
+pub(crate) fn import_mpd(
+  config: &Settings,
+  playlists: Option<&Path>,
+  music_directory: Option<&Path>,
+  database: Option<&Path>,
+) -> Result<()> {
+  check_writable()?;
+  let mpd_config = read_config();
+  let playlists = playlists
+    .map(Path::to_path_buf)
+    .or(mpd_config.playlist_directory)
+    .ok_or_else(|| {
+      miette!(
+        help = "Set it with `--playlists`",
+        "No playlist directory in mpd.conf"
+      )
+    })?;
+  let music_directory = music_directory
+    .map(Path::to_path_buf)
+    .or(mpd_config.music_directory);
+  let tags = match database {
+    Some(database) => read_database(database)?,
+    None => mpd_config
+      .db_file
+      .and_then(|database| {
+        read_database(&database)
+          .inspect_err(|e| eprintln!("{e}"))
+          .ok()
+      })
+      .unwrap_or_default(),
+  };
+
+  let db = Rhythmdb::load(config)?;
+  let library = Library::new(&db);
+  let mut files: Vec<PathBuf> = fs::read_dir(&playlists)
+    .into_diagnostic()?
+    .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+    .filter(|path| path.extension().is_some_and(|extension| extension == "m3u"))
+    .collect();
+  files.sort();
+
+  let mut imported = 0;
+  for file in files {
+    let Some(name) = file.file_stem().map(|name| name.to_string_lossy()) else {
+      continue;
+    };
+    let content = fs::read_to_string(&file).into_diagnostic()?;
+    let mut locations = vec![];
+    let mut missing = 0;
+    for line in content
+      .lines()
+      .map(str::trim)
+      .filter(|line| !line.is_empty() && !line.starts_with('#'))
+    {
+      match library.find(line, music_directory.as_deref(), &tags) {
+        Some(location) => locations.push(location),
+        None => {
+          eprintln!("{name}: `{line}` not found");
+          missing += 1;
+        }
+      }
+    }
+    if locations.is_empty() {
+      eprintln!("{name}: no track found, skipped");
+      continue;
+    }
+    let count = locations.len();
+    match StaticPlaylist::new(name.to_string(), locations).save_in(&config.playlist_path) {
+      Ok(()) => {
+        println!("{name}: {count} tracks, {missing} not found");
+        imported += 1;
+      }
+      Err(e) => eprintln!("{name}: {e}"),
+    }
+  }
+  println!("{imported} playlists imported");
+  Ok(())
+}
+
+impl Library {
+  fn new(db: &Rhythmdb) -> Library {
+    let mut library = Library {
+      by_path: HashMap::new(),
+      by_name: HashMap::new(),
+      by_tags: HashMap::new(),
+    };
+    for entry in db.filter_by_song("", Order::Default, OrderDir::Desc) {
+      let location = entry.get_location();
+      library.by_tags.insert(
+        (
+          entry.get_artist().to_lowercase(),
+          entry.get_title().to_lowercase(),
+        ),
+        location.clone(),
+      );
+      let Ok(path) = location.to_file_path() else {
+        continue;
+      };
+      if let Some(name) = path.file_name() {
+        library
+          .by_name
+          .entry(name.to_string_lossy().into_owned())
+          .or_default()
+          .push((path.clone(), location.clone()));
+      }
+      library.by_path.insert(path, location);
+    }
+    library
+  }
+
+  /// Location of the entry of a playlist: a URL, an absolute path or a path
+  /// relative to the music directory.
+  fn find(
+    &self,
+    line: &str,
+    music_directory: Option<&Path>,
+    tags: &HashMap<String, (String, String)>,
+  ) -> Option<Url> {
+    if line.contains("://") {
+      let url = Url::parse(line).ok()?;
+      return match url.to_file_path() {
+        Ok(path) => self.by_path.get(&path).cloned(),
+        Err(()) => Some(url),
+      };
+    }
+    let path = Path::new(line);
+    if path.is_absolute() {
+      return self.by_path.get(path).cloned();
+    }
+    music_directory
+      .and_then(|directory| self.by_path.get(&directory.join(path)))
+      .or_else(|| {
+        // The music directory is elsewhere, or moved since
+        let name = path.file_name()?.to_string_lossy();
+        self
+          .by_name
+          .get(name.as_ref())?
+          .iter()
+          .find(|(candidate, _)| candidate.ends_with(path))
+          .map(|(_, location)| location)
+      })
+      .or_else(|| self.by_tags.get(tags.get(line)?))
+      .cloned()
+  }
+}
+
+/// Read the first `mpd.conf` found, where MPD looks for it.
+#[instrument]
+fn read_config() -> MpdConfig {
+  let mut paths = vec![];
+  if let Some(base_dir) = BaseDirs::new() {
+    paths.push(base_dir.config_dir().join("mpd/mpd.conf"));
+    paths.push(base_dir.home_dir().join(".mpdconf"));
+    paths.push(base_dir.home_dir().join(".mpd/mpd.conf"));
+  }
+  paths.push(PathBuf::from("/etc/mpd.conf"));
+  let Some(content) = paths.iter().find_map(|path| fs::read_to_string(path).ok()) else {
+    debug!("No mpd.conf found");
+    return MpdConfig::default();
+  };
+
+  let mut config = MpdConfig::default();
+  for line in content.lines() {
+    let Some((key, value)) = line.trim().split_once(char::is_whitespace) else {
+      continue;
+    };
+    let value = value.trim().trim_matches('"');
+    let field = match key {
+      "music_directory" => &mut config.music_directory,
+      "playlist_directory" => &mut config.playlist_directory,
+      "db_file" => &mut config.db_file,
+      _ => continue,
+    };
+    *field = expand_path(value).ok();
+  }
+  config
+}
+
+/// Artist and title of the songs of the MPD db, by path relative to the
+/// music directory. A compressed db is read through `gzip`.
+#[instrument]
+fn read_database(path: &Path) -> Result<HashMap<String, (String, String)>> {
+  let bytes = fs::read(path).into_diagnostic()?;
+  let bytes = if bytes.starts_with(&[0x1f, 0x8b]) {
+    let output = Command::new("gzip")
+      .arg("-dc")
+      .arg(path)
+      .output()
+      .into_diagnostic()?;
+    if !output.status.success() {
+      return Err(miette!(
+        "Can't uncompress `{}`: {}",
+        path.display(),
+        String::from_utf8_lossy(&output.stderr).trim()
+      ));
+    }
+    output.stdout
+  } else {
+    bytes
+  };
+  let content = String::from_utf8_lossy(&bytes);
+
+  // The songs are listed in their directory, between `begin:` and `end:`
+  let mut directories: Vec<&str> = vec![];
+  let mut song: Option<(String, String, String)> = None;
+  let mut songs = HashMap::new();
+  for line in content.lines() {
+    let Some((key, value)) = line.split_once(": ") else {
+      if line == "song_end" {
+        if let Some((path, artist, title)) = song.take() {
+          songs.insert(path, (artist.to_lowercase(), title.to_lowercase()));
+        }
+      }
+      continue;
+    };
+    match (key, &mut song) {
+      ("begin", _) => directories.push(value),
+      ("end", _) => {
+        directories.pop();
+      }
+      ("song_begin", _) => {
+        let path = match directories.last() {
+          Some(directory) => format!("{directory}/{value}"),
+          None => value.to_string(),
+        };
+        song = Some((path, String::new(), String::new()));
+      }
+      ("Artist", Some((_, artist, _))) => *artist = value.to_string(),
+      ("Title", Some((_, _, title))) => *title = value.to_string(),
+      _ => {}
+    }
+  }
+  Ok(songs)
+}
@@ -1,12 +1,36 @@
 use crate::{
+  daemon,
   gstreamer::{pause, play},
-  player_state::PlayerState,
+  hooks,
+  player_state::{track_id as track_id_of, PlayerState},
+  settings::HookEvent,
 };
 use mpris_server::{
   zbus::fdo, LoopStatus, Metadata, PlaybackStatus, PlayerInterface, RootInterface, Time, Volume,
 };
+use std::time::Duration;
 use tracing::{info, instrument, warn};
 
+// Period of the position sent to the MPRIS clients while playing
+const POSITION_PERIOD: Duration = Duration::from_secs(5);
+
+/// Send the position periodically while playing: some clients extrapolate
+/// it from the last `Seeked` signal, and would drift away from the track.
+pub(crate) fn announce_position(player: &'static PlayerState) {
+  tokio::spawn(async move {
+    let mut interval = tokio::time::interval(POSITION_PERIOD);
+    loop {
+      interval.tick().await;
+      if !matches!(player.playback_status().await, Ok(PlaybackStatus::Playing)) {
+        continue;
+      }
+      if let Ok(position) = player.track_position().await {
+        player.seeked(Duration::from_millis(position)).await;
+      }
+    }
+  });
+}
+
 impl RootInterface for PlayerState {
   #[instrument(skip(self))]
   async fn identity(&self) -> fdo::Result<String> {
@@ -78,6 +102,7 @@ impl PlayerInterface for PlayerState {
   #[instrument(skip(self), ret)]
   #[instrument(skip(self))]
   async fn metadata(&self) -> fdo::Result<Metadata> {
+    daemon::touch();
     if let Some(track) = &*self.get_track().await {
       info!("Metadata {:?}", &track);
       Ok((&**track).into())
@@ -91,6 +116,7 @@ impl PlayerInterface for PlayerState {
 
   #[instrument(skip(self))]
   async fn next(&self) -> fdo::Result<()> {
+    daemon::touch();
     self
       .next_track()
       .await
@@ -100,15 +126,19 @@ impl PlayerInterface for PlayerState {
 
   #[instrument(skip(self))]
   async fn previous(&self) -> fdo::Result<()> {
+    daemon::touch();
     warn!("Not implemented and silently do nothing.");
     Ok(())
   }
 
   #[instrument(skip(self))]
   async fn pause(&self) -> fdo::Result<()> {
+    daemon::touch();
     let current_pipeline = self.get_pipeline().await;
     if let Some(pipeline) = current_pipeline {
       pause(&pipeline).map_err(|e| fdo::Error::Failed(e.to_string()))?;
+      self.set_listening(false).await;
+      hooks::fire(HookEvent::Pause, self.get_track().await.clone());
     }
 
     Ok(())
@@ -116,14 +146,23 @@ impl PlayerInterface for PlayerState {
 
   #[instrument(skip(self))]
   async fn play_pause(&self) -> fdo::Result<()> {
+    daemon::touch();
     use gstreamer::{prelude::ElementExt, State};
     let current_pipeline = self.get_pipeline().await;
     if let Some(pipeline) = current_pipeline {
       let (_, state, _) = pipeline.state(None);
       if state == State::Playing {
         pause(&pipeline).map_err(|e| fdo::Error::Failed(e.to_string()))?;
+        self.set_listening(false).await;
+        hooks::fire(HookEvent::Pause, self.get_track().await.clone());
       } else {
+        self
+          .rewind_on_resume()
+          .await
+          .map_err(|e| fdo::Error::Failed(e.to_string()))?;
         play(&pipeline).map_err(|e| fdo::Error::Failed(e.to_string()))?;
+        self.set_listening(true).await;
+        hooks::fire(HookEvent::Resume, self.get_track().await.clone());
       }
     }
 
@@ -132,32 +171,56 @@ impl PlayerInterface for PlayerState {
 
   #[instrument(skip(self))]
   async fn stop(&self) -> fdo::Result<()> {
+    daemon::touch();
     self
       .stop_track()
       .await
       .map_err(|e| fdo::Error::Failed(e.to_string()))?;
+    hooks::fire(HookEvent::Stop, self.get_track().await.clone());
 
     Ok(())
   }
 
   #[instrument(skip(self))]
   async fn play(&self) -> fdo::Result<()> {
+    daemon::touch();
     let current_pipeline = self.get_pipeline().await;
     if let Some(pipeline) = current_pipeline {
+      self
+        .rewind_on_resume()
+        .await
+        .map_err(|e| fdo::Error::Failed(e.to_string()))?;
       play(&pipeline).map_err(|e| fdo::Error::Failed(e.to_string()))?;
+      self.set_listening(true).await;
+      hooks::fire(HookEvent::Resume, self.get_track().await.clone());
     }
 
     Ok(())
   }
 
   #[instrument(skip(self))]
-  async fn seek(&self, _offset: Time) -> fdo::Result<()> {
-    todo!()
+  async fn seek(&self, offset: Time) -> fdo::Result<()> {
+    daemon::touch();
+    let position = self.position().await?;
+    let position = (position.as_micros() + offset.as_micros()).max(0) / 1_000_000;
+    self
+      .track_seek(position as u64)
+      .await
+      .map_err(|e| fdo::Error::Failed(e.to_string()))
   }
 
+  /// Ignored when the track isn't the played one anymore.
   #[instrument(skip(self))]
-  async fn set_position(&self, _track_id: mpris_server::TrackId, _position: Time) -> fdo::Result<()> {
-    todo!()
+  async fn set_position(&self, track_id: mpris_server::TrackId, position: Time) -> fdo::Result<()> {
+    daemon::touch();
+    let played = self.get_track().await.as_deref().and_then(track_id_of);
+    if played != Some(track_id) || position < Time::ZERO {
+      return Ok(());
+    }
+    self
+      .track_seek(position.as_secs() as u64)
+      .await
+      .map_err(|e| fdo::Error::Failed(e.to_string()))
   }
 
   #[instrument(skip(self))]
@@ -0,0 +1,123 @@
+use crate::{
+  cover, get_mpris_server,
+  rhythmdb::{Entry, SharedEntry},
+  ui::i18n::tr,
+};
+use futures::StreamExt;
+use mpris_server::{
+  zbus::{self, zvariant::OwnedValue, Connection, Proxy},
+  PlayerInterface,
+};
+use std::{
+  collections::HashMap,
+  sync::atomic::{AtomicBool, AtomicU32, Ordering},
+};
+use tokio::sync::OnceCell;
+use tracing::{instrument, warn};
+
+const DESTINATION: &str = "org.freedesktop.Notifications";
+const PATH: &str = "/org/freedesktop/Notifications";
+const INTERFACE: &str = "org.freedesktop.Notifications";
+
+static ENABLED: AtomicBool = AtomicBool::new(false);
+// Id of the last notification: the next one replaces it
+static LAST_ID: AtomicU32 = AtomicU32::new(0);
+// The actions are only sent to the connection of the notification
+static CONNECTION: OnceCell<Connection> = OnceCell::const_new();
+
+/// Announce the played tracks, from `playback.notifications`.
+pub(crate) fn init_notifications(enabled: bool) {
+  ENABLED.store(enabled, Ordering::Relaxed);
+}
+
+/// Notify the track, with the next and pause buttons. The D-Bus call runs in
+/// the background.
+pub(crate) fn track_changed(track: SharedEntry) {
+  if !ENABLED.load(Ordering::Relaxed) {
+    return;
+  }
+  tokio::spawn(async move {
+    if let Err(e) = notify(&track).await {
+      warn!("Can't notify the track: {e}");
+    }
+  });
+}
+
+#[instrument(skip(track))]
+async fn notify(track: &Entry) -> zbus::Result<()> {
+  let connection = connection().await?;
+  let icon = cover::cached(track)
+    .map(|path| path.display().to_string())
+    .unwrap_or_else(|| "audio-x-generic".to_string());
+  let body = [track.get_artist(), track.get_album()]
+    .into_iter()
+    .filter(|text| !text.is_empty())
+    .collect::<Vec<_>>()
+    .join(" – ");
+  let actions = ["next", tr().notify_next, "pause", tr().notify_pause];
+  let hints: HashMap<&str, OwnedValue> = HashMap::new();
+  let reply = connection
+    .call_method(
+      Some(DESTINATION),
+      PATH,
+      Some(INTERFACE),
+      "Notify",
+      &(
+        "music-player",
+        LAST_ID.load(Ordering::Relaxed),
+        icon.as_str(),
+        track.get_title(),
+        body.as_str(),
+        &actions[..],
+        hints,
+        -1_i32,
+      ),
+    )
+    .await?;
+  let id: u32 = reply.body().deserialize()?;
+  LAST_ID.store(id, Ordering::Relaxed);
+  Ok(())
+}
+
+/// Connection of the notifications, listening to their buttons from the
+/// first one.
+async fn connection() -> zbus::Result<&'static Connection> {
+  CONNECTION
+    .get_or_try_init(|| async {
+      let connection = Connection::session().await?;
+      let listened = connection.clone();
+      tokio::spawn(async move {
+        if let Err(e) = listen(listened).await {
+          warn!("Can't listen to the notification buttons: {e}");
+        }
+      });
+      Ok(connection)
+    })
+    .await
+}
+
+/// Run the buttons clicked on the last notification.
+#[instrument(skip(connection))]
+async fn listen(connection: Connection) -> zbus::Result<()> {
+  let notifications = Proxy::new(&connection, DESTINATION, PATH, INTERFACE).await?;
+  let mut invoked = notifications.receive_signal("ActionInvoked").await?;
+  while let Some(message) = invoked.next().await {
+    let (id, action): (u32, String) = message.body().deserialize()?;
+    if id != LAST_ID.load(Ordering::Relaxed) {
+      continue;
+    }
+    let Ok(server) = get_mpris_server().await else {
+      continue;
+    };
+    let player = server.imp();
+    let result = match action.as_str() {
+      "next" => player.next().await,
+      "pause" => player.pause().await,
+      _ => Ok(()),
+    };
+    if let Err(e) = result {
+      warn!("{e}");
+    }
+  }
+  Ok(())
+}
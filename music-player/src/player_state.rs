@@ -1,15 +1,26 @@
 use crate::{
-  get_mpris_server,
-  gstreamer::stop,
+  cover, daemon, flags, get_mpris_server,
+  gstreamer::{pause, play, preroll, stop},
+  hooks, inhibit, notifications,
   playlists::Playlist,
-  rhythmdb::{Entry, EntryList, Rhythmdb, SharedEntry, SongEntry},
+  resume,
+  rhythmdb::{Entry, EntryList, Rating, Rhythmdb, SharedEntry, SongEntry},
+  settings::{
+    ErrorPolicy, HookEvent, LayoutSetting, PlaybackSettings, PlayerStateSetting, Settings,
+    SuggestionSettings,
+  },
   start_playing,
 };
+use clap::ValueEnum;
 use gstreamer::Element;
 use miette::{IntoDiagnostic, Result};
-use mpris_server::{Metadata, Property, Time};
+use mpris_server::{Metadata, PlaybackStatus, Property, Signal, Time, TrackId};
 use serde::{Deserialize, Serialize};
-use std::{sync::Arc, thread::sleep, time::Duration};
+use std::{
+  sync::Arc,
+  thread::sleep,
+  time::{Duration, Instant},
+};
 use tokio::sync::{mpsc::Sender, RwLock};
 use tracing::instrument;
 
@@ -17,9 +28,21 @@ pub(crate) enum UiNotification {
   UpdateIndex(Option<usize>),
   Position(Duration),
   RebuildTable,
+  // The track was left, after being listened to for the duration
+  Played(SharedEntry, Duration),
+  // Error displayed in the status bar
+  Error(String),
+  // An audio output was removed, with its name
+  OutputRemoved(String),
+  // Reloaded from `settings.toml`
+  Settings(Box<crate::settings::Settings>),
+  // The db was saved in the background, or the error
+  Saved(std::result::Result<crate::rhythmdb::Saved, String>),
+  #[cfg(feature = "lrclib")]
+  Lyrics(url::Url, Option<crate::lyrics::Lyrics>),
 }
 
-#[derive(Copy, Clone, Debug, Deserialize, Serialize)]
+#[derive(Copy, Clone, Debug, Deserialize, Serialize, ValueEnum)]
 pub(crate) enum Shuffle {
   Next,
   #[allow(clippy::enum_variant_names)]
@@ -28,7 +51,7 @@ pub(crate) enum Shuffle {
   ShuffleLastPlayed,
 }
 
-#[derive(Copy, Clone, Debug, Deserialize, Serialize)]
+#[derive(Copy, Clone, Debug, Deserialize, Serialize, ValueEnum)]
 pub(crate) enum Repeat {
   AllTracks,
   CurrentTrack,
@@ -44,6 +67,74 @@ pub struct PlayerState {
   pub sender: RwLock<Option<Sender<UiNotification>>>,
   pub shuffle_mode: RwLock<Shuffle>,
   pub repeat_mode: RwLock<Repeat>,
+  pub preloaded: RwLock<Option<Preloaded>>,
+  pub listen_clock: RwLock<ListenClock>,
+  pub scope: RwLock<PlayScope>,
+  // Index in the queue of the played track, when it is queued
+  pub queue_position: RwLock<Option<usize>>,
+  // From `settings.toml`, updated when it is reloaded
+  pub playback: RwLock<PlaybackSettings>,
+  pub suggestions: RwLock<SuggestionSettings>,
+}
+
+/// What the tracks of a play scope are.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub(crate) enum ScopeKind {
+  #[default]
+  Library,
+  Podcasts,
+  // The tracks of a search or of filters
+  Search,
+  Artist,
+  Album,
+  // The songs of an artist, shuffled
+  ArtistRadio,
+  // The files given on the command line
+  Playlist,
+}
+
+/// The list the next track is chosen from, after the queue: the view a track
+/// was played from. Changing the tab or the search doesn't change it.
+#[derive(Debug, Default)]
+pub struct PlayScope {
+  pub(crate) kind: ScopeKind,
+  tracks: EntryList,
+}
+
+/// Time the played track was listened to: the pauses and the parts skipped
+/// by a seek are not counted.
+#[derive(Debug, Default)]
+pub struct ListenClock {
+  // Start of the current play interval, none when paused
+  since: Option<Instant>,
+  listened: Duration,
+}
+
+impl ListenClock {
+  fn start(&mut self) {
+    if self.since.is_none() {
+      self.since = Some(Instant::now());
+    }
+  }
+
+  fn stop(&mut self) {
+    if let Some(since) = self.since.take() {
+      self.listened += since.elapsed();
+    }
+  }
+
+  fn listened(&self) -> Duration {
+    self.listened + self.since.map(|since| since.elapsed()).unwrap_or_default()
+  }
+}
+
+/// Next track chosen before the end of the played one, with its pipeline
+/// prerolled in pause.
+pub struct Preloaded {
+  track: SharedEntry,
+  // Index in the list it was chosen from
+  index: usize,
+  pipeline: Element,
 }
 
 impl PlayerState {
@@ -58,9 +149,34 @@ impl PlayerState {
       sender: RwLock::new(None),
       shuffle_mode: RwLock::new(Shuffle::ShuffleLastPlayed),
       repeat_mode: RwLock::new(Repeat::AllTracks),
+      preloaded: RwLock::new(None),
+      listen_clock: RwLock::new(ListenClock::default()),
+      scope: RwLock::new(PlayScope::default()),
+      queue_position: RwLock::new(None),
+      playback: RwLock::new(PlaybackSettings::default()),
+      suggestions: RwLock::new(SuggestionSettings::default()),
     }
   }
 
+  /// Apply the settings of the playback, at the start and when
+  /// `settings.toml` is reloaded.
+  #[instrument(skip(self, settings))]
+  pub(crate) async fn apply_settings(&self, settings: &Settings) {
+    *self.playback.write().await = settings.playback;
+    *self.suggestions.write().await = settings.suggestions;
+    self.get_mut_db().await.search_weights = settings.search_weights;
+    inhibit::init_inhibit(settings.playback.inhibit_suspend);
+    notifications::init_notifications(settings.playback.notifications);
+  }
+
+  pub(crate) async fn get_playback(&self) -> PlaybackSettings {
+    *self.playback.read().await
+  }
+
+  pub(crate) async fn get_suggestions(&self) -> SuggestionSettings {
+    *self.suggestions.read().await
+  }
+
   #[instrument(skip(self))]
   pub(crate) async fn get_track(&self) -> impl std::ops::Deref<Target = Option<SharedEntry>> + '_ {
     self.current_track.read().await
@@ -95,6 +211,23 @@ impl PlayerState {
     *current_playlist = p;
   }
 
+  #[instrument(skip(self))]
+  pub(crate) async fn get_scope_kind(&self) -> ScopeKind {
+    self.scope.read().await.kind
+  }
+
+  #[instrument(skip(self, tracks))]
+  pub(crate) async fn set_scope(&self, kind: ScopeKind, tracks: EntryList) {
+    self.discard_preloaded().await;
+    *self.scope.write().await = PlayScope { kind, tracks };
+  }
+
+  /// Tracks of the play scope, as they are now in the db.
+  async fn scope_tracks(&self) -> EntryList {
+    let scope = self.scope.read().await;
+    self.get_db().await.refresh(&scope.tracks)
+  }
+
   #[instrument(skip(self))]
   pub(crate) async fn get_queue(&self) -> impl std::ops::Deref<Target = Playlist> + '_ {
     self.queue.read().await
@@ -117,15 +250,23 @@ impl PlayerState {
   pub(crate) async fn get_mut_db(&self) -> impl std::ops::DerefMut<Target = Rhythmdb> + '_ {
     self.db.write().await
   }
+  /// The db, from a blocking thread.
+  pub(crate) fn blocking_mut_db(&self) -> impl std::ops::DerefMut<Target = Rhythmdb> + '_ {
+    self.db.blocking_write()
+  }
   #[instrument(skip(self, db))]
   pub(crate) async fn set_db(&self, db: Rhythmdb) {
     let mut pdb = self.db.write().await;
     *pdb = db;
   }
 
+  /// Index of the entry in the table.
   #[instrument(skip(self))]
   pub(crate) async fn find_track_index(&self, entry: &Entry) -> Option<usize> {
-    let entries = self.playlist.read().await;
+    PlayerState::index_in(&self.playlist.read().await, entry)
+  }
+
+  fn index_in(entries: &[SharedEntry], entry: &Entry) -> Option<usize> {
     for (i, e) in entries.iter().enumerate() {
       match (entry, e.as_ref()) {
         (Entry::Song(e1), Entry::Song(e2)) => {
@@ -196,14 +337,16 @@ impl PlayerState {
   }
 
   #[instrument(skip(track_list))]
+  /// Random track of the list, the loved ones weighing more.
   pub(crate) fn choose_track(track_list: &[Arc<Entry>]) -> Result<(Arc<Entry>, usize)> {
-    use rand::Rng;
-    let index = rand::thread_rng().gen_range(0..track_list.len());
-    if let Some(song) = track_list.get(index) {
-      Ok((song.clone(), index))
-    } else {
-      miette::bail!("")
-    }
+    use rand::seq::SliceRandom;
+    let song = track_list
+      .choose_weighted(&mut rand::thread_rng(), |track| {
+        flags::shuffle_weight(&track.get_location())
+      })
+      .into_diagnostic()?;
+    let index = PlayerState::index_in(track_list, song).unwrap_or_default();
+    Ok((song.clone(), index))
   }
 
   #[instrument(skip(self, track_list))]
@@ -218,21 +361,24 @@ impl PlayerState {
     let song = {
       let now: u64 = chrono::Local::now().timestamp() as u64;
       let mut rng = rand::thread_rng();
-      track_list.choose_weighted(&mut rng, |track| match track.as_ref() {
-        Entry::Song(song) => match song.last_played {
-          Some(date) => now - date,
-          None => now - first_played,
-        },
-        Entry::PodcastPost(song) => match song.last_played {
-          Some(date) => now - date,
-          None => now - first_played,
-        },
-        _ => 1,
+      track_list.choose_weighted(&mut rng, |track| {
+        let weight = match track.as_ref() {
+          Entry::Song(song) => match song.last_played {
+            Some(date) => now - date,
+            None => now - first_played,
+          },
+          Entry::PodcastPost(song) => match song.last_played {
+            Some(date) => now - date,
+            None => now - first_played,
+          },
+          _ => 1,
+        };
+        weight * flags::shuffle_weight(&track.get_location())
       })
     }
     .into_diagnostic()?;
 
-    let index = self.find_track_index(song).await.unwrap_or_default();
+    let index = PlayerState::index_in(track_list, song).unwrap_or_default();
     Ok((song.clone(), index))
   }
 }
@@ -242,6 +388,8 @@ impl PlayerState {
   pub(crate) async fn stop_track(&self) -> Result<()> {
     if let Some(pipeline) = self.get_pipeline().await {
       stop(&pipeline)?;
+      self.set_listening(false).await;
+      self.playback_status_changed(PlaybackStatus::Stopped).await;
       self
         .notify_ui(UiNotification::Position(Duration::ZERO))
         .await?;
@@ -251,10 +399,121 @@ impl PlayerState {
 
   #[instrument(skip(self))]
   pub(crate) async fn play_track(&self, track: SharedEntry) -> Result<()> {
-    let pipeline = start_playing(&track.get_location())?;
+    self.discard_preloaded().await;
+    let pipeline = start_playing(&track.get_playback_location())?;
+    self.track_started(track, pipeline).await
+  }
+
+  /// Start or stop the listening clock of the played track and the suspend
+  /// inhibitor, on play and pause.
+  #[instrument(skip(self))]
+  pub(crate) async fn set_listening(&self, listening: bool) {
+    let mut clock = self.listen_clock.write().await;
+    if listening {
+      clock.start();
+      daemon::claim_name();
+    } else {
+      clock.stop();
+    }
+    inhibit::set_playing(listening);
+    resume::set_paused(!listening);
+    drop(clock);
+    self
+      .playback_status_changed(if listening {
+        PlaybackStatus::Playing
+      } else {
+        PlaybackStatus::Paused
+      })
+      .await;
+  }
+
+  /// Save the played track, its position and the queue, reloaded at the next
+  /// start.
+  pub(crate) async fn save_session(&self, layout: LayoutSetting) -> Result<()> {
+    if let Some(pipeline) = self.get_pipeline().await {
+      use gstreamer::{prelude::ElementExt, State};
+
+      let (_, state, _) = pipeline.state(None);
+      let pstate = if state == State::Playing || state == State::Paused {
+        PlayerStateSetting {
+          track: self.get_track().await.as_ref().map(|x| x.get_location()),
+          position: self.track_position().await.ok(),
+          shuffle_mode: Some(*self.shuffle_mode.read().await),
+          repeat_mode: Some(*self.repeat_mode.read().await),
+          layout,
+          saved_at: Some(chrono::Local::now().timestamp() as u64),
+          queue_position: *self.queue_position.read().await,
+        }
+      } else {
+        PlayerStateSetting {
+          track: None,
+          position: None,
+          repeat_mode: None,
+          shuffle_mode: None,
+          layout,
+          saved_at: None,
+          queue_position: None,
+        }
+      };
+      pstate.save()?;
+    }
+    self.get_queue().await.save()
+  }
+
+  /// Rewind the played track before it is resumed after a long pause.
+  pub(crate) async fn rewind_on_resume(&self) -> Result<()> {
+    let Some(paused_for) = resume::paused_for() else {
+      return Ok(());
+    };
+    let Some(track) = self.get_track().await.clone() else {
+      return Ok(());
+    };
+    let rewind = resume::rewind(&self.get_playback().await, &track, paused_for);
+    if !rewind.is_zero() {
+      let position = Duration::from_millis(self.track_position().await?);
+      self
+        .track_seek(position.saturating_sub(rewind).as_secs())
+        .await?;
+    }
+    Ok(())
+  }
+
+  /// Tell the MPRIS clients the playback status: they only poll the position
+  /// while playing.
+  pub(crate) async fn playback_status_changed(&self, status: PlaybackStatus) {
+    if let Ok(server) = get_mpris_server().await {
+      let _ = server
+        .properties_changed([Property::PlaybackStatus(status)])
+        .await;
+    }
+  }
+
+  /// Tell the MPRIS clients the position jumped, to move their progress bar.
+  pub(crate) async fn seeked(&self, position: Duration) {
+    if let Ok(server) = get_mpris_server().await {
+      let position = Time::from_micros(position.as_micros() as i64);
+      let _ = server.emit(Signal::Seeked { position }).await;
+    }
+  }
+
+  async fn track_started(&self, track: SharedEntry, pipeline: Element) -> Result<()> {
+    let listened = std::mem::take(&mut *self.listen_clock.write().await).listened();
+    let previous = self.get_track().await.clone();
     self.set_pipeline(pipeline).await;
     self.set_track(track.clone()).await;
+    let position = self.played_index(&*self.get_queue().await).await;
+    *self.queue_position.write().await = position;
+    self.set_listening(true).await;
+    if let Some(previous) = previous {
+      self
+        .notify_ui(UiNotification::Played(previous, listened))
+        .await?;
+    }
     self.properties_changed(vec![Property::Metadata((&*track).into())])?;
+    #[cfg(feature = "coverart")]
+    cover::fetch_in_background(track.clone());
+    notifications::track_changed(track.clone());
+    hooks::fire(HookEvent::TrackChange, Some(track));
     self
       .notify_ui(UiNotification::Position(Duration::ZERO))
       .await?;
@@ -264,70 +523,257 @@ impl PlayerState {
   #[instrument(skip(self))]
   pub(crate) async fn next_track(&self) -> Result<usize> {
     let mut queue = self.get_mut_queue().await;
-    if !queue.queue().is_empty() {
-      let get_track = self.get_track().await;
-      if let Some(current_track) = get_track.as_ref() {
-        queue.remove(current_track.get_location());
-        self.notify_ui(UiNotification::RebuildTable).await?;
-      }
+    let queued = queue.queue().len();
+    let queue_next = self.consume_queue(&mut queue).await;
+    if queue.queue().len() < queued {
+      self.notify_ui(UiNotification::RebuildTable).await?;
     }
-
-    let track_list = if queue.queue().is_empty() {
-      self.get_playlist().await.to_vec()
-    } else {
-      let queue_entries = self.get_db().await.to_entries(&queue);
-      if queue_entries.is_empty() {
-        self.get_playlist().await.to_vec()
-      } else {
-        queue_entries
+    let (track_list, queue_next) = self.next_candidates(&queue, queue_next).await;
+    // Released before the track starts, which looks for it in the queue
+    drop(queue);
+    // Where the next queued track is looked for
+    *self.queue_position.write().await = queue_next;
+
+    // The track chosen before the end of the played one, when it is still
+    // the one of the list
+    let preloaded = self.preloaded.write().await.take();
+    if let Some(preloaded) = preloaded {
+      let chosen = track_list
+        .get(preloaded.index)
+        .is_some_and(|track| track.get_location() == preloaded.track.get_location());
+      if chosen {
+        self.stop_track().await?;
+        let index = preloaded.index;
+        play(&preloaded.pipeline)?;
+        self
+          .track_started(preloaded.track.clone(), preloaded.pipeline)
+          .await?;
+        self.select_in_table(&preloaded.track).await?;
+        return Ok(index);
       }
-    };
+      let _ = stop(&preloaded.pipeline);
+    }
 
-    let shuffle_mode = self.get_shuffle_mode().await;
-    let repeat_mode = self.get_repeat_mode().await;
     loop {
       // Loop until play a track without errors
-      let (track, index) = match (shuffle_mode, repeat_mode, queue.queue().is_empty()) {
-        (_, Repeat::AllTracks, false) => (track_list[0].clone(), 0),
-        (Shuffle::Next, Repeat::AllTracks, true) => {
-          let get_track = self.get_track().await;
-          if let Some(get_track) = get_track.as_ref() {
-            let index =
-              (self.find_track_index(get_track).await.unwrap_or_default() + 1) % track_list.len();
-            (track_list[index].clone(), index)
-          } else {
-            (Arc::new(Entry::Song(SongEntry::default())), 0)
-          }
-        }
-        (_, Repeat::CurrentTrack, _) => {
-          let get_track = self.get_track().await;
-          if let Some(track) = get_track.as_ref() {
-            let index = self.find_track_index(track).await.unwrap_or_default();
-            (track.clone(), index)
-          } else {
-            (Arc::new(Entry::Song(SongEntry::default())), 0)
-          }
-        }
-        (Shuffle::Shuffle, Repeat::AllTracks, true) => PlayerState::choose_track(&track_list)?,
-        (Shuffle::ShuffleLastPlayed, Repeat::AllTracks, true) => {
-          self.choose_track_last_played(&track_list).await?
-        }
-      };
+      let (track, index) = self.choose_next(&track_list, queue_next).await?;
 
       self.stop_track().await?;
       if let Err(e) = self.play_track(track.clone()).await {
         tracing::error!("Error starting '{}': {}", &track.get_location(), e);
+        self
+          .notify_ui(UiNotification::Error(format!(
+            "Error starting '{}': {e}",
+            track.get_title()
+          )))
+          .await?;
       // Error: continue looping.
       } else {
         // Track is currently played. We can exit this function.
-        self
-          .notify_ui(UiNotification::UpdateIndex(Some(index)))
-          .await?;
+        self.select_in_table(&track).await?;
         return Ok(index);
       }
     }
   }
 
+  /// Choose the next track and preroll its pipeline, so that it starts
+  /// without delay at the end of the played one.
+  #[instrument(skip(self))]
+  pub(crate) async fn preload_next(&self) -> Result<()> {
+    if self.preloaded.read().await.is_some() {
+      return Ok(());
+    }
+    // The queue without the played track, as in `next_track`
+    let mut queue = self.get_queue().await.clone();
+    let queue_next = self.consume_queue(&mut queue).await;
+    let (track_list, queue_next) = self.next_candidates(&queue, queue_next).await;
+    if track_list.is_empty() {
+      return Ok(());
+    }
+    let (track, index) = self.choose_next(&track_list, queue_next).await?;
+    let pipeline = preroll(&track.get_playback_location())?;
+    *self.preloaded.write().await = Some(Preloaded {
+      track,
+      index,
+      pipeline,
+    });
+    Ok(())
+  }
+
+  /// Pause the playback when the audio output is lost. After an error of the
+  /// output, the pipeline is started again in pause, on the output available
+  /// now: the caller seeks it once prerolled.
+  #[instrument(skip(self))]
+  pub(crate) async fn pause_on_output_loss(&self, restart: bool) -> Result<()> {
+    let Some(pipeline) = self.get_pipeline().await else {
+      return Ok(());
+    };
+    if restart {
+      stop(&pipeline)?;
+    }
+    pause(&pipeline)?;
+    self.set_listening(false).await;
+    hooks::fire(HookEvent::Pause, self.get_track().await.clone());
+    Ok(())
+  }
+
+  /// Retry the played track after an error of the playback, skip it or
+  /// stop, from `playback.on_error`. `retried` holds the track played again
+  /// and its attempts.
+  #[instrument(skip(self, retried))]
+  pub(crate) async fn apply_error_policy(&self, retried: &mut Option<(url::Url, u32)>) -> Result<()> {
+    let Some(track) = self.get_track().await.clone() else {
+      return Ok(());
+    };
+    let location = track.get_location();
+    let attempts = match retried {
+      Some((retried, attempts)) if *retried == location => *attempts,
+      _ => 0,
+    };
+    let playback = self.get_playback().await;
+    match playback.on_error {
+      ErrorPolicy::Retry if attempts < playback.retries => {
+        *retried = Some((location, attempts + 1));
+        self.play_track(track).await?;
+      }
+      ErrorPolicy::Retry | ErrorPolicy::Skip => {
+        self.next_track().await?;
+      }
+      ErrorPolicy::Stop => self.stop_track().await?,
+    }
+    Ok(())
+  }
+
+  /// Select the played track when it is in the table.
+  async fn select_in_table(&self, track: &Entry) -> Result<()> {
+    if let Some(index) = self.find_track_index(track).await {
+      self
+        .notify_ui(UiNotification::UpdateIndex(Some(index)))
+        .await?;
+    }
+    Ok(())
+  }
+
+  /// Release the pipeline of the preloaded track.
+  async fn discard_preloaded(&self) {
+    if let Some(preloaded) = self.preloaded.write().await.take() {
+      let _ = stop(&preloaded.pipeline);
+    }
+  }
+
+  /// Index in the queue of the played track, none when it isn't queued.
+  pub(crate) async fn played_queue_index(&self) -> Option<usize> {
+    self.played_index(&*self.get_queue().await).await
+  }
+
+  async fn played_index(&self, queue: &Playlist) -> Option<usize> {
+    let location = self.get_track().await.as_ref()?.get_location();
+    let hint = self.queue_position.read().await.unwrap_or_default();
+    queue.index_from(&location, hint)
+  }
+
+  /// Remove the played track from the queue. The queued track played next
+  /// is the one after it, or the first one: its index, none when the queue
+  /// is empty.
+  async fn consume_queue(&self, queue: &mut Playlist) -> Option<usize> {
+    let played = self.played_index(queue).await;
+    if let Some(index) = played {
+      queue.remove_at(index);
+    }
+    let next = played.unwrap_or_default();
+    match queue.queue().len() {
+      0 => None,
+      len if next < len => Some(next),
+      _ => Some(0),
+    }
+  }
+
+  /// Tracks the next one is chosen from: the queue, or the play scope when
+  /// none of the queued tracks is in the db.
+  async fn next_candidates(
+    &self,
+    queue: &Playlist,
+    queue_next: Option<usize>,
+  ) -> (EntryList, Option<usize>) {
+    let queue_entries = self.get_db().await.to_entries(queue);
+    if queue_next.is_none() || queue_entries.is_empty() {
+      (self.scope_tracks().await, None)
+    } else {
+      (queue_entries, queue_next)
+    }
+  }
+
+  /// The track following the played one in the list, with the shuffle and
+  /// repeat modes. `queue_next` is the index of the next queued track when
+  /// the list is the queue.
+  #[instrument(skip(self, track_list))]
+  async fn choose_next(
+    &self,
+    track_list: &[SharedEntry],
+    queue_next: Option<usize>,
+  ) -> Result<(SharedEntry, usize)> {
+    let shuffle_mode = self.get_shuffle_mode().await;
+    let repeat_mode = self.get_repeat_mode().await;
+    Ok(match (shuffle_mode, repeat_mode, queue_next) {
+      (_, Repeat::AllTracks, Some(index)) => {
+        let index = if index < track_list.len() { index } else { 0 };
+        (track_list[index].clone(), index)
+      }
+      (Shuffle::Next, Repeat::AllTracks, None) => {
+        let get_track = self.get_track().await;
+        if let Some(get_track) = get_track.as_ref() {
+          let index =
+            (PlayerState::index_in(track_list, get_track).unwrap_or_default() + 1) % track_list.len();
+          (track_list[index].clone(), index)
+        } else {
+          (Arc::new(Entry::Song(SongEntry::default())), 0)
+        }
+      }
+      (_, Repeat::CurrentTrack, _) => {
+        let get_track = self.get_track().await;
+        if let Some(track) = get_track.as_ref() {
+          let index = PlayerState::index_in(track_list, track).unwrap_or_default();
+          (track.clone(), index)
+        } else {
+          (Arc::new(Entry::Song(SongEntry::default())), 0)
+        }
+      }
+      (Shuffle::Shuffle | Shuffle::ShuffleLastPlayed, Repeat::AllTracks, None) => {
+        let candidates = self.shuffle_candidates(track_list).await;
+        let (track, _) = if matches!(shuffle_mode, Shuffle::Shuffle) {
+          PlayerState::choose_track(&candidates)?
+        } else {
+          self.choose_track_last_played(&candidates).await?
+        };
+        let index = PlayerState::index_in(track_list, &track).unwrap_or_default();
+        (track, index)
+      }
+    })
+  }
+
+  /// Tracks the shuffle chooses from: the banned ones are left out, and the
+  /// music and the podcasts are not mixed unless `playback.shuffle_mixed`.
+  /// The whole list when none is left.
+  async fn shuffle_candidates(&self, track_list: &[SharedEntry]) -> EntryList {
+    let is_podcast = |entry: &Entry| matches!(entry, Entry::PodcastPost(_));
+    let played = self.get_track().await.clone();
+    let mixed = self.get_playback().await.shuffle_mixed;
+    let candidates: EntryList = track_list
+      .iter()
+      .filter(|track| !flags::is_banned(&track.get_location()))
+      .filter(|track| match &played {
+        Some(played) if !mixed => is_podcast(track) == is_podcast(played),
+        _ => true,
+      })
+      .cloned()
+      .collect();
+    if candidates.is_empty() {
+      track_list.to_vec()
+    } else {
+      candidates
+    }
+  }
+
   #[instrument(skip(self))]
   pub(crate) async fn track_position(&self) -> Result<u64> {
     use gstreamer::prelude::ElementExtManual;
@@ -351,55 +797,62 @@ impl PlayerState {
           new_position * gstreamer::ClockTime::SECOND,
         )
         .into_diagnostic()?;
+      self.seeked(Duration::from_secs(new_position)).await;
+      // The ui catches up at once, whoever sought
+      self
+        .notify_ui(UiNotification::Position(Duration::from_secs(new_position)))
+        .await?;
     }
     Ok(())
   }
   #[instrument(skip(self, db))]
-  pub(crate) async fn update_rating(
+  pub(crate) async fn update_rating(&self, db: &mut Rhythmdb, i: Option<usize>, rating: Rating) {
+    let track = self.get_playlist().await[i.unwrap()].clone();
+    self.update_ratings(db, &[track], rating).await
+  }
+
+  /// Rate the tracks in the db, saved later by the caller.
+  #[instrument(skip(self, db, tracks))]
+  pub(crate) async fn update_ratings(
     &self,
     db: &mut Rhythmdb,
-    i: Option<usize>,
-    rating: u64,
-    settings: &crate::settings::Settings,
-  ) -> Result<()> {
-    let playlist_view = self.get_playlist().await;
-    let track = &playlist_view[i.unwrap()];
-
-    let updated_track = match track.as_ref() {
-      Entry::Song(song) => {
-        let mut song_copy = song.to_owned();
-        song_copy.rating = Some(rating);
-        Arc::new(Entry::Song(song_copy))
-      }
-      Entry::PodcastPost(podcast) => {
-        let mut podcast_copy = podcast.to_owned();
-        podcast_copy.rating = Some(rating);
-        Arc::new(Entry::PodcastPost(podcast_copy))
-      }
-      _ => unimplemented!(),
-    };
-    db.update_entry(updated_track.clone());
-    // to avoid the lock 3 lines below (set_track)
+    tracks: &[SharedEntry],
+    rating: Rating,
+  ) {
+    // to avoid the lock in the loop (set_track)
     let get_track = { self.get_track().await.clone() };
-    if let Some(played_track) = &get_track {
-      if updated_track.get_id() == played_track.get_id() {
-        self.set_track(updated_track).await;
+    for track in tracks {
+      let updated_track = match track.as_ref() {
+        Entry::Song(song) => {
+          let mut song_copy = song.to_owned();
+          song_copy.rating = Some(rating);
+          Arc::new(Entry::Song(song_copy))
+        }
+        Entry::PodcastPost(podcast) => {
+          let mut podcast_copy = podcast.to_owned();
+          podcast_copy.rating = Some(rating);
+          Arc::new(Entry::PodcastPost(podcast_copy))
+        }
+        _ => unimplemented!(),
+      };
+      db.update_entry(updated_track.clone());
+      if let Some(played_track) = &get_track {
+        if updated_track.get_id() == played_track.get_id() {
+          self.set_track(updated_track).await;
+        }
       }
     }
-    db.save(settings)?;
-    Ok(())
   }
 }
 
 impl From<&Entry> for Metadata {
   fn from(value: &Entry) -> Self {
-    match value {
+    let builder = match value {
       Entry::Song(song) => Metadata::builder()
         .title(song.title.clone())
         .artist([song.artist.clone()])
         .album(song.album.clone())
-        .length(Time::from_secs(song.duration.unwrap_or_default() as i64))
-        .build(),
+        .length(Time::from_secs(song.duration.unwrap_or_default() as i64)),
       Entry::Iradio(_) => todo!(),
       Entry::Ignore(_) => todo!(),
       Entry::PodcastFeed(_) => todo!(),
@@ -407,8 +860,21 @@ impl From<&Entry> for Metadata {
         .title(podcast.title.clone())
         .artist([podcast.artist.clone()])
         .album(podcast.album.clone())
-        .length(Time::from_secs(podcast.duration.unwrap_or_default() as i64))
-        .build(),
+        .length(Time::from_secs(podcast.duration.unwrap_or_default() as i64)),
+    };
+    let builder = match track_id(value) {
+      Some(track_id) => builder.trackid(track_id),
+      None => builder,
+    };
+    match cover::art_url(value) {
+      Some(url) => builder.art_url(url.to_string()).build(),
+      None => builder.build(),
     }
   }
 }
+
+/// MPRIS id of the track, an object path: the clients pass it back to set
+/// the position.
+pub(crate) fn track_id(entry: &Entry) -> Option<TrackId> {
+  TrackId::try_from(format!("/org/djedi/music_player/track/{}", entry.get_id())).ok()
+}
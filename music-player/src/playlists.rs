@@ -1,8 +1,13 @@
-use directories::BaseDirs;
-use miette::{Context, IntoDiagnostic, Result};
+use crate::{
+  rhythmdb::rhythmbox_running,
+  settings::{state_dir, write_state},
+};
+use miette::{miette, IntoDiagnostic, Result};
+use quick_xml::{escape::escape, se::Serializer};
 use serde::{Deserialize, Serialize};
 use std::{
   fs,
+  io::ErrorKind,
   path::{Path, PathBuf},
 };
 use toml::{from_str, to_string_pretty};
@@ -38,6 +43,166 @@ pub(crate) struct AutomaticPlaylist {
   sort_key: String,
   #[serde(rename = "@sort-direction")]
   sort_direction: u64,
+  #[serde(
+    rename = "@limit-count",
+    default,
+    skip_serializing_if = "Option::is_none"
+  )]
+  limit_count: Option<u64>,
+  // In seconds
+  #[serde(
+    rename = "@limit-time",
+    default,
+    skip_serializing_if = "Option::is_none"
+  )]
+  limit_time: Option<u64>,
+  #[serde(default)]
+  conjunction: Conjunction,
+}
+
+/// Conditions all matched, in the query format of Rhythmbox.
+#[derive(Clone, Serialize, Deserialize, Debug, Default)]
+pub(crate) struct Conjunction {
+  #[serde(rename = "$value", default)]
+  query: Vec<Query>,
+}
+
+#[derive(Clone, Serialize, Deserialize, Debug)]
+#[serde(rename_all = "kebab-case")]
+pub(crate) enum Query {
+  Equals(Criterion),
+  Like(Criterion),
+  NotLike(Criterion),
+  Prefix(Criterion),
+  Suffix(Criterion),
+  Greater(Criterion),
+  Less(Criterion),
+  // The value is a number of seconds
+  CurrentTimeWithin(Criterion),
+  CurrentTimeNotWithin(Criterion),
+  // The conditions before and after it are alternatives
+  Disjunction,
+  Subquery(Subquery),
+}
+
+/// Condition on a property of the entries, named like in the db.
+#[derive(Clone, Serialize, Deserialize, Debug)]
+pub(crate) struct Criterion {
+  #[serde(rename = "@prop")]
+  pub(crate) prop: String,
+  #[serde(rename = "$text")]
+  pub(crate) value: String,
+}
+
+#[derive(Clone, Serialize, Deserialize, Debug)]
+pub(crate) struct Subquery {
+  conjunction: Conjunction,
+}
+
+/// Element of a playlist in `playlists.xml`, its kind as attribute.
+#[derive(Serialize)]
+struct PlaylistElement<'a, T> {
+  #[serde(rename = "@type")]
+  kind: &'static str,
+  #[serde(flatten)]
+  playlist: &'a T,
+}
+
+impl<T: Serialize> PlaylistElement<'_, T> {
+  fn to_xml(&self) -> Result<String> {
+    let mut buffer = String::new();
+    // The text of the conditions is not indented: it would be in their value
+    let serializer = Serializer::with_root(&mut buffer, Some("playlist")).into_diagnostic()?;
+    self.serialize(serializer).into_diagnostic()?;
+    Ok(buffer)
+  }
+}
+
+/// Add the element of the playlist `name` to the playlists of Rhythmbox,
+/// `playlists.xml` next to the db. The file is created when missing.
+#[instrument(skip(element))]
+fn add_playlist(playlist_path: &str, name: &str, element: &str) -> Result<()> {
+  // Rhythmbox overwrites the playlists when it quits
+  if rhythmbox_running() {
+    return Err(miette!(
+      "Rhythmbox is running: close it to add the playlist"
+    ));
+  }
+  let path = Path::new(playlist_path).with_file_name("playlists.xml");
+  let content = match fs::read_to_string(&path) {
+    Ok(content) => content,
+    Err(e) if e.kind() == ErrorKind::NotFound => String::new(),
+    Err(e) => return Err(e).into_diagnostic(),
+  };
+  if content.contains(&format!("name=\"{}\"", escape(name))) {
+    return Err(miette!("A playlist is already named `{name}`"));
+  }
+
+  let content = if let Some(end) = content.rfind("</rhythmdb-playlists>") {
+    format!("{}{element}\n{}", &content[..end], &content[end..])
+  } else if content.contains("<rhythmdb-playlists/>") {
+    content.replacen(
+      "<rhythmdb-playlists/>",
+      &format!("<rhythmdb-playlists>\n{element}\n</rhythmdb-playlists>"),
+      1,
+    )
+  } else if content.trim().is_empty() {
+    format!("<?xml version=\"1.0\"?>\n<rhythmdb-playlists>\n{element}\n</rhythmdb-playlists>\n")
+  } else {
+    return Err(miette!("`{}` is not a playlists file", path.display()));
+  };
+  fs::write(&path, content).into_diagnostic()
+}
+
+impl AutomaticPlaylist {
+  /// Playlist of the songs matching the query, sorted by the column
+  /// `sort_key` of Rhythmbox.
+  pub(crate) fn new(
+    name: String,
+    query: Vec<Query>,
+    sort_key: &str,
+    descending: bool,
+    limit_count: Option<u64>,
+    limit_time: Option<u64>,
+  ) -> AutomaticPlaylist {
+    // Rhythmbox restricts the query to the songs the same way
+    let songs = Query::Equals(Criterion {
+      prop: "type".into(),
+      value: "song".into(),
+    });
+    let query = Query::Subquery(Subquery {
+      conjunction: Conjunction { query },
+    });
+    AutomaticPlaylist {
+      name,
+      show_browser: "false".into(),
+      browser_position: 180,
+      search_type: "search-match".into(),
+      sort_key: sort_key.into(),
+      sort_direction: descending.into(),
+      limit_count,
+      limit_time,
+      conjunction: Conjunction {
+        query: vec![songs, query],
+      },
+    }
+  }
+
+  pub(crate) fn name(&self) -> &str {
+    &self.name
+  }
+
+  /// Add the playlist to the playlists of Rhythmbox, `playlists.xml` next to
+  /// the db. The file is created when missing.
+  #[instrument(skip(self))]
+  pub(crate) fn save_in(&self, playlist_path: &str) -> Result<()> {
+    let element = PlaylistElement {
+      kind: "automatic",
+      playlist: self,
+    }
+    .to_xml()?;
+    add_playlist(playlist_path, &self.name, &element)
+  }
 }
 
 #[derive(Clone, Serialize, Deserialize, Debug)]
@@ -50,6 +215,32 @@ pub(crate) struct StaticPlaylist {
   browser_position: u64,
   #[serde(rename = "@search-type")]
   search_type: String,
+  #[serde(default)]
+  location: Vec<Url>,
+}
+
+impl StaticPlaylist {
+  /// Playlist of the tracks, in their order.
+  pub(crate) fn new(name: String, location: Vec<Url>) -> StaticPlaylist {
+    StaticPlaylist {
+      name,
+      show_browser: "true".into(),
+      browser_position: 180,
+      search_type: "search-match".into(),
+      location,
+    }
+  }
+
+  /// Add the playlist to the playlists of Rhythmbox, like the automatic ones.
+  #[instrument(skip(self))]
+  pub(crate) fn save_in(&self, playlist_path: &str) -> Result<()> {
+    let element = PlaylistElement {
+      kind: "static",
+      playlist: self,
+    }
+    .to_xml()?;
+    add_playlist(playlist_path, &self.name, &element)
+  }
 }
 
 #[derive(Clone, Serialize, Deserialize, Debug)]
@@ -77,12 +268,7 @@ impl Playlist {
   }
 
   fn get_path() -> Option<PathBuf> {
-    BaseDirs::new().map(|base_dir| {
-      Path::new(base_dir.data_local_dir())
-        .join("rhythmbox")
-        .join("playlist.toml")
-        .to_path_buf()
-    })
+    state_dir().map(|directory| directory.join("playlist.toml"))
   }
 
   #[instrument]
@@ -98,9 +284,7 @@ impl Playlist {
   #[instrument]
   pub(crate) fn save(&self) -> Result<()> {
     if let Some(path) = Self::get_path() {
-      fs::write(&path, to_string_pretty(self).into_diagnostic()?.as_bytes())
-        .into_diagnostic()
-        .with_context(|| format!("Trying to save `{}`", &path.display()))?;
+      write_state(&path, &to_string_pretty(self).into_diagnostic()?)?;
     }
     Ok(())
   }
@@ -125,24 +309,92 @@ impl Playlist {
     remove_file(Self::get_path().ok_or(miette!("Can't get path"))?).into_diagnostic()
   }
 
+  /// Add the track at the end of the queue. With `skip_queued`, from
+  /// `playback.skip_queued`, a track already queued is skipped: false then.
   #[instrument]
-  pub(crate) fn enqueue(&mut self, track: Url) {
+  pub(crate) fn enqueue(&mut self, track: Url, skip_queued: bool) -> bool {
+    if skip_queued && self.position(&track).is_some() {
+      return false;
+    }
     match self {
       Playlist::Queue(queue) => queue.location.push(track),
       _ => unimplemented!(),
     }
+    true
   }
 
+  /// Insert the track after the one at `after`, the played one, or at the
+  /// head of the queue. With `skip_queued`, a track already queued is moved
+  /// there.
   #[instrument]
-  pub(crate) fn remove(&mut self, track: Url) {
+  pub(crate) fn enqueue_next(&mut self, track: Url, after: Option<usize>, skip_queued: bool) {
     match self {
       Playlist::Queue(queue) => {
-        queue.location.retain(|url| *url != track);
+        let mut index = after.map_or(0, |after| after + 1).min(queue.location.len());
+        if skip_queued {
+          index -= queue.location[..index]
+            .iter()
+            .filter(|url| **url == track)
+            .count();
+          queue.location.retain(|url| *url != track);
+        }
+        queue.location.insert(index, track);
+      }
+      _ => unimplemented!(),
+    }
+  }
+
+  /// Remove the track at the index, and only this one when it is queued
+  /// twice.
+  #[instrument]
+  pub(crate) fn remove_at(&mut self, index: usize) {
+    match self {
+      Playlist::Queue(queue) if index < queue.location.len() => {
+        queue.location.remove(index);
       }
+      Playlist::Queue(_) => {}
       _ => unimplemented!(),
     }
   }
 
+  /// Remove the track from the queue, wherever it is queued.
+  #[instrument]
+  pub(crate) fn remove_all(&mut self, track: &Url) {
+    match self {
+      Playlist::Queue(queue) => queue.location.retain(|url| url != track),
+      _ => unimplemented!(),
+    }
+  }
+
+  /// Index of the track in the queue: the first one from `from`, or before
+  /// when there is none after.
+  #[instrument]
+  pub(crate) fn index_from(&self, track: &Url, from: usize) -> Option<usize> {
+    match self {
+      Playlist::Queue(queue) => queue
+        .location
+        .iter()
+        .skip(from)
+        .position(|url| url == track)
+        .map(|index| index + from)
+        .or_else(|| queue.location.iter().position(|url| url == track)),
+      _ => unimplemented!(),
+    }
+  }
+
+  /// Position of the track in the queue, starting at 1.
+  #[instrument]
+  pub(crate) fn position(&self, track: &Url) -> Option<usize> {
+    match self {
+      Playlist::Queue(queue) => queue
+        .location
+        .iter()
+        .position(|url| url == track)
+        .map(|index| index + 1),
+      _ => None,
+    }
+  }
+
   #[instrument]
   pub(crate) fn queue(&self) -> Vec<Url> {
     match self {
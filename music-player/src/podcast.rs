@@ -0,0 +1,315 @@
+use crate::{
+  args::Podcast,
+  metered::wait_unmetered,
+  rhythmdb::{check_writable, Entry, PodcastFeedEntry, PodcastPostentry, Rhythmdb},
+  settings::Settings,
+};
+use chrono::DateTime;
+use directories::UserDirs;
+use miette::{miette, Context, IntoDiagnostic, Result};
+use serde::Deserialize;
+use std::{
+  cmp::Reverse,
+  path::{Path, PathBuf},
+  sync::Arc,
+};
+use tokio::{fs, io::AsyncWriteExt};
+use tracing::instrument;
+use url::Url;
+
+// Status of a downloaded episode in rhythmbox
+const DOWNLOAD_COMPLETE: u64 = 100;
+
+#[derive(Debug, Deserialize)]
+struct Rss {
+  channel: Channel,
+}
+
+#[derive(Debug, Deserialize)]
+struct Channel {
+  title: String,
+  #[serde(default)]
+  description: String,
+  #[serde(default)]
+  language: String,
+  #[serde(default)]
+  copyright: String,
+  #[serde(rename = "itunes:author", default)]
+  author: String,
+  image: Option<Image>,
+  #[serde(rename = "item", default)]
+  items: Vec<Item>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Image {
+  url: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct Item {
+  title: String,
+  #[serde(default)]
+  description: String,
+  #[serde(rename = "pubDate")]
+  pub_date: Option<String>,
+  #[serde(rename = "itunes:duration")]
+  duration: Option<String>,
+  enclosure: Option<Enclosure>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Enclosure {
+  #[serde(rename = "@url")]
+  url: Url,
+  #[serde(rename = "@length")]
+  length: Option<u64>,
+  #[serde(rename = "@type", default)]
+  mime_type: String,
+}
+
+/// Run a podcast command on the library.
+#[instrument(skip(config))]
+pub(crate) async fn podcast(config: &Settings, command: &Podcast) -> Result<()> {
+  if !matches!(command, Podcast::List) {
+    check_writable()?;
+  }
+  let mut db = Rhythmdb::load(config)?;
+  match command {
+    Podcast::Add { url } => {
+      if db.podcast_feeds().iter().any(|feed| &feed.location == url) {
+        return Err(miette!("The feed '{url}' is already in the library"));
+      }
+      let channel = fetch_channel(url).await?;
+      println!("{}", channel.title);
+      db.add_entry(Entry::PodcastFeed(feed_entry(url, &channel)));
+      let added = add_episodes(&mut db, url, channel);
+      println!("{added} episodes");
+    }
+    Podcast::List => {
+      for feed in db.podcast_feeds() {
+        println!(
+          "{}\t{} episodes\t{}",
+          feed.title,
+          db.podcast_posts(&feed.location).len(),
+          feed.location
+        );
+      }
+      return Ok(());
+    }
+    Podcast::Refresh {
+      feed,
+      allow_metered,
+    } => {
+      wait_unmetered(*allow_metered).await;
+      let feeds: Vec<Url> = matching_feeds(&db, feed.as_deref())
+        .map(|feed| feed.location.clone())
+        .collect();
+      for url in feeds {
+        match fetch_channel(&url).await {
+          Ok(channel) => {
+            let title = channel.title.clone();
+            let added = add_episodes(&mut db, &url, channel);
+            println!("{title}: {added} new episodes");
+          }
+          // A broken feed doesn't stop the refresh of the others
+          Err(e) => eprintln!("{url}: {e}"),
+        }
+      }
+    }
+    Podcast::Download {
+      episode,
+      latest,
+      allow_metered,
+    } => {
+      let mut posts: Vec<PodcastPostentry> = db
+        .podcast_feeds()
+        .iter()
+        .flat_map(|feed| db.podcast_posts(&feed.location))
+        .filter(|post| post.mountpoint.is_none() && matches(post, episode))
+        .cloned()
+        .collect();
+      posts.sort_by_key(|post| Reverse(post.post_time));
+      posts.truncate(latest.unwrap_or(usize::MAX));
+      if !posts.is_empty() {
+        wait_unmetered(*allow_metered).await;
+      }
+      for mut post in posts {
+        println!("{}", post.title);
+        // A failed episode doesn't lose the ones downloaded before
+        let file = match download(&post).await {
+          Ok(file) => file,
+          Err(e) => {
+            eprintln!("{}: {e}", post.title);
+            continue;
+          }
+        };
+        post.mountpoint = Some(
+          Url::from_file_path(&file)
+            .map_err(|_| miette!("Can't parse file path: '{}'", file.display()))?,
+        );
+        post.status = Some(DOWNLOAD_COMPLETE);
+        db.update_entry(Arc::new(Entry::PodcastPost(post)));
+      }
+    }
+  }
+  db.save(config)?;
+  Ok(())
+}
+
+/// The feeds whose title or location contains the text, all of them when none.
+fn matching_feeds<'a>(
+  db: &'a Rhythmdb,
+  text: Option<&'a str>,
+) -> impl Iterator<Item = &'a PodcastFeedEntry> {
+  db.podcast_feeds()
+    .into_iter()
+    .filter(move |feed| match text {
+      Some(text) => {
+        let text = text.to_lowercase();
+        feed.title.to_lowercase().contains(&text) || feed.location.as_str().contains(&text)
+      }
+      None => true,
+    })
+}
+
+/// The episode title, its feed or its location contains the text.
+fn matches(post: &PodcastPostentry, text: &str) -> bool {
+  let text = text.to_lowercase();
+  post.title.to_lowercase().contains(&text)
+    || post.album.to_lowercase().contains(&text)
+    || post.location.as_str() == text
+}
+
+#[instrument]
+async fn fetch_channel(url: &Url) -> Result<Channel> {
+  let body = client()
+    .get(url.clone())
+    .send()
+    .await
+    .into_diagnostic()?
+    .error_for_status()
+    .into_diagnostic()?
+    .text()
+    .await
+    .into_diagnostic()?;
+  let rss: Rss = quick_xml::de::from_str(&body)
+    .into_diagnostic()
+    .with_context(|| format!("'{url}' is not a rss feed"))?;
+  Ok(rss.channel)
+}
+
+fn client() -> reqwest::Client {
+  reqwest::Client::builder()
+    .user_agent(concat!("music-player/", env!("CARGO_PKG_VERSION")))
+    .build()
+    .unwrap_or_default()
+}
+
+#[allow(clippy::field_reassign_with_default)]
+fn feed_entry(url: &Url, channel: &Channel) -> PodcastFeedEntry {
+  let mut feed = PodcastFeedEntry::default();
+  feed.title = channel.title.clone();
+  feed.artist = channel.author.clone();
+  feed.album = channel.title.clone();
+  feed.location = url.clone();
+  feed.last_seen = Some(now());
+  feed.description = channel.description.clone();
+  feed.lang = channel.language.clone();
+  feed.copyright = channel.copyright.clone();
+  feed.image = channel
+    .image
+    .as_ref()
+    .map(|image| image.url.clone())
+    .unwrap_or_default();
+  feed
+}
+
+/// Add the episodes of the channel that are not in the library yet. Returns
+/// the number of episodes added.
+#[allow(clippy::field_reassign_with_default)]
+fn add_episodes(db: &mut Rhythmdb, feed: &Url, channel: Channel) -> usize {
+  let mut added = 0;
+  for item in channel.items {
+    let Some(enclosure) = item.enclosure else {
+      continue;
+    };
+    if db.find_url(&enclosure.url).is_some() {
+      continue;
+    }
+    let mut post = PodcastPostentry::default();
+    post.title = item.title;
+    post.artist = channel.author.clone();
+    post.album = channel.title.clone();
+    post.duration = item.duration.as_deref().and_then(parse_duration);
+    post.file_size = enclosure.length;
+    post.location = enclosure.url;
+    post.first_seen = now();
+    post.last_seen = Some(now());
+    post.media_type = enclosure.mime_type;
+    post.description = item.description;
+    post.subtitle = feed.clone();
+    post.lang = channel.language.clone();
+    post.copyright = channel.copyright.clone();
+    post.post_time = item
+      .pub_date
+      .and_then(|date| DateTime::parse_from_rfc2822(&date).ok())
+      .map(|date| date.timestamp() as u64);
+    db.add_entry(Entry::PodcastPost(post));
+    added += 1;
+  }
+  added
+}
+
+/// `itunes:duration` is in seconds, `mm:ss` or `hh:mm:ss`.
+fn parse_duration(duration: &str) -> Option<u64> {
+  duration.split(':').try_fold(0, |total, part| {
+    Some(total * 60 + part.trim().parse::<u64>().ok()?)
+  })
+}
+
+/// Download the episode in the podcast directory of rhythmbox: `~/Podcasts/<feed>/`.
+#[instrument(skip(post))]
+async fn download(post: &PodcastPostentry) -> Result<PathBuf> {
+  let directory = UserDirs::new()
+    .ok_or(miette!("Can't get the home directory"))?
+    .home_dir()
+    .join("Podcasts")
+    .join(file_name(&post.album.replace('/', "_"), "podcast"));
+  fs::create_dir_all(&directory).await.into_diagnostic()?;
+  let name = post
+    .location
+    .path_segments()
+    .and_then(|mut segments| segments.next_back())
+    .unwrap_or_default();
+  let path = directory.join(file_name(
+    &urlencoding::decode(name).into_diagnostic()?,
+    "episode",
+  ));
+
+  let mut response = client()
+    .get(post.location.clone())
+    .send()
+    .await
+    .into_diagnostic()?
+    .error_for_status()
+    .into_diagnostic()?;
+  let mut file = fs::File::create(&path).await.into_diagnostic()?;
+  while let Some(chunk) = response.chunk().await.into_diagnostic()? {
+    file.write_all(&chunk).await.into_diagnostic()?;
+  }
+  Ok(path)
+}
+
+/// Last component of a name given by the feed, so that the file stays in its
+/// directory: `..`, `.` and the empty names are replaced by the fallback.
+fn file_name(name: &str, fallback: &str) -> PathBuf {
+  match Path::new(name).file_name() {
+    Some(name) => PathBuf::from(name),
+    None => PathBuf::from(fallback),
+  }
+}
+
+fn now() -> u64 {
+  chrono::Local::now().timestamp() as u64
+}
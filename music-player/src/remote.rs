@@ -0,0 +1,211 @@
+use crate::{
+  commands::Track,
+  instance::{handle_request, now_playing_of, NowPlaying, Request},
+  player_state::PlayerState,
+  rhythmdb::Entry,
+  settings::RemoteSettings,
+  ui::{Order, OrderDir},
+};
+use axum::{
+  extract::{self, Query, State},
+  http::{header::AUTHORIZATION, StatusCode},
+  middleware::{self, Next},
+  response::{Html, IntoResponse, Response},
+  routing::{get, post},
+  Json, Router,
+};
+use miette::{miette, IntoDiagnostic, Report, Result, WrapErr};
+use mpris_server::PlayerInterface;
+use serde::Deserialize;
+use std::{ops::Deref, sync::Arc};
+use tokio::net::TcpListener;
+use tracing::{error, instrument};
+use url::Url;
+
+// Tracks returned by a search when no limit is given
+const SEARCH_LIMIT: usize = 50;
+
+type Player = State<&'static PlayerState>;
+
+/// Error of a request, answered with its status and its message.
+struct RemoteError(StatusCode, Report);
+
+impl IntoResponse for RemoteError {
+  fn into_response(self) -> Response {
+    (self.0, self.1.to_string()).into_response()
+  }
+}
+
+impl<E: Into<Report>> From<E> for RemoteError {
+  fn from(e: E) -> Self {
+    RemoteError(StatusCode::INTERNAL_SERVER_ERROR, e.into())
+  }
+}
+
+/// Token given as a query parameter, by the links to the web page.
+#[derive(Debug, Deserialize)]
+struct TokenQuery {
+  token: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Location {
+  location: Url,
+}
+
+#[derive(Debug, Deserialize)]
+struct Seek {
+  // In seconds
+  position: u64,
+}
+
+#[derive(Debug, Deserialize)]
+struct SearchQuery {
+  #[serde(default)]
+  q: String,
+  limit: Option<usize>,
+}
+
+/// Serve the http remote control on the address, to the requests bearing the
+/// token.
+#[instrument(skip(player, settings))]
+pub(crate) async fn listen(player: &'static PlayerState, settings: &RemoteSettings) -> Result<()> {
+  let address = &settings.address;
+  if settings.token.trim().is_empty() {
+    return Err(miette!(
+      help = "Set a secret `token` in the `[remote]` section",
+      "The remote control needs a token"
+    ));
+  }
+  let token: Arc<str> = settings.token.as_str().into();
+  let listener = TcpListener::bind(address)
+    .await
+    .into_diagnostic()
+    .wrap_err_with(|| format!("Can't serve the remote control on `{address}`"))?;
+  let app = Router::new()
+    .route("/", get(page))
+    .route("/api/status", get(status))
+    .route("/api/play", post(play))
+    .route("/api/play-pause", post(play_pause))
+    .route("/api/next", post(next))
+    .route("/api/seek", post(seek))
+    .route("/api/queue", get(queue).post(enqueue))
+    .route("/api/search", get(search))
+    .route_layer(middleware::from_fn_with_state(token, check_token))
+    .with_state(player);
+  tokio::spawn(async move {
+    if let Err(e) = axum::serve(listener, app).await {
+      error!("Remote control stopped: {e}");
+    }
+  });
+  Ok(())
+}
+
+/// Let through the requests with the token, as an `Authorization: Bearer`
+/// header or a `token` query parameter.
+async fn check_token(
+  State(token): State<Arc<str>>,
+  request: extract::Request,
+  next: Next,
+) -> Response {
+  let given = request
+    .headers()
+    .get(AUTHORIZATION)
+    .and_then(|header| header.to_str().ok())
+    .and_then(|header| header.strip_prefix("Bearer "))
+    .map(str::to_string)
+    .or_else(|| {
+      Query::<TokenQuery>::try_from_uri(request.uri())
+        .ok()
+        .and_then(|query| query.0.token)
+    });
+  if given.is_some_and(|given| same_secret(given.as_bytes(), token.as_bytes())) {
+    next.run(request).await
+  } else {
+    (StatusCode::UNAUTHORIZED, "Missing or wrong token").into_response()
+  }
+}
+
+/// Comparison taking the same time wherever the texts differ.
+fn same_secret(given: &[u8], token: &[u8]) -> bool {
+  given.len() == token.len()
+    && given
+      .iter()
+      .zip(token)
+      .fold(0, |difference, (a, b)| difference | (a ^ b))
+      == 0
+}
+
+async fn page() -> Html<&'static str> {
+  Html(include_str!("remote.html"))
+}
+
+async fn status(State(player): Player) -> Result<Json<NowPlaying>, RemoteError> {
+  Ok(Json(now_playing_of(player).await?))
+}
+
+/// Play a song or a podcast episode of the library: the other locations, and
+/// the radios, are refused.
+async fn play(State(player): Player, Json(track): Json<Location>) -> Result<(), RemoteError> {
+  let playable = player
+    .get_db()
+    .await
+    .find_url(&track.location)
+    .is_some_and(|entry| matches!(entry.as_ref(), Entry::Song(_) | Entry::PodcastPost(_)));
+  if !playable {
+    return Err(RemoteError(
+      StatusCode::BAD_REQUEST,
+      miette!(
+        "'{}' is not a song nor a podcast episode of the library",
+        track.location
+      ),
+    ));
+  }
+  handle_request(player, Request::Play(track.location)).await?;
+  Ok(())
+}
+
+async fn play_pause(State(player): Player) -> Result<(), RemoteError> {
+  player.play_pause().await.into_diagnostic()?;
+  Ok(())
+}
+
+async fn next(State(player): Player) -> Result<(), RemoteError> {
+  player.next_track().await?;
+  Ok(())
+}
+
+async fn seek(State(player): Player, Json(seek): Json<Seek>) -> Result<(), RemoteError> {
+  player.track_seek(seek.position).await?;
+  Ok(())
+}
+
+async fn queue(State(player): Player) -> Response {
+  let entries = player
+    .get_db()
+    .await
+    .to_entries(player.get_queue().await.deref());
+  let tracks: Vec<Track> = entries
+    .iter()
+    .map(|entry| Track::from(entry.as_ref()))
+    .collect();
+  Json(tracks).into_response()
+}
+
+async fn enqueue(State(player): Player, Json(track): Json<Location>) -> Result<(), RemoteError> {
+  handle_request(player, Request::Enqueue(track.location)).await?;
+  Ok(())
+}
+
+async fn search(State(player): Player, Query(query): Query<SearchQuery>) -> Response {
+  let entries = player
+    .get_db()
+    .await
+    .filter_by_song(&query.q, Order::Default, OrderDir::Desc);
+  let tracks: Vec<Track> = entries
+    .iter()
+    .take(query.limit.unwrap_or(SEARCH_LIMIT))
+    .map(|entry| Track::from(entry.as_ref()))
+    .collect();
+  Json(tracks).into_response()
+}
@@ -0,0 +1,31 @@
+use crate::{rhythmdb::Entry, settings::PlaybackSettings};
+use std::{
+  sync::Mutex,
+  time::{Duration, Instant},
+};
+
+// Start of the pause of the played track
+static PAUSED_AT: Mutex<Option<Instant>> = Mutex::new(None);
+
+/// Start or end the pause of the played track.
+pub(crate) fn set_paused(paused: bool) {
+  if let Ok(mut paused_at) = PAUSED_AT.lock() {
+    *paused_at = paused.then(Instant::now);
+  }
+}
+
+/// How long the played track is paused.
+pub(crate) fn paused_for() -> Option<Duration> {
+  PAUSED_AT.lock().ok()?.map(|paused_at| paused_at.elapsed())
+}
+
+/// Rewind of the entry resumed after the pause, from `playback.resume_rewind`
+/// and `playback.resume_after`: none for the music, or after a short pause.
+pub(crate) fn rewind(settings: &PlaybackSettings, entry: &Entry, paused_for: Duration) -> Duration {
+  let rewind = settings.resume_rewind;
+  let after = Duration::from_secs(settings.resume_after * 60);
+  match entry {
+    Entry::PodcastPost(_) if rewind > 0 && paused_for >= after => Duration::from_secs(rewind),
+    _ => Duration::ZERO,
+  }
+}
@@ -1,16 +1,28 @@
 use crate::{
+  metrics,
   playlists::Playlist,
-  settings::Settings,
+  settings::{SearchWeights, Settings},
   ui::{Order, OrderDir},
 };
+use chrono::{DateTime, Datelike, NaiveDate};
 use fuzzy_matcher::{skim::SkimMatcherV2, FuzzyMatcher};
 use id3::Tag;
 use itertools::Itertools;
-use miette::{IntoDiagnostic, Result};
+use miette::{miette, IntoDiagnostic, Result, WrapErr};
 use quick_xml::{de::from_reader, impl_deserialize_for_internally_tagged_enum};
-use serde::{Deserialize, Serialize};
-use std::{fs::File, io::BufReader, str::FromStr, sync::Arc};
-use tracing::instrument;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::{
+  cmp::Ordering,
+  collections::{BTreeMap, BTreeSet, HashMap, HashSet},
+  fs::{self, File},
+  io::{BufRead, BufReader, Write},
+  ops::RangeInclusive,
+  path::Path,
+  str::FromStr,
+  sync::Arc,
+  time::SystemTime,
+};
+use tracing::{debug, instrument, warn};
 use url::Url;
 
 pub(crate) type SharedEntry = Arc<Entry>;
@@ -24,6 +36,45 @@ pub(crate) struct Rhythmdb {
   entry: EntryList,
   #[serde(skip)]
   first_played: u64,
+  #[serde(skip)]
+  merged: Vec<MergedDb>,
+  // Locations of the entries changed during the session, to merge them in a
+  // db file modified by another program
+  #[serde(skip)]
+  changed: HashSet<Url>,
+  // Locations of the entries removed during the session, likewise
+  #[serde(skip)]
+  removed: HashSet<Url>,
+  // Modification time of the db files when they were last read or written
+  #[serde(skip)]
+  mtimes: HashMap<String, SystemTime>,
+  #[serde(skip)]
+  pub(crate) search_weights: SearchWeights,
+}
+
+/// Outcome of a save.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub(crate) enum Saved {
+  Written,
+  // Rhythmbox runs: the changes are kept for a later save
+  Deferred,
+}
+
+/// Db merged for the session: its entries are at the end of the list, and
+/// they are saved back in its own file.
+struct MergedDb {
+  path: String,
+  version: String,
+  len: usize,
+}
+
+/// Content of a db file.
+#[derive(Serialize)]
+#[serde(rename = "rhythmdb")]
+struct RhythmdbFile<'a> {
+  #[serde(rename = "@version")]
+  version: &'a str,
+  entry: &'a [SharedEntry],
 }
 
 impl Rhythmdb {
@@ -33,30 +84,45 @@ impl Rhythmdb {
       version: String::new(),
       entry: vec![],
       first_played: 0,
+      merged: vec![],
+      changed: HashSet::new(),
+      removed: HashSet::new(),
+      mtimes: HashMap::new(),
+      search_weights: SearchWeights::default(),
     }
   }
 
   #[instrument(skip(self))]
   pub fn update_entry(&mut self, entry: SharedEntry) -> SharedEntry {
-    let mut index = 0;
+    let mut index = None;
     for (i, e) in self.entry.iter().enumerate() {
       match (entry.as_ref(), e.as_ref()) {
         (Entry::Song(e1), Entry::Song(e2)) => {
           if e1._internal_id == e2._internal_id {
-            index = i;
+            index = Some(i);
             break;
           }
         }
         (Entry::PodcastPost(p1), Entry::PodcastPost(p2)) => {
           if p1._internal_id == p2._internal_id {
-            index = i;
+            index = Some(i);
             break;
           }
         }
         _ => {}
       }
     }
-    self.entry[index] = entry.clone();
+    // The entries read again from a modified db file have new ids
+    let index = index.or_else(|| {
+      self
+        .entry
+        .iter()
+        .position(|e| e.get_location() == entry.get_location())
+    });
+    if let Some(index) = index {
+      self.entry[index] = entry.clone();
+    }
+    self.changed.insert(entry.get_location());
     entry
   }
 
@@ -96,17 +162,23 @@ impl Rhythmdb {
   }
 
   pub(crate) fn clean_ignored_entries(config: &Settings) -> Result<()> {
+    check_writable()?;
     let db = Rhythmdb::load(config)?;
-    let new_db = Rhythmdb {
-      version: db.version,
-      entry: db
-        .entry
-        .into_iter()
+    for (path, version, entries) in db.files(&config.playlist_path) {
+      let entries: EntryList = entries
+        .iter()
         .filter(|e| !matches!(e.as_ref(), Entry::Ignore(_)))
-        .collect(),
-      first_played: db.first_played,
-    };
-    new_db.save(config)
+        .cloned()
+        .collect();
+      write_file(
+        Path::new(path),
+        &RhythmdbFile {
+          version,
+          entry: &entries,
+        },
+      )?;
+    }
+    Ok(())
   }
 }
 
@@ -134,6 +206,77 @@ impl Entry {
     }
   }
 
+  #[instrument(skip(self))]
+  pub(crate) fn get_title(&self) -> &str {
+    match self {
+      Entry::Iradio(r) => &r.title,
+      Entry::Ignore(i) => &i.title,
+      Entry::PodcastFeed(p) => &p.title,
+      Entry::Song(song) => &song.title,
+      Entry::PodcastPost(p) => &p.title,
+    }
+  }
+
+  #[instrument(skip(self))]
+  pub(crate) fn get_artist(&self) -> &str {
+    match self {
+      Entry::Song(song) => &song.artist,
+      Entry::PodcastPost(podcast) => &podcast.artist,
+      _ => "",
+    }
+  }
+
+  /// Album artist of a song, empty when unknown.
+  pub(crate) fn get_album_artist(&self) -> &str {
+    match self {
+      Entry::Song(song) => song.album_artist.as_deref().unwrap_or_default(),
+      _ => "",
+    }
+  }
+
+  /// Album of a song, feed of a podcast.
+  #[instrument(skip(self))]
+  pub(crate) fn get_album(&self) -> &str {
+    match self {
+      Entry::Song(song) => &song.album,
+      Entry::PodcastPost(podcast) => &podcast.album,
+      _ => "",
+    }
+  }
+
+  /// Composer of a song, empty when unknown.
+  pub(crate) fn get_composer(&self) -> &str {
+    match self {
+      Entry::Song(song) => &song.composer,
+      _ => "",
+    }
+  }
+
+  /// Disc of a song in its album, unset when unknown.
+  pub(crate) fn get_disc(&self) -> Option<u64> {
+    match self {
+      Entry::Song(song) => song.disc_number,
+      _ => None,
+    }
+  }
+
+  /// Number of a song on its disc, unset when unknown.
+  pub(crate) fn get_track_number(&self) -> Option<u64> {
+    match self {
+      Entry::Song(song) => song.track_number,
+      _ => None,
+    }
+  }
+
+  /// Work of a classical song, from the sort name of its album.
+  pub(crate) fn get_work(&self) -> Option<&str> {
+    match self {
+      Entry::Song(song) => song.album_sortname.as_deref(),
+      _ => None,
+    }
+    .filter(|work| !work.is_empty())
+  }
+
   #[instrument(skip(self))]
   pub(crate) fn get_location(&self) -> Url {
     match self {
@@ -145,12 +288,20 @@ impl Entry {
     }
   }
 
+  /// Location to play: the downloaded file of a podcast episode.
+  #[instrument(skip(self))]
+  pub(crate) fn get_playback_location(&self) -> Url {
+    match self {
+      Entry::PodcastPost(p) => p.mountpoint.clone().unwrap_or_else(|| p.location.clone()),
+      _ => self.get_location(),
+    }
+  }
+
   #[instrument(skip(self))]
   pub(crate) fn get_duration(&self) -> u64 {
     match self {
-      Entry::Iradio(_) => todo!(),
-      Entry::Ignore(_) => todo!(),
-      Entry::PodcastFeed(_) => todo!(),
+      // The streams and the feeds have no duration
+      Entry::Iradio(_) | Entry::Ignore(_) | Entry::PodcastFeed(_) => 0,
       Entry::Song(song) => song.duration.unwrap_or_default(),
       Entry::PodcastPost(podcast) => podcast.duration.unwrap_or_default(),
     }
@@ -176,6 +327,87 @@ impl Entry {
       Entry::PodcastPost(podcast) => podcast.post_time.unwrap_or_default(),
     }
   }
+
+  #[instrument(skip(self))]
+  pub(crate) fn get_rating(&self) -> Rating {
+    match self {
+      Entry::Song(song) => song.rating.unwrap_or_default(),
+      Entry::PodcastPost(podcast) => podcast.rating.unwrap_or_default(),
+      _ => Rating::default(),
+    }
+  }
+
+  #[instrument(skip(self))]
+  pub(crate) fn get_play_count(&self) -> u64 {
+    match self {
+      Entry::Song(song) => song.play_count.unwrap_or_default(),
+      Entry::PodcastPost(podcast) => podcast.play_count.unwrap_or_default(),
+      _ => 0,
+    }
+  }
+
+  #[instrument(skip(self))]
+  pub(crate) fn get_last_played(&self) -> Option<u64> {
+    match self {
+      Entry::Song(song) => song.last_played,
+      Entry::PodcastPost(podcast) => podcast.last_played,
+      _ => None,
+    }
+  }
+
+  #[instrument(skip(self))]
+  pub(crate) fn get_genre(&self) -> &str {
+    match self {
+      Entry::Song(song) => &song.genre,
+      Entry::PodcastPost(podcast) => &podcast.genre,
+      _ => "",
+    }
+  }
+
+  /// Beats per minute of a song, from the `beats-per-minute` field.
+  #[instrument(skip(self))]
+  pub(crate) fn get_bpm(&self) -> Option<f64> {
+    match self {
+      Entry::Song(song) => song.bpm(),
+      _ => None,
+    }
+  }
+
+  /// Comment of a song or an episode, unset when empty.
+  #[instrument(skip(self))]
+  pub(crate) fn get_comment(&self) -> Option<&str> {
+    match self {
+      Entry::Song(song) => song.comment.as_deref(),
+      Entry::PodcastPost(podcast) => podcast.comment.as_deref(),
+      _ => None,
+    }
+    .filter(|comment| !comment.is_empty())
+  }
+
+  /// MusicBrainz id of the release of a song.
+  #[instrument(skip(self))]
+  pub(crate) fn get_mb_albumid(&self) -> Option<&str> {
+    match self {
+      Entry::Song(song) => song.mb_albumid.as_deref().filter(|id| !id.is_empty()),
+      _ => None,
+    }
+  }
+
+  /// Release year of a song, publication year of a podcast.
+  #[instrument(skip(self))]
+  pub(crate) fn get_year(&self) -> Option<i32> {
+    match self {
+      // Rhythmbox stores the release date as a julian day
+      Entry::Song(song) if song.date > 0 => {
+        NaiveDate::from_num_days_from_ce_opt(song.date as i32).map(|date| date.year())
+      }
+      Entry::PodcastPost(podcast) => podcast
+        .post_time
+        .and_then(|time| DateTime::from_timestamp(time as i64, 0))
+        .map(|date| date.year()),
+      _ => None,
+    }
+  }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -220,25 +452,25 @@ pub(crate) struct IIgnoreEntry {
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub(crate) struct PodcastFeedEntry {
-  title: String,
-  genre: String,
-  artist: String,
-  album: String,
-  location: Url,
+  pub(crate) title: String,
+  pub(crate) genre: String,
+  pub(crate) artist: String,
+  pub(crate) album: String,
+  pub(crate) location: Url,
   #[serde(rename = "last-seen")]
-  last_seen: Option<u64>,
-  date: u64,
+  pub(crate) last_seen: Option<u64>,
+  pub(crate) date: u64,
   #[serde(rename = "media-type")]
-  media_type: String,
+  pub(crate) media_type: String,
   #[serde(skip_serializing_if = "Option::is_none")]
   status: Option<String>,
-  description: String,
+  pub(crate) description: String,
   subtitle: String,
   #[serde(skip_serializing_if = "Option::is_none")]
   summary: Option<String>,
-  lang: String,
-  copyright: String,
-  image: String,
+  pub(crate) lang: String,
+  pub(crate) copyright: String,
+  pub(crate) image: String,
   #[serde(rename = "post-time", skip_serializing_if = "Option::is_none")]
   post_time: Option<u64>,
   #[serde(skip_serializing_if = "Option::is_none")]
@@ -250,35 +482,35 @@ pub(crate) struct SongEntry {
   #[serde(skip_serializing, default = "gen_internal_id")]
   pub(crate) _internal_id: u64,
   pub(crate) title: String,
-  genre: String,
+  pub(crate) genre: String,
   pub(crate) artist: String,
   pub(crate) album: String,
   #[serde(skip_serializing_if = "Option::is_none")]
   #[serde(rename = "track-number")]
-  track_number: Option<u64>,
+  pub(crate) track_number: Option<u64>,
   #[serde(rename = "track-total")]
   #[serde(skip_serializing_if = "Option::is_none")]
-  track_total: Option<u64>,
+  pub(crate) track_total: Option<u64>,
   #[serde(rename = "disc-number")]
   #[serde(skip_serializing_if = "Option::is_none")]
-  disc_number: Option<u64>,
+  pub(crate) disc_number: Option<u64>,
   #[serde(rename = "disc-total")]
   #[serde(skip_serializing_if = "Option::is_none")]
-  disc_total: Option<u64>,
+  pub(crate) disc_total: Option<u64>,
   #[serde(skip_serializing_if = "Option::is_none")]
   pub(crate) duration: Option<u64>,
   #[serde(rename = "file-size")]
-  file_size: String,
+  pub(crate) file_size: String,
   pub(crate) location: Url,
   #[serde(skip_serializing_if = "Option::is_none")]
   mountpoint: Option<Url>,
-  mtime: u64,
+  pub(crate) mtime: u64,
   #[serde(rename = "first-seen")]
-  first_seen: u64,
+  pub(crate) first_seen: u64,
   #[serde(rename = "last-seen")]
   last_seen: Option<u64>,
   #[serde(skip_serializing_if = "Option::is_none")]
-  pub(crate) rating: Option<u64>,
+  pub(crate) rating: Option<Rating>,
   #[serde(rename = "play-count")]
   #[serde(skip_serializing_if = "Option::is_none")]
   pub(crate) play_count: Option<u64>,
@@ -286,31 +518,43 @@ pub(crate) struct SongEntry {
   #[serde(skip_serializing_if = "Option::is_none")]
   pub(crate) last_played: Option<u64>,
   #[serde(skip_serializing_if = "Option::is_none")]
-  bitrate: Option<u64>,
-  date: u64,
+  pub(crate) bitrate: Option<u64>,
+  pub(crate) date: u64,
   #[serde(rename = "media-type")]
-  media_type: String,
+  pub(crate) media_type: String,
   #[serde(skip_serializing_if = "Option::is_none")]
-  hidden: Option<u64>,
+  pub(crate) hidden: Option<u64>,
   #[serde(skip_serializing_if = "Option::is_none")]
-  comment: Option<String>,
+  pub(crate) comment: Option<String>,
   #[serde(skip_serializing_if = "Option::is_none", rename = "mb-trackid")]
-  mb_trackid: Option<String>,
+  pub(crate) mb_trackid: Option<String>,
   #[serde(skip_serializing_if = "Option::is_none", rename = "mb-artistid")]
-  mb_artistid: Option<String>,
+  pub(crate) mb_artistid: Option<String>,
   #[serde(skip_serializing_if = "Option::is_none", rename = "mb-albumid")]
-  mb_albumid: Option<String>,
+  pub(crate) mb_albumid: Option<String>,
   #[serde(skip_serializing_if = "Option::is_none", rename = "mb-albumartistid")]
-  mb_albumartistid: Option<String>,
+  pub(crate) mb_albumartistid: Option<String>,
   #[serde(skip_serializing_if = "Option::is_none", rename = "mb-artistsortname")]
   mb_artistsortname: Option<String>,
   #[serde(skip_serializing_if = "Option::is_none", rename = "album-sortname")]
   album_sortname: Option<String>,
   #[serde(skip_serializing_if = "Option::is_none", rename = "album-artist")]
-  album_artist: Option<String>,
+  pub(crate) album_artist: Option<String>,
   #[serde(skip_serializing_if = "Option::is_none", rename = "beats-per-minute")]
-  beats_per_minute: Option<String>,
-  composer: String,
+  pub(crate) beats_per_minute: Option<String>,
+  #[serde(rename = "replaygain-track-gain")]
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub(crate) track_gain: Option<f64>,
+  #[serde(rename = "replaygain-track-peak")]
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub(crate) track_peak: Option<f64>,
+  #[serde(rename = "replaygain-album-gain")]
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub(crate) album_gain: Option<f64>,
+  #[serde(rename = "replaygain-album-peak")]
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub(crate) album_peak: Option<f64>,
+  pub(crate) composer: String,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -318,7 +562,7 @@ pub(crate) struct PodcastPostentry {
   #[serde(skip_serializing, default = "gen_internal_id")]
   pub(crate) _internal_id: u64,
   pub(crate) title: String,
-  genre: String,
+  pub(crate) genre: String,
   pub(crate) artist: String,
   pub(crate) album: String,
   #[serde(rename = "track-number")]
@@ -328,16 +572,16 @@ pub(crate) struct PodcastPostentry {
   pub(crate) duration: Option<u64>,
   #[serde(rename = "file-size")]
   #[serde(skip_serializing_if = "Option::is_none")]
-  file_size: Option<u64>,
+  pub(crate) file_size: Option<u64>,
   pub(crate) location: Url,
   #[serde(skip_serializing_if = "Option::is_none")]
-  mountpoint: Option<Url>,
+  pub(crate) mountpoint: Option<Url>,
   #[serde(rename = "first-seen")]
-  first_seen: u64,
+  pub(crate) first_seen: u64,
   #[serde(skip_serializing_if = "Option::is_none", rename = "last-seen")]
-  last_seen: Option<u64>,
+  pub(crate) last_seen: Option<u64>,
   #[serde(skip_serializing_if = "Option::is_none")]
-  pub(crate) rating: Option<u64>,
+  pub(crate) rating: Option<Rating>,
   #[serde(skip_serializing_if = "Option::is_none", rename = "play-count")]
   pub(crate) play_count: Option<u64>,
   #[serde(skip_serializing_if = "Option::is_none")]
@@ -347,24 +591,87 @@ pub(crate) struct PodcastPostentry {
   bitrate: Option<u64>,
   pub(crate) date: u64,
   #[serde(rename = "media-type")]
-  media_type: String,
+  pub(crate) media_type: String,
   #[serde(skip_serializing_if = "Option::is_none")]
-  hidden: Option<u64>,
+  pub(crate) hidden: Option<u64>,
   #[serde(skip_serializing_if = "Option::is_none")]
-  status: Option<u64>,
-  description: String,
-  subtitle: Url,
+  pub(crate) status: Option<u64>,
+  pub(crate) description: String,
+  pub(crate) subtitle: Url,
   #[serde(skip_serializing_if = "Option::is_none")]
   summary: Option<String>,
-  lang: String,
-  copyright: String,
-  image: String,
+  pub(crate) lang: String,
+  pub(crate) copyright: String,
+  pub(crate) image: String,
   #[serde(rename = "post-time", skip_serializing_if = "Option::is_none")]
   pub(crate) post_time: Option<u64>,
   #[serde(skip_serializing_if = "Option::is_none")]
   comment: Option<String>,
 }
 
+/// Rating counted in half stars, from 0 to 10. Rhythmbox writes whole stars
+/// from 0 to 5: the halves are written as decimals, like `3.5`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub(crate) struct Rating(u64);
+
+impl Rating {
+  pub(crate) fn from_stars(stars: u64) -> Rating {
+    Rating((stars * 2).min(10))
+  }
+
+  pub(crate) fn from_halves(halves: u64) -> Rating {
+    Rating(halves.min(10))
+  }
+
+  /// Whole stars, the half left out.
+  pub(crate) fn stars(self) -> u64 {
+    self.0 / 2
+  }
+
+  pub(crate) fn has_half(self) -> bool {
+    self.0 % 2 == 1
+  }
+
+  pub(crate) fn stars_f64(self) -> f64 {
+    self.0 as f64 / 2.0
+  }
+
+  /// Rating one step higher: a half star, or the next whole star.
+  pub(crate) fn raised(self, half_stars: bool) -> Rating {
+    if half_stars {
+      Rating::from_halves(self.0 + 1)
+    } else {
+      Rating::from_stars(self.stars() + 1)
+    }
+  }
+
+  /// Rating one step lower: a half star, or the previous whole star.
+  pub(crate) fn lowered(self, half_stars: bool) -> Rating {
+    if half_stars {
+      Rating(self.0.saturating_sub(1))
+    } else {
+      Rating::from_stars(self.0.div_ceil(2).saturating_sub(1))
+    }
+  }
+}
+
+impl Serialize for Rating {
+  fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+    if self.has_half() {
+      serializer.serialize_f64(self.stars_f64())
+    } else {
+      serializer.serialize_u64(self.stars())
+    }
+  }
+}
+
+impl<'de> Deserialize<'de> for Rating {
+  fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Rating, D::Error> {
+    let stars = f64::deserialize(deserializer)?;
+    Ok(Rating((stars * 2.0).round().clamp(0.0, 10.0) as u64))
+  }
+}
+
 impl_deserialize_for_internally_tagged_enum! {
     Entry, "@type",
     ("iradio" => Iradio(IRadioEntry)),
@@ -374,6 +681,17 @@ impl_deserialize_for_internally_tagged_enum! {
     ("podcast-post" => PodcastPost(PodcastPostentry)),
 }
 
+impl SongEntry {
+  /// Unset when missing or zero, Rhythmbox writes 0 for the unknown ones.
+  pub(crate) fn bpm(&self) -> Option<f64> {
+    self
+      .beats_per_minute
+      .as_deref()
+      .and_then(|bpm| bpm.parse().ok())
+      .filter(|bpm| *bpm > 0.0)
+  }
+}
+
 impl Default for SongEntry {
   fn default() -> Self {
     Self {
@@ -398,6 +716,10 @@ impl Default for SongEntry {
       comment: Default::default(),
       composer: Default::default(),
       beats_per_minute: Default::default(),
+      track_gain: Default::default(),
+      track_peak: Default::default(),
+      album_gain: Default::default(),
+      album_peak: Default::default(),
       album_artist: Default::default(),
       disc_number: Default::default(),
       disc_total: Default::default(),
@@ -414,6 +736,65 @@ impl Default for SongEntry {
   }
 }
 
+impl Default for PodcastFeedEntry {
+  fn default() -> Self {
+    Self {
+      title: Default::default(),
+      genre: "Podcast".to_string(),
+      artist: Default::default(),
+      album: Default::default(),
+      location: Url::from_str("file:///").expect("Default URL"),
+      last_seen: Default::default(),
+      date: Default::default(),
+      media_type: Default::default(),
+      status: Default::default(),
+      description: Default::default(),
+      subtitle: Default::default(),
+      summary: Default::default(),
+      lang: Default::default(),
+      copyright: Default::default(),
+      image: Default::default(),
+      post_time: Default::default(),
+      comment: Default::default(),
+    }
+  }
+}
+
+impl Default for PodcastPostentry {
+  fn default() -> Self {
+    Self {
+      _internal_id: gen_internal_id(),
+      title: Default::default(),
+      genre: "Podcast".to_string(),
+      artist: Default::default(),
+      album: Default::default(),
+      track_number: Default::default(),
+      duration: Default::default(),
+      file_size: Default::default(),
+      location: Url::from_str("file:///").expect("Default URL"),
+      mountpoint: Default::default(),
+      first_seen: Default::default(),
+      last_seen: Default::default(),
+      rating: Default::default(),
+      play_count: Default::default(),
+      last_played: Default::default(),
+      bitrate: Default::default(),
+      date: Default::default(),
+      media_type: Default::default(),
+      hidden: Default::default(),
+      status: Default::default(),
+      description: Default::default(),
+      subtitle: Url::from_str("file:///").expect("Default URL"),
+      summary: Default::default(),
+      lang: Default::default(),
+      copyright: Default::default(),
+      image: Default::default(),
+      post_time: Default::default(),
+      comment: Default::default(),
+    }
+  }
+}
+
 impl From<Tag> for SongEntry {
   #[allow(clippy::field_reassign_with_default)]
   #[instrument]
@@ -427,36 +808,177 @@ impl From<Tag> for SongEntry {
 }
 
 impl Rhythmdb {
+  /// Load the db, and the dbs merged for the session after it.
   #[instrument]
   pub(crate) fn load(settings: &Settings) -> Result<Rhythmdb> {
-    let file = File::open(&settings.playlist_path).into_diagnostic()?;
-    let reader = BufReader::new(file);
+    let mut db = read_file(&settings.playlist_path)?;
+    db.record_mtime(&settings.playlist_path);
+    db.search_weights = settings.search_weights;
+    for path in &settings.merged_db_paths {
+      let merged = read_file(path)?;
+      db.record_mtime(path);
+      db.merged.push(MergedDb {
+        path: path.clone(),
+        version: merged.version,
+        len: merged.entry.len(),
+      });
+      db.entry.extend(merged.entry);
+    }
+    Ok(db)
+  }
 
-    from_reader(reader).into_diagnostic()
+  /// Save the entries in their own db file. A file modified by another
+  /// program since it was read is read again and only the entries changed
+  /// during the session are replaced. While Rhythmbox runs, the changes are
+  /// kept for a later save: it would overwrite them when it quits.
+  #[instrument(skip(self, settings))]
+  pub(crate) fn save(&mut self, settings: &Settings) -> Result<Saved> {
+    self.save_in(&settings.playlist_path)
   }
 
+  /// Save the db, its main file at `playlist_path`.
   #[instrument(skip(self))]
-  pub(crate) fn save(&self, settings: &Settings) -> Result<()> {
-    use memmap2::MmapMut;
-    use quick_xml::se::Serializer;
-    use std::fs::OpenOptions;
+  pub(crate) fn save_in(&mut self, playlist_path: &str) -> Result<Saved> {
+    if rhythmbox_running() {
+      warn!("Rhythmbox is running: the changes are saved once it is closed");
+      return Ok(Saved::Deferred);
+    }
+    let _timer = metrics::Timer::start(metrics::db_saved);
+    let mut files = vec![];
+    for (path, version, entries) in self.files(playlist_path) {
+      let (version, entries) = if self.modified_elsewhere(path) {
+        debug!("`{path}` was modified, merging the changes");
+        let disk = read_file(path)?;
+        (
+          disk.version,
+          merge_changes(disk.entry, entries, &self.changed, &self.removed),
+        )
+      } else {
+        (version.to_string(), entries.to_vec())
+      };
+      write_file(
+        Path::new(path),
+        &RhythmdbFile {
+          version: &version,
+          entry: &entries,
+        },
+      )?;
+      files.push((path.to_string(), version, entries));
+    }
+
+    // The entries are now the ones of the files
+    self.changed.clear();
+    self.removed.clear();
+    self.entry.clear();
+    let mut merged = self.merged.iter_mut();
+    for (index, (path, version, entries)) in files.into_iter().enumerate() {
+      if let Some(mtime) = mtime(&path) {
+        self.mtimes.insert(path, mtime);
+      }
+      if index == 0 {
+        self.version = version;
+      } else if let Some(merged) = merged.next() {
+        merged.version = version;
+        merged.len = entries.len();
+      }
+      self.entry.extend(entries);
+    }
+    Ok(Saved::Written)
+  }
 
-    let mut buffer = String::new();
-    let ser = Serializer::new(&mut buffer);
-    self.serialize(ser).into_diagnostic()?;
+  /// Changes not saved yet: Rhythmbox was running.
+  pub(crate) fn has_pending_changes(&self) -> bool {
+    !self.changed.is_empty() || !self.removed.is_empty()
+  }
 
-    let file = OpenOptions::new()
-      .read(true)
-      .write(true)
-      .open(&settings.playlist_path)
-      .into_diagnostic()?;
-    let slice = buffer.as_bytes();
-    file.set_len(slice.len() as u64).into_diagnostic()?;
+  fn record_mtime(&mut self, path: &str) {
+    if let Some(mtime) = mtime(path) {
+      self.mtimes.insert(path.to_string(), mtime);
+    }
+  }
 
-    let mut mmap = unsafe { MmapMut::map_mut(&file).into_diagnostic()? };
-    mmap.copy_from_slice(slice);
+  fn modified_elsewhere(&self, path: &str) -> bool {
+    mtime(path) != self.mtimes.get(path).copied()
+  }
 
-    Ok(())
+  /// Read a db from its xml: a file, or a fixture in memory.
+  #[instrument(skip(reader))]
+  pub(crate) fn read<R: BufRead>(reader: R) -> Result<Rhythmdb> {
+    from_reader(reader).into_diagnostic()
+  }
+
+  /// Write all the entries as xml, like in a db file.
+  #[instrument(skip(self, writer))]
+  pub(crate) fn write<W: Write>(&self, writer: W) -> Result<()> {
+    write_content(
+      writer,
+      &RhythmdbFile {
+        version: &self.version,
+        entry: &self.entry,
+      },
+    )
+  }
+
+  /// Save all the entries in a single file.
+  pub(crate) fn save_to(&self, path: &Path) -> Result<()> {
+    write_file(
+      path,
+      &RhythmdbFile {
+        version: &self.version,
+        entry: &self.entry,
+      },
+    )
+  }
+
+  /// Path, version and entries of each db file.
+  fn files<'a>(&'a self, playlist_path: &'a str) -> Vec<(&'a str, &'a str, &'a [SharedEntry])> {
+    let (main, mut rest) = self.entry.split_at(self.main_len());
+    let mut files = vec![(playlist_path, self.version.as_str(), main)];
+    for merged in &self.merged {
+      let (entries, tail) = rest.split_at(merged.len);
+      files.push((merged.path.as_str(), merged.version.as_str(), entries));
+      rest = tail;
+    }
+    files
+  }
+
+  /// Number of entries of the main db, the merged ones are after them.
+  fn main_len(&self) -> usize {
+    self.entry.len() - self.merged.iter().map(|merged| merged.len).sum::<usize>()
+  }
+
+  #[cfg(any(feature = "podcast", feature = "beets"))]
+  #[instrument(skip(self))]
+  pub(crate) fn add_entry(&mut self, entry: Entry) {
+    self.changed.insert(entry.get_location());
+    self.entry.insert(self.main_len(), Arc::new(entry));
+  }
+
+  #[cfg(feature = "podcast")]
+  #[instrument(skip(self))]
+  pub(crate) fn podcast_feeds(&self) -> Vec<&PodcastFeedEntry> {
+    self
+      .entry
+      .iter()
+      .filter_map(|entry| match entry.as_ref() {
+        Entry::PodcastFeed(feed) => Some(feed),
+        _ => None,
+      })
+      .collect()
+  }
+
+  /// Episodes of the feed: rhythmbox stores the feed location in their subtitle.
+  #[cfg(feature = "podcast")]
+  #[instrument(skip(self))]
+  pub(crate) fn podcast_posts(&self, feed: &Url) -> Vec<&PodcastPostentry> {
+    self
+      .entry
+      .iter()
+      .filter_map(|entry| match entry.as_ref() {
+        Entry::PodcastPost(post) if &post.subtitle == feed => Some(post),
+        _ => None,
+      })
+      .collect()
   }
 
   #[instrument(skip(self))]
@@ -481,6 +1003,7 @@ impl Rhythmdb {
     order_dir: OrderDir,
   ) -> EntryList {
     tracing::trace!("[{search}]");
+    let _timer = metrics::Timer::start(metrics::searched);
     let matcher = SkimMatcherV2::default().smart_case();
     let sort_fn = match (order_by, order_dir) {
       (Order::Default, OrderDir::Asc) => {
@@ -537,6 +1060,76 @@ impl Rhythmdb {
           _ => unimplemented!(),
         }
       }
+      (Order::Artist, OrderDir::Asc) => {
+        |(_, a): &(i64, &SharedEntry), (_, b): &(i64, &SharedEntry)| {
+          Ord::cmp(a.get_artist(), b.get_artist())
+        }
+      }
+      (Order::Artist, OrderDir::Desc) => {
+        |(_, a): &(i64, &SharedEntry), (_, b): &(i64, &SharedEntry)| {
+          Ord::cmp(b.get_artist(), a.get_artist())
+        }
+      }
+      (Order::Album, OrderDir::Asc) => {
+        |(_, a): &(i64, &SharedEntry), (_, b): &(i64, &SharedEntry)| {
+          Ord::cmp(a.get_album(), b.get_album())
+        }
+      }
+      (Order::Album, OrderDir::Desc) => {
+        |(_, a): &(i64, &SharedEntry), (_, b): &(i64, &SharedEntry)| {
+          Ord::cmp(b.get_album(), a.get_album())
+        }
+      }
+      (Order::Duration, OrderDir::Asc) => {
+        |(_, a): &(i64, &SharedEntry), (_, b): &(i64, &SharedEntry)| {
+          Ord::cmp(&a.get_duration(), &b.get_duration())
+        }
+      }
+      (Order::Duration, OrderDir::Desc) => {
+        |(_, a): &(i64, &SharedEntry), (_, b): &(i64, &SharedEntry)| {
+          Ord::cmp(&b.get_duration(), &a.get_duration())
+        }
+      }
+      (Order::Bpm, OrderDir::Asc) => |(_, a): &(i64, &SharedEntry), (_, b): &(i64, &SharedEntry)| {
+        PartialOrd::partial_cmp(&a.get_bpm(), &b.get_bpm()).unwrap_or(Ordering::Equal)
+      },
+      (Order::Bpm, OrderDir::Desc) => |(_, a): &(i64, &SharedEntry), (_, b): &(i64, &SharedEntry)| {
+        PartialOrd::partial_cmp(&b.get_bpm(), &a.get_bpm()).unwrap_or(Ordering::Equal)
+      },
+      (Order::Composer, OrderDir::Asc) => {
+        |(_, a): &(i64, &SharedEntry), (_, b): &(i64, &SharedEntry)| {
+          Ord::cmp(a.get_composer(), b.get_composer())
+        }
+      }
+      (Order::Composer, OrderDir::Desc) => {
+        |(_, a): &(i64, &SharedEntry), (_, b): &(i64, &SharedEntry)| {
+          Ord::cmp(b.get_composer(), a.get_composer())
+        }
+      }
+      (Order::Work, OrderDir::Asc) => |(_, a): &(i64, &SharedEntry), (_, b): &(i64, &SharedEntry)| {
+        Ord::cmp(&a.get_work(), &b.get_work())
+      },
+      (Order::Work, OrderDir::Desc) => {
+        |(_, a): &(i64, &SharedEntry), (_, b): &(i64, &SharedEntry)| {
+          Ord::cmp(&b.get_work(), &a.get_work())
+        }
+      }
+      (Order::Year, OrderDir::Asc) => |(_, a): &(i64, &SharedEntry), (_, b): &(i64, &SharedEntry)| {
+        Ord::cmp(&a.get_year(), &b.get_year())
+      },
+      (Order::Year, OrderDir::Desc) => {
+        |(_, a): &(i64, &SharedEntry), (_, b): &(i64, &SharedEntry)| {
+          Ord::cmp(&b.get_year(), &a.get_year())
+        }
+      }
+    };
+    let (all_fields, search) = split_all_fields(search);
+    let (years, search) = split_years(&search);
+    let search = search.as_str();
+    let weights = self.search_weights.extended(all_fields);
+    let extra_match = |weight: i64, text: &str| match weight {
+      0 => 0,
+      _ => weight * matcher.fuzzy_match(text, search).unwrap_or_default(),
     };
 
     self
@@ -546,15 +1139,20 @@ impl Rhythmdb {
         Entry::Song(ref song) => {
           if let Some(1) = song.hidden {
             None
+          } else if !in_years(entry, &years) {
+            None
           } else if search.is_empty() {
             Some((1, entry))
           } else {
             let song_match = matcher.fuzzy_match(&song.title, search);
             let artist_match = matcher.fuzzy_match(&song.artist, search);
             let album_match = matcher.fuzzy_match(&song.album, search);
-            let score = 4 * song_match.unwrap_or_default()
-              + 2 * artist_match.unwrap_or_default()
-              + album_match.unwrap_or_default();
+            let score = weights.title * song_match.unwrap_or_default()
+              + weights.artist * artist_match.unwrap_or_default()
+              + weights.album * album_match.unwrap_or_default()
+              + extra_match(weights.genre, &song.genre)
+              + extra_match(weights.composer, &song.composer)
+              + extra_match(weights.comment, entry.get_comment().unwrap_or_default());
             if score > 00 {
               Some((score, entry))
             } else {
@@ -590,6 +1188,7 @@ impl Rhythmdb {
     order_by: Order,
     order_dir: OrderDir,
   ) -> EntryList {
+    let _timer = metrics::Timer::start(metrics::searched);
     let matcher = SkimMatcherV2::default().smart_case();
     let sort_fn = match (order_by, order_dir) {
       (Order::Default, OrderDir::Asc) => {
@@ -646,7 +1245,53 @@ impl Rhythmdb {
           _ => unimplemented!(),
         }
       }
+      (Order::Artist, OrderDir::Asc) => {
+        |(_, a): &(i64, &SharedEntry), (_, b): &(i64, &SharedEntry)| {
+          Ord::cmp(a.get_artist(), b.get_artist())
+        }
+      }
+      (Order::Artist, OrderDir::Desc) => {
+        |(_, a): &(i64, &SharedEntry), (_, b): &(i64, &SharedEntry)| {
+          Ord::cmp(b.get_artist(), a.get_artist())
+        }
+      }
+      (Order::Album, OrderDir::Asc) => {
+        |(_, a): &(i64, &SharedEntry), (_, b): &(i64, &SharedEntry)| {
+          Ord::cmp(a.get_album(), b.get_album())
+        }
+      }
+      (Order::Album, OrderDir::Desc) => {
+        |(_, a): &(i64, &SharedEntry), (_, b): &(i64, &SharedEntry)| {
+          Ord::cmp(b.get_album(), a.get_album())
+        }
+      }
+      (Order::Duration, OrderDir::Asc) => {
+        |(_, a): &(i64, &SharedEntry), (_, b): &(i64, &SharedEntry)| {
+          Ord::cmp(&a.get_duration(), &b.get_duration())
+        }
+      }
+      (Order::Duration, OrderDir::Desc) => {
+        |(_, a): &(i64, &SharedEntry), (_, b): &(i64, &SharedEntry)| {
+          Ord::cmp(&b.get_duration(), &a.get_duration())
+        }
+      }
+      // The episodes have no tempo
+      (Order::Bpm | Order::Composer | Order::Work, _) => {
+        |_: &(i64, &SharedEntry), _: &(i64, &SharedEntry)| Ordering::Equal
+      }
+      (Order::Year, OrderDir::Asc) => |(_, a): &(i64, &SharedEntry), (_, b): &(i64, &SharedEntry)| {
+        Ord::cmp(&a.get_date(), &b.get_date())
+      },
+      (Order::Year, OrderDir::Desc) => {
+        |(_, a): &(i64, &SharedEntry), (_, b): &(i64, &SharedEntry)| {
+          Ord::cmp(&b.get_date(), &a.get_date())
+        }
+      }
     };
+    let (all_fields, search) = split_all_fields(search);
+    let (years, search) = split_years(&search);
+    let search = search.as_str();
+    let weights = self.search_weights.extended(all_fields);
     self
       .entry
       .iter()
@@ -654,12 +1299,22 @@ impl Rhythmdb {
         Entry::PodcastPost(ref podcast) => {
           if let Some(1) = podcast.hidden {
             None
+          } else if !in_years(entry, &years) {
+            None
           } else if search.is_empty() {
             Some((entry.get_date() as i64, entry))
           } else {
             let title_match = matcher.fuzzy_match(&podcast.title, search);
             let album_match = matcher.fuzzy_match(&podcast.album, search);
-            let score = title_match.unwrap_or_default() + 3 * album_match.unwrap_or_default();
+            let comment_match = match weights.comment {
+              0 => None,
+              _ => entry
+                .get_comment()
+                .and_then(|comment| matcher.fuzzy_match(comment, search)),
+            };
+            let score = weights.episode * title_match.unwrap_or_default()
+              + weights.feed * album_match.unwrap_or_default()
+              + weights.comment * comment_match.unwrap_or_default();
             if score > 00 {
               Some((score, entry))
             } else {
@@ -675,6 +1330,173 @@ impl Rhythmdb {
       .collect()
   }
 
+  /// Set the Rhythmbox `hidden` flag: the entry is not listed anymore.
+  #[instrument(skip(self))]
+  pub(crate) fn hide_entry(&mut self, entry: &Entry) {
+    let hidden_entry = match entry {
+      Entry::Song(song) => {
+        let mut song_copy = song.to_owned();
+        song_copy.hidden = Some(1);
+        Arc::new(Entry::Song(song_copy))
+      }
+      Entry::PodcastPost(podcast) => {
+        let mut podcast_copy = podcast.to_owned();
+        podcast_copy.hidden = Some(1);
+        Arc::new(Entry::PodcastPost(podcast_copy))
+      }
+      _ => return,
+    };
+    self.update_entry(hidden_entry);
+  }
+
+  /// Remove the entry from the db, and from the file of a merged db.
+  #[instrument(skip(self))]
+  pub(crate) fn remove_entry(&mut self, entry: &Entry) {
+    let location = entry.get_location();
+    let Some(index) = self.entry.iter().position(|e| e.get_location() == location) else {
+      return;
+    };
+    let mut end = self.main_len();
+    self.entry.remove(index);
+    if index >= end {
+      for merged in &mut self.merged {
+        end += merged.len;
+        if index < end {
+          merged.len -= 1;
+          break;
+        }
+      }
+    }
+    self.changed.remove(&location);
+    self.removed.insert(location);
+  }
+
+  /// Replace the comment of the entry, an empty one removes it.
+  #[instrument(skip(self))]
+  pub(crate) fn comment_entry(&mut self, entry: &Entry, comment: &str) -> Option<SharedEntry> {
+    let comment = Some(comment.trim().to_string()).filter(|comment| !comment.is_empty());
+    let commented_entry = match entry {
+      Entry::Song(song) => {
+        let mut song_copy = song.to_owned();
+        song_copy.comment = comment;
+        Arc::new(Entry::Song(song_copy))
+      }
+      Entry::PodcastPost(podcast) => {
+        let mut podcast_copy = podcast.to_owned();
+        podcast_copy.comment = comment;
+        Arc::new(Entry::PodcastPost(podcast_copy))
+      }
+      _ => return None,
+    };
+    Some(self.update_entry(commented_entry))
+  }
+
+  /// Albums of each artist, for the browser.
+  #[instrument(skip(self))]
+  pub(crate) fn artists_albums(&self) -> BTreeMap<String, BTreeSet<String>> {
+    let mut artists: BTreeMap<String, BTreeSet<String>> = BTreeMap::new();
+    for entry in &self.entry {
+      if let Entry::Song(song) = entry.as_ref() {
+        if song.hidden != Some(1) {
+          artists
+            .entry(song.artist.clone())
+            .or_default()
+            .insert(song.album.clone());
+        }
+      }
+    }
+    artists
+  }
+
+  /// Genres and decades of the visible entries, for the filter panel.
+  #[instrument(skip(self))]
+  pub(crate) fn genres_decades(&self) -> (BTreeSet<String>, BTreeSet<i32>) {
+    let mut genres = BTreeSet::new();
+    let mut decades = BTreeSet::new();
+    for entry in self.entry.iter().filter(|entry| !entry.get_hidden()) {
+      if let Entry::Song(_) | Entry::PodcastPost(_) = entry.as_ref() {
+        if !entry.get_genre().is_empty() {
+          genres.insert(entry.get_genre().to_string());
+        }
+        if let Some(year) = entry.get_year() {
+          decades.insert(year - year.rem_euclid(10));
+        }
+      }
+    }
+    (genres, decades)
+  }
+
+  /// Visible songs of the artist. With `album_artist`, the songs of the
+  /// albums of the artist too, like the featurings of a compilation.
+  #[instrument(skip(self))]
+  pub(crate) fn artist_songs(&self, artist: &str, album_artist: bool) -> EntryList {
+    self
+      .entry
+      .iter()
+      .filter(|entry| matches!(entry.as_ref(), Entry::Song(_)) && !entry.get_hidden())
+      .filter(|entry| {
+        entry.get_artist() == artist || (album_artist && entry.get_album_artist() == artist)
+      })
+      .cloned()
+      .collect()
+  }
+
+  /// Songs similar to the entry, the most similar first: the same artist and
+  /// the same genre weigh 2, the same decade 1. The score is weighted by the
+  /// rating, and varied a bit so that two calls don't give the same tracks.
+  #[instrument(skip(self, entry))]
+  pub(crate) fn similar_to(&self, entry: &Entry, count: usize) -> EntryList {
+    let decade = |entry: &Entry| entry.get_year().map(|year| year - year.rem_euclid(10));
+    let location = entry.get_location();
+    let mut scored: Vec<(f64, &SharedEntry)> = self
+      .entry
+      .iter()
+      .filter(|other| matches!(other.as_ref(), Entry::Song(_)) && !other.get_hidden())
+      .filter(|other| other.get_location() != location)
+      .filter_map(|other| {
+        let mut similarity = 0;
+        if !entry.get_artist().is_empty() && other.get_artist() == entry.get_artist() {
+          similarity += 2;
+        }
+        if !entry.get_genre().is_empty() && other.get_genre() == entry.get_genre() {
+          similarity += 2;
+        }
+        if decade(entry).is_some() && decade(other) == decade(entry) {
+          similarity += 1;
+        }
+        (similarity > 0).then(|| {
+          let rating = 1.0 + other.get_rating().stars_f64() / 5.0;
+          let variation = 0.75 + rand::random::<f64>() / 2.0;
+          (similarity as f64 * rating * variation, other)
+        })
+      })
+      .collect();
+    scored.sort_by(|(a, _), (b, _)| b.total_cmp(a));
+    scored
+      .into_iter()
+      .take(count)
+      .map(|(_, entry)| entry.clone())
+      .collect()
+  }
+
+  /// The entries as they are now in the db: the hidden ones are left out,
+  /// the ones outside of the library are kept.
+  pub(crate) fn refresh(&self, entries: &[SharedEntry]) -> EntryList {
+    let current: HashMap<Url, &SharedEntry> = self
+      .entry
+      .iter()
+      .map(|entry| (entry.get_location(), entry))
+      .collect();
+    entries
+      .iter()
+      .filter_map(|entry| match current.get(&entry.get_location()) {
+        Some(current) if current.get_hidden() => None,
+        Some(current) => Some((*current).clone()),
+        None => Some(entry.clone()),
+      })
+      .collect()
+  }
+
   pub(crate) fn to_entries(&self, value: &Playlist) -> Vec<SharedEntry> {
     match value {
       Playlist::Queue(q) => q
@@ -687,6 +1509,393 @@ impl Rhythmdb {
   }
 }
 
+/// The entries of a db file read again, with the ones changed during the
+/// session: replaced, or added when they are new. The removed ones are left
+/// out.
+fn merge_changes(
+  mut disk: EntryList,
+  ours: &[SharedEntry],
+  changed: &HashSet<Url>,
+  removed: &HashSet<Url>,
+) -> EntryList {
+  disk.retain(|entry| !removed.contains(&entry.get_location()));
+  for entry in ours
+    .iter()
+    .filter(|entry| changed.contains(&entry.get_location()))
+  {
+    match disk
+      .iter()
+      .position(|e| e.get_location() == entry.get_location())
+    {
+      Some(index) => disk[index] = entry.clone(),
+      None => disk.push(entry.clone()),
+    }
+  }
+  disk
+}
+
+fn mtime(path: &str) -> Option<SystemTime> {
+  fs::metadata(path)
+    .and_then(|metadata| metadata.modified())
+    .ok()
+}
+
+/// Take the `in:all` term out of the search: the genre, the composer and
+/// the comment are searched too.
+fn split_all_fields(search: &str) -> (bool, String) {
+  let terms: Vec<&str> = search.split_whitespace().collect();
+  let all_fields = terms.contains(&"in:all");
+  let terms: Vec<&str> = terms.into_iter().filter(|term| *term != "in:all").collect();
+  (all_fields, terms.join(" "))
+}
+
+/// Take the `year:` and `decade:` terms out of the search: `year:1994`,
+/// `year:1990-1995`, `decade:90s` or `decade:1990`. The other terms are the
+/// searched text.
+fn split_years(search: &str) -> (Vec<RangeInclusive<i32>>, String) {
+  let mut years = vec![];
+  let mut terms = vec![];
+  for term in search.split_whitespace() {
+    match parse_years(term) {
+      Some(range) => years.push(range),
+      None => terms.push(term),
+    }
+  }
+  (years, terms.join(" "))
+}
+
+fn parse_years(term: &str) -> Option<RangeInclusive<i32>> {
+  if let Some(value) = term.strip_prefix("year:") {
+    return match value.split_once('-') {
+      Some((from, to)) => Some(from.parse().ok()?..=to.parse().ok()?),
+      None => value.parse().ok().map(|year| year..=year),
+    };
+  }
+  let value = term.strip_prefix("decade:")?;
+  let decade: i32 = value
+    .trim_start_matches('\'')
+    .trim_end_matches('s')
+    .parse()
+    .ok()?;
+  // Two digits: the 30s to the 90s of the last century, then this one
+  let decade = match decade {
+    0..=29 => 2000 + decade,
+    30..=99 => 1900 + decade,
+    _ => decade,
+  };
+  let start = decade - decade.rem_euclid(10);
+  Some(start..=start + 9)
+}
+
+/// The entry was released in every range of years, the entries without year
+/// are left out by a range.
+fn in_years(entry: &Entry, years: &[RangeInclusive<i32>]) -> bool {
+  years.is_empty()
+    || entry
+      .get_year()
+      .is_some_and(|year| years.iter().all(|range| range.contains(&year)))
+}
+
+/// Rhythmbox keeps the db in memory and overwrites the file when it quits.
+pub(crate) fn rhythmbox_running() -> bool {
+  let Ok(processes) = fs::read_dir("/proc") else {
+    return false;
+  };
+  processes.flatten().any(|process| {
+    fs::read_to_string(process.path().join("comm")).is_ok_and(|name| name.trim_end() == "rhythmbox")
+  })
+}
+
+/// The commands writing the db refuse to run along Rhythmbox.
+pub(crate) fn check_writable() -> Result<()> {
+  if rhythmbox_running() {
+    return Err(miette!(
+      help = "Close Rhythmbox: it overwrites the db when it quits",
+      "Rhythmbox is running"
+    ));
+  }
+  Ok(())
+}
+
+fn read_file(path: &str) -> Result<Rhythmdb> {
+  let file = File::open(path)
+    .into_diagnostic()
+    .wrap_err_with(|| format!("Can't open the db `{path}`"))?;
+  Rhythmdb::read(BufReader::new(file)).wrap_err_with(|| format!("Can't read the db `{path}`"))
+}
+
+fn write_file(path: &Path, content: &RhythmdbFile) -> Result<()> {
+  use memmap2::MmapMut;
+  use std::fs::OpenOptions;
+
+  let mut buffer = vec![];
+  write_content(&mut buffer, content)?;
+
+  let file = OpenOptions::new()
+    .read(true)
+    .write(true)
+    .open(path)
+    .into_diagnostic()?;
+  let slice = buffer.as_slice();
+  file.set_len(slice.len() as u64).into_diagnostic()?;
+
+  let mut mmap = unsafe { MmapMut::map_mut(&file).into_diagnostic()? };
+  mmap.copy_from_slice(slice);
+
+  Ok(())
+}
+
+fn write_content<W: Write>(mut writer: W, content: &RhythmdbFile) -> Result<()> {
+  use quick_xml::se::Serializer;
+
+  let mut buffer = String::new();
+  let ser = Serializer::new(&mut buffer);
+  content.serialize(ser).into_diagnostic()?;
+  writer.write_all(buffer.as_bytes()).into_diagnostic()
+}
+
 fn gen_internal_id() -> u64 {
   rand::random()
 }
+
+#[cfg(test)]
+impl Rhythmdb {
+  /// Small library of the tests, in `tests/fixtures`.
+  pub(crate) fn fixture() -> Rhythmdb {
+    const FIXTURE: &str = include_str!("../tests/fixtures/rhythmdb.xml");
+    Rhythmdb::read(FIXTURE.as_bytes()).expect("Fixture db")
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn fixture() -> Rhythmdb {
+    Rhythmdb::fixture()
+  }
+
+  fn titles(entries: &[SharedEntry]) -> Vec<&str> {
+    entries.iter().map(|entry| entry.get_title()).collect()
+  }
+
+  fn song(db: &Rhythmdb, title: &str) -> SongEntry {
+    db.filter_by_song("", Order::Default, OrderDir::Desc)
+      .iter()
+      .find_map(|entry| match entry.as_ref() {
+        Entry::Song(song) if song.title == title => Some(song.clone()),
+        _ => None,
+      })
+      .expect("Song of the fixture")
+  }
+
+  /// The db written then read again, like after a save.
+  fn reread(db: &Rhythmdb) -> Rhythmdb {
+    let mut buffer = vec![];
+    db.write(&mut buffer).expect("Written db");
+    Rhythmdb::read(buffer.as_slice()).expect("Read db")
+  }
+
+  /// The rating of Teardrop, as written in the db.
+  fn written_rating(rating: Rating) -> String {
+    let mut db = fixture();
+    let mut teardrop = song(&db, "Teardrop");
+    teardrop.rating = Some(rating);
+    db.update_entry(Arc::new(Entry::Song(teardrop)));
+    let mut buffer = vec![];
+    db.write(&mut buffer).expect("Written db");
+    let written = String::from_utf8(buffer).expect("Written db");
+    let teardrop = &written[written.find("Teardrop").expect("Teardrop")..];
+    let start = teardrop.find("<rating>").expect("Rating") + "<rating>".len();
+    let end = teardrop.find("</rating>").expect("Rating");
+    teardrop[start..end].to_string()
+  }
+
+  /// The rating of Teardrop, read as written by Rhythmbox.
+  fn read_rating(written: &str) -> Option<Rating> {
+    const FIXTURE: &str = include_str!("../tests/fixtures/rhythmdb.xml");
+    let xml = FIXTURE.replace("<rating>3</rating>", &format!("<rating>{written}</rating>"));
+    let db = Rhythmdb::read(xml.as_bytes()).expect("Read db");
+    song(&db, "Teardrop").rating
+  }
+
+  #[test]
+  fn hidden_songs_left_out() {
+    let db = fixture();
+
+    assert_eq!(db.entry.len(), 5);
+    assert_eq!(
+      db.filter_by_song("", Order::Default, OrderDir::Desc).len(),
+      4
+    );
+    assert!(db
+      .filter_by_song("hidden", Order::Default, OrderDir::Desc)
+      .is_empty());
+  }
+
+  #[test]
+  fn search_title() {
+    let db = fixture();
+
+    let found = db.filter_by_song("teardrop", Order::Default, OrderDir::Desc);
+    assert_eq!(titles(&found), ["Teardrop"]);
+  }
+
+  #[test]
+  fn search_artist() {
+    let db = fixture();
+
+    let mut found = titles(&db.filter_by_song("miles", Order::Default, OrderDir::Desc))
+      .into_iter()
+      .map(str::to_string)
+      .collect::<Vec<_>>();
+    found.sort();
+    assert_eq!(found, ["Blue in Green", "So What"]);
+  }
+
+  #[test]
+  fn search_year() {
+    let db = fixture();
+
+    let found = db.filter_by_song("year:1998", Order::Title, OrderDir::Asc);
+    assert_eq!(titles(&found), ["Angel", "Teardrop"]);
+    let found = db.filter_by_song("decade:50s so", Order::Title, OrderDir::Asc);
+    assert_eq!(titles(&found), ["So What"]);
+  }
+
+  #[test]
+  fn sort_by_title() {
+    let db = fixture();
+
+    let sorted = db.filter_by_song("", Order::Title, OrderDir::Asc);
+    assert_eq!(
+      titles(&sorted),
+      ["Angel", "Blue in Green", "So What", "Teardrop"]
+    );
+    let sorted = db.filter_by_song("", Order::Title, OrderDir::Desc);
+    assert_eq!(
+      titles(&sorted),
+      ["Teardrop", "So What", "Blue in Green", "Angel"]
+    );
+  }
+
+  #[test]
+  fn sort_by_rating_and_date() {
+    let db = fixture();
+
+    let sorted = db.filter_by_song("", Order::Rating, OrderDir::Desc);
+    assert_eq!(
+      titles(&sorted),
+      ["Blue in Green", "So What", "Teardrop", "Angel"]
+    );
+    let sorted = db.filter_by_song("", Order::Date, OrderDir::Desc);
+    assert_eq!(
+      titles(&sorted),
+      ["Teardrop", "Angel", "So What", "Blue in Green"]
+    );
+  }
+
+  #[test]
+  fn sort_by_artist_and_duration() {
+    let db = fixture();
+
+    let sorted = db.filter_by_song("", Order::Artist, OrderDir::Asc);
+    assert_eq!(
+      titles(&sorted),
+      ["Teardrop", "Angel", "Blue in Green", "So What"]
+    );
+    let sorted = db.filter_by_song("", Order::Duration, OrderDir::Asc);
+    assert_eq!(
+      titles(&sorted),
+      ["Teardrop", "Blue in Green", "Angel", "So What"]
+    );
+  }
+
+  #[test]
+  fn sort_by_year() {
+    let db = fixture();
+
+    let sorted = db.filter_by_song("", Order::Year, OrderDir::Desc);
+    assert_eq!(
+      titles(&sorted),
+      ["Teardrop", "Angel", "Blue in Green", "So What"]
+    );
+  }
+
+  #[test]
+  fn update_kept_after_save() {
+    let mut db = fixture();
+    let mut teardrop = song(&db, "Teardrop");
+    teardrop.rating = Some(Rating::from_halves(9));
+    teardrop.play_count = Some(4);
+
+    db.update_entry(Arc::new(Entry::Song(teardrop)));
+    assert!(db.has_pending_changes());
+    let db = reread(&db);
+    let teardrop = song(&db, "Teardrop");
+    assert_eq!(teardrop.rating, Some(Rating::from_halves(9)));
+    assert_eq!(teardrop.play_count, Some(4));
+    assert_eq!(db.entry.len(), 5);
+  }
+
+  #[test]
+  fn rating_written_in_stars() {
+    assert_eq!(written_rating(Rating::from_halves(6)), "3");
+    assert_eq!(written_rating(Rating::from_halves(7)), "3.5");
+    assert_eq!(written_rating(Rating::from_halves(0)), "0");
+    assert_eq!(written_rating(Rating::from_halves(10)), "5");
+  }
+
+  #[test]
+  fn rating_read_in_stars() {
+    assert_eq!(read_rating("4"), Some(Rating::from_halves(8)));
+    assert_eq!(read_rating("3.5"), Some(Rating::from_halves(7)));
+    assert_eq!(read_rating("3.4"), Some(Rating::from_halves(7)));
+    assert_eq!(read_rating("0"), Some(Rating::default()));
+  }
+
+  #[test]
+  fn rating_clamped() {
+    assert_eq!(read_rating("9"), Some(Rating::from_stars(5)));
+    assert_eq!(read_rating("-1"), Some(Rating::default()));
+    assert_eq!(Rating::from_stars(7), Rating::from_stars(5));
+    assert_eq!(Rating::from_halves(12), Rating::from_halves(10));
+    assert_eq!(Rating::from_stars(5).raised(true), Rating::from_stars(5));
+    assert_eq!(Rating::default().lowered(true), Rating::default());
+  }
+
+  #[test]
+  fn remove_kept_after_save() {
+    let mut db = fixture();
+    let angel = song(&db, "Angel");
+
+    db.remove_entry(&Entry::Song(angel.clone()));
+    assert!(db.find_url(&angel.location).is_none());
+    let db = reread(&db);
+    assert!(db.find_url(&angel.location).is_none());
+    assert_eq!(db.entry.len(), 4);
+  }
+
+  #[test]
+  fn merge_removed_entries() {
+    let db = fixture();
+    let angel = song(&db, "Angel");
+    let mut teardrop = song(&db, "Teardrop");
+    teardrop.rating = Some(Rating::from_stars(1));
+    let ours = [Arc::new(Entry::Song(teardrop.clone()))];
+
+    let merged = merge_changes(
+      fixture().entry,
+      &ours,
+      &HashSet::from([teardrop.location.clone()]),
+      &HashSet::from([angel.location.clone()]),
+    );
+    assert_eq!(merged.len(), 4);
+    assert!(merged
+      .iter()
+      .all(|entry| entry.get_location() != angel.location));
+    assert!(merged
+      .iter()
+      .any(|entry| entry.get_rating() == Rating::from_stars(1)));
+  }
+}
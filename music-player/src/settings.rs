@@ -1,15 +1,21 @@
-use crate::player_state::{Repeat, Shuffle};
+use crate::{
+  player_state::{Repeat, Shuffle},
+  ui::{Order, OrderDir, TabSelection},
+};
 use clap::ArgMatches;
-use config::{Config, Environment, File};
+use config::{Config, ConfigError, Environment, File};
 use directories::{BaseDirs, ProjectDirs};
-use miette::{bail, IntoDiagnostic, Result, WrapErr};
+use miette::{bail, miette, IntoDiagnostic, LabeledSpan, NamedSource, Report, Result, WrapErr};
 use serde::{Deserialize, Serialize};
 use std::{
+  env,
   fmt::{Display, Error},
   fs::{self, remove_file},
   path::{Path, PathBuf},
+  sync::OnceLock,
 };
 use toml::{from_str, to_string_pretty};
+use toml_edit::DocumentMut;
 use tracing::{debug, instrument, trace};
 use url::Url;
 
@@ -17,21 +23,447 @@ const QUALIFIER: &str = "org";
 const ORGANISATION: &str = "djedi";
 const APPLICATION: &str = "music-player";
 
+const ENV_PREFIX: &str = "MUSIC-PLAYER-RS";
+
+static STATE_DIR: OnceLock<PathBuf> = OnceLock::new();
+
 #[derive(Debug, Deserialize)]
 pub(crate) struct Settings {
+  // Always set by `settings`
+  #[serde(default)]
   pub(crate) playlist_path: String,
+  #[serde(default)]
+  pub(crate) theme: ThemeSettings,
+  #[serde(default)]
+  pub(crate) symbols: SymbolSet,
+  // Detected from the locale when none
+  pub(crate) language: Option<Language>,
+  // Directories of the music files, `~` and the variables are expanded
+  #[serde(default)]
+  pub(crate) library_roots: Vec<PathBuf>,
+  #[serde(default)]
+  pub(crate) startup: StartupSettings,
+  // In seconds, 5 when none
+  pub(crate) seek_step: Option<u64>,
+  // Redraws of the progress gauge per second while playing, 1 when none
+  pub(crate) progress_fps: Option<u32>,
+  #[serde(default)]
+  pub(crate) last_played_format: DateFormat,
+  // Rate in half stars; the ratings are whole stars otherwise, as Rhythmbox
+  // expects them
+  #[serde(default)]
+  pub(crate) half_stars: bool,
+  #[serde(default)]
+  pub(crate) dates: DateSettings,
+  #[serde(default)]
+  pub(crate) playback: PlaybackSettings,
+  #[serde(default)]
+  pub(crate) party: PartySettings,
+  #[serde(default)]
+  pub(crate) suggestions: SuggestionSettings,
+  #[serde(default)]
+  pub(crate) truncate: TruncateSettings,
+  #[serde(default)]
+  pub(crate) search_weights: SearchWeights,
+  #[serde(default)]
+  pub(crate) daemon: DaemonSettings,
+  #[serde(default)]
+  pub(crate) log: LogSettings,
+  // Directory of the saved track and of the queue, see `state_dir`
+  pub(crate) state_dir: Option<String>,
+  #[serde(default)]
+  pub(crate) hooks: Vec<HookSettings>,
+  #[cfg(feature = "remote")]
+  pub(crate) remote: Option<RemoteSettings>,
+  #[cfg(feature = "otel")]
+  #[serde(default)]
+  pub(crate) otel: OtelSettings,
+  // Dbs given after the first `--db`, merged for the session
+  #[serde(skip)]
+  pub(crate) merged_db_paths: Vec<String>,
+}
+
+/// `[remote]` section of `settings.toml`: the http remote control is served
+/// on the address, like `127.0.0.1:8080`, to the requests bearing the token.
+#[cfg(feature = "remote")]
+#[derive(Debug, Deserialize)]
+pub(crate) struct RemoteSettings {
+  pub(crate) address: String,
+  pub(crate) token: String,
+}
+
+/// `[otel]` section: where the traces are exported, with the `otel`
+/// feature.
+#[cfg(feature = "otel")]
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+pub(crate) struct OtelSettings {
+  pub(crate) exporter: OtelExporter,
+  // Address of the Jaeger agent, or url of the OTLP collector. The default
+  // ones of the exporter when none
+  pub(crate) endpoint: Option<String>,
+  // Sent with the OTLP exports, like an authorization
+  pub(crate) headers: std::collections::HashMap<String, String>,
+}
+
+#[cfg(feature = "otel")]
+#[derive(Clone, Copy, Debug, Default, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub(crate) enum OtelExporter {
+  // Deprecated by Jaeger, kept for the existing setups
+  #[default]
+  Jaeger,
+  // OTLP over http, understood by the recent collectors
+  Otlp,
+}
+
+/// `[[hooks]]` of `settings.toml`: a command run or an url posted on the
+/// events of the player.
+#[derive(Clone, Debug, Deserialize)]
+pub(crate) struct HookSettings {
+  pub(crate) events: Vec<HookEvent>,
+  pub(crate) command: Option<String>,
+  // Posted with the `webhooks` feature
+  #[cfg_attr(not(feature = "webhooks"), allow(dead_code))]
+  pub(crate) url: Option<Url>,
+}
+
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub(crate) enum HookEvent {
+  TrackChange,
+  Pause,
+  Resume,
+  Stop,
+}
+
+impl HookEvent {
+  pub(crate) fn name(&self) -> &'static str {
+    match self {
+      HookEvent::TrackChange => "track-change",
+      HookEvent::Pause => "pause",
+      HookEvent::Resume => "resume",
+      HookEvent::Stop => "stop",
+    }
+  }
+}
+
+/// `[startup]` section of `settings.toml`: the view shown when the player
+/// starts, the music sorted by the default order when none.
+#[derive(Debug, Default, Deserialize)]
+pub(crate) struct StartupSettings {
+  pub(crate) tab: Option<TabSelection>,
+  pub(crate) sort: Option<Order>,
+  pub(crate) order: Option<OrderDir>,
+  pub(crate) search: Option<String>,
+}
+
+impl StartupSettings {
+  pub(crate) fn is_default(&self) -> bool {
+    self.tab.is_none() && self.sort.is_none() && self.order.is_none() && self.search.is_none()
+  }
+}
+
+#[derive(Copy, Clone, Debug, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub(crate) enum Language {
+  En,
+  Fr,
+}
+
+/// `[playback]` section: what to do when GStreamer fails to play a track, the
+/// preloading of the next track, the suspend inhibitor, the media keys, the
+/// rewind of the podcasts resumed and the watchdog of the stalled tracks.
+#[derive(Clone, Copy, Debug, Deserialize)]
+#[serde(default)]
+pub(crate) struct PlaybackSettings {
+  pub(crate) on_error: ErrorPolicy,
+  // Attempts of the `retry` policy before skipping the track
+  pub(crate) retries: u32,
+  // Preroll the next track near the end of the played one
+  pub(crate) preload: bool,
+  // Keep the machine awake while playing
+  pub(crate) inhibit_suspend: bool,
+  // Grab the media keys, without a daemon forwarding them through MPRIS
+  pub(crate) media_keys: bool,
+  // Desktop notification of the played tracks, with buttons
+  pub(crate) notifications: bool,
+  // Enqueue skips the tracks already queued
+  pub(crate) skip_queued: bool,
+  // Tracks enqueued by the `Enqueue similar tracks` action
+  pub(crate) similar_tracks: usize,
+  // The artist radio plays the albums of the artist too
+  pub(crate) radio_album_artist: bool,
+  // The shuffle mixes the music and the podcasts
+  pub(crate) shuffle_mixed: bool,
+  // Seconds a podcast is rewound when resumed after `resume_after` minutes
+  pub(crate) resume_rewind: u64,
+  pub(crate) resume_after: u64,
+  // Seconds the position may stay still while playing before the pipeline
+  // is unstuck, 0 to never
+  pub(crate) stall_timeout: u64,
+}
+
+impl Default for PlaybackSettings {
+  fn default() -> Self {
+    PlaybackSettings {
+      on_error: ErrorPolicy::default(),
+      retries: 2,
+      preload: true,
+      inhibit_suspend: true,
+      media_keys: false,
+      notifications: false,
+      skip_queued: false,
+      similar_tracks: 10,
+      radio_album_artist: false,
+      shuffle_mixed: false,
+      resume_rewind: 10,
+      resume_after: 10,
+      stall_timeout: 10,
+    }
+  }
+}
+
+/// `[truncate]` section: maximum widths of the text columns, in terminal
+/// cells. A longer text ends with an ellipsis; the column width is the limit
+/// when none.
+#[derive(Clone, Copy, Debug, Default, Deserialize)]
+#[serde(default)]
+pub(crate) struct TruncateSettings {
+  pub(crate) title: Option<u16>,
+  pub(crate) artist: Option<u16>,
+  pub(crate) composer: Option<u16>,
+  pub(crate) album: Option<u16>,
+  pub(crate) work: Option<u16>,
+  pub(crate) comment: Option<u16>,
+}
+
+/// `[search_weights]` section: weight of the fuzzy score of each field in the
+/// score of a track, to favor the matches of the title, the artist or the
+/// album. 0 leaves the field out of the search: the genre, the composer and
+/// the comment by default.
+#[derive(Clone, Copy, Debug, Deserialize)]
+#[serde(default)]
+pub(crate) struct SearchWeights {
+  pub(crate) title: i64,
+  pub(crate) artist: i64,
+  pub(crate) album: i64,
+  pub(crate) genre: i64,
+  pub(crate) composer: i64,
+  pub(crate) comment: i64,
+  // Of the podcasts
+  pub(crate) episode: i64,
+  pub(crate) feed: i64,
+}
+
+impl Default for SearchWeights {
+  fn default() -> Self {
+    SearchWeights {
+      title: 4,
+      artist: 2,
+      album: 1,
+      genre: 0,
+      composer: 0,
+      comment: 0,
+      episode: 1,
+      feed: 3,
+    }
+  }
+}
+
+impl SearchWeights {
+  /// The weights of a search with `in:all`: the fields left out count once.
+  pub(crate) fn extended(self, all_fields: bool) -> SearchWeights {
+    if !all_fields {
+      return self;
+    }
+    SearchWeights {
+      genre: self.genre.max(1),
+      composer: self.composer.max(1),
+      comment: self.comment.max(1),
+      ..self
+    }
+  }
+}
+
+/// `[party]` section: the party mode locks the rating, the hiding and the
+/// quitting keys, to let guests browse and enqueue only.
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(default)]
+pub(crate) struct PartySettings {
+  // Start locked
+  pub(crate) locked: bool,
+  // Asked to unlock, when some
+  pub(crate) pin: Option<String>,
+}
+
+/// `[suggestions]` section: ratings suggested for the unrated tracks from
+/// their plays and skips, reviewed with ⎇-z.
+#[derive(Clone, Copy, Debug, Deserialize)]
+#[serde(default)]
+pub(crate) struct SuggestionSettings {
+  // Count the skips and suggest ratings
+  pub(crate) enabled: bool,
+  // Plays and skips before a track gets a suggestion
+  pub(crate) min_plays: u64,
+}
+
+impl Default for SuggestionSettings {
+  fn default() -> Self {
+    SuggestionSettings {
+      enabled: false,
+      min_plays: 20,
+    }
+  }
+}
+
+/// `[log]` section: the log file is rotated once bigger than `max_size`.
+#[derive(Clone, Debug, Deserialize)]
+#[serde(default)]
+pub(crate) struct LogSettings {
+  // `~` and the variables are expanded. In the state directory when none, see
+  // `path`
+  pub(crate) file: Option<String>,
+  // In MiB
+  pub(crate) max_size: u64,
+  // Rotated files kept
+  pub(crate) keep: u32,
+  // No log file at all
+  pub(crate) disabled: bool,
+}
+
+impl Default for LogSettings {
+  fn default() -> Self {
+    LogSettings {
+      file: None,
+      max_size: 5,
+      keep: 2,
+      disabled: false,
+    }
+  }
+}
+
+impl LogSettings {
+  /// Path of the log file: `file`, otherwise `music-player.log` in the XDG
+  /// state directory. None when disabled.
+  pub(crate) fn path(&self) -> Result<Option<PathBuf>> {
+    if self.disabled {
+      return Ok(None);
+    }
+    if let Some(file) = &self.file {
+      return Ok(Some(expand_path(file)?));
+    }
+    Ok(
+      ProjectDirs::from(QUALIFIER, ORGANISATION, APPLICATION).map(|proj_dirs| {
+        proj_dirs
+          .state_dir()
+          .unwrap_or(proj_dirs.data_local_dir())
+          .join("music-player.log")
+      }),
+    )
+  }
+}
+
+/// `[daemon]` section: the player started with `--daemon`, by systemd or
+/// D-Bus, exits once idle.
+#[derive(Clone, Debug, Deserialize)]
+#[serde(default)]
+pub(crate) struct DaemonSettings {
+  // In minutes without playing nor control call, 0 to never exit
+  pub(crate) idle_timeout: u64,
+  // Address of the Prometheus metrics, like `127.0.0.1:9464`. None when
+  // not served
+  pub(crate) metrics: Option<String>,
+}
+
+impl Default for DaemonSettings {
+  fn default() -> Self {
+    DaemonSettings {
+      idle_timeout: 10,
+      metrics: None,
+    }
+  }
+}
+
+#[derive(Clone, Copy, Debug, Default, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub(crate) enum ErrorPolicy {
+  // Play the track again, then skip it
+  Retry,
+  #[default]
+  Skip,
+  Stop,
+}
+
+/// `[dates]` section: strftime patterns of the dates, by age. The default
+/// ones are kept for the missing patterns.
+#[derive(Clone, Debug, Default, Deserialize)]
+pub(crate) struct DateSettings {
+  pub(crate) today: Option<String>,
+  pub(crate) older: Option<String>,
+  // Replace the default steps
+  pub(crate) steps: Option<Vec<DateStep>>,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+pub(crate) struct DateStep {
+  // Age of the dates, like `1week`
+  pub(crate) until: String,
+  pub(crate) format: String,
+}
+
+/// Dates of the Last Played column: `Today 10:32` or `3 minutes ago`.
+#[derive(Copy, Clone, Debug, Default, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub(crate) enum DateFormat {
+  #[default]
+  Absolute,
+  Relative,
+}
+
+/// Symbols drawn in the ui. `ascii` is for the fonts without the unicode glyphs.
+#[derive(Copy, Clone, Debug, Default, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub(crate) enum SymbolSet {
+  #[default]
+  Unicode,
+  Ascii,
+}
+
+#[derive(Copy, Clone, Debug, Default, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub(crate) enum ThemePreset {
+  #[default]
+  Dark,
+  Light,
+  HighContrast,
+}
+
+/// `[theme]` section of `settings.toml`. Colors are either a name (`magenta`,
+/// `dark-gray`…), an ANSI index or a `#rrggbb` value.
+#[derive(Debug, Default, Deserialize)]
+pub(crate) struct ThemeSettings {
+  #[serde(default)]
+  pub(crate) preset: ThemePreset,
+  pub(crate) default: Option<String>,
+  pub(crate) default_dark: Option<String>,
+  pub(crate) primary: Option<String>,
+  pub(crate) secondary: Option<String>,
+  pub(crate) border: Option<String>,
+  pub(crate) border_selected: Option<String>,
+  pub(crate) selected: Option<String>,
+  pub(crate) help_key: Option<String>,
+  pub(crate) error: Option<String>,
 }
 
 #[instrument(skip(matches))]
 pub(crate) fn settings(matches: &ArgMatches) -> Result<Settings> {
-  let env_prefix: &str = "MUSIC-PLAYER-RS";
   let mut settings_builder = Config::builder();
   settings_builder = settings_builder
     .set_default("uri", "http://localhost:8080")
     .into_diagnostic()?;
 
-  if let Some(proj_dirs) = ProjectDirs::from(QUALIFIER, ORGANISATION, APPLICATION) {
-    let path = Path::new(proj_dirs.config_dir()).join("settings.toml");
+  if let Some(path) = settings_path() {
     let path = path.to_str().unwrap();
     settings_builder = settings_builder.add_source(File::with_name(path).required(false));
     settings_builder = settings_builder
@@ -55,15 +487,296 @@ pub(crate) fn settings(matches: &ArgMatches) -> Result<Settings> {
   }
   .into_diagnostic()?;
 
-  settings_builder = settings_builder.add_source(Environment::with_prefix(env_prefix));
-  let config = settings_builder.build().into_diagnostic()?;
-  let mut settings: Settings = config.clone().try_deserialize().into_diagnostic()?;
+  settings_builder = settings_builder.add_source(Environment::with_prefix(ENV_PREFIX));
+  let config = apply_profile(settings_builder.build().into_diagnostic()?, matches)?;
+  let mut settings: Settings = config.clone().try_deserialize().map_err(settings_error)?;
 
   settings.playlist_path = get_settings(&config, matches, "playlist_path")?;
+  settings.library_roots = settings
+    .library_roots
+    .iter()
+    .map(|root| expand_path(&root.to_string_lossy()))
+    .collect::<Result<_>>()?;
+  settings.merged_db_paths = matches
+    .get_many::<String>("playlist_path")
+    .map(|paths| paths.skip(1).cloned().collect())
+    .unwrap_or_default();
 
   Ok(settings)
 }
 
+/// Report of invalid settings: the bad value in `settings.toml`, otherwise
+/// the environment variables overriding the settings.
+fn settings_error(error: ConfigError) -> Report {
+  if let Some(path) = settings_path() {
+    if let Ok(text) = fs::read_to_string(&path) {
+      if let Err(report) = check_settings(&path, &text) {
+        return report;
+      }
+    }
+  }
+  let variables: Vec<String> = env::vars()
+    .map(|(name, _)| name)
+    .filter(|name| name.to_uppercase().starts_with(&format!("{ENV_PREFIX}_")))
+    .collect();
+  if variables.is_empty() {
+    miette!("Invalid settings: {error}")
+  } else {
+    miette!(
+      help = format!("Overridden by: {}", variables.join(", ")),
+      "Invalid settings: {error}"
+    )
+  }
+}
+
+/// Check the content of `settings.toml`. The report points at the bad value.
+fn check_settings(path: &Path, text: &str) -> Result<()> {
+  let Err(e) = toml::from_str::<Settings>(text) else {
+    return Ok(());
+  };
+  let message = e.message().trim();
+  let Some(span) = e.span() else {
+    return Err(miette!(
+      "Invalid settings in `{}`: {message}",
+      path.display()
+    ));
+  };
+  let message = match toml_edit::ImDocument::parse(text)
+    .ok()
+    .and_then(|document| key_at(document.as_table(), span.start))
+  {
+    Some(key) => format!("Invalid value for `{key}`: {message}"),
+    None => message.to_string(),
+  };
+  Err(
+    miette!(labels = vec![LabeledSpan::at(span, "here")], "{message}").with_source_code(
+      NamedSource::new(path.display().to_string(), text.to_string()),
+    ),
+  )
+}
+
+/// Dotted key of the value at the offset.
+fn key_at(table: &toml_edit::Table, offset: usize) -> Option<String> {
+  table.iter().find_map(|(key, item)| match item {
+    toml_edit::Item::Table(table) => key_at(table, offset).map(|sub_key| format!("{key}.{sub_key}")),
+    _ => item
+      .span()
+      .filter(|span| span.contains(&offset))
+      .map(|_| key.to_string()),
+  })
+}
+
+/// Set the directory of the state files from the settings.
+#[instrument(skip(settings))]
+pub(crate) fn init_state_dir(settings: &Settings) -> Result<()> {
+  if let Some(directory) = &settings.state_dir {
+    // Only set once at startup: ignore a second call.
+    let _ = STATE_DIR.set(expand_path(directory)?);
+  }
+  Ok(())
+}
+
+/// Directory of the state files: the `state_dir` setting, otherwise the
+/// rhythmbox directory when it exists, otherwise the state directory of the
+/// app.
+pub(crate) fn state_dir() -> Option<PathBuf> {
+  if let Some(directory) = STATE_DIR.get() {
+    return Some(directory.clone());
+  }
+  let rhythmbox = BaseDirs::new()?.data_local_dir().join("rhythmbox");
+  if rhythmbox.is_dir() {
+    return Some(rhythmbox);
+  }
+  ProjectDirs::from(QUALIFIER, ORGANISATION, APPLICATION).map(|proj_dirs| {
+    proj_dirs
+      .state_dir()
+      .unwrap_or(proj_dirs.data_local_dir())
+      .to_path_buf()
+  })
+}
+
+/// Cache directory of the app: `~/.cache/music-player` on Linux.
+pub(crate) fn cache_dir() -> Option<PathBuf> {
+  ProjectDirs::from(QUALIFIER, ORGANISATION, APPLICATION)
+    .map(|proj_dirs| proj_dirs.cache_dir().to_path_buf())
+}
+
+/// Write a state file, with its directory.
+pub(crate) fn write_state(path: &Path, content: &str) -> Result<()> {
+  if let Some(directory) = path.parent() {
+    fs::create_dir_all(directory)
+      .into_diagnostic()
+      .with_context(|| format!("Trying to create `{}`", directory.display()))?;
+  }
+  fs::write(path, content.as_bytes())
+    .into_diagnostic()
+    .with_context(|| format!("Trying to save `{}`", path.display()))
+}
+
+/// Override the settings with the ones of the profile. The fields of the
+/// tables are overridden one by one, the missing ones fall back to the
+/// default profile.
+#[instrument(skip(config, matches))]
+fn apply_profile(config: Config, matches: &ArgMatches) -> Result<Config> {
+  const PROFILE_KEYS: [&str; 7] = [
+    "theme",
+    "symbols",
+    "language",
+    "library_roots",
+    "startup",
+    "dates",
+    "party",
+  ];
+  let Some(profile) = matches.get_one::<String>("profile") else {
+    return Ok(config);
+  };
+  let mut builder = Config::builder().add_source(config.clone());
+  for key in PROFILE_KEYS {
+    let Ok(value) = config.get::<config::Value>(&format!("profile.{profile}.{key}")) else {
+      continue;
+    };
+    trace!("profile {profile} overrides {key}");
+    match value.clone().into_table() {
+      Ok(table) => {
+        for (field, value) in table {
+          builder = builder
+            .set_override(format!("{key}.{field}"), value)
+            .into_diagnostic()?;
+        }
+      }
+      Err(_) => builder = builder.set_override(key, value).into_diagnostic()?,
+    }
+  }
+  builder.build().into_diagnostic()
+}
+
+/// Expand the leading `~` and the `$VAR` or `${VAR}` variables of a path.
+#[instrument]
+pub(crate) fn expand_path(path: &str) -> Result<PathBuf> {
+  let mut expanded = String::new();
+  let mut rest = path;
+  if let Some(tail) = path
+    .strip_prefix('~')
+    .filter(|tail| tail.is_empty() || tail.starts_with('/'))
+  {
+    let base_dir = BaseDirs::new().ok_or(miette!("Can't get the home directory"))?;
+    expanded.push_str(&base_dir.home_dir().to_string_lossy());
+    rest = tail;
+  }
+  while let Some(start) = rest.find('$') {
+    expanded.push_str(&rest[..start]);
+    let variable = &rest[start + 1..];
+    let (name, tail) = match variable.strip_prefix('{') {
+      Some(braced) => braced
+        .split_once('}')
+        .ok_or_else(|| miette!("Missing `}}` in `{path}`"))?,
+      None => variable.split_at(
+        variable
+          .find(|c: char| !(c.is_alphanumeric() || c == '_'))
+          .unwrap_or(variable.len()),
+      ),
+    };
+    let value = env::var(name).map_err(|_| miette!("Unknown variable `{name}` in `{path}`"))?;
+    expanded.push_str(&value);
+    rest = tail;
+  }
+  expanded.push_str(rest);
+  Ok(PathBuf::from(expanded))
+}
+
+/// Path of `settings.toml`.
+pub(crate) fn settings_path() -> Option<PathBuf> {
+  ProjectDirs::from(QUALIFIER, ORGANISATION, APPLICATION)
+    .map(|proj_dirs| Path::new(proj_dirs.config_dir()).join("settings.toml"))
+}
+
+/// Print the value of a dotted key of `settings.toml`.
+#[instrument]
+pub(crate) fn get_setting(key: &str) -> Result<()> {
+  let path = settings_path().ok_or(miette!("Can't get path"))?;
+  let document: DocumentMut = fs::read_to_string(&path)
+    .unwrap_or_default()
+    .parse()
+    .into_diagnostic()
+    .with_context(|| format!("Trying to parse `{}`", path.display()))?;
+  let mut item = document.as_item();
+  for part in key.split('.') {
+    item = item
+      .get(part)
+      .ok_or_else(|| miette!("`{key}` is not set in `{}`", path.display()))?;
+  }
+  match item.as_str() {
+    Some(value) => println!("{value}"),
+    None => println!("{}", item.to_string().trim()),
+  }
+  Ok(())
+}
+
+/// Set a dotted key of `settings.toml`, keeping the comments of the file. The
+/// value is a toml value, or a string. The file is only written when the
+/// settings stay valid.
+#[instrument]
+pub(crate) fn set_setting(key: &str, value: &str) -> Result<()> {
+  const KEYS: [&str; 23] = [
+    "playlist_path",
+    "library_roots",
+    "theme",
+    "symbols",
+    "language",
+    "profile",
+    "remote",
+    "otel",
+    "startup",
+    "seek_step",
+    "progress_fps",
+    "last_played_format",
+    "half_stars",
+    "dates",
+    "playback",
+    "party",
+    "suggestions",
+    "truncate",
+    "search_weights",
+    "daemon",
+    "log",
+    "state_dir",
+    "hooks",
+  ];
+  let parts: Vec<&str> = key.split('.').collect();
+  if !KEYS.contains(&parts[0]) {
+    bail!("Unknown setting `{key}`. Settings: {}", KEYS.join(", "));
+  }
+
+  let path = settings_path().ok_or(miette!("Can't get path"))?;
+  let mut document: DocumentMut = fs::read_to_string(&path)
+    .unwrap_or_default()
+    .parse()
+    .into_diagnostic()
+    .with_context(|| format!("Trying to parse `{}`", path.display()))?;
+  let mut table = document.as_table_mut();
+  for part in &parts[..parts.len() - 1] {
+    table = table
+      .entry(part)
+      .or_insert(toml_edit::table())
+      .as_table_mut()
+      .ok_or_else(|| miette!("`{part}` is not a table"))?;
+  }
+  let value = value
+    .parse::<toml_edit::Value>()
+    .unwrap_or_else(|_| value.into());
+  table[parts[parts.len() - 1]] = toml_edit::value(value);
+
+  let text = document.to_string();
+  check_settings(&path, &text)?;
+
+  if let Some(directory) = path.parent() {
+    fs::create_dir_all(directory).into_diagnostic()?;
+  }
+  fs::write(&path, text)
+    .into_diagnostic()
+    .with_context(|| format!("Trying to save `{}`", &path.display()))
+}
+
 #[instrument(skip(config, matches))]
 fn get_settings(config: &Config, matches: &ArgMatches, arg: &str) -> Result<String> {
   if let Some(value) = matches.get_one::<String>(arg) {
@@ -91,6 +804,106 @@ pub(crate) struct PlayerStateSetting {
   pub(crate) position: Option<u64>,
   pub(crate) shuffle_mode: Option<Shuffle>,
   pub(crate) repeat_mode: Option<Repeat>,
+  #[serde(default)]
+  pub(crate) layout: LayoutSetting,
+  // Unix time of the save, when the player was closed
+  #[serde(default)]
+  pub(crate) saved_at: Option<u64>,
+  // Index of the played track in the queue, when it was queued
+  #[serde(default)]
+  pub(crate) queue_position: Option<usize>,
+}
+
+/// Tab, sort, search and scroll of the ui, saved between sessions.
+#[derive(Debug, Deserialize, Serialize)]
+pub(crate) struct UiSession {
+  pub(crate) tab: TabSelection,
+  pub(crate) order_by: Order,
+  pub(crate) order_dir: OrderDir,
+  pub(crate) search: String,
+  pub(crate) selected: Option<usize>,
+  // First row visible in the table
+  pub(crate) offset: usize,
+}
+
+impl UiSession {
+  /// The saved session, none when it can't be read.
+  #[instrument]
+  pub(crate) fn load() -> Option<UiSession> {
+    let text = fs::read_to_string(Self::get_path()?).ok()?;
+    from_str(&text)
+      .inspect_err(|e| debug!("Can't read the ui session: {e}"))
+      .ok()
+  }
+
+  #[instrument]
+  pub(crate) fn save(&self) -> Result<()> {
+    if let Some(path) = Self::get_path() {
+      write_state(&path, &to_string_pretty(self).into_diagnostic()?)?;
+    }
+    Ok(())
+  }
+
+  fn get_path() -> Option<PathBuf> {
+    state_dir().map(|directory| directory.join("ui_session.toml"))
+  }
+
+  pub(crate) fn clean() -> Result<()> {
+    match Self::get_path() {
+      Some(path) if path.exists() => remove_file(path).into_diagnostic(),
+      _ => Ok(()),
+    }
+  }
+}
+
+/// Panels shown and their sizes, saved between sessions.
+#[derive(Clone, Copy, Debug, Deserialize, Serialize)]
+#[serde(default)]
+pub(crate) struct LayoutSetting {
+  pub(crate) show_search: bool,
+  pub(crate) show_control: bool,
+  pub(crate) show_comments: bool,
+  // Composer and work columns, for the classical music
+  pub(crate) show_classical: bool,
+  // Widths of the side panels, in percent
+  pub(crate) browser_width: u16,
+  pub(crate) lyrics_width: u16,
+}
+
+impl LayoutSetting {
+  const MIN_WIDTH: u16 = 10;
+  const MAX_WIDTH: u16 = 60;
+  const STEP: u16 = 5;
+
+  pub(crate) fn resize_browser(&mut self, grow: bool) {
+    self.browser_width = Self::resize(self.browser_width, grow);
+  }
+
+  pub(crate) fn resize_lyrics(&mut self, grow: bool) {
+    self.lyrics_width = Self::resize(self.lyrics_width, grow);
+  }
+
+  fn resize(width: u16, grow: bool) -> u16 {
+    if grow {
+      width.saturating_add(Self::STEP)
+    } else {
+      width.saturating_sub(Self::STEP)
+    }
+    .clamp(Self::MIN_WIDTH, Self::MAX_WIDTH)
+  }
+}
+
+impl Default for LayoutSetting {
+  fn default() -> Self {
+    LayoutSetting {
+      show_search: true,
+      show_control: true,
+      show_comments: false,
+      show_classical: false,
+      browser_width: 20,
+      lyrics_width: 33,
+    }
+  }
 }
 
 impl PlayerStateSetting {
@@ -106,20 +919,13 @@ impl PlayerStateSetting {
   #[instrument]
   pub(crate) fn save(&self) -> Result<()> {
     if let Some(path) = Self::get_path() {
-      fs::write(&path, to_string_pretty(self).into_diagnostic()?.as_bytes())
-        .into_diagnostic()
-        .with_context(|| format!("Trying to save `{}`", &path.display()))?;
+      write_state(&path, &to_string_pretty(self).into_diagnostic()?)?;
     }
     Ok(())
   }
 
   fn get_path() -> Option<PathBuf> {
-    BaseDirs::new().map(|base_dir| {
-      Path::new(base_dir.data_local_dir())
-        .join("rhythmbox")
-        .join("music_player.toml")
-        .to_path_buf()
-    })
+    state_dir().map(|directory| directory.join("music_player.toml"))
   }
 
   pub(crate) fn dump() -> Result<()> {
@@ -137,7 +943,6 @@ impl PlayerStateSetting {
   }
 
   pub(crate) fn clean() -> Result<()> {
-    use miette::miette;
     remove_file(Self::get_path().ok_or(miette!("Can't get path"))?).into_diagnostic()
   }
 }
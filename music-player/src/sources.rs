@@ -0,0 +1,119 @@
+use crate::rhythmdb::{Entry, SharedEntry, SongEntry};
+use miette::{miette, Context, IntoDiagnostic, Result};
+use std::{
+  fs,
+  io::{stdin, BufRead},
+  path::{Path, PathBuf},
+  sync::Arc,
+};
+use tracing::instrument;
+use url::Url;
+
+const AUDIO_EXTENSIONS: [&str; 8] = ["mp3", "ogg", "oga", "opus", "flac", "m4a", "wav", "wma"];
+
+/// Locations of the paths or the urls given on the command line. The
+/// directories are walked recursively and their audio files sorted by path.
+/// The relative paths missing from the current directory are searched in
+/// the library roots.
+#[instrument]
+pub(crate) fn collect_locations(sources: &[String], library_roots: &[PathBuf]) -> Result<Vec<Url>> {
+  let mut locations = vec![];
+  for source in sources {
+    match Url::parse(source) {
+      Ok(url) if url.scheme() == "file" => {
+        let path = url
+          .to_file_path()
+          .map_err(|_| miette!("Can't parse file url: '{source}'"))?;
+        collect_path(&path, &mut locations)?;
+      }
+      // A single letter is the drive of a windows path
+      Ok(url) if url.scheme().len() > 1 => locations.push(url),
+      _ => collect_path(&resolve(Path::new(source), library_roots), &mut locations)?,
+    }
+  }
+  if !sources.is_empty() && locations.is_empty() {
+    return Err(miette!("No audio file found"));
+  }
+  Ok(locations)
+}
+
+/// Paths or urls read from stdin, one per line.
+#[instrument]
+pub(crate) fn read_stdin() -> Result<Vec<String>> {
+  stdin()
+    .lock()
+    .lines()
+    .map(|line| line.map(|line| line.trim().to_string()))
+    .filter(|line| !matches!(line, Ok(line) if line.is_empty()))
+    .collect::<Result<_, _>>()
+    .into_diagnostic()
+}
+
+fn resolve(path: &Path, library_roots: &[PathBuf]) -> PathBuf {
+  if path.is_absolute() || path.exists() {
+    return path.to_path_buf();
+  }
+  library_roots
+    .iter()
+    .map(|root| root.join(path))
+    .find(|path| path.exists())
+    .unwrap_or_else(|| path.to_path_buf())
+}
+
+fn collect_path(path: &Path, locations: &mut Vec<Url>) -> Result<()> {
+  let path = fs::canonicalize(path)
+    .into_diagnostic()
+    .with_context(|| format!("Can't find `{}`", path.display()))?;
+  if path.is_dir() {
+    let mut files = vec![];
+    walk(&path, &mut files)?;
+    locations.extend(
+      files
+        .iter()
+        .filter_map(|file| Url::from_file_path(file).ok()),
+    );
+  } else {
+    // An explicit file is played whatever its extension
+    locations.push(
+      Url::from_file_path(&path)
+        .map_err(|_| miette!("Can't parse file path: '{}'", path.display()))?,
+    );
+  }
+  Ok(())
+}
+
+fn walk(path: &Path, files: &mut Vec<PathBuf>) -> Result<()> {
+  if path.is_dir() {
+    let mut entries = fs::read_dir(path)
+      .into_diagnostic()
+      .with_context(|| format!("Can't read the directory `{}`", path.display()))?
+      .map(|entry| entry.map(|entry| entry.path()))
+      .collect::<Result<Vec<_>, _>>()
+      .into_diagnostic()?;
+    entries.sort();
+    for entry in entries {
+      walk(&entry, files)?;
+    }
+  } else if path
+    .extension()
+    .and_then(|extension| extension.to_str())
+    .is_some_and(|extension| AUDIO_EXTENSIONS.contains(&extension.to_lowercase().as_str()))
+  {
+    files.push(path.to_path_buf());
+  }
+  Ok(())
+}
+
+/// Track of a location, with the tags of the local files.
+pub(crate) fn track_from_location(location: &Url) -> SharedEntry {
+  let tag = location
+    .to_file_path()
+    .ok()
+    .and_then(|path| id3::Tag::read_from_path(path).ok());
+  let mut track = match tag {
+    Some(tag) => SongEntry::from(tag),
+    None => SongEntry::default(),
+  };
+  track.location = location.clone();
+  Arc::new(Entry::Song(track))
+}
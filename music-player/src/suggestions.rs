@@ -0,0 +1,83 @@
+use crate::{
+  rhythmdb::{Entry, Rating, Rhythmdb, SharedEntry},
+  settings::{state_dir, write_state, SuggestionSettings},
+  ui::{Order, OrderDir},
+};
+use miette::{IntoDiagnostic, Result};
+use std::{collections::BTreeMap, fs, path::PathBuf};
+use toml::{from_str, to_string_pretty};
+use tracing::{instrument, warn};
+
+/// Rating suggested for an unrated track, reviewed before it is saved.
+#[derive(Debug, Clone)]
+pub(crate) struct Suggestion {
+  pub(crate) entry: SharedEntry,
+  pub(crate) rating: Rating,
+  pub(crate) plays: u64,
+  pub(crate) skips: u64,
+}
+
+/// Count a track left before it was played enough, when the suggestions are
+/// enabled. The skips are kept in the state directory: the Rhythmbox db has
+/// no field for them.
+#[instrument(skip(track))]
+pub(crate) fn record_skip(settings: &SuggestionSettings, track: &Entry) {
+  if !settings.enabled {
+    return;
+  }
+  let mut skips = load_skips();
+  *skips.entry(track.get_location().to_string()).or_default() += 1;
+  if let Err(e) = save_skips(&skips) {
+    warn!("Can't record the skip: {e}");
+  }
+}
+
+/// Ratings of the unrated songs played or skipped `min_plays` times, the
+/// most played first. The share of the plays gives 1 to 5 stars.
+#[instrument(skip(db))]
+pub(crate) fn suggest(settings: &SuggestionSettings, db: &Rhythmdb) -> Vec<Suggestion> {
+  let min_plays = settings.min_plays;
+  let skips = load_skips();
+  let mut suggestions: Vec<Suggestion> = db
+    .filter_by_song("", Order::Default, OrderDir::Desc)
+    .into_iter()
+    .filter(|entry| entry.get_rating() == Rating::default())
+    .filter_map(|entry| {
+      let plays = entry.get_play_count();
+      let skips = skips
+        .get(entry.get_location().as_str())
+        .copied()
+        .unwrap_or_default();
+      if plays + skips < min_plays.max(1) {
+        return None;
+      }
+      let ratio = plays as f64 / (plays + skips) as f64;
+      Some(Suggestion {
+        entry,
+        rating: Rating::from_stars(1 + (ratio * 4.0).round() as u64),
+        plays,
+        skips,
+      })
+    })
+    .collect();
+  suggestions.sort_by_key(|suggestion| std::cmp::Reverse(suggestion.plays + suggestion.skips));
+  suggestions
+}
+
+fn load_skips() -> BTreeMap<String, u64> {
+  get_path()
+    .and_then(|path| fs::read_to_string(path).ok())
+    .and_then(|text| from_str(&text).ok())
+    .unwrap_or_default()
+}
+
+fn save_skips(skips: &BTreeMap<String, u64>) -> Result<()> {
+  if let Some(path) = get_path() {
+    write_state(&path, &to_string_pretty(skips).into_diagnostic()?)?;
+  }
+  Ok(())
+}
+
+fn get_path() -> Option<PathBuf> {
+  state_dir().map(|directory| directory.join("skips.toml"))
+}
@@ -1,39 +1,79 @@
-#[cfg(feature = "console")]
-use miette::IntoDiagnostic;
-use miette::Result;
+use crate::settings::Settings;
+#[cfg(feature = "otel")]
+use crate::settings::{OtelExporter, OtelSettings};
+use chrono::{DateTime, Local};
+use miette::{IntoDiagnostic, Result};
+use std::{
+  collections::VecDeque,
+  fmt::Debug,
+  fs::{self, File, OpenOptions},
+  io::{self, Write},
+  path::{Path, PathBuf},
+  sync::Mutex,
+};
+use tracing::{field::Field, Event, Level, Subscriber};
+use tracing_subscriber::{
+  field::Visit,
+  filter::LevelFilter,
+  layer::{Context, Layer},
+  EnvFilter,
+};
 
-pub fn init_tracing() -> Result<()> {
+// Warnings and errors kept for the log panel of the ui
+const RECENT_LOGS: usize = 200;
+
+static RECENT: Mutex<VecDeque<LogLine>> = Mutex::new(VecDeque::new());
+
+/// Warning or error logged, shown in the log panel.
+#[derive(Clone, Debug)]
+pub(crate) struct LogLine {
+  pub(crate) time: DateTime<Local>,
+  pub(crate) level: Level,
+  pub(crate) target: String,
+  pub(crate) message: String,
+}
+
+/// Log in the file of `[log]`, at the level of `--log-level`, otherwise of
+/// `RUST_LOG`. The warnings and the errors are also kept for the ui.
+pub fn init_tracing(settings: &Settings, level: Option<&str>) -> Result<()> {
   use tracing_error::ErrorLayer;
-  use tracing_subscriber::{prelude::*, EnvFilter};
+  use tracing_subscriber::prelude::*;
+  let filter = || match level {
+    Some(level) => EnvFilter::try_new(level).into_diagnostic(),
+    None => Ok(EnvFilter::from_default_env()),
+  };
   let registry = tracing_subscriber::registry()
-    .with(EnvFilter::from_default_env())
-    .with(ErrorLayer::default());
+    .with(ErrorLayer::default().with_filter(filter()?))
+    .with(RecentLogs.with_filter(LevelFilter::WARN));
 
-  #[cfg(feature = "console")]
-  let registry = registry.with(
+  let log = &settings.log;
+  let log_file = match log.path()? {
+    Some(path) => Some(LogFile::open(path, log.max_size * 1024 * 1024, log.keep).into_diagnostic()?),
+    None => None,
+  };
+  let file_filter = filter()?;
+  let registry = registry.with(log_file.map(|log_file| {
     tracing_subscriber::fmt::layer()
       .compact()
+      .with_ansi(false)
       .with_file(false)
       .with_line_number(true)
-      .with_writer(std::fs::File::create("/tmp/music-player.log").into_diagnostic()?),
-  );
+      .with_writer(Mutex::new(log_file))
+      .with_filter(file_filter)
+  }));
 
   #[cfg(feature = "forest")]
   let registry = registry.with(
-    tracing_forest::ForestLayer::default(), //      .with_writer(std::fs::File::create("/tmp/music-player-forest.log").into_diagnostic()?),
+    tracing_forest::ForestLayer::default().with_filter(filter()?), //      .with_writer(std::fs::File::create("/tmp/music-player-forest.log").into_diagnostic()?),
   );
 
   #[cfg(feature = "otel")]
-  let registry = registry.with({
-    opentelemetry::global::set_text_map_propagator(opentelemetry_jaeger::Propagator::new());
-    let tracer = opentelemetry_jaeger::new_agent_pipeline()
-      .with_service_name("skl_main")
-      .install_simple()
-      .unwrap();
+  let registry = registry.with(
     tracing_opentelemetry::layer()
-      .with_tracer(tracer)
+      .with_tracer(otel_tracer(&settings.otel)?)
       .with_exception_fields(true)
-  });
+      .with_filter(filter()?),
+  );
 
   #[cfg(feature = "tokio-console")]
   let registry = registry.with(console_subscriber::spawn());
@@ -41,3 +81,154 @@ pub fn init_tracing() -> Result<()> {
 
   Ok(())
 }
+
+/// Tracer exporting the spans to Jaeger or to an OTLP collector.
+#[cfg(feature = "otel")]
+fn otel_tracer(settings: &OtelSettings) -> Result<opentelemetry::sdk::trace::Tracer> {
+  const SERVICE_NAME: &str = "music-player";
+  match settings.exporter {
+    OtelExporter::Jaeger => {
+      opentelemetry::global::set_text_map_propagator(opentelemetry_jaeger::Propagator::new());
+      let mut pipeline = opentelemetry_jaeger::new_agent_pipeline().with_service_name(SERVICE_NAME);
+      if let Some(endpoint) = &settings.endpoint {
+        pipeline = pipeline.with_endpoint(endpoint);
+      }
+      pipeline.install_simple().into_diagnostic()
+    }
+    OtelExporter::Otlp => {
+      use opentelemetry::{
+        sdk::{trace, Resource},
+        KeyValue,
+      };
+      use opentelemetry_otlp::WithExportConfig;
+      let mut exporter = opentelemetry_otlp::new_exporter()
+        .http()
+        .with_headers(settings.headers.clone());
+      if let Some(endpoint) = &settings.endpoint {
+        exporter = exporter.with_endpoint(endpoint);
+      }
+      opentelemetry_otlp::new_pipeline()
+        .tracing()
+        .with_exporter(exporter)
+        .with_trace_config(
+          trace::config().with_resource(Resource::new(vec![KeyValue::new(
+            "service.name",
+            SERVICE_NAME,
+          )])),
+        )
+        .install_batch(opentelemetry::runtime::Tokio)
+        .into_diagnostic()
+    }
+  }
+}
+
+/// Export the spans still batched before the player exits.
+pub fn shutdown_tracing() {
+  #[cfg(feature = "otel")]
+  opentelemetry::global::shutdown_tracer_provider();
+}
+
+/// The warnings and errors logged since the start, the most recent first.
+pub(crate) fn recent_logs() -> Vec<LogLine> {
+  RECENT
+    .lock()
+    .map(|recent| recent.iter().rev().cloned().collect())
+    .unwrap_or_default()
+}
+
+/// Layer keeping the last events in `RECENT`.
+struct RecentLogs;
+
+impl<S: Subscriber> Layer<S> for RecentLogs {
+  fn on_event(&self, event: &Event<'_>, _ctx: Context<'_, S>) {
+    let mut message = Message::default();
+    event.record(&mut message);
+    let line = LogLine {
+      time: Local::now(),
+      level: *event.metadata().level(),
+      target: event.metadata().target().to_string(),
+      message: message.0,
+    };
+    if let Ok(mut recent) = RECENT.lock() {
+      if recent.len() == RECENT_LOGS {
+        recent.pop_front();
+      }
+      recent.push_back(line);
+    }
+  }
+}
+
+/// Message of an event, followed by its other fields.
+#[derive(Default)]
+struct Message(String);
+
+impl Visit for Message {
+  fn record_debug(&mut self, field: &Field, value: &dyn Debug) {
+    if field.name() == "message" {
+      self.0.insert_str(0, &format!("{value:?}"));
+    } else {
+      self.0.push_str(&format!(" {}={value:?}", field.name()));
+    }
+  }
+}
+
+/// Log file moved to `<path>.1` once bigger than `max_size`, the older ones
+/// shifted up to `<path>.<keep>`.
+struct LogFile {
+  path: PathBuf,
+  // In bytes
+  max_size: u64,
+  keep: u32,
+  file: File,
+  size: u64,
+}
+
+impl LogFile {
+  fn open(path: PathBuf, max_size: u64, keep: u32) -> io::Result<LogFile> {
+    if let Some(directory) = path.parent() {
+      fs::create_dir_all(directory)?;
+    }
+    let file = OpenOptions::new().create(true).append(true).open(&path)?;
+    let size = file.metadata()?.len();
+    Ok(LogFile {
+      path,
+      max_size,
+      keep,
+      file,
+      size,
+    })
+  }
+
+  fn rotate(&mut self) -> io::Result<()> {
+    for index in (1..self.keep).rev() {
+      let _ = fs::rename(rotated(&self.path, index), rotated(&self.path, index + 1));
+    }
+    if self.keep > 0 {
+      fs::rename(&self.path, rotated(&self.path, 1))?;
+    }
+    self.file = File::create(&self.path)?;
+    self.size = 0;
+    Ok(())
+  }
+}
+
+impl Write for LogFile {
+  fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+    if self.size > 0 && self.size + buf.len() as u64 > self.max_size {
+      self.rotate()?;
+    }
+    let written = self.file.write(buf)?;
+    self.size += written as u64;
+    Ok(written)
+  }
+
+  fn flush(&mut self) -> io::Result<()> {
+    self.file.flush()
+  }
+}
+
+fn rotated(path: &Path, index: u32) -> PathBuf {
+  let mut name = path.as_os_str().to_owned();
+  name.push(format!(".{index}"));
+  PathBuf::from(name)
+}
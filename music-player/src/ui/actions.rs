@@ -0,0 +1,265 @@
+use super::{i18n::tr, rendering::rating, theme::theme};
+use crate::{
+  flags,
+  rhythmdb::{Entry, Rating, SharedEntry},
+};
+use miette::{miette, IntoDiagnostic, Result};
+use ratatui::{
+  prelude::{Constraint, Layout, Rect},
+  widgets::{Block, Borders, Clear, List, ListState, Padding},
+  Frame,
+};
+use std::{fs, process::Command};
+use tracing::instrument;
+use url::Url;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub(crate) enum Action {
+  Play,
+  PlayNext,
+  Enqueue,
+  EnqueueSimilar,
+  ArtistRadio,
+  Rate,
+  Love,
+  Unlove,
+  Ban,
+  Unban,
+  Comment,
+  ShowAlbum,
+  Hide,
+  OpenFolder,
+  Delete,
+}
+
+impl Action {
+  fn label(&self) -> &'static str {
+    match self {
+      Action::Play => tr().action_play,
+      Action::PlayNext => tr().action_play_next,
+      Action::Enqueue => tr().action_enqueue,
+      Action::EnqueueSimilar => tr().action_enqueue_similar,
+      Action::ArtistRadio => tr().action_artist_radio,
+      Action::Rate => tr().action_rate,
+      Action::Love => tr().action_love,
+      Action::Unlove => tr().action_unlove,
+      Action::Ban => tr().action_ban,
+      Action::Unban => tr().action_unban,
+      Action::Comment => tr().action_comment,
+      Action::ShowAlbum => tr().action_show_album,
+      Action::Hide => tr().action_hide,
+      Action::OpenFolder => tr().action_open_folder,
+      Action::Delete => tr().action_delete,
+    }
+  }
+}
+
+/// Actions popup on the selected row.
+#[derive(Debug)]
+pub(crate) struct ActionMenu {
+  pub(crate) entry: SharedEntry,
+  // Index of the entry in the table
+  pub(crate) index: usize,
+  pub(crate) state: ListState,
+  // The rating sub menu is opened
+  pub(crate) rating: bool,
+  // The deletion of the file waits for a confirmation
+  pub(crate) deleting: bool,
+  // Party mode: the rating, the flags, the comment and the hiding are left
+  // out
+  locked: bool,
+  // The sub menu has the half stars
+  half_stars: bool,
+}
+
+impl ActionMenu {
+  pub(crate) fn new(entry: SharedEntry, index: usize, locked: bool, half_stars: bool) -> ActionMenu {
+    ActionMenu {
+      entry,
+      index,
+      state: ListState::default().with_selected(Some(0)),
+      rating: false,
+      deleting: false,
+      locked,
+      half_stars,
+    }
+  }
+
+  /// Available actions for the entry.
+  pub(crate) fn actions(&self) -> Vec<Action> {
+    let mut actions = vec![Action::Play, Action::PlayNext, Action::Enqueue];
+    if let Entry::Song(_) = self.entry.as_ref() {
+      actions.push(Action::EnqueueSimilar);
+      if !self.entry.get_artist().is_empty() {
+        actions.push(Action::ArtistRadio);
+      }
+    }
+    if !self.locked {
+      let location = self.entry.get_location();
+      actions.push(Action::Rate);
+      actions.push(if flags::is_loved(&location) {
+        Action::Unlove
+      } else {
+        Action::Love
+      });
+      actions.push(if flags::is_banned(&location) {
+        Action::Unban
+      } else {
+        Action::Ban
+      });
+      actions.push(Action::Comment);
+    }
+    if let Entry::Song(_) = self.entry.as_ref() {
+      actions.push(Action::ShowAlbum);
+    }
+    if !self.locked {
+      actions.push(Action::Hide);
+    }
+    if self.entry.get_location().scheme() == "file" {
+      actions.push(Action::OpenFolder);
+      if !self.locked {
+        actions.push(Action::Delete);
+      }
+    }
+    actions
+  }
+
+  fn len(&self) -> usize {
+    if self.rating {
+      self.ratings().len()
+    } else if self.deleting {
+      self.confirmations().len()
+    } else {
+      self.actions().len()
+    }
+  }
+
+  pub(crate) fn next(&mut self) {
+    let i = match self.state.selected() {
+      Some(i) if i + 1 < self.len() => i + 1,
+      _ => 0,
+    };
+    self.state.select(Some(i));
+  }
+
+  pub(crate) fn previous(&mut self) {
+    let i = match self.state.selected() {
+      Some(0) | None => self.len() - 1,
+      Some(i) => i - 1,
+    };
+    self.state.select(Some(i));
+  }
+
+  pub(crate) fn open_rating(&mut self, open: bool) {
+    self.rating = open;
+    self.state.select(Some(0));
+  }
+
+  /// Ask for a confirmation before deleting the file, the cancel selected.
+  pub(crate) fn open_deleting(&mut self, open: bool) {
+    self.deleting = open;
+    self.state.select(Some(0));
+  }
+
+  /// Choices of the confirmation sub menu: cancel, then delete.
+  fn confirmations(&self) -> [&'static str; 2] {
+    [tr().cancel_delete, tr().confirm_delete]
+  }
+
+  /// The delete is confirmed in its sub menu.
+  pub(crate) fn delete_confirmed(&self) -> bool {
+    self.deleting && self.state.selected() == Some(1)
+  }
+
+  pub(crate) fn selected_action(&self) -> Option<Action> {
+    self.actions().get(self.state.selected()?).copied()
+  }
+
+  /// Choices of the rating sub menu, from 5 stars to none.
+  fn ratings(&self) -> Vec<Rating> {
+    if self.half_stars {
+      (0..=10).rev().map(Rating::from_halves).collect()
+    } else {
+      (0..=5).rev().map(Rating::from_stars).collect()
+    }
+  }
+
+  pub(crate) fn selected_rating(&self) -> Option<Rating> {
+    self.ratings().get(self.state.selected()?).copied()
+  }
+}
+
+#[instrument(skip(frame))]
+pub(crate) fn render_action_menu(area: Rect, frame: &mut Frame<'_>, menu: &mut ActionMenu) {
+  let labels: Vec<String> = if menu.rating {
+    menu
+      .ratings()
+      .into_iter()
+      .map(|r| rating(Some(r)))
+      .collect()
+  } else if menu.deleting {
+    menu
+      .confirmations()
+      .iter()
+      .map(|label| label.to_string())
+      .collect()
+  } else {
+    menu
+      .actions()
+      .iter()
+      .map(|action| action.label().to_string())
+      .collect()
+  };
+  let [menu_area] = Layout::vertical([Constraint::Length(2 + labels.len() as u16)])
+    .flex(ratatui::layout::Flex::Center)
+    .areas(area);
+  let [menu_area] = Layout::horizontal([Constraint::Length(30)])
+    .flex(ratatui::layout::Flex::Center)
+    .areas(menu_area);
+
+  let list = List::new(labels)
+    .style(theme().default)
+    .highlight_style(theme().selected)
+    .highlight_symbol(">>")
+    .block(
+      Block::default()
+        .style(theme().border)
+        .padding(Padding::horizontal(1))
+        .borders(Borders::ALL)
+        .title(if menu.rating {
+          tr().rate
+        } else if menu.deleting {
+          tr().delete_title
+        } else {
+          tr().actions
+        }),
+    );
+
+  frame.render_widget(Clear, menu_area);
+  frame.render_stateful_widget(list, menu_area, &mut menu.state);
+}
+
+/// Open the folder of a local track with the desktop file manager.
+#[instrument]
+pub(crate) fn open_containing_folder(location: &Url) -> Result<()> {
+  let path = location
+    .to_file_path()
+    .map_err(|_| miette!("Not a local file: '{location}'"))?;
+  let folder = path
+    .parent()
+    .ok_or_else(|| miette!("No parent folder: '{}'", path.display()))?;
+  Command::new("xdg-open")
+    .arg(folder)
+    .spawn()
+    .into_diagnostic()?;
+  Ok(())
+}
+
+/// Delete the file of a local track.
+#[instrument]
+pub(crate) fn delete_file(location: &Url) -> Result<()> {
+  let path = location
+    .to_file_path()
+    .map_err(|_| miette!("Not a local file: '{location}'"))?;
+  fs::remove_file(&path).into_diagnostic()
+}
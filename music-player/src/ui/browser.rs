@@ -0,0 +1,170 @@
+use super::{glyphs::glyphs, i18n::tr, theme::theme};
+use crate::rhythmdb::{Entry, Rhythmdb};
+use ratatui::{
+  prelude::Rect,
+  style::Modifier,
+  widgets::{Block, BorderType, Borders, List, ListItem, ListState},
+  Frame,
+};
+use std::collections::{BTreeMap, BTreeSet};
+use tracing::instrument;
+
+/// Restrict the music tab to an artist or an album.
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) enum BrowseFilter {
+  Artist(String),
+  Album(String, String),
+}
+
+impl BrowseFilter {
+  pub(crate) fn matches(&self, entry: &Entry) -> bool {
+    match (self, entry) {
+      (BrowseFilter::Artist(artist), Entry::Song(song)) => &song.artist == artist,
+      (BrowseFilter::Album(artist, album), Entry::Song(song)) => {
+        &song.artist == artist && &song.album == album
+      }
+      _ => true,
+    }
+  }
+}
+
+enum Node<'a> {
+  All,
+  Artist(&'a str),
+  Album(&'a str, &'a str),
+}
+
+/// Artists → Albums tree displayed in the sidebar.
+#[derive(Debug, Default)]
+pub(crate) struct Browser {
+  artists: BTreeMap<String, BTreeSet<String>>,
+  expanded: BTreeSet<String>,
+  pub(crate) state: ListState,
+}
+
+impl Browser {
+  #[instrument(skip(db))]
+  pub(crate) fn new(db: &Rhythmdb) -> Browser {
+    let mut browser = Browser {
+      artists: db.artists_albums(),
+      expanded: BTreeSet::new(),
+      state: ListState::default(),
+    };
+    browser.state.select(Some(0));
+    browser
+  }
+
+  /// Visible nodes: the albums are listed under the expanded artists.
+  fn nodes(&self) -> Vec<Node<'_>> {
+    let mut nodes = vec![Node::All];
+    for (artist, albums) in &self.artists {
+      nodes.push(Node::Artist(artist));
+      if self.expanded.contains(artist) {
+        nodes.extend(albums.iter().map(|album| Node::Album(artist, album)));
+      }
+    }
+    nodes
+  }
+
+  pub(crate) fn next(&mut self) {
+    let len = self.nodes().len();
+    let i = match self.state.selected() {
+      Some(i) if i + 1 < len => i + 1,
+      _ => 0,
+    };
+    self.state.select(Some(i));
+  }
+
+  pub(crate) fn previous(&mut self) {
+    let len = self.nodes().len();
+    let i = match self.state.selected() {
+      Some(0) | None => len - 1,
+      Some(i) => i - 1,
+    };
+    self.state.select(Some(i));
+  }
+
+  /// Expand or collapse the selected artist. Collapsing an album selects its artist.
+  pub(crate) fn expand(&mut self, expand: bool) {
+    let nodes = self.nodes();
+    let artist = match nodes.get(self.state.selected().unwrap_or_default()) {
+      Some(Node::Artist(artist)) | Some(Node::Album(artist, _)) => artist.to_string(),
+      _ => return,
+    };
+    let artist_index = nodes
+      .iter()
+      .position(|node| matches!(node, Node::Artist(a) if *a == artist));
+    if expand {
+      self.expanded.insert(artist);
+    } else {
+      self.expanded.remove(&artist);
+      self.state.select(artist_index);
+    }
+  }
+
+  /// Expand the artist and select its album.
+  pub(crate) fn select_album(&mut self, artist: &str, album: &str) {
+    self.expanded.insert(artist.to_string());
+    let index = self
+      .nodes()
+      .iter()
+      .position(|node| matches!(node, Node::Album(a, b) if *a == artist && *b == album));
+    if index.is_some() {
+      self.state.select(index);
+    }
+  }
+
+  pub(crate) fn selected_filter(&self) -> Option<BrowseFilter> {
+    match self
+      .nodes()
+      .get(self.state.selected().unwrap_or_default())?
+    {
+      Node::All => None,
+      Node::Artist(artist) => Some(BrowseFilter::Artist(artist.to_string())),
+      Node::Album(artist, album) => Some(BrowseFilter::Album(artist.to_string(), album.to_string())),
+    }
+  }
+}
+
+#[instrument(skip(frame, browser))]
+pub(crate) fn render_browser_panel(
+  area: Rect,
+  frame: &mut Frame<'_>,
+  browser: &mut Browser,
+  focused: bool,
+) {
+  let items: Vec<ListItem> = browser
+    .nodes()
+    .iter()
+    .map(|node| match node {
+      Node::All => {
+        ListItem::new(tr().all_artists).style(theme().default.add_modifier(Modifier::BOLD))
+      }
+      Node::Artist(artist) => ListItem::new(format!(
+        "{} {artist}",
+        if browser.expanded.contains(*artist) {
+          glyphs().expanded
+        } else {
+          glyphs().collapsed
+        }
+      ))
+      .style(theme().default),
+      Node::Album(_, album) => ListItem::new(format!("   {album}")).style(theme().default_dark),
+    })
+    .collect();
+
+  let list = List::new(items)
+    .block(
+      Block::default()
+        .borders(Borders::ALL)
+        .border_type(BorderType::Rounded)
+        .style(if focused {
+          theme().border_selected
+        } else {
+          theme().border
+        })
+        .title(tr().browse),
+    )
+    .highlight_style(theme().selected);
+  frame.render_stateful_widget(list, area, &mut browser.state);
+}
@@ -0,0 +1,60 @@
+use super::{glyphs::glyphs, i18n::tr, theme::theme};
+use crate::rhythmdb::SharedEntry;
+use ratatui::{
+  layout::Flex,
+  prelude::{Constraint, Layout, Rect},
+  text::Line,
+  widgets::{Block, Borders, Clear, Padding, Paragraph, Wrap},
+  Frame,
+};
+use tracing::instrument;
+
+/// Comment typed for an entry, saved in the db on enter.
+#[derive(Debug)]
+pub(crate) struct CommentEditor {
+  pub(crate) entry: SharedEntry,
+  pub(crate) text: String,
+}
+
+impl CommentEditor {
+  pub(crate) fn new(entry: SharedEntry) -> CommentEditor {
+    let text = entry.get_comment().unwrap_or_default().to_string();
+    CommentEditor { entry, text }
+  }
+}
+
+/// First line of the comment, for the comment column.
+pub(crate) fn comment_cell(comment: Option<&str>) -> String {
+  let Some(comment) = comment else {
+    return String::new();
+  };
+  match comment.split_once('\n') {
+    Some((first, _)) => format!("{first}{}", glyphs().ellipsis),
+    None => comment.to_string(),
+  }
+}
+
+#[instrument(skip(frame, editor))]
+pub(crate) fn render_comment_editor(area: Rect, frame: &mut Frame<'_>, editor: &CommentEditor) {
+  let [editor_area] = Layout::vertical([Constraint::Length(6)])
+    .flex(Flex::Center)
+    .areas(area);
+  let [editor_area] = Layout::horizontal([Constraint::Length(60)])
+    .flex(Flex::Center)
+    .areas(editor_area);
+
+  let input = Paragraph::new(format!("{}{}", editor.text, glyphs().cursor))
+    .style(theme().default)
+    .wrap(Wrap { trim: false })
+    .block(
+      Block::default()
+        .style(theme().border)
+        .padding(Padding::horizontal(1))
+        .borders(Borders::ALL)
+        .title(format!("{} – {}", tr().comment, editor.entry.get_title()))
+        .title_bottom(Line::from(tr().comment_hint).right_aligned()),
+    );
+
+  frame.render_widget(Clear, editor_area);
+  frame.render_widget(input, editor_area);
+}
@@ -0,0 +1,43 @@
+use crate::settings::DateSettings;
+use chrono::{DateTime, Local, TimeDelta, Utc};
+use humandate::HumanDateFormatter;
+use miette::{miette, IntoDiagnostic, Result, WrapErr};
+use std::sync::{PoisonError, RwLock};
+use tracing::instrument;
+
+// The default formats when none
+static DATES: RwLock<Option<HumanDateFormatter>> = RwLock::new(None);
+
+/// Build the formatter of the dates from the patterns of the settings.
+#[instrument]
+pub(crate) fn init_dates(settings: &DateSettings) -> Result<()> {
+  let mut formatter = HumanDateFormatter::default();
+  if let Some(today) = &settings.today {
+    formatter = formatter.today(today);
+  }
+  if let Some(older) = &settings.older {
+    formatter = formatter.older(older);
+  }
+  if let Some(steps) = &settings.steps {
+    formatter = formatter.without_steps();
+    for step in steps {
+      let until = humantime::parse_duration(&step.until)
+        .into_diagnostic()
+        .wrap_err_with(|| format!("Invalid date step `{}`", step.until))?;
+      let until = TimeDelta::from_std(until).into_diagnostic()?;
+      formatter = formatter.step(until, &step.format);
+    }
+  }
+  formatter.validate().map_err(|e| miette!("{e}"))?;
+  *DATES.write().unwrap_or_else(PoisonError::into_inner) = Some(formatter);
+  Ok(())
+}
+
+/// Format the date with the patterns of the settings.
+pub(crate) fn format_date(date: DateTime<Utc>) -> String {
+  let date = date.with_timezone(&Local);
+  match &*DATES.read().unwrap_or_else(PoisonError::into_inner) {
+    Some(formatter) => formatter.format(&date),
+    None => HumanDateFormatter::default().format(&date),
+  }
+}
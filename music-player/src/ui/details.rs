@@ -0,0 +1,83 @@
+use super::{dates::format_date, i18n::tr, theme::theme};
+use crate::{cover, rhythmdb::Entry};
+use chrono::DateTime;
+use ratatui::{
+  prelude::{Constraint, Layout, Rect},
+  text::{Line, Span},
+  widgets::{Block, Borders, Clear, Padding, Paragraph, Wrap},
+  Frame,
+};
+use tracing::instrument;
+
+/// Every field of the entry, as stored in the db, then the cached cover.
+fn entry_fields(entry: &Entry) -> Vec<(String, String)> {
+  let Ok(toml::Value::Table(table)) = toml::Value::try_from(entry) else {
+    return vec![];
+  };
+  let cover = cover::cached(entry).map(|path| ("cover".to_string(), path.display().to_string()));
+  table
+    .into_iter()
+    .map(|(key, value)| {
+      let value = match (key.as_str(), value) {
+        (
+          "first-seen" | "last-seen" | "last-played" | "post-time" | "mtime",
+          toml::Value::Integer(timestamp),
+        ) => {
+          format!(
+            "{timestamp} ({})",
+            format_date(DateTime::from_timestamp(timestamp, 0).unwrap_or_default())
+          )
+        }
+        (_, toml::Value::String(value)) => value,
+        (_, value) => value.to_string(),
+      };
+      (key, value)
+    })
+    .chain(cover)
+    .collect()
+}
+
+#[instrument(skip(frame, entry))]
+pub(crate) fn render_details_panel(
+  area: Rect,
+  frame: &mut Frame<'_>,
+  entry: &Entry,
+  error: Option<&str>,
+  scroll: u16,
+) {
+  let [details_area] = Layout::vertical([Constraint::Fill(1)])
+    .margin(3)
+    .horizontal_margin(10)
+    .areas(area);
+
+  let mut lines: Vec<Line> = entry_fields(entry)
+    .into_iter()
+    .map(|(key, value)| {
+      Line::from(vec![
+        Span::styled(format!("{key}: "), theme().help_key),
+        Span::styled(value, theme().default),
+      ])
+    })
+    .collect();
+  // The last error of GStreamer on the track, in the session
+  if let Some(error) = error {
+    lines.push(Line::from(vec![
+      Span::styled(format!("{}: ", tr().playback_error_field), theme().help_key),
+      Span::styled(error.to_string(), theme().error),
+    ]));
+  }
+
+  let details = Paragraph::new(lines)
+    .wrap(Wrap { trim: false })
+    .scroll((scroll, 0))
+    .block(
+      Block::default()
+        .style(theme().border)
+        .padding(Padding::horizontal(1))
+        .borders(Borders::ALL)
+        .title(tr().details),
+    );
+
+  frame.render_widget(Clear, details_area);
+  frame.render_widget(details, details_area);
+}
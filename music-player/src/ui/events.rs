@@ -1,14 +1,40 @@
-use super::Ui;
+use super::{
+  actions::{delete_file, open_containing_folder, Action, ActionMenu},
+  browser::{BrowseFilter, Browser},
+  comment::CommentEditor,
+  facets::FacetPanel,
+  i18n::{fill, tr},
+  playlist_editor::PlaylistEditor,
+  suggestions::SuggestionPanel,
+  Ui,
+};
 use crate::{
-  player_state::{PlayerState, Repeat, Shuffle},
-  settings::{PlayerStateSetting, Settings},
-  ui::{filter_playlist, rendering::render_table, Order, OrderDir, Panel, TabSelection},
+  flags, hooks,
+  player_state::{PlayerState, Repeat, ScopeKind, Shuffle},
+  rhythmdb::{Entry, Rating, SharedEntry},
+  settings::{HookEvent, Settings},
+  suggestions::suggest,
+  ui::{
+    filter_playlist,
+    rendering::{cell_widths, column_orders, render_table, RowMarks},
+    Order, OrderDir, Panel, TabSelection,
+  },
+};
+use crossterm::event::{
+  KeyCode, KeyEvent, KeyEventKind, KeyModifiers, MouseButton, MouseEvent, MouseEventKind,
+};
+use miette::{IntoDiagnostic, Result};
+use ratatui::layout::Position;
+use std::{
+  ops::{Deref, DerefMut},
+  time::Duration,
 };
-use crossterm::event::{KeyCode, KeyEvent, KeyEventKind, KeyModifiers};
-use miette::Result;
-use std::ops::{Deref, DerefMut};
+use tokio::time::timeout;
 use tracing::{debug, instrument};
 
+// Time given to the `stop` hooks when quitting
+const HOOK_TIMEOUT: Duration = Duration::from_secs(5);
+
 pub(crate) enum EventProcessStatus {
   None,
   Quit,
@@ -22,41 +48,74 @@ pub(crate) async fn handle_keys(
   settings: &Settings,
 ) -> Result<EventProcessStatus> {
   debug!("{:?}", key);
-  if key.kind == KeyEventKind::Press {
+  if key.kind == KeyEventKind::Release {
+    // The seek of the arrows is committed when they are released
+    if matches!(key.code, KeyCode::Left | KeyCode::Right) {
+      commit_scrub(app, player).await?;
+    }
+    return Ok(EventProcessStatus::None);
+  }
+  if matches!(key.kind, KeyEventKind::Press | KeyEventKind::Repeat) {
+    if app.panel == Panel::Unlock {
+      type_pin(app, key);
+      return Ok(EventProcessStatus::None);
+    }
+    if app.panel == Panel::Comment {
+      type_comment(app, player, key).await?;
+      return Ok(EventProcessStatus::None);
+    }
+    if app.panel == Panel::Playlist {
+      edit_playlist(app, settings, key);
+      return Ok(EventProcessStatus::None);
+    }
+    if app.locked && is_locked_key(&app.panel, key) {
+      app.warning(tr().locked_key);
+      return Ok(EventProcessStatus::None);
+    }
     match (&app.panel, key.modifiers, key.code) {
       // ctrl-c, exc : Quit
       (_, KeyModifiers::CONTROL, KeyCode::Char('c')) | (_, KeyModifiers::NONE, KeyCode::Esc) => {
-        if let Some(pipeline) = player.get_pipeline().await {
-          use gstreamer::{prelude::ElementExt, State};
-
-          let (_, state, _) = pipeline.state(None);
-          let pstate = if state == State::Playing || state == State::Paused {
-            PlayerStateSetting {
-              track: player.get_track().await.as_ref().map(|x| x.get_location()),
-              position: player.track_position().await.ok(),
-              shuffle_mode: Some(*player.shuffle_mode.read().await),
-              repeat_mode: Some(*player.repeat_mode.read().await),
-            }
-          } else {
-            PlayerStateSetting {
-              track: None,
-              position: None,
-              repeat_mode: None,
-              shuffle_mode: None,
-            }
-          };
-          pstate.save()?;
+        player.save_session(app.layout).await?;
+        app.session().save()?;
+        // The changes of a pending save, or deferred while Rhythmbox was
+        // running
+        let mut db = player.get_mut_db().await;
+        if db.has_pending_changes() {
+          db.save(settings)?;
         }
-        player.get_queue().await.save()?;
+        drop(db);
+        // The player exits: wait for the hooks
+        let track = player.get_track().await.clone();
+        let _ = timeout(HOOK_TIMEOUT, hooks::run(HookEvent::Stop, track)).await;
         return Ok(EventProcessStatus::Quit);
       }
-      // enter: play the selected track
+      // enter: play the selected track, the next ones are chosen from the
+      // table. The played track is paused or resumed instead.
       (Panel::None, KeyModifiers::NONE, KeyCode::Enter) => {
-        let track_list = player.get_playlist().await;
-        let track = track_list[app.table_state.selected().unwrap_or_default()].clone();
+        let track_list = player.get_playlist().await.to_vec();
+        let Some(track) = track_list
+          .get(app.table_state.selected().unwrap_or_default())
+          .cloned()
+        else {
+          return Ok(EventProcessStatus::None);
+        };
+        if is_loaded(player, &track).await {
+          use mpris_server::PlayerInterface;
+          player.play_pause().await.into_diagnostic()?;
+          return Ok(EventProcessStatus::None);
+        }
+        if let Some(kind) = app.scope_kind() {
+          player.set_scope(kind, track_list).await;
+        }
         player.stop_track().await?;
         player.play_track(track).await?;
       }
+      // ctrl-r: play the played track from the start
+      (Panel::None, KeyModifiers::CONTROL, KeyCode::Char('r')) => {
+        app.scrub = None;
+        player.track_seek(0).await?;
+        app.position.set(Duration::ZERO);
+      }
       // down: select the next track
       (Panel::None, KeyModifiers::NONE, KeyCode::Down) => {
         let i = match app.table_state.selected() {
@@ -71,6 +130,25 @@ pub(crate) async fn handle_keys(
         };
         app.table_state.select(Some(i));
       }
+      // shift-down, shift-up: mark the selected track and move, a marked
+      // track is unmarked
+      (Panel::None, KeyModifiers::SHIFT, KeyCode::Down | KeyCode::Up) => {
+        if let Some(i) = app.table_state.selected() {
+          if let Some(entry) = player.get_playlist().await.get(i) {
+            let location = entry.get_location();
+            if !app.marked.remove(&location) {
+              app.marked.insert(location);
+            }
+          }
+          let i = if key.code == KeyCode::Down {
+            (i + 1).min(app.row_len.saturating_sub(1))
+          } else {
+            i.saturating_sub(1)
+          };
+          app.table_state.select(Some(i));
+          build_table(app, player, false).await;
+        }
+      }
       // home: select the fist track
       (Panel::None, KeyModifiers::NONE, KeyCode::Home) => {
         app.table_state.select(Some(0));
@@ -89,14 +167,14 @@ pub(crate) async fn handle_keys(
         };
         app.table_state.select(Some(i));
       }
-      // page down:
+      // page down: move by the number of visible rows
       (Panel::None, KeyModifiers::NONE, KeyCode::PageDown) => {
         let i = match app.table_state.selected() {
           Some(i) => {
-            if i >= app.row_len - 15 {
+            if i + 1 >= app.row_len {
               0
             } else {
-              i + 15 // FIXME: height on the rect
+              (i + app.table_page).min(app.row_len - 1)
             }
           }
           None => 0,
@@ -106,37 +184,28 @@ pub(crate) async fn handle_keys(
       // page up
       (Panel::None, KeyModifiers::NONE, KeyCode::PageUp) => {
         let i = match app.table_state.selected() {
-          Some(i) => {
-            if i < 15 {
-              app.row_len - 1
-            } else {
-              i - 15
-            }
-          }
+          Some(0) => app.row_len.saturating_sub(1),
+          Some(i) => i.saturating_sub(app.table_page),
           None => 0,
         };
         app.table_state.select(Some(i));
       }
 
-      // <-- : seek 5 secs before
-      (Panel::None, KeyModifiers::NONE, KeyCode::Left) => {
-        if let Some(pipeline) = player.get_pipeline().await {
-          let position = app.get_track_elapsed_duration(&pipeline);
-          let new_position: i64 = position.as_secs() as i64 - 5;
-          let new_position = if new_position < 0 {
-            0
-          } else {
-            new_position as u64
-          };
-          player.track_seek(new_position).await?;
-        }
-      }
-      // --> : seek 5 secs after
-      (Panel::None, KeyModifiers::NONE, KeyCode::Right) => {
-        if let Some(pipeline) = player.get_pipeline().await {
-          let position = app.get_track_elapsed_duration(&pipeline);
-          player.track_seek(5 + position.as_secs()).await?;
-        }
+      // <--, --> : move the scrub position backward or forward, the seek is
+      // committed on release
+      (Panel::None, KeyModifiers::NONE, code @ (KeyCode::Left | KeyCode::Right))
+        if player.get_pipeline().await.is_some() =>
+      {
+        let position = match &app.scrub {
+          Some(scrub) => scrub.position,
+          None => app.position.now(),
+        };
+        let step = Duration::from_secs(app.seek_step);
+        app.scrub_to(if code == KeyCode::Left {
+          position.saturating_sub(step)
+        } else {
+          position + step
+        });
       }
       // alt-g : go to the track played in the current view
       (Panel::None, KeyModifiers::ALT, KeyCode::Char('g')) => {
@@ -148,17 +217,17 @@ pub(crate) async fn handle_keys(
       }
       // alt-p : view podcasts
       (Panel::None, KeyModifiers::ALT, KeyCode::Char('p')) => {
-        app.selected_tab = TabSelection::Podcast;
+        app.select_tab(TabSelection::Podcast);
         build_table(app, player, true).await;
       }
       // alt-m: view musics
       (Panel::None, KeyModifiers::ALT, KeyCode::Char('m')) => {
-        app.selected_tab = TabSelection::Music;
+        app.select_tab(TabSelection::Music);
         build_table(app, player, true).await;
       }
       // alt-q: view queue
       (Panel::None, KeyModifiers::ALT, KeyCode::Char('q')) => {
-        app.selected_tab = TabSelection::Queue;
+        app.select_tab(TabSelection::Queue);
         build_table(app, player, true).await;
       }
 
@@ -166,9 +235,20 @@ pub(crate) async fn handle_keys(
       (Panel::None, KeyModifiers::ALT, KeyCode::Char('e')) => {
         if app.selected_tab != TabSelection::Queue {
           if let Some(index) = app.table_state.selected() {
-            let track_list = player.get_playlist().await;
-            let track = &track_list[index];
-            player.queue.write().await.enqueue(track.get_location());
+            // Release the playlist before the table is rebuilt
+            let track = player.get_playlist().await[index].clone();
+            let skip_queued = app.playback.skip_queued;
+            if player
+              .queue
+              .write()
+              .await
+              .enqueue(track.get_location(), skip_queued)
+            {
+              app.info(fill(tr().enqueued, track.get_title()));
+            } else {
+              app.info(fill(tr().already_queued, track.get_title()));
+            }
+            build_table(app, player, false).await;
           };
         }
       }
@@ -194,11 +274,349 @@ pub(crate) async fn handle_keys(
           .await
       }
 
+      // ctrl-l: lock the party mode, or unlock it with the pin
+      (Panel::None, KeyModifiers::CONTROL, KeyCode::Char('l')) => {
+        if !app.locked {
+          app.locked = true;
+          app.info(tr().locked);
+        } else if app.party_pin.is_some() {
+          app.pin_input.clear();
+          app.panel = Panel::Unlock;
+        } else {
+          app.locked = false;
+          app.info(tr().unlocked);
+        }
+      }
+
+      // alt-y: toggle the lyrics panel
+      (Panel::None, KeyModifiers::ALT, KeyCode::Char('y')) => {
+        app.show_lyrics = !app.show_lyrics;
+      }
+
+      // ////////////////////////////////////////
+      // Layout
+      // ////////////////////////////////////////
+      // alt-f: show/hide the search bar
+      (Panel::None | Panel::Browser, KeyModifiers::ALT, KeyCode::Char('f')) => {
+        app.layout.show_search = !app.layout.show_search;
+      }
+      // alt-n: show/hide the control bar
+      (Panel::None | Panel::Browser, KeyModifiers::ALT, KeyCode::Char('n')) => {
+        app.layout.show_control = !app.layout.show_control;
+      }
+      // alt-k: show/hide the comment column
+      (Panel::None | Panel::Browser, KeyModifiers::ALT, KeyCode::Char('k')) => {
+        app.layout.show_comments = !app.layout.show_comments;
+      }
+      // alt-w: show/hide the composer and work columns
+      (Panel::None | Panel::Browser, KeyModifiers::ALT, KeyCode::Char('w')) => {
+        app.layout.show_classical = !app.layout.show_classical;
+      }
+      // alt-[, alt-]: resize the browser
+      (Panel::None | Panel::Browser, KeyModifiers::ALT, KeyCode::Char(c @ ('[' | ']'))) => {
+        app.layout.resize_browser(c == ']');
+      }
+      // alt-<, alt->: resize the lyrics panel
+      (Panel::None | Panel::Browser, _, KeyCode::Char(c @ ('<' | '>')))
+        if key.modifiers.contains(KeyModifiers::ALT) =>
+      {
+        app.layout.resize_lyrics(c == '<');
+      }
+
       // alt-h: display help
       (_, KeyModifiers::ALT, KeyCode::Char('h')) => {
         app.panel = match app.panel {
-          Panel::None => Panel::Help,
           Panel::Help => Panel::None,
+          _ => {
+            app.help_filter.clear();
+            app.help_scroll = 0;
+            Panel::Help
+          }
+        }
+      }
+      (Panel::Help, KeyModifiers::NONE, KeyCode::Down) => {
+        app.help_scroll = app.help_scroll.saturating_add(1);
+      }
+      (Panel::Help, KeyModifiers::NONE, KeyCode::Up) => {
+        app.help_scroll = app.help_scroll.saturating_sub(1);
+      }
+      (Panel::Help, KeyModifiers::NONE, KeyCode::PageDown) => {
+        app.help_scroll = app.help_scroll.saturating_add(10);
+      }
+      (Panel::Help, KeyModifiers::NONE, KeyCode::PageUp) => {
+        app.help_scroll = app.help_scroll.saturating_sub(10);
+      }
+      (Panel::Help, KeyModifiers::NONE, KeyCode::Home) => {
+        app.help_scroll = 0;
+      }
+      (Panel::Help, KeyModifiers::NONE, KeyCode::Backspace) => {
+        app.help_filter.pop();
+        app.help_scroll = 0;
+      }
+      (Panel::Help, KeyModifiers::NONE | KeyModifiers::SHIFT, KeyCode::Char(c)) => {
+        app.help_filter.push(c);
+        app.help_scroll = 0;
+      }
+
+      // ////////////////////////////////////////
+      // Actions
+      // ////////////////////////////////////////
+
+      // alt-a: actions on the selected track
+      (Panel::None, KeyModifiers::ALT, KeyCode::Char('a')) => {
+        if let Some(index) = app.table_state.selected() {
+          if let Some(entry) = player.get_playlist().await.get(index) {
+            app.action_menu = Some(ActionMenu::new(
+              entry.clone(),
+              index,
+              app.locked,
+              app.half_stars,
+            ));
+            app.panel = Panel::Actions;
+          }
+        }
+      }
+      (Panel::Actions, KeyModifiers::ALT, KeyCode::Char('a')) => {
+        app.action_menu = None;
+        app.panel = Panel::None;
+      }
+      (Panel::Actions, KeyModifiers::NONE, KeyCode::Down) => {
+        if let Some(menu) = app.action_menu.as_mut() {
+          menu.next();
+        }
+      }
+      (Panel::Actions, KeyModifiers::NONE, KeyCode::Up) => {
+        if let Some(menu) = app.action_menu.as_mut() {
+          menu.previous();
+        }
+      }
+      (Panel::Actions, KeyModifiers::NONE, KeyCode::Left) => {
+        if let Some(menu) = app.action_menu.as_mut() {
+          menu.open_rating(false);
+          menu.open_deleting(false);
+        }
+      }
+      (Panel::Actions, KeyModifiers::NONE, KeyCode::Enter) => {
+        if let Some(menu) = app.action_menu.take() {
+          app.panel = Panel::None;
+          run_action(app, player, menu).await?;
+        }
+      }
+
+      // ////////////////////////////////////////
+      // Filters
+      // ////////////////////////////////////////
+
+      // alt-u: create an automatic playlist
+      (Panel::None, KeyModifiers::ALT, KeyCode::Char('u')) => {
+        app.playlist_editor = Some(PlaylistEditor::new());
+        app.panel = Panel::Playlist;
+      }
+      // alt-v: filter by rating, genre, decade, duration
+      (Panel::None, KeyModifiers::ALT, KeyCode::Char('v')) => {
+        app.facet_panel = Some(FacetPanel::new(player.get_db().await.deref()));
+        app.panel = Panel::Filters;
+      }
+      (Panel::Filters, KeyModifiers::ALT, KeyCode::Char('v')) => {
+        app.facet_panel = None;
+        app.panel = Panel::None;
+      }
+      (Panel::Filters, KeyModifiers::NONE, KeyCode::Down) => {
+        if let Some(panel) = app.facet_panel.as_mut() {
+          panel.next();
+        }
+      }
+      (Panel::Filters, KeyModifiers::NONE, KeyCode::Up) => {
+        if let Some(panel) = app.facet_panel.as_mut() {
+          panel.previous();
+        }
+      }
+      // space, enter, →: check the choice or increase the rating; ←: decrease it
+      (
+        Panel::Filters,
+        KeyModifiers::NONE,
+        code @ (KeyCode::Char(' ') | KeyCode::Enter | KeyCode::Right | KeyCode::Left),
+      ) => {
+        if let Some(panel) = app.facet_panel.as_ref() {
+          panel.toggle(&mut app.facets, code != KeyCode::Left);
+          build_table(app, player, true).await;
+        }
+      }
+      // delete: remove every filter
+      (Panel::Filters, KeyModifiers::NONE, KeyCode::Delete) => {
+        app.facets = Default::default();
+        build_table(app, player, true).await;
+      }
+
+      // ////////////////////////////////////////
+      // Rating suggestions
+      // ////////////////////////////////////////
+
+      // alt-z: review the ratings suggested from the plays and skips
+      (Panel::None, KeyModifiers::ALT, KeyCode::Char('z')) => {
+        let settings = player.get_suggestions().await;
+        if !settings.enabled {
+          app.warning(tr().suggestions_off);
+          return Ok(EventProcessStatus::None);
+        }
+        let panel = SuggestionPanel::new(suggest(&settings, player.get_db().await.deref()));
+        if panel.is_empty() {
+          app.info(tr().no_suggestions);
+        } else {
+          app.suggestion_panel = Some(panel);
+          app.panel = Panel::Suggestions;
+        }
+      }
+      (Panel::Suggestions, KeyModifiers::ALT, KeyCode::Char('z')) => {
+        app.suggestion_panel = None;
+        app.panel = Panel::None;
+      }
+      (Panel::Suggestions, KeyModifiers::NONE, KeyCode::Down) => {
+        if let Some(panel) = app.suggestion_panel.as_mut() {
+          panel.next();
+        }
+      }
+      (Panel::Suggestions, KeyModifiers::NONE, KeyCode::Up) => {
+        if let Some(panel) = app.suggestion_panel.as_mut() {
+          panel.previous();
+        }
+      }
+      // →, ←: adjust the suggested rating
+      (Panel::Suggestions, KeyModifiers::NONE, code @ (KeyCode::Right | KeyCode::Left)) => {
+        if let Some(panel) = app.suggestion_panel.as_mut() {
+          panel.adjust(code == KeyCode::Right, app.half_stars);
+        }
+      }
+      // enter: rate the track; delete: dismiss the suggestion
+      (Panel::Suggestions, KeyModifiers::NONE, code @ (KeyCode::Enter | KeyCode::Delete)) => {
+        let Some(panel) = app.suggestion_panel.as_mut() else {
+          return Ok(EventProcessStatus::None);
+        };
+        let suggestion = panel.take_selected();
+        if panel.is_empty() {
+          app.suggestion_panel = None;
+          app.panel = Panel::None;
+        }
+        if let (Some(suggestion), KeyCode::Enter) = (suggestion, code) {
+          let mut db = player.get_mut_db().await;
+          // The entry may have changed since the suggestion
+          if let Some(track) = db.find_url(&suggestion.entry.get_location()) {
+            player
+              .update_ratings(db.deref_mut(), &[track], suggestion.rating)
+              .await;
+          }
+          drop(db);
+          app.info(tr().saved_rating);
+          app.request_save();
+          build_table(app, player, false).await;
+        }
+      }
+
+      // ////////////////////////////////////////
+      // Details
+      // ////////////////////////////////////////
+
+      // alt-i: show every field of the selected track
+      (Panel::None, KeyModifiers::ALT, KeyCode::Char('i')) => {
+        if let Some(index) = app.table_state.selected() {
+          app.details = player.get_playlist().await.get(index).cloned();
+          app.details_scroll = 0;
+          app.panel = Panel::Details;
+        }
+      }
+      (Panel::Details, KeyModifiers::ALT, KeyCode::Char('i')) => {
+        app.details = None;
+        app.panel = Panel::None;
+      }
+      (Panel::Details, KeyModifiers::NONE, KeyCode::Down) => {
+        app.details_scroll = app.details_scroll.saturating_add(1);
+      }
+      (Panel::Details, KeyModifiers::NONE, KeyCode::Up) => {
+        app.details_scroll = app.details_scroll.saturating_sub(1);
+      }
+      (Panel::Details, KeyModifiers::NONE, KeyCode::PageDown) => {
+        app.details_scroll = app.details_scroll.saturating_add(10);
+      }
+      (Panel::Details, KeyModifiers::NONE, KeyCode::PageUp) => {
+        app.details_scroll = app.details_scroll.saturating_sub(10);
+      }
+      (Panel::Details, KeyModifiers::NONE, KeyCode::Home) => {
+        app.details_scroll = 0;
+      }
+
+      // alt-j: show the recent warnings and errors
+      (Panel::None, KeyModifiers::ALT, KeyCode::Char('j')) => {
+        app.logs_scroll = 0;
+        app.panel = Panel::Logs;
+      }
+      (Panel::Logs, KeyModifiers::ALT, KeyCode::Char('j')) => app.panel = Panel::None,
+      (Panel::Logs, KeyModifiers::NONE, KeyCode::Down) => {
+        app.logs_scroll = app.logs_scroll.saturating_add(1);
+      }
+      (Panel::Logs, KeyModifiers::NONE, KeyCode::Up) => {
+        app.logs_scroll = app.logs_scroll.saturating_sub(1);
+      }
+      (Panel::Logs, KeyModifiers::NONE, KeyCode::PageDown) => {
+        app.logs_scroll = app.logs_scroll.saturating_add(10);
+      }
+      (Panel::Logs, KeyModifiers::NONE, KeyCode::PageUp) => {
+        app.logs_scroll = app.logs_scroll.saturating_sub(10);
+      }
+      (Panel::Logs, KeyModifiers::NONE, KeyCode::Home) => {
+        app.logs_scroll = 0;
+      }
+
+      // ////////////////////////////////////////
+      // Browser
+      // ////////////////////////////////////////
+
+      // alt-b: show/hide the artist/album browser
+      (Panel::None | Panel::Browser, KeyModifiers::ALT, KeyCode::Char('b')) => {
+        if app.browser.is_some() {
+          app.browser = None;
+          app.panel = Panel::None;
+          // A hidden browser no longer filters the tracks
+          if app.browse_filter.take().is_some() {
+            build_table(app, player, true).await;
+          }
+        } else {
+          app.browser = Some(Browser::new(player.get_db().await.deref()));
+          app.panel = Panel::Browser;
+        }
+      }
+      // tab: switch the focus between the browser and the tracks
+      (Panel::None, KeyModifiers::NONE, KeyCode::Tab) if app.browser.is_some() => {
+        app.panel = Panel::Browser;
+      }
+      (Panel::Browser, KeyModifiers::NONE, KeyCode::Tab) => {
+        app.panel = Panel::None;
+      }
+      (Panel::Browser, KeyModifiers::NONE, KeyCode::Down) => {
+        if let Some(browser) = app.browser.as_mut() {
+          browser.next();
+        }
+      }
+      (Panel::Browser, KeyModifiers::NONE, KeyCode::Up) => {
+        if let Some(browser) = app.browser.as_mut() {
+          browser.previous();
+        }
+      }
+      (Panel::Browser, KeyModifiers::NONE, KeyCode::Right) => {
+        if let Some(browser) = app.browser.as_mut() {
+          browser.expand(true);
+        }
+      }
+      (Panel::Browser, KeyModifiers::NONE, KeyCode::Left) => {
+        if let Some(browser) = app.browser.as_mut() {
+          browser.expand(false);
+        }
+      }
+      // enter: filter the tracks with the selected artist or album
+      (Panel::Browser, KeyModifiers::NONE, KeyCode::Enter) => {
+        if let Some(browser) = app.browser.as_ref() {
+          app.browse_filter = browser.selected_filter();
+          app.select_tab(TabSelection::Music);
+          build_table(app, player, true).await;
         }
       }
 
@@ -230,73 +648,54 @@ pub(crate) async fn handle_keys(
         order_column(app, player, Order::LastPlayed).await;
       }
 
+      // alt-, alt-.: order-by the previous or the next column
+      (Panel::None, KeyModifiers::ALT, KeyCode::Char(',')) => {
+        order_next_column(app, player, false).await;
+      }
+      (Panel::None, KeyModifiers::ALT, KeyCode::Char('.')) => {
+        order_next_column(app, player, true).await;
+      }
+
       // ////////////////////////////////////////
       // Raring
       // ////////////////////////////////////////
-      (Panel::None, KeyModifiers::ALT, KeyCode::Char('0')) => {
-        player
-          .update_rating(
-            player.get_mut_db().await.deref_mut(),
-            app.table_state.selected(),
-            0,
-            settings,
-          )
-          .await?;
-        build_table(app, player, false).await;
-      }
-      (Panel::None, KeyModifiers::ALT, KeyCode::Char('1')) => {
-        player
-          .update_rating(
-            player.get_mut_db().await.deref_mut(),
-            app.table_state.selected(),
-            1,
-            settings,
-          )
-          .await?;
-        build_table(app, player, false).await;
-      }
-      (Panel::None, KeyModifiers::ALT, KeyCode::Char('2')) => {
-        player
-          .update_rating(
-            player.get_mut_db().await.deref_mut(),
-            app.table_state.selected(),
-            2,
-            settings,
-          )
-          .await?;
+      (Panel::None, KeyModifiers::ALT, KeyCode::Char(c @ '0'..='5')) => {
+        let rating = Rating::from_stars(c.to_digit(10).unwrap_or_default() as u64);
+        if app.marked.is_empty() {
+          player
+            .update_rating(
+              player.get_mut_db().await.deref_mut(),
+              app.table_state.selected(),
+              rating,
+            )
+            .await;
+          app.info(tr().saved_rating);
+        } else {
+          // The marked tracks are rated at once
+          let mut db = player.get_mut_db().await;
+          let tracks: Vec<SharedEntry> = app
+            .marked
+            .iter()
+            .filter_map(|location| db.find_url(location))
+            .collect();
+          player.update_ratings(db.deref_mut(), &tracks, rating).await;
+          drop(db);
+          app.info(fill(tr().saved_ratings, tracks.len()));
+        }
+        app.request_save();
         build_table(app, player, false).await;
       }
-      (Panel::None, KeyModifiers::ALT, KeyCode::Char('3')) => {
-        player
-          .update_rating(
-            player.get_mut_db().await.deref_mut(),
-            app.table_state.selected(),
-            3,
-            settings,
-          )
-          .await?;
+      // alt-+, alt--: raise or lower the rating by a step
+      (Panel::None, _, KeyCode::Char(c @ ('+' | '-')))
+        if key.modifiers.contains(KeyModifiers::ALT) =>
+      {
+        step_rating(app, player, c == '+').await;
+        app.request_save();
         build_table(app, player, false).await;
       }
-      (Panel::None, KeyModifiers::ALT, KeyCode::Char('4')) => {
-        player
-          .update_rating(
-            player.get_mut_db().await.deref_mut(),
-            app.table_state.selected(),
-            4,
-            settings,
-          )
-          .await?;
-        build_table(app, player, false).await;
-      }
-      (Panel::None, KeyModifiers::ALT, KeyCode::Char('5')) => {
-        player
-          .update_rating(
-            player.get_mut_db().await.deref_mut(),
-            app.table_state.selected(),
-            5,
-            settings,
-          )
-          .await?;
+      // alt-x: clear the marks
+      (Panel::None, KeyModifiers::ALT, KeyCode::Char('x')) => {
+        app.marked.clear();
         build_table(app, player, false).await;
       }
 
@@ -306,11 +705,40 @@ pub(crate) async fn handle_keys(
 
       // backspace: delete previous char in search
       (Panel::None, KeyModifiers::NONE, KeyCode::Backspace) => {
-        app.search.pop();
+        app.search.backspace();
+        build_table(app, player, true).await;
+      }
+      // delete: delete the char under the cursor
+      (Panel::None, KeyModifiers::NONE, KeyCode::Delete) => {
+        app.search.delete();
+        build_table(app, player, true).await;
+      }
+      // ctrl-w: delete the previous word
+      (Panel::None, KeyModifiers::CONTROL, KeyCode::Char('w')) => {
+        app.search.delete_word();
+        build_table(app, player, true).await;
+      }
+      // ctrl-u: clear the search
+      (Panel::None, KeyModifiers::CONTROL, KeyCode::Char('u')) => {
+        app.search.clear();
         build_table(app, player, true).await;
       }
+      // ctrl-k: delete to the end of the search
+      (Panel::None, KeyModifiers::CONTROL, KeyCode::Char('k')) => {
+        app.search.kill_to_end();
+        build_table(app, player, true).await;
+      }
+      // ctrl-a, ctrl-e: move the cursor to the start or the end
+      (Panel::None, KeyModifiers::CONTROL, KeyCode::Char('a')) => app.search.home(),
+      (Panel::None, KeyModifiers::CONTROL, KeyCode::Char('e')) => app.search.end(),
+      // ctrl-b, ctrl-f: move the cursor by a char
+      (Panel::None, KeyModifiers::CONTROL, KeyCode::Char('b')) => app.search.left(),
+      (Panel::None, KeyModifiers::CONTROL, KeyCode::Char('f')) => app.search.right(),
+      // ctrl-←, ctrl-→: move the cursor by a word
+      (Panel::None, KeyModifiers::CONTROL, KeyCode::Left) => app.search.word_left(),
+      (Panel::None, KeyModifiers::CONTROL, KeyCode::Right) => app.search.word_right(),
       (Panel::None, KeyModifiers::NONE, KeyCode::Char(c)) => {
-        app.search = app.search.clone() + &c.to_string();
+        app.search.insert(c);
         app.order_by = Order::Default;
         app.order_dir = OrderDir::Desc;
         build_table(app, player, true).await;
@@ -322,6 +750,393 @@ pub(crate) async fn handle_keys(
   Ok(EventProcessStatus::None)
 }
 
+/// The track is the played one, playing or paused.
+async fn is_loaded(player: &'static PlayerState, track: &SharedEntry) -> bool {
+  use gstreamer::{prelude::ElementExt, State};
+  let played = player
+    .get_track()
+    .await
+    .as_ref()
+    .map(|played| played.get_location());
+  if played != Some(track.get_location()) {
+    return false;
+  }
+  match player.get_pipeline().await {
+    Some(pipeline) => matches!(pipeline.current_state(), State::Playing | State::Paused),
+    None => false,
+  }
+}
+
+/// Seek to the scrub position.
+#[instrument(skip(app, player))]
+pub(crate) async fn commit_scrub(app: &mut Ui<'_>, player: &'static PlayerState) -> Result<()> {
+  if let Some(scrub) = app.scrub.take() {
+    player.track_seek(scrub.position.as_secs()).await?;
+    app.position.set(scrub.position);
+  }
+  Ok(())
+}
+
+/// Keys disabled by the party mode: quitting and rating.
+fn is_locked_key(panel: &Panel, key: KeyEvent) -> bool {
+  let rating_step =
+    key.modifiers.contains(KeyModifiers::ALT) && matches!(key.code, KeyCode::Char('+' | '-'));
+  (matches!(panel, Panel::None) && rating_step)
+    || matches!(
+      (panel, key.modifiers, key.code),
+      (_, KeyModifiers::CONTROL, KeyCode::Char('c'))
+        | (_, KeyModifiers::NONE, KeyCode::Esc)
+        | (
+          Panel::None,
+          KeyModifiers::ALT,
+          KeyCode::Char('0'..='5' | 'z')
+        )
+    )
+}
+
+/// Raise or lower the rating of the selected track, or of each marked track,
+/// by a half star with `half_stars`, by a whole star otherwise.
+#[instrument(skip(app, player))]
+async fn step_rating(app: &mut Ui<'_>, player: &'static PlayerState, up: bool) {
+  let mut db = player.get_mut_db().await;
+  let tracks: Vec<SharedEntry> = if app.marked.is_empty() {
+    let playlist = player.get_playlist().await;
+    app
+      .table_state
+      .selected()
+      .and_then(|i| playlist.get(i).cloned())
+      .into_iter()
+      .collect()
+  } else {
+    app
+      .marked
+      .iter()
+      .filter_map(|location| db.find_url(location))
+      .collect()
+  };
+  for track in &tracks {
+    let rating = if up {
+      track.get_rating().raised(app.half_stars)
+    } else {
+      track.get_rating().lowered(app.half_stars)
+    };
+    player
+      .update_ratings(db.deref_mut(), std::slice::from_ref(track), rating)
+      .await;
+  }
+  drop(db);
+  match tracks.len() {
+    0 => {}
+    1 => app.info(tr().saved_rating),
+    count => app.info(fill(tr().saved_ratings, count)),
+  }
+}
+
+/// Keys of the comment editor: enter saves the comment in the db, escape
+/// drops it.
+#[instrument(skip(app, player))]
+async fn type_comment(app: &mut Ui<'_>, player: &'static PlayerState, key: KeyEvent) -> Result<()> {
+  let Some(editor) = app.comment_editor.as_mut() else {
+    app.panel = Panel::None;
+    return Ok(());
+  };
+  match (key.modifiers, key.code) {
+    (KeyModifiers::CONTROL, KeyCode::Char('u')) => editor.text.clear(),
+    (KeyModifiers::NONE | KeyModifiers::SHIFT, KeyCode::Char(c)) => editor.text.push(c),
+    (_, KeyCode::Backspace) => {
+      editor.text.pop();
+    }
+    (_, KeyCode::Enter) => {
+      app.panel = Panel::None;
+      let Some(editor) = app.comment_editor.take() else {
+        return Ok(());
+      };
+      let mut db = player.get_mut_db().await;
+      let commented = db.comment_entry(&editor.entry, &editor.text);
+      drop(db);
+      app.request_save();
+      // The played track is saved again by its play count
+      let played = player.get_track().await.clone();
+      if let (Some(commented), Some(played)) = (commented, played) {
+        if commented.get_location() == played.get_location() {
+          player.set_track(commented).await;
+        }
+      }
+      build_table(app, player, false).await;
+      app.info(fill(tr().saved_comment, editor.entry.get_title()));
+    }
+    (_, KeyCode::Esc) => {
+      app.comment_editor = None;
+      app.panel = Panel::None;
+    }
+    _ => {}
+  }
+  Ok(())
+}
+
+/// Keys of the automatic playlist editor: enter adds the playlist to
+/// Rhythmbox, escape drops it.
+#[instrument(skip(app, settings))]
+fn edit_playlist(app: &mut Ui<'_>, settings: &Settings, key: KeyEvent) {
+  let Some(editor) = app.playlist_editor.as_mut() else {
+    app.panel = Panel::None;
+    return;
+  };
+  match (key.modifiers, key.code) {
+    (KeyModifiers::CONTROL, KeyCode::Char('n')) => editor.add_criterion(),
+    (KeyModifiers::CONTROL, KeyCode::Char('d')) => editor.remove_criterion(),
+    (KeyModifiers::CONTROL, KeyCode::Char('u')) => {
+      if let Some(text) = editor.text_mut() {
+        text.clear();
+      }
+    }
+    (KeyModifiers::NONE | KeyModifiers::SHIFT, KeyCode::Char(c)) => {
+      if let Some(text) = editor.text_mut() {
+        text.push(c);
+      }
+    }
+    (_, KeyCode::Backspace) => {
+      if let Some(text) = editor.text_mut() {
+        text.pop();
+      }
+    }
+    (_, KeyCode::Tab) => editor.next_cell(),
+    (_, KeyCode::BackTab) => editor.previous_cell(),
+    (_, KeyCode::Down) => editor.down(),
+    (_, KeyCode::Up) => editor.up(),
+    (_, KeyCode::Right) => editor.cycle(true),
+    (_, KeyCode::Left) => editor.cycle(false),
+    (_, KeyCode::Enter) => {
+      let saved = editor.to_playlist().and_then(|playlist| {
+        playlist
+          .save_in(&settings.playlist_path)
+          .map_err(|e| fill(tr().playlist_error, e))?;
+        Ok(playlist)
+      });
+      match saved {
+        Ok(playlist) => {
+          app.info(fill(tr().saved_playlist, playlist.name()));
+          app.playlist_editor = None;
+          app.panel = Panel::None;
+        }
+        Err(e) => app.error(e),
+      }
+    }
+    (_, KeyCode::Esc) => {
+      app.playlist_editor = None;
+      app.panel = Panel::None;
+    }
+    _ => {}
+  }
+}
+
+/// Keys of the pin prompt: enter unlocks the party mode when the pin is
+/// right, escape cancels.
+fn type_pin(app: &mut Ui<'_>, key: KeyEvent) {
+  match key.code {
+    KeyCode::Char(c) => app.pin_input.push(c),
+    KeyCode::Backspace => {
+      app.pin_input.pop();
+    }
+    KeyCode::Enter => {
+      if app.party_pin.as_deref() == Some(app.pin_input.as_str()) {
+        app.locked = false;
+        app.info(tr().unlocked);
+      } else {
+        app.error(tr().wrong_pin);
+      }
+      app.pin_input.clear();
+      app.panel = Panel::None;
+    }
+    KeyCode::Esc => {
+      app.pin_input.clear();
+      app.panel = Panel::None;
+    }
+    _ => {}
+  }
+}
+
+#[instrument(skip(app, player))]
+async fn run_action(
+  app: &mut Ui<'_>,
+  player: &'static PlayerState,
+  mut menu: ActionMenu,
+) -> Result<()> {
+  if menu.rating {
+    if let Some(rating) = menu.selected_rating() {
+      player
+        .update_rating(
+          player.get_mut_db().await.deref_mut(),
+          Some(menu.index),
+          rating,
+        )
+        .await;
+      app.request_save();
+      build_table(app, player, false).await;
+      app.info(tr().saved_rating);
+    }
+    return Ok(());
+  }
+  if menu.deleting {
+    if menu.delete_confirmed() {
+      delete_track(app, player, &menu.entry).await?;
+    }
+    return Ok(());
+  }
+
+  match menu.selected_action() {
+    Some(Action::Play) => {
+      if let Some(kind) = app.scope_kind() {
+        let track_list = player.get_playlist().await.to_vec();
+        player.set_scope(kind, track_list).await;
+      }
+      player.stop_track().await?;
+      player.play_track(menu.entry.clone()).await?;
+    }
+    Some(Action::PlayNext) => {
+      let played = player.played_queue_index().await;
+      player.get_mut_queue().await.enqueue_next(
+        menu.entry.get_location(),
+        played,
+        app.playback.skip_queued,
+      );
+      app.info(fill(tr().played_next, menu.entry.get_title()));
+      build_table(app, player, false).await;
+    }
+    Some(Action::Enqueue) => {
+      let enqueued = player
+        .get_mut_queue()
+        .await
+        .enqueue(menu.entry.get_location(), app.playback.skip_queued);
+      if enqueued {
+        app.info(fill(tr().enqueued, menu.entry.get_title()));
+      } else {
+        app.info(fill(tr().already_queued, menu.entry.get_title()));
+      }
+      build_table(app, player, false).await;
+    }
+    Some(Action::EnqueueSimilar) => {
+      let similar = player
+        .get_db()
+        .await
+        .similar_to(&menu.entry, app.playback.similar_tracks);
+      let mut queue = player.get_mut_queue().await;
+      let mut enqueued = 0;
+      for entry in &similar {
+        if queue.enqueue(entry.get_location(), app.playback.skip_queued) {
+          enqueued += 1;
+        }
+      }
+      drop(queue);
+      app.info(fill(
+        &fill(tr().enqueued_similar, enqueued),
+        menu.entry.get_title(),
+      ));
+      build_table(app, player, false).await;
+    }
+    // Play the track, then shuffle the songs of its artist
+    Some(Action::ArtistRadio) => {
+      let songs = player
+        .get_db()
+        .await
+        .artist_songs(menu.entry.get_artist(), app.playback.radio_album_artist);
+      player.set_scope(ScopeKind::ArtistRadio, songs).await;
+      if matches!(player.get_shuffle_mode().await, Shuffle::Next) {
+        player.set_shuffle_mode(Shuffle::Shuffle).await;
+      }
+      player.stop_track().await?;
+      player.play_track(menu.entry.clone()).await?;
+    }
+    Some(Action::Rate) => {
+      // Keep the menu opened on the rating choices
+      menu.open_rating(true);
+      app.action_menu = Some(menu);
+      app.panel = Panel::Actions;
+    }
+    Some(Action::Comment) => {
+      app.comment_editor = Some(CommentEditor::new(menu.entry));
+      app.panel = Panel::Comment;
+    }
+    Some(Action::ShowAlbum) => {
+      if let Entry::Song(song) = menu.entry.as_ref() {
+        // The browser shows the filter, and lifts it when hidden
+        let browser = match app.browser.take() {
+          Some(browser) => browser,
+          None => Browser::new(player.get_db().await.deref()),
+        };
+        app
+          .browser
+          .insert(browser)
+          .select_album(&song.artist, &song.album);
+        app.browse_filter = Some(BrowseFilter::Album(song.artist.clone(), song.album.clone()));
+        app.search.clear();
+        app.select_tab(TabSelection::Music);
+        build_table(app, player, true).await;
+      }
+    }
+    Some(Action::Hide) => {
+      let mut db = player.get_mut_db().await;
+      db.hide_entry(&menu.entry);
+      drop(db);
+      app.request_save();
+      build_table(app, player, false).await;
+      app.info(fill(tr().hidden, menu.entry.get_title()));
+    }
+    Some(Action::Love | Action::Unlove) => {
+      match flags::toggle_loved(&menu.entry.get_location()) {
+        Ok(true) => app.info(fill(tr().loved, menu.entry.get_title())),
+        Ok(false) => app.info(fill(tr().unloved, menu.entry.get_title())),
+        Err(e) => app.error(e.to_string()),
+      }
+      build_table(app, player, false).await;
+    }
+    Some(Action::Ban | Action::Unban) => {
+      match flags::toggle_banned(&menu.entry.get_location()) {
+        Ok(true) => app.info(fill(tr().banned, menu.entry.get_title())),
+        Ok(false) => app.info(fill(tr().unbanned, menu.entry.get_title())),
+        Err(e) => app.error(e.to_string()),
+      }
+      build_table(app, player, false).await;
+    }
+    Some(Action::OpenFolder) => {
+      if let Err(e) = open_containing_folder(&menu.entry.get_location()) {
+        app.error(fill(tr().open_folder_error, e));
+      }
+    }
+    Some(Action::Delete) => {
+      // Keep the menu opened on the confirmation
+      menu.open_deleting(true);
+      app.action_menu = Some(menu);
+      app.panel = Panel::Actions;
+    }
+    None => {}
+  }
+  Ok(())
+}
+
+/// Delete the file of the track, then its entry and its places in the
+/// queue.
+#[instrument(skip(app, player))]
+async fn delete_track(
+  app: &mut Ui<'_>,
+  player: &'static PlayerState,
+  entry: &SharedEntry,
+) -> Result<()> {
+  let location = entry.get_location();
+  if let Err(e) = delete_file(&location) {
+    app.error(fill(tr().delete_error, e));
+    return Ok(());
+  }
+  player.get_mut_queue().await.remove_all(&location);
+  let mut db = player.get_mut_db().await;
+  db.remove_entry(entry);
+  drop(db);
+  app.request_save();
+  build_table(app, player, false).await;
+  app.info(fill(tr().deleted, entry.get_title()));
+  Ok(())
+}
+
 #[instrument(skip(app, player))]
 async fn order_column(app: &mut Ui<'_>, player: &'static PlayerState, column: Order) {
   if app.order_by == column {
@@ -337,25 +1152,82 @@ async fn order_column(app: &mut Ui<'_>, player: &'static PlayerState, column: Or
   build_table(app, player, true).await;
 }
 
+/// Order by the column before or after the ordered one. Past the first or
+/// the last column, order by the search score.
+#[instrument(skip(app, player))]
+async fn order_next_column(app: &mut Ui<'_>, player: &'static PlayerState, forward: bool) {
+  let columns: Vec<Order> = column_orders(app.selected_tab)
+    .into_iter()
+    .filter(|column| *column != Order::Default)
+    // The hidden classical columns are skipped
+    .filter(|column| app.layout.show_classical || !matches!(column, Order::Composer | Order::Work))
+    .collect();
+  let column = match (
+    columns.iter().position(|column| *column == app.order_by),
+    forward,
+  ) {
+    (Some(index), true) => columns.get(index + 1),
+    (Some(index), false) => index.checked_sub(1).map(|index| &columns[index]),
+    (None, true) => columns.first(),
+    (None, false) => columns.last(),
+  };
+  app.order_by = column.copied().unwrap_or(Order::Default);
+  app.order_dir = OrderDir::Desc;
+  build_table(app, player, true).await;
+}
+
+/// A click on a column header orders by this column.
+#[instrument(skip(app, player))]
+pub(crate) async fn handle_mouse(mouse: MouseEvent, app: &mut Ui<'_>, player: &'static PlayerState) {
+  if app.panel != Panel::None || mouse.kind != MouseEventKind::Down(MouseButton::Left) {
+    return;
+  }
+  let position = Position::new(mouse.column, mouse.row);
+  if let Some(index) = app
+    .header_areas
+    .iter()
+    .position(|area| area.contains(position))
+  {
+    let column = column_orders(app.selected_tab)[index];
+    // The comment column is not sorted
+    if column != Order::Default {
+      order_column(app, player, column).await;
+    }
+  }
+}
+
 #[instrument(skip(app, player))]
 pub(crate) async fn build_table(app: &mut Ui<'_>, player: &'static PlayerState, set_select: bool) {
-  let track_list = filter_playlist(
+  let mut track_list = filter_playlist(
     app.selected_tab,
-    &app.search,
+    app.search.text(),
+    app.browse_filter.as_ref(),
     player.get_db().await.deref(),
     player.get_queue().await.deref(),
     app.order_by,
     app.order_dir,
   );
+  if app.facets.is_active() {
+    track_list.retain(|entry| app.facets.matches(entry));
+  }
 
+  let widths = cell_widths(app);
   let (rows_len, table, track_index) = render_table(
     &track_list,
     app.order_by,
     app.order_dir,
-    &*player.get_track().await,
+    RowMarks {
+      current_track: &*player.get_track().await,
+      queue: player.get_queue().await.deref(),
+      marked: &app.marked,
+      discs: app.groups_discs(),
+    },
     app.selected_tab,
+    app.last_played_format,
+    widths,
   );
   player.set_playlist(track_list).await;
+  app.cell_widths = widths;
   app.table = table;
   app.row_len = rows_len;
   if set_select {
@@ -0,0 +1,256 @@
+use super::{i18n::tr, rendering::rating, theme::theme};
+use crate::rhythmdb::{Entry, Rating, Rhythmdb};
+use ratatui::{
+  prelude::{Constraint, Layout, Rect},
+  widgets::{Block, Borders, Clear, List, ListItem, ListState, Padding},
+  Frame,
+};
+use std::collections::BTreeSet;
+use tracing::instrument;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub(crate) enum DurationRange {
+  Short,
+  Medium,
+  Long,
+}
+
+impl DurationRange {
+  const ALL: [DurationRange; 3] = [
+    DurationRange::Short,
+    DurationRange::Medium,
+    DurationRange::Long,
+  ];
+
+  fn of(duration: u64) -> DurationRange {
+    match duration {
+      0..=179 => DurationRange::Short,
+      180..=360 => DurationRange::Medium,
+      _ => DurationRange::Long,
+    }
+  }
+
+  fn label(&self) -> &'static str {
+    match self {
+      DurationRange::Short => tr().duration_short,
+      DurationRange::Medium => tr().duration_medium,
+      DurationRange::Long => tr().duration_long,
+    }
+  }
+}
+
+/// Tempo ranges, for the workout playlists.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub(crate) enum BpmRange {
+  Slow,
+  Moderate,
+  Fast,
+  VeryFast,
+}
+
+impl BpmRange {
+  const ALL: [BpmRange; 4] = [
+    BpmRange::Slow,
+    BpmRange::Moderate,
+    BpmRange::Fast,
+    BpmRange::VeryFast,
+  ];
+
+  fn of(bpm: f64) -> BpmRange {
+    match bpm {
+      ..90.0 => BpmRange::Slow,
+      ..120.0 => BpmRange::Moderate,
+      ..150.0 => BpmRange::Fast,
+      _ => BpmRange::VeryFast,
+    }
+  }
+
+  fn label(&self) -> &'static str {
+    match self {
+      BpmRange::Slow => tr().bpm_slow,
+      BpmRange::Moderate => tr().bpm_moderate,
+      BpmRange::Fast => tr().bpm_fast,
+      BpmRange::VeryFast => tr().bpm_very_fast,
+    }
+  }
+}
+
+/// Filters composed with the text search. An empty set means no restriction.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub(crate) struct Facets {
+  pub(crate) min_rating: u64,
+  pub(crate) genres: BTreeSet<String>,
+  pub(crate) decades: BTreeSet<i32>,
+  pub(crate) durations: BTreeSet<DurationRange>,
+  // The tracks without BPM are left out by a range
+  pub(crate) bpms: BTreeSet<BpmRange>,
+  pub(crate) hide_podcasts: bool,
+}
+
+impl Facets {
+  pub(crate) fn is_active(&self) -> bool {
+    self != &Facets::default()
+  }
+
+  pub(crate) fn matches(&self, entry: &Entry) -> bool {
+    entry.get_rating() >= Rating::from_stars(self.min_rating)
+      && (self.genres.is_empty() || self.genres.contains(entry.get_genre()))
+      && (self.decades.is_empty()
+        || entry
+          .get_year()
+          .is_some_and(|year| self.decades.contains(&(year - year.rem_euclid(10)))))
+      && (self.durations.is_empty()
+        || self
+          .durations
+          .contains(&DurationRange::of(entry.get_duration())))
+      && (self.bpms.is_empty()
+        || entry
+          .get_bpm()
+          .is_some_and(|bpm| self.bpms.contains(&BpmRange::of(bpm))))
+      && !(self.hide_podcasts && matches!(entry, Entry::PodcastPost(_)))
+  }
+}
+
+enum Item {
+  MinRating,
+  HidePodcasts,
+  Duration(DurationRange),
+  Bpm(BpmRange),
+  Decade(i32),
+  Genre(String),
+}
+
+/// Filter popup: ←/→ change the minimum rating, ␣ toggles the other choices.
+#[derive(Debug)]
+pub(crate) struct FacetPanel {
+  genres: BTreeSet<String>,
+  decades: BTreeSet<i32>,
+  pub(crate) state: ListState,
+}
+
+impl FacetPanel {
+  #[instrument(skip(db))]
+  pub(crate) fn new(db: &Rhythmdb) -> FacetPanel {
+    let (genres, decades) = db.genres_decades();
+    FacetPanel {
+      genres,
+      decades,
+      state: ListState::default().with_selected(Some(0)),
+    }
+  }
+
+  fn items(&self) -> Vec<Item> {
+    let mut items = vec![Item::MinRating, Item::HidePodcasts];
+    items.extend(DurationRange::ALL.map(Item::Duration));
+    items.extend(BpmRange::ALL.map(Item::Bpm));
+    items.extend(self.decades.iter().map(|decade| Item::Decade(*decade)));
+    items.extend(self.genres.iter().cloned().map(Item::Genre));
+    items
+  }
+
+  pub(crate) fn next(&mut self) {
+    let len = self.items().len();
+    let i = match self.state.selected() {
+      Some(i) if i + 1 < len => i + 1,
+      _ => 0,
+    };
+    self.state.select(Some(i));
+  }
+
+  pub(crate) fn previous(&mut self) {
+    let len = self.items().len();
+    let i = match self.state.selected() {
+      Some(0) | None => len - 1,
+      Some(i) => i - 1,
+    };
+    self.state.select(Some(i));
+  }
+
+  /// Toggle the selected choice. On the rating row, `increase` selects the
+  /// direction.
+  pub(crate) fn toggle(&self, facets: &mut Facets, increase: bool) {
+    let items = self.items();
+    match items.get(self.state.selected().unwrap_or_default()) {
+      Some(Item::MinRating) if increase => facets.min_rating = (facets.min_rating + 1).min(5),
+      Some(Item::MinRating) => facets.min_rating = facets.min_rating.saturating_sub(1),
+      Some(Item::HidePodcasts) => facets.hide_podcasts = !facets.hide_podcasts,
+      Some(Item::Duration(range)) => toggle(&mut facets.durations, *range),
+      Some(Item::Bpm(range)) => toggle(&mut facets.bpms, *range),
+      Some(Item::Decade(decade)) => toggle(&mut facets.decades, *decade),
+      Some(Item::Genre(genre)) => toggle(&mut facets.genres, genre.clone()),
+      None => {}
+    }
+  }
+}
+
+fn toggle<T: Ord>(set: &mut BTreeSet<T>, value: T) {
+  if !set.remove(&value) {
+    set.insert(value);
+  }
+}
+
+#[instrument(skip(frame, panel))]
+pub(crate) fn render_facet_panel(
+  area: Rect,
+  frame: &mut Frame<'_>,
+  panel: &mut FacetPanel,
+  facets: &Facets,
+) {
+  let check = |checked: bool| if checked { "[x]" } else { "[ ]" };
+  let items: Vec<ListItem> = panel
+    .items()
+    .iter()
+    .map(|item| match item {
+      Item::MinRating => ListItem::new(format!(
+        "{}: < {} >",
+        tr().min_rating,
+        rating(Some(Rating::from_stars(facets.min_rating)))
+      )),
+      Item::HidePodcasts => ListItem::new(format!(
+        "{} {}",
+        check(facets.hide_podcasts),
+        tr().hide_podcasts
+      )),
+      Item::Duration(range) => ListItem::new(format!(
+        "{} {}",
+        check(facets.durations.contains(range)),
+        range.label()
+      )),
+      Item::Bpm(range) => ListItem::new(format!(
+        "{} {}",
+        check(facets.bpms.contains(range)),
+        range.label()
+      )),
+      Item::Decade(decade) => ListItem::new(format!(
+        "{} {decade}s",
+        check(facets.decades.contains(decade))
+      )),
+      Item::Genre(genre) => {
+        ListItem::new(format!("{} {genre}", check(facets.genres.contains(genre))))
+      }
+    })
+    .collect();
+
+  let [panel_area] = Layout::vertical([Constraint::Max(2 + items.len() as u16)])
+    .flex(ratatui::layout::Flex::Center)
+    .margin(3)
+    .areas(area);
+  let [panel_area] = Layout::horizontal([Constraint::Length(40)])
+    .flex(ratatui::layout::Flex::Center)
+    .areas(panel_area);
+
+  let list = List::new(items)
+    .style(theme().default)
+    .highlight_style(theme().selected)
+    .highlight_symbol(">>")
+    .block(
+      Block::default()
+        .style(theme().border)
+        .padding(Padding::horizontal(1))
+        .borders(Borders::ALL)
+        .title(tr().filters),
+    );
+
+  frame.render_widget(Clear, panel_area);
+  frame.render_stateful_widget(list, panel_area, &mut panel.state);
+}
@@ -0,0 +1,117 @@
+use crate::settings::SymbolSet;
+use std::sync::{PoisonError, RwLock};
+use tracing::instrument;
+
+static GLYPHS: RwLock<&'static Glyphs> = RwLock::new(&UNICODE);
+
+/// Symbols drawn in the ui.
+#[derive(Debug)]
+pub(crate) struct Glyphs {
+  pub(crate) shuffle_next: &'static str,
+  pub(crate) shuffle: &'static str,
+  pub(crate) shuffle_last_played: &'static str,
+  pub(crate) repeat_all: &'static str,
+  pub(crate) repeat_current: &'static str,
+  pub(crate) sort_asc: &'static str,
+  pub(crate) sort_desc: &'static str,
+  pub(crate) star: &'static str,
+  pub(crate) star_half: &'static str,
+  pub(crate) star_empty: &'static str,
+  pub(crate) loved: &'static str,
+  pub(crate) expanded: &'static str,
+  pub(crate) collapsed: &'static str,
+  pub(crate) played: &'static str,
+  pub(crate) queued: &'static str,
+  pub(crate) playing: &'static str,
+  pub(crate) locked: &'static str,
+  pub(crate) ellipsis: &'static str,
+  pub(crate) cursor: &'static str,
+  // Replacements of the key symbols used in the help
+  keys: &'static [(&'static str, &'static str)],
+}
+
+const UNICODE: Glyphs = Glyphs {
+  shuffle_next: "⇶",
+  shuffle: "🔀",
+  shuffle_last_played: "🎜",
+  repeat_all: "🔁",
+  repeat_current: "🔂",
+  sort_asc: "⏶",
+  sort_desc: "⏷",
+  star: "★",
+  star_half: "½",
+  star_empty: "☆",
+  loved: "♥",
+  expanded: "▾",
+  collapsed: "▸",
+  played: "✓",
+  queued: "♪",
+  playing: "▶",
+  locked: "🔒",
+  ellipsis: "…",
+  cursor: "▏",
+  keys: &[],
+};
+
+const ASCII: Glyphs = Glyphs {
+  shuffle_next: ">",
+  shuffle: "S",
+  shuffle_last_played: "SL",
+  repeat_all: "R",
+  repeat_current: "R1",
+  sort_asc: "^",
+  sort_desc: "v",
+  star: "*",
+  star_half: "/",
+  star_empty: ".",
+  loved: "<3",
+  expanded: "v",
+  collapsed: ">",
+  played: "+",
+  queued: "#",
+  playing: ">",
+  locked: "L",
+  ellipsis: "...",
+  cursor: "_",
+  keys: &[
+    ("⎇-", "M-"),
+    ("⇧-", "S-"),
+    ("⎋", "Esc"),
+    ("⇥", "Tab"),
+    ("↓", "Down"),
+    ("↑", "Up"),
+    ("⇟", "PgDn"),
+    ("⇞", "PgUp"),
+    ("⏎", "Enter"),
+    ("⏯", "Play"),
+    ("⏹", "Stop"),
+    ("⏭", "Next"),
+    ("←", "Left"),
+    ("→", "Right"),
+  ],
+};
+
+impl Glyphs {
+  /// Key of the help, written with the symbol set.
+  pub(crate) fn key(&self, key: &str) -> String {
+    self
+      .keys
+      .iter()
+      .fold(key.to_string(), |key, (from, to)| key.replace(from, to))
+  }
+}
+
+/// The symbols used to render the ui. Fallback to unicode when `init_glyphs`
+/// has not been called.
+pub(crate) fn glyphs() -> &'static Glyphs {
+  *GLYPHS.read().unwrap_or_else(PoisonError::into_inner)
+}
+
+#[instrument]
+pub(crate) fn init_glyphs(symbols: SymbolSet) {
+  // Called again when the settings are reloaded
+  *GLYPHS.write().unwrap_or_else(PoisonError::into_inner) = match symbols {
+    SymbolSet::Unicode => &UNICODE,
+    SymbolSet::Ascii => &ASCII,
+  };
+}
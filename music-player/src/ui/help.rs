@@ -1,60 +1,61 @@
-use super::rendering::THEME;
+use super::{
+  glyphs::glyphs,
+  i18n::{fill, tr},
+  theme::theme,
+};
 use ratatui::{
   layout::Alignment,
   prelude::{Constraint, Layout, Rect},
-  text::Text,
+  text::{Line, Text},
   widgets::{Block, Borders, Clear, Padding, Row, Table},
   Frame,
 };
 use tracing::instrument;
 
-#[instrument]
-pub(crate) fn render_help_panel(area: Rect, frame: &mut Frame<'_>) {
-  let help_rows = [
-    ("⎇-h", "Display this help"),
-    ("⎋, ^-c", "Quit the player"),
-    ("⎇-m", "Show local tracks"),
-    ("⎇-p", "Show podcasts"),
-    ("⎇-q", "Show queue"),
-    ("⎇-e", "Enqueue the selected track"),
-    ("⎇-s", "Order by search score"),
-    ("⎇-t", "Order by title"),
-    ("⎇-d", "Order by date"),
-    ("⎇-r", "Order by rating"),
-    ("⎇-l", "Order by last played"),
-    ("⎇-0..5", "Rate the selected track"),
-    ("⎇-o", "Toggle shuffle mode"),
-    ("⎇-c", "Repeat current track"),
-    ("⎇-g", "Select the current playing track"),
-    ("↓,↑,⇟,⇞", "Select the tracks"),
-    ("⏎", "Play the selected track"),
-    ("⏯", "Play / Pause"),
-    ("⏹", "Stop"),
-    ("⏭", "Next track"),
-    ("←, →", "Seek 5 seconds backward or forward"),
-  ];
-  let [help_area] = Layout::vertical([Constraint::Length(2 + help_rows.len() as u16)])
+/// Render the help popup. Only the rows matching `filter` are listed, from the
+/// `scroll` row. The scroll is clamped so the last row stays at the bottom.
+#[instrument(skip(frame))]
+pub(crate) fn render_help_panel(area: Rect, frame: &mut Frame<'_>, filter: &str, scroll: &mut u16) {
+  let filter = filter.to_lowercase();
+  let help_rows: Vec<_> = tr()
+    .help_rows
+    .iter()
+    .map(|(key, text)| (glyphs().key(key), *text))
+    .filter(|(key, text)| {
+      key.to_lowercase().contains(&filter) || text.to_lowercase().contains(&filter)
+    })
+    .collect();
+
+  let [help_area] = Layout::vertical([Constraint::Max(2 + help_rows.len().max(1) as u16)])
     .margin(5)
     .horizontal_margin(15)
     .areas(area);
+  // Borders are not rows
+  let visible_rows = help_area.height.saturating_sub(2);
+  *scroll = (*scroll).min((help_rows.len() as u16).saturating_sub(visible_rows));
 
   let help = Table::new(
-    help_rows.map(|(key, text)| {
+    help_rows.iter().skip(*scroll as usize).map(|(key, text)| {
       Row::new(vec![
-        Text::from(key)
+        Text::from(key.as_str())
           .alignment(Alignment::Right)
-          .style(THEME.help_key),
-        Text::from(text).style(THEME.default),
+          .style(theme().help_key),
+        Text::from(*text).style(theme().default),
       ])
     }),
     [Constraint::Fill(1), Constraint::Fill(2)],
   )
   .block(
     Block::default()
-      .style(THEME.border)
+      .style(theme().border)
       .padding(Padding::horizontal(1))
       .borders(Borders::ALL)
-      .title("Help"),
+      .title(tr().help)
+      .title_bottom(if filter.is_empty() {
+        Line::from(tr().help_hint).style(theme().default_dark)
+      } else {
+        Line::from(fill(tr().help_filter, &filter)).style(theme().secondary)
+      }),
   );
 
   frame.render_widget(Clear, help_area);
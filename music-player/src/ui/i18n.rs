@@ -0,0 +1,629 @@
+use crate::{player_state::ScopeKind, settings::Language};
+use std::{
+  env,
+  sync::{PoisonError, RwLock},
+};
+use tracing::instrument;
+
+static MESSAGES: RwLock<&'static Messages> = RwLock::new(&ENGLISH);
+
+/// Strings of the ui in one language. `{}` in a message is replaced by its
+/// argument with `fill`.
+#[derive(Debug)]
+pub(crate) struct Messages {
+  pub(crate) app_title: &'static str,
+  pub(crate) terminal_too_small: &'static str,
+  // Tabs
+  pub(crate) music: &'static str,
+  pub(crate) podcasts: &'static str,
+  pub(crate) queue: &'static str,
+  // Panels
+  pub(crate) search: &'static str,
+  pub(crate) browse: &'static str,
+  pub(crate) all_artists: &'static str,
+  pub(crate) lyrics: &'static str,
+  pub(crate) no_lyrics: &'static str,
+  pub(crate) details: &'static str,
+  pub(crate) logs: &'static str,
+  pub(crate) no_logs: &'static str,
+  pub(crate) actions: &'static str,
+  pub(crate) rate: &'static str,
+  pub(crate) unlock: &'static str,
+  pub(crate) comment: &'static str,
+  pub(crate) comment_hint: &'static str,
+  // Automatic playlist editor
+  pub(crate) playlist_editor: &'static str,
+  pub(crate) playlist_hint: &'static str,
+  pub(crate) playlist_name: &'static str,
+  pub(crate) playlist_match_all: &'static str,
+  pub(crate) playlist_match_any: &'static str,
+  pub(crate) playlist_limit: &'static str,
+  pub(crate) playlist_no_limit: &'static str,
+  pub(crate) playlist_limit_tracks: &'static str,
+  pub(crate) playlist_limit_minutes: &'static str,
+  pub(crate) playlist_sort: &'static str,
+  pub(crate) playlist_ascending: &'static str,
+  pub(crate) playlist_descending: &'static str,
+  pub(crate) op_contains: &'static str,
+  pub(crate) op_not_contains: &'static str,
+  pub(crate) op_is: &'static str,
+  pub(crate) op_starts_with: &'static str,
+  pub(crate) op_ends_with: &'static str,
+  pub(crate) op_greater: &'static str,
+  pub(crate) op_less: &'static str,
+  pub(crate) op_within: &'static str,
+  pub(crate) op_not_within: &'static str,
+  // Play scopes
+  pub(crate) scope_library: &'static str,
+  pub(crate) scope_podcasts: &'static str,
+  pub(crate) scope_search: &'static str,
+  pub(crate) scope_artist: &'static str,
+  pub(crate) scope_album: &'static str,
+  pub(crate) scope_artist_radio: &'static str,
+  pub(crate) scope_playlist: &'static str,
+  pub(crate) nothing_playing: &'static str,
+  pub(crate) help: &'static str,
+  pub(crate) help_hint: &'static str,
+  pub(crate) help_filter: &'static str,
+  pub(crate) filters: &'static str,
+  pub(crate) filters_active: &'static str,
+  pub(crate) min_rating: &'static str,
+  pub(crate) hide_podcasts: &'static str,
+  pub(crate) duration_short: &'static str,
+  pub(crate) duration_medium: &'static str,
+  pub(crate) duration_long: &'static str,
+  pub(crate) bpm_slow: &'static str,
+  pub(crate) bpm_moderate: &'static str,
+  pub(crate) bpm_fast: &'static str,
+  pub(crate) bpm_very_fast: &'static str,
+  // Table headers
+  pub(crate) title: &'static str,
+  pub(crate) artist: &'static str,
+  pub(crate) album: &'static str,
+  pub(crate) duration: &'static str,
+  pub(crate) rating: &'static str,
+  pub(crate) last_played: &'static str,
+  pub(crate) bpm: &'static str,
+  pub(crate) year: &'static str,
+  pub(crate) composer: &'static str,
+  pub(crate) work: &'static str,
+  pub(crate) genre: &'static str,
+  pub(crate) play_count: &'static str,
+  pub(crate) first_seen: &'static str,
+  pub(crate) date: &'static str,
+  pub(crate) feed: &'static str,
+  track: &'static str,
+  tracks: &'static str,
+  pub(crate) queue_sorted: &'static str,
+  pub(crate) marked: &'static str,
+  pub(crate) disc: &'static str,
+  is_plural: fn(usize) -> bool,
+  // Actions
+  pub(crate) action_play: &'static str,
+  pub(crate) action_play_next: &'static str,
+  pub(crate) action_enqueue: &'static str,
+  pub(crate) action_enqueue_similar: &'static str,
+  pub(crate) action_artist_radio: &'static str,
+  pub(crate) action_rate: &'static str,
+  pub(crate) action_love: &'static str,
+  pub(crate) action_unlove: &'static str,
+  pub(crate) action_ban: &'static str,
+  pub(crate) action_unban: &'static str,
+  pub(crate) action_show_album: &'static str,
+  pub(crate) action_hide: &'static str,
+  pub(crate) action_open_folder: &'static str,
+  pub(crate) action_delete: &'static str,
+  pub(crate) delete_title: &'static str,
+  pub(crate) cancel_delete: &'static str,
+  pub(crate) confirm_delete: &'static str,
+  pub(crate) action_comment: &'static str,
+  // Buttons of the desktop notifications
+  pub(crate) notify_next: &'static str,
+  pub(crate) notify_pause: &'static str,
+  // Status bar
+  pub(crate) saved_rating: &'static str,
+  pub(crate) saved_ratings: &'static str,
+  pub(crate) suggestions: &'static str,
+  pub(crate) suggestions_hint: &'static str,
+  pub(crate) suggestion_counts: &'static str,
+  pub(crate) suggestions_off: &'static str,
+  pub(crate) no_suggestions: &'static str,
+  pub(crate) saved_db: &'static str,
+  pub(crate) save_failed: &'static str,
+  pub(crate) save_deferred: &'static str,
+  pub(crate) enqueued: &'static str,
+  pub(crate) played_next: &'static str,
+  pub(crate) already_queued: &'static str,
+  pub(crate) enqueued_similar: &'static str,
+  pub(crate) hidden: &'static str,
+  pub(crate) loved: &'static str,
+  pub(crate) unloved: &'static str,
+  pub(crate) banned: &'static str,
+  pub(crate) unbanned: &'static str,
+  pub(crate) saved_comment: &'static str,
+  pub(crate) saved_playlist: &'static str,
+  pub(crate) playlist_no_name: &'static str,
+  pub(crate) playlist_invalid_value: &'static str,
+  pub(crate) playlist_error: &'static str,
+  pub(crate) open_folder_error: &'static str,
+  pub(crate) deleted: &'static str,
+  pub(crate) delete_error: &'static str,
+  pub(crate) settings_reloaded: &'static str,
+  pub(crate) invalid_settings: &'static str,
+  pub(crate) playback_error: &'static str,
+  pub(crate) playback_warning: &'static str,
+  pub(crate) playback_error_field: &'static str,
+  pub(crate) output_lost: &'static str,
+  pub(crate) stalled: &'static str,
+  pub(crate) stalled_seek: &'static str,
+  pub(crate) stalled_restart: &'static str,
+  pub(crate) locked: &'static str,
+  pub(crate) unlocked: &'static str,
+  pub(crate) locked_key: &'static str,
+  pub(crate) wrong_pin: &'static str,
+  // Help rows: key, description
+  pub(crate) help_rows: &'static [(&'static str, &'static str)],
+}
+
+const ENGLISH: Messages = Messages {
+  app_title: "Music player",
+  terminal_too_small: "Terminal too small",
+  music: "Music",
+  podcasts: "Podcasts",
+  queue: "Queue",
+  search: "Search",
+  browse: "Browse",
+  all_artists: "All artists",
+  lyrics: "Lyrics",
+  no_lyrics: "No lyrics found",
+  details: "Details",
+  logs: "Logs",
+  no_logs: "No warning nor error",
+  actions: "Actions",
+  rate: "Rate",
+  unlock: "Pin",
+  comment: "Comment",
+  comment_hint: "Enter to save, Esc to cancel",
+  playlist_editor: "Automatic playlist",
+  playlist_hint: "⇥ next, ←/→ change, ^-n add, ^-d remove, ⏎ save, ⎋ cancel",
+  playlist_name: "Name",
+  playlist_match_all: "Match all the conditions",
+  playlist_match_any: "Match any condition",
+  playlist_limit: "Limit",
+  playlist_no_limit: "None",
+  playlist_limit_tracks: "Tracks",
+  playlist_limit_minutes: "Minutes",
+  playlist_sort: "Sort by",
+  playlist_ascending: "Ascending",
+  playlist_descending: "Descending",
+  op_contains: "contains",
+  op_not_contains: "doesn't contain",
+  op_is: "is",
+  op_starts_with: "starts with",
+  op_ends_with: "ends with",
+  op_greater: "is greater than",
+  op_less: "is less than",
+  op_within: "in the last days",
+  op_not_within: "not in the last days",
+  scope_library: "Playing the library",
+  scope_podcasts: "Playing the podcasts",
+  scope_search: "Playing the search results",
+  scope_artist: "Playing the artist",
+  scope_album: "Playing the album",
+  scope_artist_radio: "Artist radio",
+  scope_playlist: "Playing the given tracks",
+  nothing_playing: "Nothing playing: choose a track with Enter",
+  help: "Help",
+  help_hint: "Type to filter",
+  help_filter: "Filter: {}",
+  filters: "Filters",
+  filters_active: "Filters on",
+  min_rating: "Minimum rating",
+  hide_podcasts: "Hide podcasts",
+  duration_short: "Under 3 min",
+  duration_medium: "3 to 6 min",
+  duration_long: "Over 6 min",
+  bpm_slow: "Under 90 BPM",
+  bpm_moderate: "90 to 120 BPM",
+  bpm_fast: "120 to 150 BPM",
+  bpm_very_fast: "Over 150 BPM",
+  title: "Title",
+  artist: "Artist",
+  album: "Album",
+  duration: "Duration",
+  rating: "Rating",
+  last_played: "Last Played",
+  bpm: "BPM",
+  year: "Year",
+  composer: "Composer",
+  work: "Work",
+  genre: "Genre",
+  play_count: "Play Count",
+  first_seen: "Date Added",
+  date: "Date",
+  feed: "Feed",
+  track: "track",
+  tracks: "tracks",
+  queue_sorted: "Sorted view, ⎇-s for the play order",
+  marked: "{} marked, ⎇-x to clear",
+  disc: "Disc {}",
+  is_plural: |count| count != 1,
+  action_play: "Play",
+  action_play_next: "Play next",
+  action_enqueue: "Enqueue",
+  action_enqueue_similar: "Enqueue similar tracks",
+  action_artist_radio: "Artist radio",
+  action_rate: "Rate…",
+  action_love: "Love",
+  action_unlove: "Unlove",
+  action_ban: "Ban from the shuffle",
+  action_unban: "Unban",
+  action_show_album: "Show album",
+  action_hide: "Hide entry",
+  action_open_folder: "Open containing folder",
+  action_delete: "Delete the file",
+  delete_title: "Delete the file?",
+  cancel_delete: "No, keep it",
+  confirm_delete: "Yes, delete it",
+  action_comment: "Comment…",
+  notify_next: "Next",
+  notify_pause: "Pause",
+  saved_rating: "Rated",
+  saved_ratings: "Rated {} tracks",
+  suggestions: "Suggested ratings",
+  suggestions_hint: "←/→ adjust, ↵ rate, ⌦ dismiss",
+  suggestion_counts: "{} plays, {} skips",
+  suggestions_off: "The rating suggestions are off, see `suggestions.enabled`",
+  no_suggestions: "No rating to suggest",
+  saved_db: "Library saved",
+  save_failed: "Can't save the library: {}",
+  save_deferred: "Rhythmbox is running: the library is saved once it is closed",
+  enqueued: "Enqueued '{}'",
+  played_next: "'{}' will be played next",
+  already_queued: "'{}' is already queued, skipped",
+  enqueued_similar: "Enqueued {} tracks similar to '{}'",
+  hidden: "Hidden '{}'",
+  loved: "Loved '{}'",
+  unloved: "Unloved '{}'",
+  banned: "Banned '{}' from the shuffle",
+  unbanned: "Unbanned '{}'",
+  saved_comment: "Saved the comment of '{}'",
+  saved_playlist: "Added the playlist '{}' to Rhythmbox",
+  playlist_no_name: "The playlist has no name",
+  playlist_invalid_value: "Invalid value: '{}'",
+  playlist_error: "Can't add the playlist: {}",
+  open_folder_error: "Can't open the folder: {}",
+  deleted: "Deleted '{}'",
+  delete_error: "Can't delete the file: {}",
+  settings_reloaded: "Settings reloaded",
+  invalid_settings: "Invalid settings: {}",
+  playback_error: "Playback error: {}",
+  playback_warning: "Playback warning: {}",
+  playback_error_field: "playback error",
+  output_lost: "Audio output lost, paused: {}",
+  stalled: "the playback stalled",
+  stalled_seek: "Playback stalled at {}, resumed there",
+  stalled_restart: "Playback still stalled, the track is started again",
+  locked: "Party mode: rating, hiding and quitting are locked",
+  unlocked: "Party mode off",
+  locked_key: "Locked by the party mode, ^-l to unlock",
+  wrong_pin: "Wrong pin",
+  help_rows: &[
+    ("⎇-h", "Display this help"),
+    ("⎋, ^-c", "Quit the player"),
+    ("⎇-m", "Show local tracks"),
+    ("⎇-p", "Show podcasts"),
+    ("⎇-q", "Show queue"),
+    ("⎇-e", "Enqueue the selected track"),
+    ("⎇-a", "Actions on the selected track"),
+    ("⎇-s", "Order by search score, the queue by play order"),
+    ("⎇-t", "Order by title"),
+    ("⎇-d", "Order by date"),
+    ("⎇-r", "Order by rating"),
+    ("⎇-l", "Order by last played"),
+    ("⎇-,, ⎇-.", "Order by the previous or the next column"),
+    ("Click", "Order by the clicked column header"),
+    ("⎇-0..5", "Rate the selected track, or the marked ones"),
+    ("⎇-+, ⎇--", "Raise or lower the rating by a step"),
+    ("⇧-↓, ⇧-↑", "Mark the tracks"),
+    ("⎇-x", "Clear the marks"),
+    ("⎇-o", "Toggle shuffle mode"),
+    ("⎇-c", "Repeat current track"),
+    ("⎇-g", "Select the current playing track"),
+    ("⎇-i", "Show the details of the selected track"),
+    ("⎇-j", "Show the recent warnings and errors"),
+    ("⎇-y", "Toggle the lyrics panel"),
+    ("⎇-b", "Toggle the artist/album browser"),
+    ("⎇-v", "Filter by rating, genre, decade, duration, tempo"),
+    ("⎇-z", "Review the suggested ratings"),
+    ("⎇-u", "Create an automatic playlist for Rhythmbox"),
+    ("⎇-f", "Toggle the search bar"),
+    ("⎇-n", "Toggle the control bar"),
+    ("⎇-k", "Toggle the comment column"),
+    ("⎇-w", "Toggle the composer and work columns"),
+    ("⎇-[, ⎇-]", "Narrow or widen the browser"),
+    ("⎇-<, ⎇->", "Widen or narrow the lyrics panel"),
+    ("^-l", "Lock or unlock the party mode"),
+    ("⇥", "Switch focus between the browser and the tracks"),
+    ("↓,↑,⇟,⇞", "Select the tracks"),
+    ("⏎", "Play the selected track, pause/resume the played one"),
+    ("^-r", "Restart the played track"),
+    ("⏯", "Play / Pause"),
+    ("⏹", "Stop"),
+    ("⏭", "Next track"),
+    ("←, →", "Seek backward or forward"),
+    ("^-a, ^-e", "Move to the start or the end of the search"),
+    (
+      "^-b, ^-f, ^-←, ^-→",
+      "Move in the search by a char or a word",
+    ),
+    (
+      "^-w, ^-k, ^-u",
+      "Delete the previous word, to the end, all the search",
+    ),
+  ],
+};
+
+const FRENCH: Messages = Messages {
+  app_title: "Lecteur de musique",
+  terminal_too_small: "Terminal trop petit",
+  music: "Musique",
+  podcasts: "Podcasts",
+  queue: "File d'attente",
+  search: "Recherche",
+  browse: "Parcourir",
+  all_artists: "Tous les artistes",
+  lyrics: "Paroles",
+  no_lyrics: "Aucune parole trouvée",
+  details: "Détails",
+  logs: "Journal",
+  no_logs: "Aucun avertissement ni erreur",
+  actions: "Actions",
+  rate: "Noter",
+  unlock: "Code",
+  comment: "Commentaire",
+  comment_hint: "Entrée pour enregistrer, Échap pour annuler",
+  playlist_editor: "Liste de lecture automatique",
+  playlist_hint: "⇥ suivant, ←/→ changer, ^-n ajouter, ^-d retirer, ⏎ enregistrer, ⎋ annuler",
+  playlist_name: "Nom",
+  playlist_match_all: "Toutes les conditions",
+  playlist_match_any: "Au moins une condition",
+  playlist_limit: "Limite",
+  playlist_no_limit: "Aucune",
+  playlist_limit_tracks: "Pistes",
+  playlist_limit_minutes: "Minutes",
+  playlist_sort: "Trier par",
+  playlist_ascending: "Croissant",
+  playlist_descending: "Décroissant",
+  op_contains: "contient",
+  op_not_contains: "ne contient pas",
+  op_is: "est",
+  op_starts_with: "commence par",
+  op_ends_with: "finit par",
+  op_greater: "est supérieur à",
+  op_less: "est inférieur à",
+  op_within: "dans les derniers jours",
+  op_not_within: "pas dans les derniers jours",
+  scope_library: "Lecture de la bibliothèque",
+  scope_podcasts: "Lecture des podcasts",
+  scope_search: "Lecture des résultats de recherche",
+  scope_artist: "Lecture de l'artiste",
+  scope_album: "Lecture de l'album",
+  scope_artist_radio: "Radio de l'artiste",
+  scope_playlist: "Lecture des pistes données",
+  nothing_playing: "Aucune lecture : choisissez une piste avec Entrée",
+  help: "Aide",
+  help_hint: "Tapez pour filtrer",
+  help_filter: "Filtre : {}",
+  filters: "Filtres",
+  filters_active: "Filtres actifs",
+  min_rating: "Note minimale",
+  hide_podcasts: "Masquer les podcasts",
+  duration_short: "Moins de 3 min",
+  duration_medium: "De 3 à 6 min",
+  duration_long: "Plus de 6 min",
+  bpm_slow: "Moins de 90 BPM",
+  bpm_moderate: "De 90 à 120 BPM",
+  bpm_fast: "De 120 à 150 BPM",
+  bpm_very_fast: "Plus de 150 BPM",
+  title: "Titre",
+  artist: "Artiste",
+  album: "Album",
+  duration: "Durée",
+  rating: "Note",
+  last_played: "Lu le",
+  bpm: "BPM",
+  year: "Année",
+  composer: "Compositeur",
+  work: "Œuvre",
+  genre: "Genre",
+  play_count: "Écoutes",
+  first_seen: "Ajouté le",
+  date: "Date",
+  feed: "Flux",
+  track: "piste",
+  tracks: "pistes",
+  queue_sorted: "Vue triée, ⎇-s pour l'ordre de lecture",
+  marked: "{} marquées, ⎇-x pour effacer",
+  disc: "Disque {}",
+  is_plural: |count| count > 1,
+  action_play: "Lire",
+  action_play_next: "Lire ensuite",
+  action_enqueue: "Ajouter à la file",
+  action_enqueue_similar: "Ajouter des pistes similaires",
+  action_artist_radio: "Radio de l'artiste",
+  action_rate: "Noter…",
+  action_love: "Adorer",
+  action_unlove: "Ne plus adorer",
+  action_ban: "Bannir de l'aléatoire",
+  action_unban: "Ne plus bannir",
+  action_show_album: "Afficher l'album",
+  action_hide: "Masquer",
+  action_open_folder: "Ouvrir le dossier",
+  action_delete: "Supprimer le fichier",
+  delete_title: "Supprimer le fichier ?",
+  cancel_delete: "Non, le garder",
+  confirm_delete: "Oui, le supprimer",
+  action_comment: "Commenter…",
+  notify_next: "Suivant",
+  notify_pause: "Pause",
+  saved_rating: "Note attribuée",
+  saved_ratings: "{} pistes notées",
+  suggestions: "Notes suggérées",
+  suggestions_hint: "←/→ ajuster, ↵ noter, ⌦ ignorer",
+  suggestion_counts: "{} écoutes, {} sauts",
+  suggestions_off: "Les suggestions de notes sont désactivées, voir `suggestions.enabled`",
+  no_suggestions: "Aucune note à suggérer",
+  saved_db: "Bibliothèque enregistrée",
+  save_failed: "Impossible d'enregistrer la bibliothèque : {}",
+  save_deferred: "Rhythmbox est ouvert : la bibliothèque sera enregistrée à sa fermeture",
+  enqueued: "'{}' ajouté à la file",
+  played_next: "'{}' sera lu ensuite",
+  already_queued: "'{}' est déjà dans la file, ignoré",
+  enqueued_similar: "{} pistes similaires à '{}' ajoutées à la file",
+  hidden: "'{}' masqué",
+  loved: "'{}' adoré",
+  unloved: "'{}' n'est plus adoré",
+  banned: "'{}' banni de l'aléatoire",
+  unbanned: "'{}' n'est plus banni",
+  saved_comment: "Commentaire de '{}' enregistré",
+  saved_playlist: "Liste de lecture '{}' ajoutée à Rhythmbox",
+  playlist_no_name: "La liste de lecture n'a pas de nom",
+  playlist_invalid_value: "Valeur invalide : '{}'",
+  playlist_error: "Impossible d'ajouter la liste de lecture : {}",
+  open_folder_error: "Impossible d'ouvrir le dossier : {}",
+  deleted: "'{}' supprimé",
+  delete_error: "Impossible de supprimer le fichier : {}",
+  settings_reloaded: "Paramètres rechargés",
+  invalid_settings: "Paramètres invalides : {}",
+  playback_error: "Erreur de lecture : {}",
+  playback_warning: "Avertissement de lecture : {}",
+  playback_error_field: "erreur de lecture",
+  output_lost: "Sortie audio perdue, en pause : {}",
+  stalled: "la lecture est bloquée",
+  stalled_seek: "Lecture bloquée à {}, reprise à cet endroit",
+  stalled_restart: "Lecture toujours bloquée, la piste est relancée",
+  locked: "Mode fête : noter, masquer et quitter sont verrouillés",
+  unlocked: "Mode fête désactivé",
+  locked_key: "Verrouillé par le mode fête, ^-l pour déverrouiller",
+  wrong_pin: "Code erroné",
+  help_rows: &[
+    ("⎇-h", "Afficher cette aide"),
+    ("⎋, ^-c", "Quitter le lecteur"),
+    ("⎇-m", "Afficher les pistes locales"),
+    ("⎇-p", "Afficher les podcasts"),
+    ("⎇-q", "Afficher la file d'attente"),
+    ("⎇-e", "Ajouter la piste sélectionnée à la file"),
+    ("⎇-a", "Actions sur la piste sélectionnée"),
+    ("⎇-s", "Trier par pertinence, la file par ordre de lecture"),
+    ("⎇-t", "Trier par titre"),
+    ("⎇-d", "Trier par date"),
+    ("⎇-r", "Trier par note"),
+    ("⎇-l", "Trier par dernière écoute"),
+    ("⎇-,, ⎇-.", "Trier par la colonne précédente ou suivante"),
+    ("Clic", "Trier par l'en-tête de colonne cliqué"),
+    (
+      "⎇-0..5",
+      "Noter la piste sélectionnée, ou les pistes marquées",
+    ),
+    ("⎇-+, ⎇--", "Monter ou baisser la note d'un cran"),
+    ("⇧-↓, ⇧-↑", "Marquer les pistes"),
+    ("⎇-x", "Effacer les marques"),
+    ("⎇-o", "Changer le mode aléatoire"),
+    ("⎇-c", "Répéter la piste en cours"),
+    ("⎇-g", "Sélectionner la piste en cours"),
+    ("⎇-i", "Afficher les détails de la piste sélectionnée"),
+    ("⎇-j", "Afficher les derniers avertissements et erreurs"),
+    ("⎇-y", "Afficher/masquer les paroles"),
+    ("⎇-b", "Afficher/masquer le navigateur artistes/albums"),
+    ("⎇-v", "Filtrer par note, genre, décennie, durée, tempo"),
+    ("⎇-z", "Revoir les notes suggérées"),
+    (
+      "⎇-u",
+      "Créer une liste de lecture automatique pour Rhythmbox",
+    ),
+    ("⎇-f", "Afficher/masquer la recherche"),
+    ("⎇-n", "Afficher/masquer la barre de contrôle"),
+    ("⎇-k", "Afficher/masquer la colonne des commentaires"),
+    ("⎇-w", "Afficher/masquer les colonnes compositeur et œuvre"),
+    ("⎇-[, ⎇-]", "Rétrécir ou élargir le navigateur"),
+    ("⎇-<, ⎇->", "Élargir ou rétrécir les paroles"),
+    ("^-l", "Verrouiller ou déverrouiller le mode fête"),
+    ("⇥", "Passer du navigateur aux pistes"),
+    ("↓,↑,⇟,⇞", "Sélectionner les pistes"),
+    (
+      "⏎",
+      "Lire la piste sélectionnée, pause/reprise de celle jouée",
+    ),
+    ("^-r", "Reprendre la piste jouée au début"),
+    ("⏯", "Lecture / Pause"),
+    ("⏹", "Arrêt"),
+    ("⏭", "Piste suivante"),
+    ("←, →", "Reculer ou avancer"),
+    ("^-a, ^-e", "Aller au début ou à la fin de la recherche"),
+    (
+      "^-b, ^-f, ^-←, ^-→",
+      "Se déplacer dans la recherche par caractère ou par mot",
+    ),
+    (
+      "^-w, ^-k, ^-u",
+      "Effacer le mot précédent, jusqu'à la fin, toute la recherche",
+    ),
+  ],
+};
+
+impl Messages {
+  pub(crate) fn scope(&self, kind: ScopeKind) -> &'static str {
+    match kind {
+      ScopeKind::Library => self.scope_library,
+      ScopeKind::Podcasts => self.scope_podcasts,
+      ScopeKind::Search => self.scope_search,
+      ScopeKind::Artist => self.scope_artist,
+      ScopeKind::Album => self.scope_album,
+      ScopeKind::ArtistRadio => self.scope_artist_radio,
+      ScopeKind::Playlist => self.scope_playlist,
+    }
+  }
+
+  /// `count tracks`, with the plural of the language.
+  pub(crate) fn tracks(&self, count: usize) -> String {
+    if (self.is_plural)(count) {
+      format!("{count} {}", self.tracks)
+    } else {
+      format!("{count} {}", self.track)
+    }
+  }
+}
+
+/// Replace the `{}` of the message.
+pub(crate) fn fill(message: &str, argument: impl std::fmt::Display) -> String {
+  message.replacen("{}", &argument.to_string(), 1)
+}
+
+/// The strings of the ui. Fallback to english when `init_messages` has not
+/// been called.
+pub(crate) fn tr() -> &'static Messages {
+  *MESSAGES.read().unwrap_or_else(PoisonError::into_inner)
+}
+
+/// Select the language from the settings, otherwise from the locale.
+#[instrument]
+pub(crate) fn init_messages(language: Option<Language>) {
+  let language = language.unwrap_or_else(|| {
+    let locale = ["LC_ALL", "LC_MESSAGES", "LANG"]
+      .iter()
+      .filter_map(|var| env::var(var).ok())
+      .find(|locale| !locale.is_empty())
+      .unwrap_or_default();
+    if locale.starts_with("fr") {
+      Language::Fr
+    } else {
+      Language::En
+    }
+  });
+  // Called again when the settings are reloaded
+  *MESSAGES.write().unwrap_or_else(PoisonError::into_inner) = match language {
+    Language::En => &ENGLISH,
+    Language::Fr => &FRENCH,
+  };
+}
@@ -0,0 +1,58 @@
+use super::{i18n::tr, theme::theme};
+use crate::trace::recent_logs;
+use ratatui::{
+  prelude::{Constraint, Layout, Rect},
+  text::{Line, Span},
+  widgets::{Block, Borders, Clear, Padding, Paragraph, Wrap},
+  Frame,
+};
+use tracing::{instrument, Level};
+
+/// Render the warnings and the errors logged since the start, the most
+/// recent first: the failures of GStreamer or of the saves without opening
+/// the log file.
+#[instrument(skip(frame))]
+pub(crate) fn render_logs_panel(area: Rect, frame: &mut Frame<'_>, scroll: u16) {
+  let [logs_area] = Layout::vertical([Constraint::Fill(1)])
+    .margin(3)
+    .horizontal_margin(10)
+    .areas(area);
+
+  let logs = recent_logs();
+  let lines: Vec<Line> = if logs.is_empty() {
+    vec![Line::styled(tr().no_logs, theme().default_dark)]
+  } else {
+    logs
+      .into_iter()
+      .map(|log| {
+        let style = if log.level == Level::ERROR {
+          theme().error
+        } else {
+          theme().primary
+        };
+        Line::from(vec![
+          Span::styled(
+            format!("{} {:5} ", log.time.format("%H:%M:%S"), log.level),
+            style,
+          ),
+          Span::styled(format!("{}: ", log.target), theme().help_key),
+          Span::styled(log.message, theme().default),
+        ])
+      })
+      .collect()
+  };
+
+  let panel = Paragraph::new(lines)
+    .wrap(Wrap { trim: false })
+    .scroll((scroll, 0))
+    .block(
+      Block::default()
+        .style(theme().border)
+        .padding(Padding::horizontal(1))
+        .borders(Borders::ALL)
+        .title(tr().logs),
+    );
+
+  frame.render_widget(Clear, logs_area);
+  frame.render_widget(panel, logs_area);
+}
@@ -0,0 +1,58 @@
+use super::{i18n::tr, theme::theme};
+use crate::lyrics::Lyrics;
+use ratatui::{
+  prelude::Rect,
+  style::Modifier,
+  text::Line,
+  widgets::{Block, BorderType, Borders, Padding, Paragraph, Wrap},
+  Frame,
+};
+use std::time::Duration;
+use tracing::instrument;
+
+#[instrument(skip(frame, lyrics))]
+pub(crate) fn render_lyrics_panel(
+  area: Rect,
+  frame: &mut Frame<'_>,
+  lyrics: Option<&Lyrics>,
+  position: Duration,
+) {
+  let block = Block::default()
+    .borders(Borders::ALL)
+    .border_type(BorderType::Rounded)
+    .padding(Padding::horizontal(1))
+    .style(theme().border)
+    .title(tr().lyrics);
+
+  let Some(lyrics) = lyrics else {
+    let paragraph = Paragraph::new(tr().no_lyrics)
+      .style(theme().default_dark)
+      .block(block);
+    frame.render_widget(paragraph, area);
+    return;
+  };
+
+  let current_line = lyrics.current_line(position);
+  let lines: Vec<Line> = lyrics
+    .lines()
+    .into_iter()
+    .enumerate()
+    .map(|(index, line)| {
+      if Some(index) == current_line {
+        Line::from(line).style(theme().primary.add_modifier(Modifier::BOLD))
+      } else {
+        Line::from(line).style(theme().default)
+      }
+    })
+    .collect();
+
+  // Keep the sung line in the middle of the panel.
+  let scroll = current_line
+    .unwrap_or_default()
+    .saturating_sub(area.height as usize / 2) as u16;
+  let paragraph = Paragraph::new(lines)
+    .block(block)
+    .wrap(Wrap { trim: true })
+    .scroll((scroll, 0));
+  frame.render_widget(paragraph, area);
+}
@@ -1,47 +1,139 @@
+mod actions;
+mod browser;
+mod comment;
+mod dates;
+mod details;
 mod events;
+mod facets;
+mod glyphs;
 mod help;
+pub(crate) mod i18n;
+mod logs;
+mod lyrics;
+mod party;
+mod playlist_editor;
+mod position;
 mod rendering;
+mod search;
+mod status;
+mod suggestions;
+mod theme;
+mod watchdog;
 
 use self::{
-  events::{build_table, handle_keys, EventProcessStatus},
-  rendering::render_table,
+  actions::ActionMenu,
+  browser::{BrowseFilter, Browser},
+  comment::CommentEditor,
+  dates::init_dates,
+  events::{build_table, commit_scrub, handle_keys, handle_mouse, EventProcessStatus},
+  facets::{FacetPanel, Facets},
+  glyphs::init_glyphs,
+  i18n::{fill, init_messages, tr},
+  playlist_editor::PlaylistEditor,
+  position::PositionClock,
+  rendering::{render_table, Playback, RowMarks, COLUMNS},
+  search::Search,
+  status::{StatusLevel, StatusMessage},
+  suggestions::SuggestionPanel,
+  theme::init_theme,
+  watchdog::{Recovery, Watchdog},
 };
 use crate::{
   get_mpris_server,
-  player_state::{PlayerState, UiNotification},
+  gstreamer::{is_output_error, monitor_audio_outputs, StreamInfo},
+  history,
+  lyrics::Lyrics,
+  media_keys, metrics, mplayer,
+  player_state::{PlayerState, ScopeKind, UiNotification},
   playlists::Playlist,
-  rhythmdb::{Entry, EntryList},
-  settings::Settings,
+  rhythmdb::{Entry, EntryList, Saved, SharedEntry},
+  settings::{
+    settings, settings_path, DateFormat, LayoutSetting, PlaybackSettings, PlayerStateSetting,
+    Settings, TruncateSettings, UiSession,
+  },
+  suggestions::record_skip,
   ui::rendering::render_ui,
   Rhythmdb,
 };
-use crossterm::event::{self};
+use clap::ArgMatches;
+use crossterm::{
+  event::{
+    self, DisableBracketedPaste, DisableMouseCapture, EnableBracketedPaste, EnableMouseCapture,
+    KeyboardEnhancementFlags, PopKeyboardEnhancementFlags, PushKeyboardEnhancementFlags,
+  },
+  execute,
+  terminal::supports_keyboard_enhancement,
+};
 use futures::{FutureExt, StreamExt};
-use gstreamer::{Element, MessageView};
+use gstreamer::{ClockTime, Element, MessageView};
+use humandate::HumanDuration;
 use if_chain::if_chain;
 use miette::{IntoDiagnostic, Result};
-use ratatui::widgets::{Table, TableState};
-use std::{sync::Arc, time::Duration};
-use tokio::{select, sync::mpsc::channel};
-use tracing::{instrument, trace};
+use ratatui::{
+  layout::Rect,
+  widgets::{Table, TableState},
+  DefaultTerminal,
+};
+use serde::{Deserialize, Serialize};
+use std::{
+  collections::{HashMap, HashSet},
+  fs,
+  io::stdout,
+  ops::Deref,
+  path::Path,
+  sync::Arc,
+  time::Duration,
+};
+use tokio::{
+  select,
+  sync::mpsc::{channel, Receiver, Sender},
+  time::{sleep_until, Instant},
+};
+use tracing::{error, instrument, trace, warn};
+use url::Url;
 
-#[derive(Copy, Clone, Debug, PartialEq)]
-enum TabSelection {
+// Seek of the arrow keys, in seconds
+const SEEK_STEP: u64 = 5;
+// Redraws of the progress gauge per second, at most
+const MAX_PROGRESS_FPS: u32 = 10;
+// Seek of the arrows committed once they are left for this long, when the
+// terminal doesn't report the key releases
+const SCRUB_DELAY: Duration = Duration::from_millis(500);
+// The db is saved once no rating came for this long
+const SAVE_DELAY: Duration = Duration::from_secs(1);
+// Part of the played track after which the next one is preloaded
+const PRELOAD_RATIO: f64 = 0.9;
+// A track is played once half of it, or 4 minutes, was listened to
+const PLAYED_RATIO: f64 = 0.5;
+const PLAYED_ENOUGH: Duration = Duration::from_secs(240);
+
+#[derive(Copy, Clone, Debug, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub(crate) enum TabSelection {
   Music = 0,
   Podcast = 1,
   Queue = 2,
 }
 
-#[derive(Clone, Copy, PartialEq, Debug)]
+#[derive(Clone, Copy, PartialEq, Debug, Deserialize, Serialize)]
+#[serde(rename_all = "kebab-case")]
 pub(crate) enum Order {
   Default,
   Title,
+  Artist,
+  Album,
   Date,
+  Duration,
   Rating,
   LastPlayed,
+  Bpm,
+  Year,
+  Composer,
+  Work,
 }
 
-#[derive(Clone, Copy, PartialEq, Debug)]
+#[derive(Clone, Copy, PartialEq, Debug, Deserialize, Serialize)]
+#[serde(rename_all = "kebab-case")]
 pub(crate) enum OrderDir {
   Asc,
   Desc,
@@ -50,160 +142,823 @@ pub(crate) enum OrderDir {
 #[derive(PartialEq, Debug)]
 pub(crate) enum Panel {
   Help,
+  // The browser sidebar has the focus
+  Browser,
+  Details,
+  // Recent warnings and errors
+  Logs,
+  Actions,
+  Filters,
+  // Pin asked to unlock the party mode
+  Unlock,
+  Comment,
+  // Automatic playlist editor
+  Playlist,
+  // Review of the suggested ratings
+  Suggestions,
   None,
 }
 
 struct Ui<'a> {
   selected_tab: TabSelection,
   panel: Panel,
-  // Sometime the track position is none so we will use this
-  current_elapsed_duration: Duration,
+  // Position of the played track, counted between the queries of the
+  // pipeline
+  position: PositionClock,
   table_state: TableState,
   table: Table<'a>,
   row_len: usize,
-  search: String,
+  // Number of rows visible in the table, updated on each rendering
+  table_page: usize,
+  search: Search,
   order_by: Order,
   order_dir: OrderDir,
+  // Last order of each tab, indexed by the tab
+  tab_orders: [(Order, OrderDir); 3],
+  // Areas of the column headers, updated on each rendering
+  header_areas: [Rect; COLUMNS],
+  // Widths the cells of the table were cut to
+  cell_widths: [u16; COLUMNS],
+  truncate: TruncateSettings,
+  // The browser sidebar is hidden when none
+  browser: Option<Browser>,
+  browse_filter: Option<BrowseFilter>,
+  facets: Facets,
+  // The filter popup is opened when some
+  facet_panel: Option<FacetPanel>,
+  suggestion_panel: Option<SuggestionPanel>,
+  action_menu: Option<ActionMenu>,
+  // Rows marked for a batch rating, by location
+  marked: HashSet<Url>,
+  comment_editor: Option<CommentEditor>,
+  playlist_editor: Option<PlaylistEditor>,
+  // Filter typed in the help popup
+  help_filter: String,
+  help_scroll: u16,
+  // Entry shown in the details popup
+  details: Option<SharedEntry>,
+  details_scroll: u16,
+  logs_scroll: u16,
+  status: Option<StatusMessage>,
+  // Format of the played stream
+  stream_info: StreamInfo,
+  layout: LayoutSetting,
+  show_lyrics: bool,
+  lyrics: Option<Lyrics>,
+  // Location of the track the lyrics were loaded for
+  lyrics_location: Option<Url>,
+  // In seconds
+  seek_step: u64,
+  // Redraws per second while playing
+  progress_fps: u32,
+  last_played_format: DateFormat,
+  // Ratings set in half stars
+  half_stars: bool,
+  playback: PlaybackSettings,
+  // Last error of GStreamer on each track of the session
+  playback_errors: HashMap<Url, String>,
+  // Track played again by the `retry` policy and its attempts
+  retried: Option<(Url, u32)>,
+  // Progress of the played track, to unstick a stalled pipeline
+  watchdog: Watchdog,
+  // Party mode: the destructive keys are disabled
+  locked: bool,
+  party_pin: Option<String>,
+  pin_input: String,
+  // Position chosen with the arrows, not sought yet
+  scrub: Option<Scrub>,
+  // Save requests of the background saver
+  saves: Option<Sender<()>>,
+}
+
+/// Provisional position of the scrub mode.
+#[derive(Debug)]
+struct Scrub {
+  position: Duration,
+  // The seek is committed then, unless an arrow is pressed again
+  deadline: Instant,
 }
 
 impl<'a> Ui<'a> {
-  fn new(start_index: usize) -> Ui<'a> {
+  fn new(start_index: usize, layout: LayoutSetting) -> Ui<'a> {
     let mut result = Ui {
       selected_tab: TabSelection::Music,
       panel: Panel::None,
-      current_elapsed_duration: Duration::from_secs(0),
+      position: PositionClock::default(),
       table_state: TableState::default(),
       table: Table::default(),
       row_len: 0,
-      search: "".into(),
+      table_page: 15,
+      search: Search::default(),
       order_by: Order::Default,
       order_dir: OrderDir::Desc,
+      tab_orders: [(Order::Default, OrderDir::Desc); 3],
+      header_areas: [Rect::default(); COLUMNS],
+      cell_widths: [0; COLUMNS],
+      truncate: TruncateSettings::default(),
+      browser: None,
+      browse_filter: None,
+      facets: Facets::default(),
+      facet_panel: None,
+      suggestion_panel: None,
+      action_menu: None,
+      marked: HashSet::new(),
+      comment_editor: None,
+      playlist_editor: None,
+      help_filter: String::new(),
+      help_scroll: 0,
+      details: None,
+      details_scroll: 0,
+      logs_scroll: 0,
+      status: None,
+      stream_info: StreamInfo::default(),
+      layout,
+      show_lyrics: false,
+      lyrics: None,
+      lyrics_location: None,
+      seek_step: SEEK_STEP,
+      progress_fps: 1,
+      last_played_format: DateFormat::default(),
+      half_stars: false,
+      playback: PlaybackSettings::default(),
+      playback_errors: HashMap::new(),
+      retried: None,
+      watchdog: Watchdog::default(),
+      locked: false,
+      party_pin: None,
+      pin_input: String::new(),
+      scrub: None,
+      saves: None,
     };
     result.table_state.select(Some(start_index));
     result
   }
 
-  #[instrument(skip(self))]
-  fn get_track_elapsed_duration(&mut self, pipeline: &Element) -> Duration {
-    use gstreamer::{prelude::ElementExtManual, ClockTime};
-    if let Some(position) = pipeline.query_position::<ClockTime>() {
-      self.current_elapsed_duration = Duration::from_secs(position.seconds());
+  /// Scope of the tracks of the table, none for the queue: the queue is
+  /// always played first.
+  fn scope_kind(&self) -> Option<ScopeKind> {
+    let searched = !self.search.is_empty() || self.facets.is_active();
+    match (self.selected_tab, &self.browse_filter) {
+      (TabSelection::Queue, _) => None,
+      (_, _) if searched => Some(ScopeKind::Search),
+      (TabSelection::Podcast, _) => Some(ScopeKind::Podcasts),
+      (TabSelection::Music, Some(BrowseFilter::Artist(_))) => Some(ScopeKind::Artist),
+      (TabSelection::Music, Some(BrowseFilter::Album(..))) => Some(ScopeKind::Album),
+      (TabSelection::Music, None) => Some(ScopeKind::Library),
+    }
+  }
+
+  /// Move the provisional position of the scrub mode.
+  fn scrub_to(&mut self, position: Duration) {
+    self.scrub = Some(Scrub {
+      position,
+      deadline: Instant::now() + SCRUB_DELAY,
+    });
+  }
+
+  /// An album is viewed in its order: its discs are set apart.
+  fn groups_discs(&self) -> bool {
+    self.selected_tab == TabSelection::Music
+      && matches!(self.browse_filter, Some(BrowseFilter::Album(..)))
+      && self.order_by == Order::Default
+      && self.search.text().is_empty()
+  }
+
+  /// Switch to the tab, with the order it was last sorted by.
+  fn select_tab(&mut self, tab: TabSelection) {
+    self.tab_orders[self.selected_tab as usize] = (self.order_by, self.order_dir);
+    self.selected_tab = tab;
+    (self.order_by, self.order_dir) = self.tab_orders[tab as usize];
+  }
+
+  /// Apply the settings reloaded from `settings.toml`.
+  fn reload_settings(&mut self, settings: &Settings) -> bool {
+    if let Err(e) = init_theme(&settings.theme) {
+      self.error(fill(tr().invalid_settings, e));
+      return false;
+    }
+    if let Err(e) = init_dates(&settings.dates) {
+      self.error(fill(tr().invalid_settings, e));
+      return false;
+    }
+    init_glyphs(settings.symbols);
+    init_messages(settings.language);
+    self.seek_step = settings.seek_step.unwrap_or(SEEK_STEP);
+    self.progress_fps = progress_fps(settings);
+    self.last_played_format = settings.last_played_format;
+    self.half_stars = settings.half_stars;
+    self.truncate = settings.truncate;
+    self.playback = settings.playback;
+    self.party_pin = settings.party.pin.clone();
+    self.info(tr().settings_reloaded);
+    true
+  }
+
+  /// Tab, sort, search and scroll, to reopen the ui where it was left.
+  fn session(&self) -> UiSession {
+    UiSession {
+      tab: self.selected_tab,
+      order_by: self.order_by,
+      order_dir: self.order_dir,
+      search: self.search.text().to_string(),
+      selected: self.table_state.selected(),
+      offset: self.table_state.offset(),
+    }
+  }
+
+  /// Show a message in the status bar.
+  fn info(&mut self, text: impl Into<String>) {
+    self.status = Some(StatusMessage::new(StatusLevel::Info, text.into()));
+  }
+
+  /// Save the db in the background: the requests close in time are
+  /// coalesced in a single save.
+  fn request_save(&mut self) {
+    if let Some(saves) = &self.saves {
+      // A full channel already holds a request
+      let _ = saves.try_send(());
+    }
+  }
+
+  /// Show a warning in the status bar.
+  fn warning(&mut self, text: impl Into<String>) {
+    self.status = Some(StatusMessage::new(StatusLevel::Warning, text.into()));
+  }
+
+  /// Show an error in the status bar. It is logged too: the log panel keeps
+  /// it once the status bar is cleared.
+  fn error(&mut self, text: impl Into<String>) {
+    let text = text.into();
+    error!("{text}");
+    self.status = Some(StatusMessage::new(StatusLevel::Error, text));
+  }
+
+  /// Load the lyrics when the played track changes.
+  #[cfg_attr(not(feature = "lrclib"), allow(unused_variables))]
+  #[instrument(skip(self, player))]
+  fn update_lyrics(&mut self, player: &'static PlayerState, track: &SharedEntry) {
+    let location = track.get_location();
+    if self.lyrics_location.as_ref() == Some(&location) {
+      return;
+    }
+    self.lyrics = Lyrics::load(&location);
+
+    #[cfg(feature = "lrclib")]
+    if self.lyrics.is_none() {
+      let track = track.clone();
+      tokio::spawn(async move {
+        match crate::lyrics::fetch(&track).await {
+          Ok(lyrics) => {
+            let _ = player
+              .notify_ui(UiNotification::Lyrics(track.get_location(), lyrics))
+              .await;
+          }
+          Err(e) => tracing::warn!("Can't fetch lyrics: {e}"),
+        }
+      });
     }
-    self.current_elapsed_duration
+    self.lyrics_location = Some(location);
   }
 }
 
-#[rustfmt::skip::macros(select)]
-pub(crate) async fn ui(start_index: usize, settings: &Settings) -> Result<()> {
+pub(crate) async fn ui(start_index: usize, settings: &Settings, matches: ArgMatches) -> Result<()> {
+  init_theme(&settings.theme)?;
+  init_dates(&settings.dates)?;
+  init_glyphs(settings.symbols);
+  init_messages(settings.language);
   let player_app = get_mpris_server().await?;
   let player = player_app.imp();
   let (tx, mut rx) = channel(16);
   player.set_sender(tx).await;
 
-  let mut app = Ui::new(start_index);
-  let (rows_len, table, _) = render_table(
-    &player.get_playlist().await,
-    app.order_by,
-    app.order_dir,
-    &None,
-    app.selected_tab,
-  );
-  app.table = table;
-  app.row_len = rows_len;
+  let layout = PlayerStateSetting::load()?
+    .map(|saved| saved.layout)
+    .unwrap_or_default();
+  let mut app = Ui::new(start_index, layout);
+  app.seek_step = settings.seek_step.unwrap_or(SEEK_STEP);
+  app.progress_fps = progress_fps(settings);
+  app.last_played_format = settings.last_played_format;
+  app.half_stars = settings.half_stars;
+  app.truncate = settings.truncate;
+  app.playback = settings.playback;
+  app.locked = settings.party.locked;
+  app.party_pin = settings.party.pin.clone();
+  app.saves = Some(spawn_saver(player, settings.playlist_path.clone()));
+  watch_settings(player, matches);
+  watch_audio_outputs(player);
+  mplayer::announce_position(player);
+  if settings.playback.media_keys {
+    media_keys::grab_media_keys(player);
+  }
+  let startup = &settings.startup;
+  match (startup.is_default(), UiSession::load()) {
+    (true, None) => {
+      let (rows_len, table, _) = render_table(
+        &player.get_playlist().await,
+        app.order_by,
+        app.order_dir,
+        RowMarks {
+          current_track: &None,
+          queue: player.get_queue().await.deref(),
+          marked: &app.marked,
+          discs: false,
+        },
+        app.selected_tab,
+        app.last_played_format,
+        app.cell_widths,
+      );
+      app.table = table;
+      app.row_len = rows_len;
+    }
+    // Reopen the ui where it was left
+    (true, Some(session)) => {
+      app.selected_tab = session.tab;
+      (app.order_by, app.order_dir) = (session.order_by, session.order_dir);
+      app.tab_orders[session.tab as usize] = (session.order_by, session.order_dir);
+      app.search.insert_str(&session.search);
+      build_table(&mut app, player, false).await;
+      app.table_state.select(session.selected);
+      *app.table_state.offset_mut() = session.offset;
+    }
+    (false, _) => {
+      app.selected_tab = startup.tab.unwrap_or(TabSelection::Music);
+      let order = (
+        startup.sort.unwrap_or(Order::Default),
+        startup.order.unwrap_or(OrderDir::Desc),
+      );
+      (app.order_by, app.order_dir) = order;
+      app.tab_orders = [order; 3];
+      if let Some(search) = &startup.search {
+        app.search.insert_str(search);
+      }
+      build_table(&mut app, player, true).await;
+    }
+  }
 
+  set_panic_hook();
   let mut terminal = ratatui::init();
+  // Pasted text is received as a single event instead of key presses
+  execute!(stdout(), EnableBracketedPaste, EnableMouseCapture).into_diagnostic()?;
+  // The key releases commit the seeks of the scrub mode
+  let enhanced = supports_keyboard_enhancement().unwrap_or(false);
+  if enhanced {
+    execute!(
+      stdout(),
+      PushKeyboardEnhancementFlags(KeyboardEnhancementFlags::REPORT_EVENT_TYPES)
+    )
+    .into_diagnostic()?;
+  }
+  let result = event_loop(&mut terminal, &mut app, player, settings, &mut rx).await;
+  if enhanced {
+    let _ = execute!(stdout(), PopKeyboardEnhancementFlags);
+  }
+  let _ = execute!(stdout(), DisableBracketedPaste, DisableMouseCapture);
+  ratatui::restore();
+  result
+}
+
+/// Reload the settings when `settings.toml` is modified, with the arguments
+/// the player was started with.
+pub(crate) fn watch_settings(player: &'static PlayerState, matches: ArgMatches) {
+  let Some(path) = settings_path() else {
+    return;
+  };
+  let modified = |path: &Path| {
+    fs::metadata(path)
+      .and_then(|metadata| metadata.modified())
+      .ok()
+  };
+  tokio::spawn(async move {
+    let mut last_modified = modified(&path);
+    let mut interval = tokio::time::interval(Duration::from_secs(1));
+    loop {
+      interval.tick().await;
+      let current = modified(&path);
+      if current == last_modified {
+        continue;
+      }
+      last_modified = current;
+      let notification = match settings(&matches) {
+        Ok(settings) => UiNotification::Settings(Box::new(settings)),
+        Err(e) => UiNotification::Error(fill(tr().invalid_settings, e)),
+      };
+      if player.notify_ui(notification).await.is_err() {
+        break;
+      }
+    }
+  });
+}
+
+/// Save the db once the requests stop for `SAVE_DELAY`, without blocking
+/// the key handling. The result is shown in the status bar.
+pub(crate) fn spawn_saver(player: &'static PlayerState, playlist_path: String) -> Sender<()> {
+  let (tx, mut rx) = channel(1);
+  tokio::spawn(async move {
+    while rx.recv().await.is_some() {
+      while let Ok(Some(())) = tokio::time::timeout(SAVE_DELAY, rx.recv()).await {}
+      // The files are written on a blocking thread, not on a worker of the
+      // runtime
+      let path = playlist_path.clone();
+      let result = tokio::task::spawn_blocking(move || player.blocking_mut_db().save_in(&path))
+        .await
+        .map_err(|e| e.to_string())
+        .and_then(|result| result.map_err(|e| e.to_string()));
+      let notification = UiNotification::Saved(result);
+      if player.notify_ui(notification).await.is_err() {
+        break;
+      }
+    }
+  });
+  tx
+}
+
+/// Report the removed audio outputs, like unplugged headphones.
+fn watch_audio_outputs(player: &'static PlayerState) {
+  use gstreamer::prelude::{DeviceExt, DeviceMonitorExt};
+  let monitor = match monitor_audio_outputs() {
+    Ok(monitor) => monitor,
+    Err(e) => {
+      warn!("{e}");
+      return;
+    }
+  };
+  tokio::spawn(async move {
+    let mut messages = monitor.bus().stream();
+    while let Some(message) = messages.next().await {
+      if let MessageView::DeviceRemoved(removed) = message.view() {
+        let name = removed.device().display_name().to_string();
+        if player
+          .notify_ui(UiNotification::OutputRemoved(name))
+          .await
+          .is_err()
+        {
+          break;
+        }
+      }
+    }
+  });
+}
+
+/// Restore the terminal before the panic is reported, otherwise the report is
+/// printed in raw mode and the shell is left unusable.
+fn set_panic_hook() {
+  miette::set_panic_hook();
+  let hook = std::panic::take_hook();
+  std::panic::set_hook(Box::new(move |info| {
+    ratatui::restore();
+    hook(info);
+  }));
+}
+
+#[rustfmt::skip::macros(select)]
+async fn event_loop(
+  terminal: &mut DefaultTerminal,
+  app: &mut Ui<'_>,
+  player: &'static PlayerState,
+  settings: &Settings,
+  rx: &mut Receiver<UiNotification>,
+) -> Result<()> {
   terminal.clear().into_diagnostic()?;
 
   let mut ct_reader = crossterm::event::EventStream::new();
   let mut tick = tokio::time::interval(Duration::from_millis(1000));
 
   loop {
-    //  draw the UI
-    if let Some(pipeline) = player.get_pipeline().await {
-      if let Some(song_entry) = &*player.get_track().await {
-        let shuffle_mode = player.get_shuffle_mode().await;
-        let repeat_mode = player.get_repeat_mode().await;
-        terminal
-          .draw(|frame| {
-            render_ui(
-              frame,
-              &mut app,
-              &pipeline,
-              song_entry,
-              shuffle_mode,
-              repeat_mode,
-            )
-            .expect("Error during ui rendering")
-          })
-          .into_diagnostic()?;
+    // Draw the UI, idle without a track: the library stays usable when
+    // nothing could be played
+    let pipeline = player.get_pipeline().await;
+    let track = player.get_track().await.clone();
+    let song_entry = track.as_ref().filter(|_| pipeline.is_some());
+    match &pipeline {
+      Some(pipeline) => app.position.sync_if_stale(pipeline),
+      None => app.position.stop(),
+    }
+    if let (Some(pipeline), Some(song_entry)) = (&pipeline, song_entry) {
+      if app.show_lyrics {
+        app.update_lyrics(player, song_entry);
+      }
+      let location = song_entry.get_location();
+      if app.stream_info.location.as_ref() != Some(&location) {
+        app.stream_info = StreamInfo::new(location);
+      }
+      app.stream_info.update_caps(pipeline);
+    }
+    let playback = Playback {
+      track: song_entry.map(AsRef::as_ref),
+      elapsed: app.position.now(),
+      shuffle_mode: player.get_shuffle_mode().await,
+      repeat_mode: player.get_repeat_mode().await,
+      scope: player.get_scope_kind().await,
+    };
+    terminal
+      .draw(|frame| render_ui(frame, app, &playback).expect("Error during ui rendering"))
+      .into_diagnostic()?;
+    // The columns were resized: cut the cells again
+    if rendering::cell_widths(app) != app.cell_widths {
+      build_table(app, player, false).await;
+    }
+
+    // handle events
+    let crossterm_event = ct_reader.next().fuse();
+    let tick_delay = tick.tick();
+
+    use gstreamer::prelude::ElementExt;
+    // Without a pipeline, no message comes from gstreamer
+    let mut stream = pipeline
+      .as_ref()
+      .and_then(|pipeline| pipeline.bus())
+      .map(|bus| bus.stream());
+    let g_event = async {
+      match stream.as_mut() {
+        Some(stream) => stream.next().await,
+        None => std::future::pending().await,
       }
+    };
 
-      // handle events
-      let crossterm_event = ct_reader.next().fuse();
-      let tick_delay = tick.tick();
+    async fn go_next(player: &PlayerState) -> Result<()> {
+      player.next_track().await?;
+      Ok(())
+    }
 
-      use gstreamer::prelude::ElementExt;
-      let gstreamer_bus = pipeline.bus();
-      let evt = gstreamer_bus.unwrap();
-      let mut stream = evt.stream();
-      let g_event = stream.next();
+    // The gauge moves between the ticks while playing
+    let frame_delay = (app.progress_fps > 1 && app.position.is_running())
+      .then(|| Duration::from_millis(1000 / u64::from(app.progress_fps)));
+    let next_frame = async move {
+      match frame_delay {
+        Some(delay) => tokio::time::sleep(delay).await,
+        None => std::future::pending().await,
+      }
+    };
 
-      async fn go_next(player: &PlayerState, settings: &Settings) -> Result<()> {
-        update_last_played(player, settings).await?;
-        player.next_track().await?;
-        Ok(())
+    let scrub_deadline = app.scrub.as_ref().map(|scrub| scrub.deadline);
+    let scrub_committed = async move {
+      match scrub_deadline {
+        Some(deadline) => sleep_until(deadline).await,
+        None => std::future::pending().await,
       }
+    };
 
-      select! {
+    select! {
+	  _ = next_frame => {}
+	  _ = scrub_committed => {
+	      if let Err(e) = commit_scrub(app, player).await {
+		  app.error(e.to_string());
+	      }
+	  }
 	  _ = tick_delay => {
-	      use gstreamer::{prelude::ElementExtManual, ClockTime};
+	      if let Some(pipeline) = pipeline.as_ref().filter(|_| app.playback.preload) {
+		  preload_near_end(player, pipeline).await;
+	      }
+	      use gstreamer::prelude::ElementExtManual;
+	      let position = pipeline.as_ref().and_then(|pipeline| app.position.sync(pipeline));
 	      // Sometime gstreamer stucks fraction of second before
 	      // the end of a track and don't send EOS message. The
 	      // following code is my attempt to catch the end of
 	      // the track and go to the next one.
 	      if_chain! {
-		  if let Some(position) = pipeline.query_position::<ClockTime>();
+		  if let Some(pipeline) = &pipeline;
+		  if let Some(position) = position;
 		  if let Some (duration) = pipeline.query_duration::<ClockTime>();
 		  let _ = trace!("{position:?}/{duration:?}");
 		  let diff = duration.saturating_sub(position);
 		  if  diff <= ClockTime::from_mseconds(100);
 		  then {
-		      go_next(player, settings).await?;
+		      go_next(player).await?;
+		  }
+	      }
+	      if let Some(pipeline) = &pipeline {
+		  if let Err(e) = watch_stall(app, player, pipeline, position).await {
+		      app.error(e.to_string());
 		  }
 	      }
 	  }
 	  Some(msg)= g_event => {
 	      trace!("{msg:?}");
 	      trace!("{:?}",msg.view());
-	      if let MessageView::Eos(_) = msg.view() {
-		  go_next(player, settings).await?;
+	      match msg.view() {
+		  MessageView::Eos(_) => go_next(player).await?,
+		  MessageView::Error(err) if is_output_error(&err.error()) => {
+		      app.warning(fill(tr().output_lost, err.error()));
+		      // Sought back on `AsyncDone`
+		      if let Some(track) = player.get_track().await.clone() {
+			  app.watchdog.resume_at(track.get_location(), app.position.now());
+		      }
+		      if let Err(e) = player.pause_on_output_loss(true).await {
+			  app.error(e.to_string());
+		      }
+		  }
+		  MessageView::Error(err) => {
+		      let message = err.error().to_string();
+		      app.error(fill(tr().playback_error, &message));
+		      if let Err(e) = on_playback_error(app, player, message).await {
+			  app.error(e.to_string());
+		      }
+		  }
+		  MessageView::Warning(warning) => {
+		      app.warning(fill(tr().playback_warning, warning.error()));
+		  }
+		  MessageView::Tag(tag) => app.stream_info.update_tags(&tag.tags()),
+		  // A seek or a preroll is done: the position catches up
+		  MessageView::AsyncDone(_) => {
+		      if let Some(pipeline) = &pipeline {
+			  app.position.sync(pipeline);
+		      }
+		      if let Err(e) = resume_restarted(app, player).await {
+			  app.error(e.to_string());
+		      }
+		  }
+		  MessageView::StateChanged(_) => {
+		      if let Some(pipeline) = &pipeline {
+			  app.position.follow_state(pipeline);
+		      }
+		  }
+		  _ => {}
 	      }
 	  }
 	  Some(Ok(evt)) = crossterm_event => {
-	      if let event::Event::Key(key) = evt  {
-		  if let EventProcessStatus::Quit = handle_keys(key, &mut app, player, settings).await? {
-		      break;
+	      match evt {
+		  event::Event::Key(key) => match handle_keys(key, app, player, settings).await {
+		      Ok(EventProcessStatus::Quit) => break,
+		      Ok(EventProcessStatus::None) => {}
+		      Err(e) => app.error(e.to_string()),
+		  },
+		  event::Event::Paste(text) if app.panel == Panel::None => {
+		      app.search.insert_str(&text);
+		      build_table(app, player, true).await;
 		  }
+		  event::Event::Mouse(mouse) => handle_mouse(mouse, app, player).await,
+		  _ => {}
 	      }
 	  }
 	  Some(message) = rx.recv() => {
 	      match message {
 		  UiNotification::UpdateIndex(index) => app.table_state.select(index),
-		  UiNotification::Position(position) => app.current_elapsed_duration = position,
-		  UiNotification::RebuildTable => build_table(&mut app, player, true).await,
+		  UiNotification::Position(position) => app.position.set(position),
+		  UiNotification::RebuildTable => build_table(app, player, true).await,
+		  UiNotification::Played(track, listened) => {
+		      if is_played(&track, listened) {
+			  record_play(&track, listened);
+			  update_last_played(player, &track).await;
+			  app.request_save();
+		      } else {
+			  record_skip(&player.get_suggestions().await, &track);
+		      }
+		  }
+		  UiNotification::Error(text) => app.error(text),
+		  UiNotification::Saved(Ok(Saved::Written)) => app.info(tr().saved_db),
+		  UiNotification::Saved(Ok(Saved::Deferred)) => app.warning(tr().save_deferred),
+		  UiNotification::Saved(Err(e)) => app.error(fill(tr().save_failed, e)),
+		  UiNotification::OutputRemoved(name) => {
+		      use gstreamer::{prelude::ElementExt, State};
+		      if pipeline.as_ref().is_some_and(|pipeline| pipeline.current_state() == State::Playing) {
+			  app.warning(fill(tr().output_lost, name));
+			  player.pause_on_output_loss(false).await?;
+		      }
+		  }
+		  UiNotification::Settings(settings) => {
+		      if app.reload_settings(&settings) {
+			  player.apply_settings(&settings).await;
+		      }
+		      build_table(app, player, false).await;
+		  }
+		  #[cfg(feature = "lrclib")]
+		  UiNotification::Lyrics(location, lyrics) => {
+		      if app.lyrics_location.as_ref() == Some(&location) {
+			  app.lyrics = lyrics;
+		      }
+		  }
 	      }
 	  }
-      }
     }
   }
 
-  ratatui::restore();
   Ok(())
 }
 
-#[instrument(skip(player))]
-async fn update_last_played(player: &PlayerState, settings: &Settings) -> Result<()> {
-  if let Some(track) = &*player.get_track().await {
+fn progress_fps(settings: &Settings) -> u32 {
+  settings
+    .progress_fps
+    .unwrap_or(1)
+    .clamp(1, MAX_PROGRESS_FPS)
+}
+
+/// Choose and preroll the next track once the played one passes
+/// `PRELOAD_RATIO`: it starts without delay.
+async fn preload_near_end(player: &PlayerState, pipeline: &Element) {
+  use gstreamer::prelude::ElementExtManual;
+  let (Some(position), Some(duration)) = (
+    pipeline.query_position::<ClockTime>(),
+    pipeline.query_duration::<ClockTime>(),
+  ) else {
+    return;
+  };
+  if duration.nseconds() > 0
+    && position.nseconds() as f64 >= duration.nseconds() as f64 * PRELOAD_RATIO
+  {
+    if let Err(e) = player.preload_next().await {
+      warn!("Can't preload the next track: {e}");
+    }
+  }
+}
+
+/// Record the error against the played track, then retry it, skip it or
+/// stop as set in the settings.
+#[instrument(skip(app, player))]
+async fn on_playback_error(app: &mut Ui<'_>, player: &PlayerState, message: String) -> Result<()> {
+  metrics::playback_error();
+  let Some(track) = player.get_track().await.clone() else {
+    return Ok(());
+  };
+  let location = track.get_location();
+  warn!("Can't play {location}: {message}");
+  app.playback_errors.insert(location, message);
+  player.apply_error_policy(&mut app.retried).await
+}
+
+/// Unstick a pipeline left in Playing while the position of the track
+/// doesn't move for `stall_timeout`: a flush seek first, then the track is
+/// started again at that position, then the error policy applies.
+#[instrument(skip(app, player, pipeline))]
+async fn watch_stall(
+  app: &mut Ui<'_>,
+  player: &PlayerState,
+  pipeline: &Element,
+  position: Option<ClockTime>,
+) -> Result<()> {
+  use gstreamer::{prelude::ElementExt, State};
+  let timeout = app.playback.stall_timeout;
+  if timeout == 0 || pipeline.current_state() != State::Playing {
+    app.watchdog.reset();
+    return Ok(());
+  }
+  let (Some(position), Some(track)) = (position, player.get_track().await.clone()) else {
+    return Ok(());
+  };
+  let location = track.get_location();
+  let Some(recovery) = app
+    .watchdog
+    .check(&location, position, Duration::from_secs(timeout))
+  else {
+    return Ok(());
+  };
+  let at = Duration::from_secs(position.seconds());
+  warn!("Playback of {location} stalled at {at:?}: {recovery:?}");
+  match recovery {
+    Recovery::Seek => {
+      app.warning(fill(tr().stalled_seek, at.format_compact()));
+      player.track_seek(at.as_secs()).await?;
+    }
+    Recovery::Restart => {
+      app.warning(tr().stalled_restart);
+      // Sought once prerolled, on `AsyncDone`
+      app.watchdog.resume_at(location, at);
+      player.play_track(track).await?;
+    }
+    Recovery::GiveUp => {
+      app.watchdog.clear();
+      let message = tr().stalled.to_string();
+      app.error(fill(tr().playback_error, &message));
+      on_playback_error(app, player, message).await?;
+    }
+  }
+  Ok(())
+}
+
+/// Seek the track started again, after a stall or an error of the output,
+/// to where it was, once it is prerolled.
+async fn resume_restarted(app: &mut Ui<'_>, player: &PlayerState) -> Result<()> {
+  let Some(track) = player.get_track().await.clone() else {
+    return Ok(());
+  };
+  if let Some(at) = app.watchdog.take_resume(&track.get_location()) {
+    player.track_seek(at.as_secs()).await?;
+  }
+  Ok(())
+}
+
+/// Whether the track was listened to long enough to count as a play.
+pub(crate) fn is_played(track: &Entry, listened: Duration) -> bool {
+  let duration = Duration::from_secs(track.get_duration());
+  let enough = duration.mul_f64(PLAYED_RATIO).min(PLAYED_ENOUGH);
+  !listened.is_zero() && listened >= enough
+}
+
+/// Add the track played to the history. A failure is only logged: it
+/// doesn't stop the playback.
+#[instrument(skip(track))]
+pub(crate) fn record_play(track: &Entry, listened: Duration) {
+  metrics::track_played();
+  if let Err(e) = history::record(track, listened) {
+    warn!("Can't record the play: {e}");
+  }
+}
+
+/// Count the play of the track in the db, saved by the caller.
+#[instrument(skip(player, track))]
+pub(crate) async fn update_last_played(player: &PlayerState, track: &Entry) {
+  // The rating may have changed while the track was played
+  let track = player.get_db().await.find_url(&track.get_location());
+  if let Some(track) = track {
     let updated_track = match track.as_ref() {
       Entry::Song(song) => {
         let mut song_copy = song.to_owned();
@@ -227,23 +982,66 @@ async fn update_last_played(player: &PlayerState, settings: &Settings) -> Result
     };
     let mut db = player.get_mut_db().await;
     db.update_entry(updated_track);
-    db.save(settings)?;
   }
-  Ok(())
 }
 
 #[instrument(skip(selected_tab, db, playlist))]
 fn filter_playlist(
   selected_tab: TabSelection,
   search: &str,
+  browse_filter: Option<&BrowseFilter>,
   db: &Rhythmdb,
   playlist: &Playlist,
   order_by: Order,
   order_dir: OrderDir,
 ) -> EntryList {
   match selected_tab {
-    TabSelection::Music => db.filter_by_song(search, order_by, order_dir),
+    TabSelection::Music => {
+      let mut entries = db.filter_by_song(search, order_by, order_dir);
+      if let Some(browse_filter) = browse_filter {
+        entries.retain(|entry| browse_filter.matches(entry));
+      }
+      // An album is in its order, disc by disc
+      if matches!(browse_filter, Some(BrowseFilter::Album(..)))
+        && order_by == Order::Default
+        && search.is_empty()
+      {
+        entries.sort_by_key(|entry| (entry.get_disc(), entry.get_track_number()));
+      }
+      entries
+    }
     TabSelection::Podcast => db.filter_by_podcast(search, order_by, order_dir),
-    TabSelection::Queue => db.to_entries(playlist),
+    TabSelection::Queue => {
+      let mut entries = db.to_entries(playlist);
+      sort_queue_view(&mut entries, order_by, order_dir);
+      entries
+    }
   }
 }
+
+/// Sort the queue tab by a column. Only the view is sorted: the queue keeps
+/// its play order, the order by default.
+fn sort_queue_view(entries: &mut EntryList, order_by: Order, order_dir: OrderDir) {
+  let compare = |a: &SharedEntry, b: &SharedEntry| match order_by {
+    Order::Default => std::cmp::Ordering::Equal,
+    Order::Title => a.get_title().cmp(b.get_title()),
+    Order::Artist => a.get_artist().cmp(b.get_artist()),
+    Order::Album => a.get_album().cmp(b.get_album()),
+    Order::Date => a.get_date().cmp(&b.get_date()),
+    Order::Duration => a.get_duration().cmp(&b.get_duration()),
+    Order::Rating => a.get_rating().cmp(&b.get_rating()),
+    Order::LastPlayed => a.get_last_played().cmp(&b.get_last_played()),
+    Order::Bpm => a
+      .get_bpm()
+      .partial_cmp(&b.get_bpm())
+      .unwrap_or(std::cmp::Ordering::Equal),
+    Order::Year => a.get_year().cmp(&b.get_year()),
+    Order::Composer => a.get_composer().cmp(b.get_composer()),
+    Order::Work => a.get_work().cmp(&b.get_work()),
+  };
+  // Stable: the ties stay in the play order
+  entries.sort_by(|a, b| match order_dir {
+    OrderDir::Asc => compare(a, b),
+    OrderDir::Desc => compare(b, a),
+  });
+}
@@ -0,0 +1,33 @@
+use super::{i18n::tr, theme::theme};
+use ratatui::{
+  layout::Flex,
+  prelude::{Constraint, Layout, Rect},
+  widgets::{Block, Borders, Clear, Padding, Paragraph},
+  Frame,
+};
+use tracing::instrument;
+
+/// Render the prompt of the pin unlocking the party mode, the typed chars
+/// masked.
+#[instrument(skip(frame))]
+pub(crate) fn render_pin_prompt(area: Rect, frame: &mut Frame<'_>, typed: usize) {
+  let [prompt_area] = Layout::vertical([Constraint::Length(3)])
+    .flex(Flex::Center)
+    .areas(area);
+  let [prompt_area] = Layout::horizontal([Constraint::Length(30)])
+    .flex(Flex::Center)
+    .areas(prompt_area);
+
+  let prompt = Paragraph::new("•".repeat(typed))
+    .style(theme().default)
+    .block(
+      Block::default()
+        .style(theme().border)
+        .padding(Padding::horizontal(1))
+        .borders(Borders::ALL)
+        .title(tr().unlock),
+    );
+
+  frame.render_widget(Clear, prompt_area);
+  frame.render_widget(prompt, prompt_area);
+}
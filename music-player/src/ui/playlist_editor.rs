@@ -0,0 +1,495 @@
+use super::{
+  i18n::{fill, tr},
+  theme::theme,
+  OrderDir,
+};
+use crate::playlists::{AutomaticPlaylist, Criterion, Query};
+use ratatui::{
+  layout::Flex,
+  prelude::{Constraint, Layout, Rect},
+  style::Style,
+  text::{Line, Span},
+  widgets::{Block, Borders, Clear, Padding, Paragraph},
+  Frame,
+};
+use tracing::instrument;
+
+const DAY: u64 = 24 * 60 * 60;
+
+/// Property of the songs a condition is on.
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Field {
+  Title,
+  Artist,
+  Album,
+  Genre,
+  Rating,
+  PlayCount,
+  // In seconds
+  Duration,
+  LastPlayed,
+  FirstSeen,
+}
+
+impl Field {
+  const ALL: [Field; 9] = [
+    Field::Title,
+    Field::Artist,
+    Field::Album,
+    Field::Genre,
+    Field::Rating,
+    Field::PlayCount,
+    Field::Duration,
+    Field::LastPlayed,
+    Field::FirstSeen,
+  ];
+
+  fn label(&self) -> &'static str {
+    match self {
+      Field::Title => tr().title,
+      Field::Artist => tr().artist,
+      Field::Album => tr().album,
+      Field::Genre => tr().genre,
+      Field::Rating => tr().rating,
+      Field::PlayCount => tr().play_count,
+      Field::Duration => tr().duration,
+      Field::LastPlayed => tr().last_played,
+      Field::FirstSeen => tr().first_seen,
+    }
+  }
+
+  /// Name of the property in the db.
+  fn prop(&self) -> &'static str {
+    match self {
+      Field::Title => "title",
+      Field::Artist => "artist",
+      Field::Album => "album",
+      Field::Genre => "genre",
+      Field::Rating => "rating",
+      Field::PlayCount => "play-count",
+      Field::Duration => "duration",
+      Field::LastPlayed => "last-played",
+      Field::FirstSeen => "first-seen",
+    }
+  }
+
+  /// Name of the column sorting the playlist in Rhythmbox.
+  fn sort_key(&self) -> &'static str {
+    match self {
+      Field::Title => "Title",
+      Field::Artist => "Artist",
+      Field::Album => "Album",
+      Field::Genre => "Genre",
+      Field::Rating => "Rating",
+      Field::PlayCount => "PlayCount",
+      Field::Duration => "Duration",
+      Field::LastPlayed => "LastPlayed",
+      Field::FirstSeen => "FirstSeen",
+    }
+  }
+
+  fn is_text(&self) -> bool {
+    matches!(
+      self,
+      Field::Title | Field::Artist | Field::Album | Field::Genre
+    )
+  }
+
+  fn operators(&self) -> &'static [Operator] {
+    match self {
+      _ if self.is_text() => &[
+        Operator::Contains,
+        Operator::NotContains,
+        Operator::Is,
+        Operator::StartsWith,
+        Operator::EndsWith,
+      ],
+      Field::Rating | Field::PlayCount | Field::Duration => {
+        &[Operator::Is, Operator::Greater, Operator::Less]
+      }
+      _ => &[Operator::Within, Operator::NotWithin],
+    }
+  }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Operator {
+  Contains,
+  NotContains,
+  Is,
+  StartsWith,
+  EndsWith,
+  Greater,
+  Less,
+  // In the last days
+  Within,
+  NotWithin,
+}
+
+impl Operator {
+  fn label(&self) -> &'static str {
+    match self {
+      Operator::Contains => tr().op_contains,
+      Operator::NotContains => tr().op_not_contains,
+      Operator::Is => tr().op_is,
+      Operator::StartsWith => tr().op_starts_with,
+      Operator::EndsWith => tr().op_ends_with,
+      Operator::Greater => tr().op_greater,
+      Operator::Less => tr().op_less,
+      Operator::Within => tr().op_within,
+      Operator::NotWithin => tr().op_not_within,
+    }
+  }
+
+  /// Condition of the operator, None when the value is not a number while
+  /// one is expected.
+  fn query(&self, field: Field, value: &str) -> Option<Query> {
+    let number = || value.trim().parse::<u64>().ok();
+    let criterion = |value: String| Criterion {
+      prop: field.prop().into(),
+      value,
+    };
+    Some(match self {
+      Operator::Contains => Query::Like(criterion(value.into())),
+      Operator::NotContains => Query::NotLike(criterion(value.into())),
+      Operator::Is if field.is_text() => Query::Equals(criterion(value.into())),
+      Operator::Is => Query::Equals(criterion(number()?.to_string())),
+      Operator::StartsWith => Query::Prefix(criterion(value.into())),
+      Operator::EndsWith => Query::Suffix(criterion(value.into())),
+      Operator::Greater => Query::Greater(criterion(number()?.to_string())),
+      Operator::Less => Query::Less(criterion(number()?.to_string())),
+      Operator::Within => Query::CurrentTimeWithin(criterion((number()? * DAY).to_string())),
+      Operator::NotWithin => Query::CurrentTimeNotWithin(criterion((number()? * DAY).to_string())),
+    })
+  }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Limit {
+  None,
+  Tracks,
+  Minutes,
+}
+
+/// Condition row of the editor.
+#[derive(Debug)]
+struct CriterionRow {
+  field: Field,
+  operator: Operator,
+  value: String,
+}
+
+impl CriterionRow {
+  fn new() -> CriterionRow {
+    CriterionRow {
+      field: Field::Artist,
+      operator: Operator::Contains,
+      value: String::new(),
+    }
+  }
+}
+
+/// Line of the editor, with its cells.
+enum EditorLine {
+  Name,
+  Match,
+  Criterion(usize),
+  Limit,
+  Sort,
+}
+
+/// Editor of an automatic playlist: its name, the conditions matched by the
+/// songs, a limit and an order. ⇥ moves between the cells, ←/→ change the
+/// choices, the text cells are typed.
+#[derive(Debug)]
+pub(crate) struct PlaylistEditor {
+  name: String,
+  // Any condition matched instead of all
+  match_any: bool,
+  criteria: Vec<CriterionRow>,
+  limit: Limit,
+  limit_value: String,
+  sort: Field,
+  sort_dir: OrderDir,
+  // Focused line and cell in it
+  line: usize,
+  cell: usize,
+}
+
+impl PlaylistEditor {
+  pub(crate) fn new() -> PlaylistEditor {
+    PlaylistEditor {
+      name: String::new(),
+      match_any: false,
+      criteria: vec![CriterionRow::new()],
+      limit: Limit::None,
+      limit_value: String::new(),
+      sort: Field::Artist,
+      sort_dir: OrderDir::Asc,
+      line: 0,
+      cell: 0,
+    }
+  }
+
+  fn lines(&self) -> Vec<EditorLine> {
+    let mut lines = vec![EditorLine::Name, EditorLine::Match];
+    lines.extend((0..self.criteria.len()).map(EditorLine::Criterion));
+    lines.extend([EditorLine::Limit, EditorLine::Sort]);
+    lines
+  }
+
+  fn focused(&self) -> EditorLine {
+    match self.line {
+      0 => EditorLine::Name,
+      1 => EditorLine::Match,
+      line if line < self.criteria.len() + 2 => EditorLine::Criterion(line - 2),
+      line if line == self.criteria.len() + 2 => EditorLine::Limit,
+      _ => EditorLine::Sort,
+    }
+  }
+
+  fn cells(line: &EditorLine) -> usize {
+    match line {
+      EditorLine::Name | EditorLine::Match => 1,
+      EditorLine::Criterion(_) => 3,
+      EditorLine::Limit | EditorLine::Sort => 2,
+    }
+  }
+
+  pub(crate) fn up(&mut self) {
+    self.line = self.line.saturating_sub(1);
+    self.cell = self.cell.min(Self::cells(&self.focused()) - 1);
+  }
+
+  pub(crate) fn down(&mut self) {
+    self.line = (self.line + 1).min(self.lines().len() - 1);
+    self.cell = self.cell.min(Self::cells(&self.focused()) - 1);
+  }
+
+  /// Focus the next cell, on the next line after the last one.
+  pub(crate) fn next_cell(&mut self) {
+    if self.cell + 1 < Self::cells(&self.focused()) {
+      self.cell += 1;
+    } else if self.line + 1 < self.lines().len() {
+      self.line += 1;
+      self.cell = 0;
+    }
+  }
+
+  pub(crate) fn previous_cell(&mut self) {
+    if self.cell > 0 {
+      self.cell -= 1;
+    } else if self.line > 0 {
+      self.line -= 1;
+      self.cell = Self::cells(&self.focused()) - 1;
+    }
+  }
+
+  /// Change the choice of the focused cell, to the next one when `forward`.
+  pub(crate) fn cycle(&mut self, forward: bool) {
+    match (self.focused(), self.cell) {
+      (EditorLine::Match, _) => self.match_any = !self.match_any,
+      (EditorLine::Criterion(i), 0) => {
+        let row = &mut self.criteria[i];
+        row.field = cycle(&Field::ALL, row.field, forward);
+        if !row.field.operators().contains(&row.operator) {
+          row.operator = row.field.operators()[0];
+        }
+      }
+      (EditorLine::Criterion(i), 1) => {
+        let row = &mut self.criteria[i];
+        row.operator = cycle(row.field.operators(), row.operator, forward);
+      }
+      (EditorLine::Limit, 0) => {
+        self.limit = cycle(
+          &[Limit::None, Limit::Tracks, Limit::Minutes],
+          self.limit,
+          forward,
+        )
+      }
+      (EditorLine::Sort, 0) => self.sort = cycle(&Field::ALL, self.sort, forward),
+      (EditorLine::Sort, _) => {
+        self.sort_dir = match self.sort_dir {
+          OrderDir::Asc => OrderDir::Desc,
+          OrderDir::Desc => OrderDir::Asc,
+        }
+      }
+      _ => {}
+    }
+  }
+
+  /// Text of the focused cell, when it is typed.
+  pub(crate) fn text_mut(&mut self) -> Option<&mut String> {
+    match (self.focused(), self.cell) {
+      (EditorLine::Name, _) => Some(&mut self.name),
+      (EditorLine::Criterion(i), 2) => Some(&mut self.criteria[i].value),
+      (EditorLine::Limit, 1) => Some(&mut self.limit_value),
+      _ => None,
+    }
+  }
+
+  /// Add a condition after the focused one, or at the end.
+  pub(crate) fn add_criterion(&mut self) {
+    let index = match self.focused() {
+      EditorLine::Criterion(i) => i + 1,
+      _ => self.criteria.len(),
+    };
+    self.criteria.insert(index, CriterionRow::new());
+    self.line = index + 2;
+    self.cell = 0;
+  }
+
+  /// Remove the focused condition, the last one is kept.
+  pub(crate) fn remove_criterion(&mut self) {
+    if let EditorLine::Criterion(i) = self.focused() {
+      if self.criteria.len() > 1 {
+        self.criteria.remove(i);
+        self.line = self.line.min(self.criteria.len() + 1);
+      }
+    }
+  }
+
+  /// Playlist of the editor, or the message of the first invalid cell.
+  pub(crate) fn to_playlist(&self) -> Result<AutomaticPlaylist, String> {
+    let name = self.name.trim();
+    if name.is_empty() {
+      return Err(tr().playlist_no_name.into());
+    }
+    let mut query = vec![];
+    for row in &self.criteria {
+      if self.match_any && !query.is_empty() {
+        query.push(Query::Disjunction);
+      }
+      let criterion = row
+        .operator
+        .query(row.field, &row.value)
+        .ok_or_else(|| fill(tr().playlist_invalid_value, &row.value))?;
+      query.push(criterion);
+    }
+    let limit = match self.limit {
+      Limit::None => None,
+      Limit::Tracks | Limit::Minutes => Some(
+        self
+          .limit_value
+          .trim()
+          .parse::<u64>()
+          .map_err(|_| fill(tr().playlist_invalid_value, &self.limit_value))?,
+      ),
+    };
+    Ok(AutomaticPlaylist::new(
+      name.to_string(),
+      query,
+      self.sort.sort_key(),
+      self.sort_dir == OrderDir::Desc,
+      limit.filter(|_| self.limit == Limit::Tracks),
+      limit
+        .filter(|_| self.limit == Limit::Minutes)
+        .map(|minutes| minutes * 60),
+    ))
+  }
+}
+
+/// Next or previous choice, around the ends.
+fn cycle<T: Copy + PartialEq>(choices: &[T], current: T, forward: bool) -> T {
+  let index = choices
+    .iter()
+    .position(|choice| *choice == current)
+    .unwrap_or_default();
+  let index = if forward {
+    (index + 1) % choices.len()
+  } else {
+    (index + choices.len() - 1) % choices.len()
+  };
+  choices[index]
+}
+
+#[instrument(skip(frame, editor))]
+pub(crate) fn render_playlist_editor(area: Rect, frame: &mut Frame<'_>, editor: &PlaylistEditor) {
+  let lines = editor.lines();
+  let [editor_area] = Layout::vertical([Constraint::Length(2 + lines.len() as u16)])
+    .flex(Flex::Center)
+    .areas(area);
+  let [editor_area] = Layout::horizontal([Constraint::Length(80)])
+    .flex(Flex::Center)
+    .areas(editor_area);
+
+  let text: Vec<Line> = lines
+    .iter()
+    .enumerate()
+    .map(|(index, line)| {
+      // Style of the cell, the focused one highlighted
+      let style = |cell: usize| {
+        if index == editor.line && cell == editor.cell {
+          theme().selected
+        } else {
+          Style::default()
+        }
+      };
+      let choice = |cell: usize, label: &str| Span::styled(format!("< {label} >"), style(cell));
+      let typed = |cell: usize, text: &str| Span::styled(format!("[{text}]"), style(cell));
+      match line {
+        EditorLine::Name => Line::from(vec![
+          Span::raw(format!("{}: ", tr().playlist_name)),
+          typed(0, &editor.name),
+        ]),
+        EditorLine::Match => choice(
+          0,
+          if editor.match_any {
+            tr().playlist_match_any
+          } else {
+            tr().playlist_match_all
+          },
+        )
+        .into(),
+        EditorLine::Criterion(i) => {
+          let row = &editor.criteria[*i];
+          Line::from(vec![
+            Span::raw("  "),
+            choice(0, row.field.label()),
+            Span::raw(" "),
+            choice(1, row.operator.label()),
+            Span::raw(" "),
+            typed(2, &row.value),
+          ])
+        }
+        EditorLine::Limit => Line::from(vec![
+          Span::raw(format!("{}: ", tr().playlist_limit)),
+          choice(
+            0,
+            match editor.limit {
+              Limit::None => tr().playlist_no_limit,
+              Limit::Tracks => tr().playlist_limit_tracks,
+              Limit::Minutes => tr().playlist_limit_minutes,
+            },
+          ),
+          Span::raw(" "),
+          typed(1, &editor.limit_value),
+        ]),
+        EditorLine::Sort => Line::from(vec![
+          Span::raw(format!("{}: ", tr().playlist_sort)),
+          choice(0, editor.sort.label()),
+          Span::raw(" "),
+          choice(
+            1,
+            match editor.sort_dir {
+              OrderDir::Asc => tr().playlist_ascending,
+              OrderDir::Desc => tr().playlist_descending,
+            },
+          ),
+        ]),
+      }
+    })
+    .collect();
+
+  let paragraph = Paragraph::new(text).style(theme().default).block(
+    Block::default()
+      .style(theme().border)
+      .padding(Padding::horizontal(1))
+      .borders(Borders::ALL)
+      .title(tr().playlist_editor)
+      .title_bottom(Line::from(tr().playlist_hint).right_aligned()),
+  );
+
+  frame.render_widget(Clear, editor_area);
+  frame.render_widget(paragraph, editor_area);
+}
@@ -0,0 +1,150 @@
+use gstreamer::{
+  prelude::{ElementExt, ElementExtManual},
+  ClockTime, Element, State,
+};
+use std::time::{Duration, Instant};
+use tracing::instrument;
+
+/// The pipeline is queried again after this delay, to catch its drift.
+const RESYNC: Duration = Duration::from_secs(1);
+
+/// Position of the played track, counted from the last position read from
+/// the pipeline: the gauge moves between two queries.
+#[derive(Debug)]
+pub(crate) struct PositionClock {
+  position: ClockTime,
+  // When the position was read
+  synced_at: Instant,
+  // The position only advances while playing
+  running: bool,
+}
+
+impl Default for PositionClock {
+  fn default() -> Self {
+    PositionClock {
+      position: ClockTime::ZERO,
+      synced_at: Instant::now(),
+      running: false,
+    }
+  }
+}
+
+impl PositionClock {
+  pub(crate) fn is_running(&self) -> bool {
+    self.running
+  }
+
+  /// Position at this instant.
+  pub(crate) fn now(&self) -> Duration {
+    let position = Duration::from_nanos(self.position.nseconds());
+    if self.running {
+      position + self.synced_at.elapsed()
+    } else {
+      position
+    }
+  }
+
+  /// Read the position and the state of the pipeline, the position is
+  /// returned when it is known.
+  #[instrument(skip(self, pipeline))]
+  pub(crate) fn sync(&mut self, pipeline: &Element) -> Option<ClockTime> {
+    let position = pipeline.query_position::<ClockTime>();
+    match position {
+      Some(position) => {
+        self.position = position;
+        self.synced_at = Instant::now();
+      }
+      // Not prerolled yet: counted from the last position
+      None => self.hold(),
+    }
+    self.running = pipeline.current_state() == State::Playing;
+    position
+  }
+
+  /// Query the pipeline only when the counted position may have drifted.
+  pub(crate) fn sync_if_stale(&mut self, pipeline: &Element) {
+    if self.synced_at.elapsed() >= RESYNC {
+      self.sync(pipeline);
+    }
+  }
+
+  /// Catch up with a play or a pause of the pipeline, without querying it on
+  /// the state changes of each of its elements.
+  pub(crate) fn follow_state(&mut self, pipeline: &Element) {
+    if (pipeline.current_state() == State::Playing) != self.running {
+      self.sync(pipeline);
+    }
+  }
+
+  /// Position set by a seek or a new track, before the pipeline reaches it.
+  pub(crate) fn set(&mut self, position: Duration) {
+    self.position = ClockTime::from_nseconds(position.as_nanos() as u64);
+    self.synced_at = Instant::now();
+  }
+
+  /// Stop counting, at the position reached.
+  pub(crate) fn stop(&mut self) {
+    self.hold();
+    self.running = false;
+  }
+
+  fn hold(&mut self) {
+    self.set(self.now());
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use std::thread::sleep;
+
+  const TICK: Duration = Duration::from_millis(20);
+
+  #[test]
+  fn stopped_clock_holds() {
+    let mut clock = PositionClock::default();
+    clock.set(Duration::from_secs(10));
+    sleep(TICK);
+    assert_eq!(clock.now(), Duration::from_secs(10));
+  }
+
+  #[test]
+  fn running_clock_counts() {
+    let mut clock = PositionClock {
+      running: true,
+      ..PositionClock::default()
+    };
+    clock.set(Duration::from_secs(10));
+    sleep(TICK);
+    assert!(clock.now() >= Duration::from_secs(10) + TICK);
+  }
+
+  #[test]
+  fn seek_catches_up() {
+    let mut clock = PositionClock {
+      running: true,
+      ..PositionClock::default()
+    };
+    clock.set(Duration::from_secs(10));
+    sleep(TICK);
+    clock.set(Duration::from_secs(90));
+    assert!(clock.now() >= Duration::from_secs(90));
+    assert!(clock.now() < Duration::from_secs(91));
+  }
+
+  #[test]
+  fn stop_holds_the_position_reached() {
+    let mut clock = PositionClock {
+      running: true,
+      ..PositionClock::default()
+    };
+    clock.set(Duration::from_secs(10));
+    sleep(TICK);
+    clock.stop();
+    let reached = clock.now();
+    assert!(reached >= Duration::from_secs(10) + TICK);
+    sleep(TICK);
+    assert_eq!(clock.now(), reached);
+    assert!(!clock.is_running());
+  }
+}
@@ -1,187 +1,400 @@
-use super::{help::render_help_panel, Order, OrderDir, Panel, TabSelection};
+use super::{
+  actions::render_action_menu,
+  browser::render_browser_panel,
+  comment::{comment_cell, render_comment_editor},
+  dates::format_date,
+  details::render_details_panel,
+  facets::render_facet_panel,
+  glyphs::glyphs,
+  help::render_help_panel,
+  i18n::{fill, tr},
+  logs::render_logs_panel,
+  lyrics::render_lyrics_panel,
+  party::render_pin_prompt,
+  playlist_editor::render_playlist_editor,
+  status::render_status_bar,
+  suggestions::render_suggestion_panel,
+  theme::theme,
+  Order, OrderDir, Panel, TabSelection,
+};
 use crate::{
-  player_state::{Repeat, Shuffle},
-  rhythmdb::{Entry, SharedEntry},
+  flags,
+  player_state::{Repeat, ScopeKind, Shuffle},
+  playlists::Playlist,
+  rhythmdb::{Entry, PodcastPostentry, Rating, SharedEntry},
+  settings::DateFormat,
   ui::Ui,
 };
 use chrono::DateTime;
-use gstreamer::Element;
-use humandate::HumanDate;
-use humantime::format_duration;
+use humandate::{HumanDate, HumanDuration};
 use miette::Result;
 use ratatui::{
-  prelude::{Constraint, Direction, Layout, Rect, Style},
-  style::{Color, Modifier, Stylize},
+  prelude::{Constraint, Direction, Layout, Margin, Rect},
+  style::{Modifier, Stylize},
   symbols,
-  text::{Line, Span},
-  widgets::{Block, BorderType, Borders, Cell, LineGauge, Padding, Paragraph, Table, Tabs},
+  text::{Line, Span, Text},
+  widgets::{
+    Block, BorderType, Borders, Cell, LineGauge, Padding, Paragraph, Scrollbar, ScrollbarOrientation,
+    ScrollbarState, Table, Tabs,
+  },
   Frame,
 };
-use std::time::Duration;
+use std::{collections::HashSet, time::Duration};
 use tracing::instrument;
+use unicode_width::{UnicodeWidthChar, UnicodeWidthStr};
+use url::Url;
 
 // ⏴ 	⏵ 	⏶ 	⏷ 	⏸ 	⏹ 	⏺ 	⏻ 	⏼ ⏭ 	⏮ 	⏯
 // 🔂 🔁 🔀
 
-pub(crate) struct Theme {
-  pub(crate) default: Style,
-  pub(crate) default_dark: Style,
-  pub(crate) primary: Style,
-  pub(crate) secondary: Style,
-  pub(crate) border: Style,
-  pub(crate) _border_selected: Style,
-  pub(crate) selected: Style,
-  pub(crate) help_key: Style,
-}
+/// Below this size, the compact layout is used.
+const COMPACT_WIDTH: u16 = 80;
+const COMPACT_HEIGHT: u16 = 20;
+/// Below this size, nothing usable can be drawn.
+const MIN_WIDTH: u16 = 30;
+const MIN_HEIGHT: u16 = 8;
 
-pub(crate) const THEME: Theme = Theme {
-  default: Style::reset(), //.fg(Color::White),
-  default_dark: Style::new().fg(Color::DarkGray),
-  primary: Style::new().fg(Color::Magenta),
-  secondary: Style::new().fg(Color::Rgb(192, 64, 192)),
-  border: Style::new().fg(Color::Rgb(128, 0, 128)),
-  _border_selected: Style::new().fg(Color::LightCyan),
-  selected: Style::new().fg(Color::Magenta),
-  help_key: Style::new().fg(Color::Green),
-};
+/// What the control bar shows of the playback, read from the player before
+/// drawing: the rendering never queries the pipeline. Without a track, the
+/// control bar is idle.
+#[derive(Debug)]
+pub(crate) struct Playback<'a> {
+  pub(crate) track: Option<&'a Entry>,
+  pub(crate) elapsed: Duration,
+  pub(crate) shuffle_mode: Shuffle,
+  pub(crate) repeat_mode: Repeat,
+  pub(crate) scope: ScopeKind,
+}
 
 #[instrument(skip(app))]
 pub(crate) fn render_ui(
   frame: &mut Frame<'_>,
   app: &mut Ui<'_>,
-  pipeline: &Element,
-  track_entry: &Entry,
-  shuffle_mode: Shuffle,
-  repeat_mode: Repeat,
+  playback: &Playback<'_>,
 ) -> Result<()> {
+  let Playback {
+    track: track_entry,
+    elapsed: elapsed_duration,
+    shuffle_mode,
+    repeat_mode,
+    scope,
+  } = *playback;
   let area = frame.area();
-  let [title_area, search_area, table_area, control_area] = Layout::default()
+  if area.width < MIN_WIDTH || area.height < MIN_HEIGHT {
+    frame.render_widget(
+      Paragraph::new(tr().terminal_too_small)
+        .style(theme().default)
+        .centered(),
+      area,
+    );
+    return Ok(());
+  }
+  let compact = area.width < COMPACT_WIDTH || area.height < COMPACT_HEIGHT;
+  // The search bar stays visible while a search is typed or filters are on
+  let show_search = app.layout.show_search || !app.search.is_empty() || app.facets.is_active();
+  let [title_area, search_area, table_area, status_area, control_area] = Layout::default()
     .direction(Direction::Vertical)
     .constraints(vec![
       Constraint::Length(1),
-      Constraint::Length(3),
+      Constraint::Length(if show_search { 3 } else { 0 }),
       Constraint::Fill(1),
-      Constraint::Length(4),
+      Constraint::Length(1),
+      Constraint::Length(match (app.layout.show_control, compact) {
+        (false, _) => 0,
+        (true, true) => 1,
+        (true, false) => 4,
+      }),
     ])
     .areas(area);
 
-  let [title_area, _filler_, shuffle_area, reapeat_area, tabs_area] = Layout::default()
+  let [title_area, _filler_, locked_area, shuffle_area, reapeat_area, tabs_area] = Layout::default()
     .direction(Direction::Horizontal)
     .constraints(vec![
-      Constraint::Length(15),
+      Constraint::Length(tr().app_title.chars().count() as u16 + 3),
       Constraint::Fill(1),
+      Constraint::Length(if app.locked { 2 } else { 0 }),
       Constraint::Length(2),
       Constraint::Length(2),
-      Constraint::Length(25),
+      Constraint::Length(tabs_width()),
     ])
     .areas(title_area);
 
   // Top bar
-  let title_paragraph = Paragraph::new("Music player");
+  let title_paragraph = Paragraph::new(tr().app_title);
   frame.render_widget(title_paragraph, title_area);
   render_tabs(frame, tabs_area, app.selected_tab);
+  frame.render_widget(
+    Paragraph::new(glyphs().locked).style(theme().primary),
+    locked_area,
+  );
   render_shuffle(frame, shuffle_area, shuffle_mode);
   render_repeat(frame, reapeat_area, repeat_mode);
 
   // Search
+  let (before_cursor, after_cursor) = app.search.split();
+  let mut after_cursor = after_cursor.chars();
   let search = Paragraph::new(Line::from(vec![
-    Span::from(app.search.clone()),
-    Span::from("_".to_string()).style(THEME.secondary.add_modifier(Modifier::SLOW_BLINK)),
+    Span::from(before_cursor.to_string()),
+    match after_cursor.next() {
+      Some(c) => Span::from(c.to_string()).style(theme().secondary.add_modifier(Modifier::REVERSED)),
+      None => Span::from("_".to_string()).style(theme().secondary.add_modifier(Modifier::SLOW_BLINK)),
+    },
+    Span::from(after_cursor.as_str().to_string()),
   ]))
-  .style(THEME.default)
+  .style(theme().default)
   .block(
     Block::new()
       .borders(Borders::ALL)
       .border_type(BorderType::Rounded)
-      .title("Search")
-      .style(THEME.border),
+      .title(tr().search)
+      .title_bottom(if app.facets.is_active() {
+        Line::from(tr().filters_active)
+          .style(theme().secondary)
+          .right_aligned()
+      } else {
+        Line::default()
+      })
+      .style(theme().border),
   );
   frame.render_widget(search, search_area);
+  let table_area = if let Some(browser) = app.browser.as_mut() {
+    let [browser_area, table_area] = Layout::horizontal([
+      Constraint::Percentage(app.layout.browser_width),
+      Constraint::Fill(1),
+    ])
+    .areas(table_area);
+    render_browser_panel(browser_area, frame, browser, app.panel == Panel::Browser);
+    table_area
+  } else {
+    table_area
+  };
+  let table_area = if app.show_lyrics {
+    let [table_area, lyrics_area] = Layout::horizontal([
+      Constraint::Fill(1),
+      Constraint::Percentage(app.layout.lyrics_width),
+    ])
+    .areas(table_area);
+    render_lyrics_panel(lyrics_area, frame, app.lyrics.as_ref(), elapsed_duration);
+    table_area
+  } else {
+    table_area
+  };
+  // Borders and header are not rows
+  app.table_page = (table_area.height as usize).saturating_sub(3).max(1);
+  if let Some(selected) = app.table_state.selected() {
+    if selected >= app.row_len {
+      app.table_state.select(app.row_len.checked_sub(1));
+    }
+  }
+  let widths = table_widths(
+    app.selected_tab,
+    compact,
+    app.layout.show_comments,
+    app.layout.show_classical,
+  );
+  app.header_areas = header_areas(table_area, widths);
+  app.table = std::mem::take(&mut app.table).widths(widths);
   frame.render_stateful_widget(&app.table, table_area, &mut app.table_state);
+  render_scrollbar(frame, table_area, app);
 
-  // Control
+  if app
+    .status
+    .as_ref()
+    .is_some_and(|status| status.is_expired())
   {
-    let elapsed_duration = app.get_track_elapsed_duration(pipeline);
-    let info = Paragraph::new(match track_entry {
-      Entry::Iradio(_) => todo!(),
-      Entry::Ignore(_) => todo!(),
-      Entry::PodcastFeed(_) => todo!(),
-      Entry::Song(song) => format!("{} - {}", song.title, song.artist,),
-      Entry::PodcastPost(podcast) => format!("{} - {}", podcast.title, podcast.album,),
-    })
-    .block(
-      Block::default()
-        .padding(Padding::horizontal(1))
-        .borders(Borders::ALL)
-        .border_type(BorderType::Rounded)
-        .style(THEME.border),
-    )
-    .style(THEME.default);
+    app.status = None;
+  }
+  render_status_bar(status_area, frame, app.status.as_ref());
+
+  // Control
+  let info = match track_entry {
+    Some(Entry::Song(song)) => Line::from(format!("{} - {}", song.title, song.artist,)),
+    Some(Entry::PodcastPost(podcast)) => {
+      Line::from(format!("{} - {}", podcast.title, podcast.album,))
+    }
+    Some(Entry::Iradio(_) | Entry::Ignore(_) | Entry::PodcastFeed(_)) => todo!(),
+    None => Line::styled(tr().nothing_playing, theme().default_dark),
+  };
+  let indicatif = match (track_entry, &app.scrub) {
+    (Some(track_entry), Some(scrub)) => Some(
+      progress_gauge(scrub.position, track_entry.get_duration())
+        .filled_style(theme().secondary.add_modifier(Modifier::BOLD)),
+    ),
+    (Some(track_entry), None) => Some(progress_gauge(elapsed_duration, track_entry.get_duration())),
+    (None, _) => None,
+  };
+  if compact {
+    // Single line: the track and the progress
+    let [info_area, gauge_area] = Layout::horizontal([
+      Constraint::Fill(1),
+      Constraint::Length(if indicatif.is_some() { 24 } else { 0 }),
+    ])
+    .areas(control_area);
+    frame.render_widget(Paragraph::new(info).style(theme().default), info_area);
+    if let Some(indicatif) = indicatif {
+      frame.render_widget(indicatif, gauge_area);
+    }
+  } else {
+    let info = Paragraph::new(info)
+      .block(
+        Block::default()
+          .padding(Padding::horizontal(1))
+          .borders(Borders::ALL)
+          .border_type(BorderType::Rounded)
+          .title_bottom(if track_entry.is_some() {
+            Line::from(tr().scope(scope)).style(theme().default_dark)
+          } else {
+            Line::default()
+          })
+          .style(theme().border),
+      )
+      .style(theme().default);
     frame.render_widget(info, control_area);
+    // Idle: no stream nor progress to show
+    if let Some(indicatif) = indicatif {
+      let [stream_area] = Layout::default()
+        .margin(1)
+        .horizontal_margin(2)
+        .constraints(vec![Constraint::Length(1)])
+        .areas(control_area);
+      frame.render_widget(
+        Paragraph::new(app.stream_info.to_string())
+          .style(theme().default_dark)
+          .right_aligned(),
+        stream_area,
+      );
 
-    let [_not_used_, second_line] = Layout::default()
-      .direction(Direction::Vertical)
-      .margin(1)
-      .horizontal_margin(2)
-      .constraints(vec![Constraint::Length(2), Constraint::Length(1)])
-      .areas(control_area);
-    let duration = track_entry.get_duration();
-    let ratio = elapsed_duration.as_secs_f64() / duration as f64;
-    let indicatif = LineGauge::default()
-      .filled_style(THEME.primary.add_modifier(Modifier::BOLD))
-      .line_set(symbols::line::THICK)
-      .label(format!(
-        "{} / {}",
-        format_duration(elapsed_duration),
-        format_duration(Duration::from_secs(duration)),
-      ))
-      .style(THEME.default_dark)
-      .ratio(if ratio > 1.0 {
-        1.0
-      } else if ratio < 0.0 || ratio.is_nan() {
-        0.0
-      } else {
-        ratio
-      });
-    frame.render_widget(indicatif, second_line);
+      let [_not_used_, second_line] = Layout::default()
+        .direction(Direction::Vertical)
+        .margin(1)
+        .horizontal_margin(2)
+        .constraints(vec![Constraint::Length(2), Constraint::Length(1)])
+        .areas(control_area);
+      frame.render_widget(indicatif, second_line);
+    }
+  }
 
-    if app.panel == Panel::Help {
-      render_help_panel(area, frame);
+  if app.panel == Panel::Help {
+    render_help_panel(area, frame, &app.help_filter, &mut app.help_scroll);
+  }
+  if app.panel == Panel::Actions {
+    if let Some(menu) = app.action_menu.as_mut() {
+      render_action_menu(area, frame, menu);
+    }
+  }
+  if app.panel == Panel::Filters {
+    if let Some(panel) = app.facet_panel.as_mut() {
+      render_facet_panel(area, frame, panel, &app.facets);
+    }
+  }
+  if app.panel == Panel::Unlock {
+    render_pin_prompt(area, frame, app.pin_input.chars().count());
+  }
+  if app.panel == Panel::Comment {
+    if let Some(editor) = &app.comment_editor {
+      render_comment_editor(area, frame, editor);
     }
-    Ok(())
   }
+  if app.panel == Panel::Playlist {
+    if let Some(editor) = &app.playlist_editor {
+      render_playlist_editor(area, frame, editor);
+    }
+  }
+  if app.panel == Panel::Suggestions {
+    if let Some(panel) = app.suggestion_panel.as_mut() {
+      render_suggestion_panel(area, frame, panel);
+    }
+  }
+  if app.panel == Panel::Logs {
+    render_logs_panel(area, frame, app.logs_scroll);
+  }
+  if app.panel == Panel::Details {
+    if let Some(entry) = &app.details {
+      let error = app.playback_errors.get(&entry.get_location());
+      render_details_panel(
+        area,
+        frame,
+        entry,
+        error.map(String::as_str),
+        app.details_scroll,
+      );
+    }
+  }
+  Ok(())
+}
+
+/// Progress of the played track.
+fn progress_gauge<'a>(elapsed_duration: Duration, duration: u64) -> LineGauge<'a> {
+  let ratio = elapsed_duration.as_secs_f64() / duration as f64;
+  LineGauge::default()
+    .filled_style(theme().primary.add_modifier(Modifier::BOLD))
+    .line_set(symbols::line::THICK)
+    .label(format!(
+      "{} / {}",
+      elapsed_duration.format_compact(),
+      Duration::from_secs(duration).format_compact(),
+    ))
+    .style(theme().default_dark)
+    .ratio(if ratio > 1.0 {
+      1.0
+    } else if ratio < 0.0 || ratio.is_nan() {
+      0.0
+    } else {
+      ratio
+    })
+}
+
+/// Scrollbar on the right border of the track table.
+#[instrument(skip(frame, app))]
+fn render_scrollbar(frame: &mut Frame<'_>, table_area: Rect, app: &Ui<'_>) {
+  let scrollbar = Scrollbar::new(ScrollbarOrientation::VerticalRight)
+    .begin_symbol(None)
+    .end_symbol(None)
+    .style(theme().border);
+  let mut scrollbar_state = ScrollbarState::new(app.row_len)
+    .viewport_content_length(app.table_page)
+    .position(app.table_state.selected().unwrap_or_default());
+  frame.render_stateful_widget(
+    scrollbar,
+    table_area.inner(Margin {
+      vertical: 1,
+      horizontal: 0,
+    }),
+    &mut scrollbar_state,
+  );
+}
+
+/// Width of the tabs: each title is padded by a space on both sides and
+/// separated by a divider.
+fn tabs_width() -> u16 {
+  [tr().music, tr().podcasts, tr().queue]
+    .iter()
+    .map(|title| title.chars().count() as u16 + 3)
+    .sum::<u16>()
+    - 1
 }
 
 #[instrument]
 fn render_tabs(frame: &mut Frame<'_>, tabs_area: Rect, selected_tab: TabSelection) {
-  let music = vec![
-    Span::styled("M", THEME.default_dark.add_modifier(Modifier::UNDERLINED)),
-    Span::raw("usic"),
-  ];
-  let podcasts = vec![
-    Span::styled("P", THEME.default_dark.add_modifier(Modifier::UNDERLINED)),
-    Span::raw("odcats"),
-  ];
-  let queue = vec![
-    Span::styled("Q", THEME.default_dark.add_modifier(Modifier::UNDERLINED)),
-    Span::raw("ueue"),
-  ];
-
-  let tabs = Tabs::new(vec![music, podcasts, queue])
-    .style(THEME.default_dark)
-    .highlight_style(THEME.selected)
-    .select(selected_tab as usize);
+  let tabs = Tabs::new(vec![
+    Line::from(shortcut_label(tr().music, 'm')),
+    Line::from(shortcut_label(tr().podcasts, 'p')),
+    Line::from(shortcut_label(tr().queue, 'q')),
+  ])
+  .style(theme().default_dark)
+  .highlight_style(theme().selected)
+  .select(selected_tab as usize);
   frame.render_widget(tabs, tabs_area);
 }
 
 #[instrument]
 fn render_shuffle(frame: &mut Frame<'_>, area: Rect, selected: Shuffle) {
   let widget = Paragraph::new(match selected {
-    Shuffle::Next => "⇶",
-    Shuffle::Shuffle => "🔀",
-    Shuffle::ShuffleLastPlayed => "🎜",
+    Shuffle::Next => glyphs().shuffle_next,
+    Shuffle::Shuffle => glyphs().shuffle,
+    Shuffle::ShuffleLastPlayed => glyphs().shuffle_last_played,
   })
-  .style(THEME.default_dark);
+  .style(theme().default_dark);
 
   frame.render_widget(widget, area);
 }
@@ -189,229 +402,618 @@ fn render_shuffle(frame: &mut Frame<'_>, area: Rect, selected: Shuffle) {
 #[instrument]
 fn render_repeat(frame: &mut Frame<'_>, area: Rect, selected: Repeat) {
   let widget = Paragraph::new(match selected {
-    Repeat::AllTracks => "🔁",
-    Repeat::CurrentTrack => "🔂",
+    Repeat::AllTracks => glyphs().repeat_all,
+    Repeat::CurrentTrack => glyphs().repeat_current,
   })
-  .style(THEME.default_dark);
+  .style(theme().default_dark);
   frame.render_widget(widget, area);
 }
 
+/// What sets rows apart: the playing track, the positions in the queue and
+/// the marked rows.
+#[derive(Debug)]
+pub(crate) struct RowMarks<'a> {
+  pub(crate) current_track: &'a Option<SharedEntry>,
+  pub(crate) queue: &'a Playlist,
+  pub(crate) marked: &'a HashSet<Url>,
+  // An album in its order: a separator starts each disc
+  pub(crate) discs: bool,
+}
+
 #[instrument(skip(entries))]
 pub(crate) fn render_table<'a>(
   entries: &[SharedEntry],
   order_by: Order,
   order_dir: OrderDir,
-  current_track: &Option<SharedEntry>,
+  marks: RowMarks<'_>,
   selected_tab: TabSelection,
+  last_played_format: DateFormat,
+  widths: [u16; COLUMNS],
 ) -> (usize, Table<'a>, Option<usize>) {
   use ratatui::widgets::Row;
 
+  // Marker of the playing track and badge of the tracks in the queue: the
+  // play order stays visible in the sorted queue tab
+  let title = |entry: &Entry, playing: bool| {
+    let title = match marks.queue.position(&entry.get_location()) {
+      Some(position) => format!("{}{position} {}", glyphs().queued, entry.get_title()),
+      None => entry.get_title().to_string(),
+    };
+    if playing {
+      format!("{} {title}", glyphs().playing)
+    } else {
+      title
+    }
+  };
+
+  // The discs are set apart when the album has several
+  let discs: HashSet<Option<u64>> = entries.iter().map(|entry| entry.get_disc()).collect();
+  let split_discs = marks.discs && discs.len() > 1;
+
   let mut current_index = None;
   let rows: Vec<Row> = entries
     .iter()
     .enumerate()
     .map(|(index, entry)| {
-      Row::new(match (entry.as_ref(), selected_tab) {
+      let playing = is_playing(entry, marks.current_track);
+      if playing {
+        current_index = Some(index);
+      }
+      let marked = marks.marked.contains(&entry.get_location());
+      let cells = match (entry.as_ref(), selected_tab) {
         (Entry::Iradio(_), _) => todo!(),
         (Entry::Ignore(_), _) => unimplemented!(),
         (Entry::PodcastFeed(_), _) => todo!(),
         (Entry::Song(s), _) => {
-          if let Some(ct) = &current_track {
-            if let Entry::Song(current_track) = ct.as_ref() {
-              if s._internal_id == current_track._internal_id {
-                current_index = Some(index);
-              }
-            }
-          }
           vec![
-            s.title.to_owned(),
+            title(entry, playing),
             s.artist.to_owned(),
+            s.composer.to_owned(),
             s.album.to_owned(),
-            format_duration(Duration::from_secs(s.duration.unwrap_or_default())).to_string(),
+            entry.get_work().unwrap_or_default().to_string(),
+            entry
+              .get_year()
+              .map(|year| year.to_string())
+              .unwrap_or_default(),
+            comment_cell(entry.get_comment()),
+            Duration::from_secs(s.duration.unwrap_or_default()).format_compact(),
+            s.bpm().map(|bpm| format!("{bpm:.0}")).unwrap_or_default(),
             rating(s.rating),
-            if let Some(lp) = s.last_played {
-              DateTime::from_timestamp(lp as i64, 0)
-                .unwrap_or_default()
-                .format_from_now()
-            } else {
-              "-".to_string()
-            },
+            loved_cell(entry),
+            format_last_played(s.last_played, last_played_format),
           ]
         }
         (Entry::PodcastPost(p), TabSelection::Podcast) => {
-          if let Some(ct) = &current_track {
-            if let Entry::PodcastPost(current_track) = ct.as_ref() {
-              if p._internal_id == current_track._internal_id {
-                current_index = Some(index);
-              }
-            }
-          }
           vec![
-            DateTime::from_timestamp(p.post_time.unwrap_or_default() as i64, 0)
-              .unwrap_or_default()
-              .format_from_now()
-              .to_string(),
-            p.title.to_owned(),
-            p.album.to_owned(),
-            format_duration(Duration::from_secs(p.duration.unwrap_or_default())).to_string(),
-            rating(p.rating),
-            if let Some(lp) = p.last_played {
-              DateTime::from_timestamp(lp as i64, 0)
-                .unwrap_or_default()
-                .format_from_now()
-                .to_string()
+            format_date(
+              DateTime::from_timestamp(p.post_time.unwrap_or_default() as i64, 0).unwrap_or_default(),
+            ),
+            if is_played(p) {
+              format!("{} {}", glyphs().played, title(entry, playing))
             } else {
-              "-".to_string()
+              title(entry, playing)
             },
+            String::new(),
+            p.album.to_owned(),
+            String::new(),
+            String::new(),
+            comment_cell(entry.get_comment()),
+            Duration::from_secs(p.duration.unwrap_or_default()).format_compact(),
+            String::new(),
+            rating(p.rating),
+            loved_cell(entry),
+            format_last_played(p.last_played, last_played_format),
           ]
         }
         (Entry::PodcastPost(p), _) => {
-          if let Some(ct) = &current_track {
-            if let Entry::PodcastPost(current_track) = ct.as_ref() {
-              if p._internal_id == current_track._internal_id {
-                current_index = Some(index);
-              }
-            }
-          }
           vec![
-            p.title.to_owned(),
+            title(entry, playing),
             p.artist.to_owned(),
+            String::new(),
             p.album.to_owned(),
-            format_duration(Duration::from_secs(p.duration.unwrap_or_default())).to_string(),
+            String::new(),
+            entry
+              .get_year()
+              .map(|year| year.to_string())
+              .unwrap_or_default(),
+            comment_cell(entry.get_comment()),
+            Duration::from_secs(p.duration.unwrap_or_default()).format_compact(),
+            String::new(),
             rating(p.rating),
-            if let Some(lp) = p.last_played {
-              DateTime::from_timestamp(lp as i64, 0)
-                .unwrap_or_default()
-                .format_from_now()
-                .to_string()
-            } else {
-              "-".to_string()
-            },
+            loved_cell(entry),
+            format_last_played(p.last_played, last_played_format),
           ]
         }
+      };
+      let cells = cells
+        .into_iter()
+        .zip(widths)
+        .map(|(cell, width)| truncate(cell, width));
+      let disc = entry.get_disc();
+      let first_of_disc = index == 0 || entries[index - 1].get_disc() != disc;
+      let row = if split_discs && first_of_disc {
+        // The separator is a line above the first track of the disc, the
+        // rows stay the tracks
+        let separator = disc.map(|disc| fill(tr().disc, disc)).unwrap_or_default();
+        let mut separators = std::iter::once(separator).chain(std::iter::repeat(String::new()));
+        Row::new(cells.map(|cell| {
+          Cell::from(Text::from(vec![
+            Line::styled(separators.next().unwrap_or_default(), theme().default_dark),
+            Line::from(cell),
+          ]))
+        }))
+        .height(2)
+      } else {
+        Row::new(cells)
+      };
+      row.style(match (entry.as_ref(), selected_tab) {
+        _ if playing => theme().primary.bold(),
+        _ if marked => theme().secondary.bold(),
+        // New episodes stand out from the played ones
+        (Entry::PodcastPost(p), TabSelection::Podcast) if is_played(p) => theme().default_dark,
+        _ => theme().default,
       })
-      .style(THEME.default)
     })
     .collect();
 
-  let widths = match selected_tab {
+  let rows_len = rows.len();
+  let table = Table::default()
+    .rows(rows)
+    .widths(table_widths(selected_tab, false, false, false))
+    .column_spacing(1)
+    .header(
+      Row::new(column_orders(selected_tab).into_iter().enumerate().map(
+        |(index, column)| match index {
+          COMMENT_COLUMN => Cell::from(tr().comment),
+          LOVED_COLUMN => Cell::from(glyphs().loved),
+          _ => sortable_header(selected_tab, column, order_by, order_dir),
+        },
+      ))
+      .style(theme().default_dark.bold()),
+    )
+    .block(
+      Block::default()
+        .borders(Borders::ALL)
+        .border_type(BorderType::Rounded)
+        .style(theme().border)
+        .title_bottom(Line::from(tr().tracks(rows_len)).right_aligned())
+        .title_bottom(if marks.marked.is_empty() {
+          Line::default()
+        } else {
+          Line::from(fill(tr().marked, marks.marked.len())).style(theme().secondary)
+        })
+        .title_bottom(
+          if selected_tab == TabSelection::Queue && order_by != Order::Default {
+            Line::from(tr().queue_sorted).style(theme().secondary)
+          } else {
+            Line::default()
+          },
+        ),
+    )
+    .highlight_style(theme().selected)
+    .highlight_symbol(">>");
+  (rows_len, table, current_index)
+}
+
+/// Widths the cells of the table are cut to: the ones of the columns in the
+/// last rendering, capped by the `[truncate]` settings. 0 before the first
+/// rendering, for no limit.
+pub(super) fn cell_widths(app: &Ui) -> [u16; COLUMNS] {
+  let limits = app.truncate;
+  let orders = column_orders(app.selected_tab);
+  std::array::from_fn(|index| {
+    let limit = match orders[index] {
+      _ if index == COMMENT_COLUMN => limits.comment,
+      Order::Title => limits.title,
+      Order::Artist => limits.artist,
+      Order::Composer => limits.composer,
+      Order::Album => limits.album,
+      Order::Work => limits.work,
+      _ => None,
+    };
+    let width = app.header_areas[index].width;
+    match limit {
+      Some(limit) if width == 0 || limit < width => limit,
+      _ => width,
+    }
+  })
+}
+
+/// Cut the text to the width in terminal cells, ending with an ellipsis: the
+/// wide chars take two cells and the combining ones none. No limit at 0.
+fn truncate(text: String, width: u16) -> String {
+  let width = width as usize;
+  if width == 0 || text.width() <= width {
+    return text;
+  }
+  let ellipsis = glyphs().ellipsis;
+  let available = width.saturating_sub(ellipsis.width());
+  let (mut used, mut end) = (0, 0);
+  for (index, c) in text.char_indices() {
+    used += c.width().unwrap_or_default();
+    if used > available {
+      break;
+    }
+    end = index + c.len_utf8();
+  }
+  format!("{}{ellipsis}", &text[..end])
+}
+
+/// Heart of the loved tracks, for the loved column.
+fn loved_cell(entry: &Entry) -> String {
+  if flags::is_loved(&entry.get_location()) {
+    glyphs().loved.to_string()
+  } else {
+    String::new()
+  }
+}
+
+/// The entry is the track being played.
+fn is_playing(entry: &Entry, current_track: &Option<SharedEntry>) -> bool {
+  match (entry, current_track.as_deref()) {
+    (Entry::Song(song), Some(Entry::Song(current))) => song._internal_id == current._internal_id,
+    (Entry::PodcastPost(post), Some(Entry::PodcastPost(current))) => {
+      post._internal_id == current._internal_id
+    }
+    _ => false,
+  }
+}
+
+fn format_last_played(last_played: Option<u64>, format: DateFormat) -> String {
+  let Some(last_played) = last_played else {
+    return "-".to_string();
+  };
+  let date = DateTime::from_timestamp(last_played as i64, 0).unwrap_or_default();
+  match format {
+    DateFormat::Absolute => format_date(date),
+    DateFormat::Relative => date.format_relative(),
+  }
+}
+
+/// The episode has been played at least once.
+fn is_played(podcast: &PodcastPostentry) -> bool {
+  podcast.play_count.unwrap_or_default() > 0
+}
+
+/// Label with the shortcut key underlined.
+fn shortcut_label(label: &str, key: char) -> Vec<Span<'static>> {
+  match label
+    .char_indices()
+    .find(|(_, c)| c.to_lowercase().eq(key.to_lowercase()))
+  {
+    Some((index, c)) => vec![
+      Span::raw(label[..index].to_string()),
+      Span::raw(c.to_string()).add_modifier(Modifier::UNDERLINED),
+      Span::raw(label[index + c.len_utf8()..].to_string()),
+    ],
+    None => vec![Span::raw(label.to_string())],
+  }
+}
+
+pub(super) const COLUMNS: usize = 12;
+// Index of the comment column, hidden by default
+const COMMENT_COLUMN: usize = 6;
+// Index of the column of the loved tracks
+const LOVED_COLUMN: usize = 10;
+
+/// Order of each column of the tab. The comment and the loved columns are
+/// not sorted. The episodes have their date instead of a year, and no
+/// composer, work or tempo: these columns are hidden, without order.
+pub(super) fn column_orders(selected_tab: TabSelection) -> [Order; COLUMNS] {
+  match selected_tab {
     TabSelection::Podcast => [
+      Order::Date,
+      Order::Title,
+      Order::Default,
+      Order::Album,
+      Order::Default,
+      Order::Default,
+      Order::Default,
+      Order::Duration,
+      Order::Default,
+      Order::Rating,
+      Order::Default,
+      Order::LastPlayed,
+    ],
+    _ => [
+      Order::Title,
+      Order::Artist,
+      Order::Composer,
+      Order::Album,
+      Order::Work,
+      Order::Year,
+      Order::Default,
+      Order::Duration,
+      Order::Bpm,
+      Order::Rating,
+      Order::Default,
+      Order::LastPlayed,
+    ],
+  }
+}
+
+/// Header of a column, with its shortcut and the order direction when the
+/// table is ordered by this column.
+fn sortable_header(
+  selected_tab: TabSelection,
+  column: Order,
+  order_by: Order,
+  order_dir: OrderDir,
+) -> Cell<'static> {
+  let label = match (column, selected_tab) {
+    (Order::Album, TabSelection::Podcast) => tr().feed,
+    (Order::Title, _) => tr().title,
+    (Order::Artist, _) => tr().artist,
+    (Order::Album, _) => tr().album,
+    (Order::Date, _) => tr().date,
+    (Order::Duration, _) => tr().duration,
+    (Order::Rating, _) => tr().rating,
+    (Order::LastPlayed, _) => tr().last_played,
+    (Order::Bpm, _) => tr().bpm,
+    (Order::Year, _) => tr().year,
+    (Order::Composer, _) => tr().composer,
+    (Order::Work, _) => tr().work,
+    (Order::Default, _) => "",
+  };
+  let mut spans = match column {
+    Order::Title => shortcut_label(label, 't'),
+    Order::Date => shortcut_label(label, 'd'),
+    Order::Rating => shortcut_label(label, 'r'),
+    Order::LastPlayed => shortcut_label(label, 'l'),
+    _ => vec![Span::raw(label)],
+  };
+  if order_by == column {
+    spans.push(Span::raw(format!(
+      " {}",
+      match order_dir {
+        OrderDir::Asc => glyphs().sort_asc,
+        OrderDir::Desc => glyphs().sort_desc,
+      }
+    )));
+  }
+  Cell::from(Line::from(spans))
+}
+
+/// Areas of the column headers: the first line inside the borders, after the
+/// highlight symbol.
+fn header_areas(table_area: Rect, widths: [Constraint; COLUMNS]) -> [Rect; COLUMNS] {
+  let header = Rect {
+    x: table_area.x + 3,
+    y: table_area.y + 1,
+    width: table_area.width.saturating_sub(4),
+    height: 1,
+  };
+  Layout::horizontal(widths).spacing(1).areas(header)
+}
+
+/// Widths of the table columns. The compact layout only keeps the title, the
+/// artist (or the feed) and the rating.
+fn table_widths(
+  selected_tab: TabSelection,
+  compact: bool,
+  comments: bool,
+  classical: bool,
+) -> [Constraint; COLUMNS] {
+  let comment = if comments && !compact {
+    Constraint::Fill(2)
+  } else {
+    Constraint::Length(0)
+  };
+  let (composer, work) = if classical && !compact {
+    (Constraint::Fill(2), Constraint::Fill(2))
+  } else {
+    (Constraint::Length(0), Constraint::Length(0))
+  };
+  match (selected_tab, compact) {
+    (TabSelection::Podcast, false) => [
       Constraint::Length(14),
       Constraint::Fill(3),
+      Constraint::Length(0),
       Constraint::Fill(1),
+      Constraint::Length(0),
+      Constraint::Length(0),
+      comment,
       Constraint::Length(6),
+      Constraint::Length(0),
       Constraint::Length(6),
+      Constraint::Length(2),
       Constraint::Length(14),
     ],
-    _ => [
+    (TabSelection::Podcast, true) => [
+      Constraint::Length(0),
+      Constraint::Fill(3),
+      Constraint::Length(0),
+      Constraint::Fill(1),
+      Constraint::Length(0),
+      Constraint::Length(0),
+      comment,
+      Constraint::Length(0),
+      Constraint::Length(0),
+      Constraint::Length(6),
+      Constraint::Length(0),
+      Constraint::Length(0),
+    ],
+    (_, false) => [
       Constraint::Fill(3),
       Constraint::Fill(2),
+      composer,
       Constraint::Fill(1),
+      work,
+      Constraint::Length(6),
+      comment,
       Constraint::Length(6),
+      Constraint::Length(5),
       Constraint::Length(6),
+      Constraint::Length(2),
       Constraint::Length(14),
     ],
+    (_, true) => [
+      Constraint::Fill(3),
+      Constraint::Fill(2),
+      composer,
+      Constraint::Length(0),
+      work,
+      Constraint::Length(0),
+      comment,
+      Constraint::Length(0),
+      Constraint::Length(0),
+      Constraint::Length(6),
+      Constraint::Length(0),
+      Constraint::Length(0),
+    ],
+  }
+}
+
+#[instrument]
+pub(crate) fn rating(rating: Option<Rating>) -> String {
+  let rating = rating.unwrap_or_default();
+  let stars = rating.stars() as usize;
+  let half = if rating.has_half() {
+    glyphs().star_half
+  } else {
+    ""
   };
+  let empty = 5 - stars - usize::from(rating.has_half());
+  glyphs().star.repeat(stars) + half + &glyphs().star_empty.repeat(empty)
+}
 
-  let rows_len = rows.len();
-  let table = Table::default()
-    .rows(rows)
-    .widths(widths)
-    .column_spacing(1)
-    .header(
-      Row::new(match selected_tab {
-        TabSelection::Podcast => vec![
-          "Date".into(),
-          Cell::from(Line::from(vec![
-            Span::raw("T").add_modifier(Modifier::UNDERLINED),
-            Span::raw("itle"),
-            match (order_by, order_dir) {
-              (Order::Title, OrderDir::Asc) => Span::raw(" ⏶"),
-              (Order::Title, OrderDir::Desc) => Span::raw(" ⏷"),
-              _ => Span::raw(""),
-            },
-          ])),
-          "Feed".into(),
-          "Duration".into(),
-          Cell::from(Line::from(vec![
-            Span::raw("R").add_modifier(Modifier::UNDERLINED),
-            Span::raw("ating"),
-            match (order_by, order_dir) {
-              (Order::Rating, OrderDir::Asc) => Span::raw(" ⏶"),
-              (Order::Rating, OrderDir::Desc) => Span::raw(" ⏷"),
-              _ => Span::raw(""),
-            },
-          ])),
-          Cell::from(Line::from(vec![
-            Span::raw("L").add_modifier(Modifier::UNDERLINED),
-            Span::raw("ast Played"),
-            match (order_by, order_dir) {
-              (Order::LastPlayed, OrderDir::Asc) => Span::raw(" ⏶"),
-              (Order::LastPlayed, OrderDir::Desc) => Span::raw(" ⏷"),
-              _ => Span::raw(""),
-            },
-          ])),
-        ],
-
-        _ => vec![
-          Cell::from(Line::from(vec![
-            Span::raw("T").add_modifier(Modifier::UNDERLINED),
-            Span::raw("itle"),
-            match (order_by, order_dir) {
-              (Order::Title, OrderDir::Asc) => Span::raw(" ⏶"),
-              (Order::Title, OrderDir::Desc) => Span::raw(" ⏷"),
-              _ => Span::raw(""),
-            },
-          ])),
-          "Artist".into(),
-          "Album".into(),
-          "Duration".into(),
-          Cell::from(Line::from(vec![
-            Span::raw("R").add_modifier(Modifier::UNDERLINED),
-            Span::raw("ating"),
-            match (order_by, order_dir) {
-              (Order::Rating, OrderDir::Asc) => Span::raw(" ⏶"),
-              (Order::Rating, OrderDir::Desc) => Span::raw(" ⏷"),
-              _ => Span::raw(""),
-            },
-          ])),
-          Cell::from(Line::from(vec![
-            Span::raw("L").add_modifier(Modifier::UNDERLINED),
-            Span::raw("ast Played"),
-            match (order_by, order_dir) {
-              (Order::LastPlayed, OrderDir::Asc) => Span::raw(" ⏶"),
-              (Order::LastPlayed, OrderDir::Desc) => Span::raw(" ⏷"),
-              _ => Span::raw(""),
-            },
-          ])),
-        ],
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::{
+    rhythmdb::{EntryList, Rhythmdb},
+    settings::LayoutSetting,
+    ui::{actions::ActionMenu, Order, OrderDir, Panel},
+  };
+  use insta::assert_snapshot;
+  use ratatui::{backend::TestBackend, Terminal};
+  use std::sync::Arc;
+
+  /// The songs of the fixture, by title.
+  fn songs() -> EntryList {
+    Rhythmdb::fixture().filter_by_song("", Order::Title, OrderDir::Asc)
+  }
+
+  fn find(entries: &[SharedEntry], title: &str) -> SharedEntry {
+    entries
+      .iter()
+      .find(|entry| entry.get_title() == title)
+      .cloned()
+      .expect("Song of the fixture")
+  }
+
+  /// Draw the ui like the event loop: once to lay out the columns, then
+  /// again with the cells cut to their widths. So What is queued.
+  fn draw(
+    app: &mut Ui<'_>,
+    entries: &[SharedEntry],
+    playing: Option<&str>,
+    discs: bool,
+    width: u16,
+    height: u16,
+  ) -> TestBackend {
+    let playing = playing.map(|title| find(&songs(), title));
+    let mut queue = Playlist::new();
+    queue.enqueue(find(&songs(), "So What").get_location(), false);
+    let playback = Playback {
+      track: playing.as_deref(),
+      elapsed: Duration::from_secs(62),
+      shuffle_mode: Shuffle::Shuffle,
+      repeat_mode: Repeat::AllTracks,
+      scope: ScopeKind::Library,
+    };
+
+    let mut terminal = Terminal::new(TestBackend::new(width, height)).expect("Test terminal");
+    for _ in 0..2 {
+      let widths = cell_widths(app);
+      let (row_len, table, _) = render_table(
+        entries,
+        app.order_by,
+        app.order_dir,
+        RowMarks {
+          current_track: &playing,
+          queue: &queue,
+          marked: &app.marked,
+          discs,
+        },
+        app.selected_tab,
+        app.last_played_format,
+        widths,
+      );
+      app.table = table;
+      app.row_len = row_len;
+      app.cell_widths = widths;
+      terminal
+        .draw(|frame| render_ui(frame, app, &playback).expect("Rendered ui"))
+        .expect("Drawn ui");
+    }
+    terminal.backend().clone()
+  }
+
+  #[test]
+  fn library() {
+    let mut app = Ui::new(0, LayoutSetting::default());
+    (app.order_by, app.order_dir) = (Order::Title, OrderDir::Asc);
+
+    assert_snapshot!(draw(&mut app, &songs(), Some("Teardrop"), false, 120, 16));
+  }
+
+  #[test]
+  fn compact_library() {
+    let mut app = Ui::new(1, LayoutSetting::default());
+
+    assert_snapshot!(draw(&mut app, &songs(), Some("Teardrop"), false, 70, 12));
+  }
+
+  #[test]
+  fn album_discs() {
+    let mut app = Ui::new(0, LayoutSetting::default());
+    let album: EntryList = songs()
+      .iter()
+      .filter_map(|entry| match entry.as_ref() {
+        Entry::Song(song) if song.album == "Kind of Blue" => {
+          let mut song = song.clone();
+          song.disc_number = Some(if song.title == "So What" { 1 } else { 2 });
+          Some(Arc::new(Entry::Song(song)))
+        }
+        _ => None,
       })
-      .style(THEME.default_dark.bold()),
-    )
-    .block(
-      Block::default()
-        .borders(Borders::ALL)
-        .border_type(BorderType::Rounded)
-        .style(THEME.border)
-        .title_bottom(
-          Line::from(pluralizer::pluralize("track", rows_len as isize, true)).right_aligned(),
-        ),
-    )
-    .highlight_style(THEME.selected)
-    .highlight_symbol(">>");
-  (rows_len, table, current_index)
-}
+      .rev()
+      .collect();
 
-#[instrument]
-fn rating(rating: Option<u64>) -> String {
-  match rating {
-    Some(5) => "★★★★★",
-    Some(4) => "★★★★☆",
-    Some(3) => "★★★☆☆",
-    Some(2) => "★★☆☆☆",
-    Some(1) => "★☆☆☆☆",
-    Some(_) => "☆☆☆☆☆",
-    None => "☆☆☆☆☆",
-  }
-  .into()
+    assert_snapshot!(draw(&mut app, &album, Some("Teardrop"), true, 120, 16));
+  }
+
+  #[test]
+  fn delete_confirmation() {
+    let mut app = Ui::new(0, LayoutSetting::default());
+    let entries = songs();
+    let mut menu = ActionMenu::new(find(&entries, "Angel"), 0, false, false);
+    menu.open_deleting(true);
+    app.action_menu = Some(menu);
+    app.panel = Panel::Actions;
+
+    assert_snapshot!(draw(&mut app, &entries, Some("Teardrop"), false, 120, 16));
+  }
+
+  #[test]
+  fn nothing_playing() {
+    let mut app = Ui::new(0, LayoutSetting::default());
+
+    assert_snapshot!(draw(&mut app, &songs(), None, false, 120, 24));
+  }
+
+  #[test]
+  fn compact_nothing_playing() {
+    let mut app = Ui::new(0, LayoutSetting::default());
+
+    assert_snapshot!(draw(&mut app, &songs(), None, false, 70, 12));
+  }
+
+  #[test]
+  fn terminal_too_small() {
+    let mut app = Ui::new(0, LayoutSetting::default());
+
+    assert_snapshot!(draw(&mut app, &songs(), Some("Teardrop"), false, 20, 5));
+  }
 }
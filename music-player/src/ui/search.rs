@@ -0,0 +1,109 @@
+/// Text of the search box with its cursor. The cursor is a byte offset, always
+/// on a char boundary.
+#[derive(Debug, Default)]
+pub(crate) struct Search {
+  text: String,
+  cursor: usize,
+}
+
+impl Search {
+  pub(crate) fn text(&self) -> &str {
+    &self.text
+  }
+
+  pub(crate) fn is_empty(&self) -> bool {
+    self.text.is_empty()
+  }
+
+  /// The text split at the cursor.
+  pub(crate) fn split(&self) -> (&str, &str) {
+    self.text.split_at(self.cursor)
+  }
+
+  pub(crate) fn insert(&mut self, c: char) {
+    self.text.insert(self.cursor, c);
+    self.cursor += c.len_utf8();
+  }
+
+  /// Insert a pasted text. The line breaks are replaced by spaces.
+  pub(crate) fn insert_str(&mut self, text: &str) {
+    let text = text.replace(['\n', '\r'], " ");
+    self.text.insert_str(self.cursor, &text);
+    self.cursor += text.len();
+  }
+
+  /// Delete the char before the cursor.
+  pub(crate) fn backspace(&mut self) {
+    if let Some(c) = self.text[..self.cursor].chars().next_back() {
+      self.cursor -= c.len_utf8();
+      self.text.remove(self.cursor);
+    }
+  }
+
+  /// Delete the char under the cursor.
+  pub(crate) fn delete(&mut self) {
+    if self.cursor < self.text.len() {
+      self.text.remove(self.cursor);
+    }
+  }
+
+  pub(crate) fn left(&mut self) {
+    if let Some(c) = self.text[..self.cursor].chars().next_back() {
+      self.cursor -= c.len_utf8();
+    }
+  }
+
+  pub(crate) fn right(&mut self) {
+    if let Some(c) = self.text[self.cursor..].chars().next() {
+      self.cursor += c.len_utf8();
+    }
+  }
+
+  pub(crate) fn home(&mut self) {
+    self.cursor = 0;
+  }
+
+  pub(crate) fn end(&mut self) {
+    self.cursor = self.text.len();
+  }
+
+  /// Move to the start of the previous word.
+  pub(crate) fn word_left(&mut self) {
+    self.cursor = self.previous_word();
+  }
+
+  /// Move after the end of the next word.
+  pub(crate) fn word_right(&mut self) {
+    let rest = &self.text[self.cursor..];
+    let word_start = rest.len() - rest.trim_start().len();
+    let word_len = rest[word_start..]
+      .find(char::is_whitespace)
+      .unwrap_or(rest.len() - word_start);
+    self.cursor += word_start + word_len;
+  }
+
+  /// Delete the word before the cursor (ctrl-w).
+  pub(crate) fn delete_word(&mut self) {
+    let start = self.previous_word();
+    self.text.replace_range(start..self.cursor, "");
+    self.cursor = start;
+  }
+
+  /// Delete from the cursor to the end of the line (ctrl-k).
+  pub(crate) fn kill_to_end(&mut self) {
+    self.text.truncate(self.cursor);
+  }
+
+  pub(crate) fn clear(&mut self) {
+    self.text.clear();
+    self.cursor = 0;
+  }
+
+  fn previous_word(&self) -> usize {
+    let before = self.text[..self.cursor].trim_end();
+    before
+      .rfind(char::is_whitespace)
+      .map(|index| index + before[index..].chars().next().map_or(1, char::len_utf8))
+      .unwrap_or(0)
+  }
+}
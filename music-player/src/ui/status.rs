@@ -0,0 +1,48 @@
+use super::theme::theme;
+use ratatui::{prelude::Rect, widgets::Paragraph, Frame};
+use std::time::{Duration, Instant};
+use tracing::instrument;
+
+/// How long a message stays in the status bar.
+const STATUS_TIMEOUT: Duration = Duration::from_secs(5);
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub(crate) enum StatusLevel {
+  Info,
+  Warning,
+  Error,
+}
+
+#[derive(Debug)]
+pub(crate) struct StatusMessage {
+  text: String,
+  level: StatusLevel,
+  since: Instant,
+}
+
+impl StatusMessage {
+  pub(crate) fn new(level: StatusLevel, text: String) -> StatusMessage {
+    StatusMessage {
+      text,
+      level,
+      since: Instant::now(),
+    }
+  }
+
+  pub(crate) fn is_expired(&self) -> bool {
+    self.since.elapsed() > STATUS_TIMEOUT
+  }
+}
+
+#[instrument(skip(frame))]
+pub(crate) fn render_status_bar(area: Rect, frame: &mut Frame<'_>, status: Option<&StatusMessage>) {
+  let Some(status) = status else {
+    return;
+  };
+  let paragraph = Paragraph::new(status.text.as_str()).style(match status.level {
+    StatusLevel::Info => theme().secondary,
+    StatusLevel::Warning => theme().primary,
+    StatusLevel::Error => theme().error,
+  });
+  frame.render_widget(paragraph, area);
+}
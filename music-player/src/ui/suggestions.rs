@@ -0,0 +1,126 @@
+use super::{
+  i18n::{fill, tr},
+  rendering::rating,
+  theme::theme,
+};
+use crate::suggestions::Suggestion;
+use ratatui::{
+  layout::Flex,
+  prelude::{Constraint, Layout, Rect},
+  text::Line,
+  widgets::{Block, Borders, Clear, List, ListItem, ListState, Padding},
+  Frame,
+};
+use tracing::instrument;
+
+/// Review of the suggested ratings: ←/→ adjust the selected one, enter
+/// confirms it and delete dismisses it.
+#[derive(Debug)]
+pub(crate) struct SuggestionPanel {
+  suggestions: Vec<Suggestion>,
+  pub(crate) state: ListState,
+}
+
+impl SuggestionPanel {
+  pub(crate) fn new(suggestions: Vec<Suggestion>) -> SuggestionPanel {
+    SuggestionPanel {
+      suggestions,
+      state: ListState::default().with_selected(Some(0)),
+    }
+  }
+
+  pub(crate) fn is_empty(&self) -> bool {
+    self.suggestions.is_empty()
+  }
+
+  pub(crate) fn next(&mut self) {
+    let i = match self.state.selected() {
+      Some(i) if i + 1 < self.suggestions.len() => i + 1,
+      _ => 0,
+    };
+    self.state.select(Some(i));
+  }
+
+  pub(crate) fn previous(&mut self) {
+    let i = match self.state.selected() {
+      Some(0) | None => self.suggestions.len().saturating_sub(1),
+      Some(i) => i - 1,
+    };
+    self.state.select(Some(i));
+  }
+
+  /// Raise or lower the selected rating by a step.
+  pub(crate) fn adjust(&mut self, up: bool, half_stars: bool) {
+    let Some(suggestion) = self
+      .state
+      .selected()
+      .and_then(|i| self.suggestions.get_mut(i))
+    else {
+      return;
+    };
+    suggestion.rating = if up {
+      suggestion.rating.raised(half_stars)
+    } else {
+      suggestion.rating.lowered(half_stars)
+    };
+  }
+
+  /// Remove the selected suggestion from the list, to confirm or dismiss it.
+  pub(crate) fn take_selected(&mut self) -> Option<Suggestion> {
+    let i = self.state.selected()?;
+    if i >= self.suggestions.len() {
+      return None;
+    }
+    let suggestion = self.suggestions.remove(i);
+    if i >= self.suggestions.len() {
+      self.state.select(Some(i.saturating_sub(1)));
+    }
+    Some(suggestion)
+  }
+}
+
+#[instrument(skip(frame, panel))]
+pub(crate) fn render_suggestion_panel(
+  area: Rect,
+  frame: &mut Frame<'_>,
+  panel: &mut SuggestionPanel,
+) {
+  let [panel_area] = Layout::vertical([Constraint::Percentage(60)])
+    .flex(Flex::Center)
+    .areas(area);
+  let [panel_area] = Layout::horizontal([Constraint::Length(80)])
+    .flex(Flex::Center)
+    .areas(panel_area);
+
+  let items: Vec<ListItem> = panel
+    .suggestions
+    .iter()
+    .map(|suggestion| {
+      ListItem::new(format!(
+        "{}  {} – {}  ({})",
+        rating(Some(suggestion.rating)),
+        suggestion.entry.get_title(),
+        suggestion.entry.get_artist(),
+        fill(
+          &fill(tr().suggestion_counts, suggestion.plays),
+          suggestion.skips
+        )
+      ))
+    })
+    .collect();
+  let list = List::new(items)
+    .style(theme().default)
+    .highlight_style(theme().selected)
+    .highlight_symbol(">>")
+    .block(
+      Block::default()
+        .style(theme().border)
+        .padding(Padding::horizontal(1))
+        .borders(Borders::ALL)
+        .title(tr().suggestions)
+        .title_bottom(Line::from(tr().suggestions_hint).right_aligned()),
+    );
+
+  frame.render_widget(Clear, panel_area);
+  frame.render_stateful_widget(list, panel_area, &mut panel.state);
+}
@@ -0,0 +1,96 @@
+use crate::settings::{ThemePreset, ThemeSettings};
+use miette::{miette, Result};
+use ratatui::prelude::{Color, Style};
+use std::{
+  str::FromStr,
+  sync::{PoisonError, RwLock},
+};
+use tracing::instrument;
+
+static THEME: RwLock<Theme> = RwLock::new(DARK);
+
+#[derive(Clone, Copy, Debug)]
+pub(crate) struct Theme {
+  pub(crate) default: Style,
+  pub(crate) default_dark: Style,
+  pub(crate) primary: Style,
+  pub(crate) secondary: Style,
+  pub(crate) border: Style,
+  pub(crate) border_selected: Style,
+  pub(crate) selected: Style,
+  pub(crate) help_key: Style,
+  pub(crate) error: Style,
+}
+
+const DARK: Theme = Theme {
+  default: Style::reset(), //.fg(Color::White),
+  default_dark: Style::new().fg(Color::DarkGray),
+  primary: Style::new().fg(Color::Magenta),
+  secondary: Style::new().fg(Color::Rgb(192, 64, 192)),
+  border: Style::new().fg(Color::Rgb(128, 0, 128)),
+  border_selected: Style::new().fg(Color::LightCyan),
+  selected: Style::new().fg(Color::Magenta),
+  help_key: Style::new().fg(Color::Green),
+  error: Style::new().fg(Color::LightRed),
+};
+
+const LIGHT: Theme = Theme {
+  default: Style::reset(),
+  default_dark: Style::new().fg(Color::Gray),
+  primary: Style::new().fg(Color::Rgb(128, 0, 128)),
+  secondary: Style::new().fg(Color::Rgb(160, 32, 160)),
+  border: Style::new().fg(Color::Rgb(96, 0, 96)),
+  border_selected: Style::new().fg(Color::Blue),
+  selected: Style::new().fg(Color::Rgb(128, 0, 128)),
+  help_key: Style::new().fg(Color::Blue),
+  error: Style::new().fg(Color::Red),
+};
+
+const HIGH_CONTRAST: Theme = Theme {
+  default: Style::new().fg(Color::White),
+  default_dark: Style::new().fg(Color::White),
+  primary: Style::new().fg(Color::Yellow),
+  secondary: Style::new().fg(Color::LightYellow),
+  border: Style::new().fg(Color::White),
+  border_selected: Style::new().fg(Color::LightCyan),
+  selected: Style::new().fg(Color::Black).bg(Color::Yellow),
+  help_key: Style::new().fg(Color::LightCyan),
+  error: Style::new().fg(Color::White).bg(Color::Red),
+};
+
+/// The theme used to render the ui. Fallback to the dark preset when
+/// `init_theme` has not been called.
+pub(crate) fn theme() -> Theme {
+  *THEME.read().unwrap_or_else(PoisonError::into_inner)
+}
+
+/// Build the theme from the preset and the colors overridden in the settings.
+#[instrument]
+pub(crate) fn init_theme(settings: &ThemeSettings) -> Result<()> {
+  let preset = match settings.preset {
+    ThemePreset::Dark => DARK,
+    ThemePreset::Light => LIGHT,
+    ThemePreset::HighContrast => HIGH_CONTRAST,
+  };
+  let theme = Theme {
+    default: with_color(preset.default, &settings.default)?,
+    default_dark: with_color(preset.default_dark, &settings.default_dark)?,
+    primary: with_color(preset.primary, &settings.primary)?,
+    secondary: with_color(preset.secondary, &settings.secondary)?,
+    border: with_color(preset.border, &settings.border)?,
+    border_selected: with_color(preset.border_selected, &settings.border_selected)?,
+    selected: with_color(preset.selected, &settings.selected)?,
+    help_key: with_color(preset.help_key, &settings.help_key)?,
+    error: with_color(preset.error, &settings.error)?,
+  };
+  // Called again when the settings are reloaded
+  *THEME.write().unwrap_or_else(PoisonError::into_inner) = theme;
+  Ok(())
+}
+
+fn with_color(style: Style, color: &Option<String>) -> Result<Style> {
+  Ok(match color {
+    Some(color) => style.fg(Color::from_str(color).map_err(|_| miette!("Invalid color: '{color}'"))?),
+    None => style,
+  })
+}
@@ -0,0 +1,224 @@
+use gstreamer::ClockTime;
+use std::time::{Duration, Instant};
+use url::Url;
+
+/// What is tried on a stalled pipeline, in turn.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub(crate) enum Recovery {
+  // Flush seek at the position reached
+  Seek,
+  // Start the track again, at the position reached
+  Restart,
+  // The error policy applies
+  GiveUp,
+}
+
+/// Last progress of the played track, to find a pipeline left in Playing
+/// while its position doesn't move.
+#[derive(Debug, Default)]
+pub(crate) struct Watchdog {
+  // Track, its last position and since when it stays there
+  last: Option<(Url, ClockTime, Instant)>,
+  // Track and position of the stall, and the recoveries tried since: kept
+  // until the track moves past it
+  stall: Option<(Url, ClockTime, u32)>,
+  // Track started again, and where to seek it once prerolled
+  resume: Option<(Url, Duration)>,
+}
+
+impl Watchdog {
+  /// Recovery due once the track didn't move for the timeout; the next one
+  /// is due after the timeout again.
+  pub(crate) fn check(
+    &mut self,
+    location: &Url,
+    position: ClockTime,
+    timeout: Duration,
+  ) -> Option<Recovery> {
+    match &mut self.last {
+      Some((last_location, last_position, since))
+        if last_location == location && *last_position == position =>
+      {
+        if since.elapsed() < timeout {
+          return None;
+        }
+        *since = Instant::now();
+        let attempts = match &mut self.stall {
+          Some((_, _, attempts)) => {
+            *attempts += 1;
+            *attempts
+          }
+          None => {
+            self.stall = Some((location.clone(), position, 1));
+            1
+          }
+        };
+        Some(match attempts {
+          1 => Recovery::Seek,
+          2 => Recovery::Restart,
+          _ => Recovery::GiveUp,
+        })
+      }
+      _ => {
+        if self
+          .stall
+          .as_ref()
+          .is_some_and(|(stalled, stalled_at, _)| stalled != location || position > *stalled_at)
+        {
+          self.stall = None;
+        }
+        self.last = Some((location.clone(), position, Instant::now()));
+        None
+      }
+    }
+  }
+
+  /// Forget the last progress: a pause doesn't count as a stall.
+  pub(crate) fn reset(&mut self) {
+    self.last = None;
+  }
+
+  /// Forget the stall too, once the error policy took over.
+  pub(crate) fn clear(&mut self) {
+    self.last = None;
+    self.stall = None;
+    self.resume = None;
+  }
+
+  /// The track is started again, to be sought at the position.
+  pub(crate) fn resume_at(&mut self, location: Url, position: Duration) {
+    self.resume = Some((location, position));
+  }
+
+  /// Position to seek the track to, once: none for another track.
+  pub(crate) fn take_resume(&mut self, location: &Url) -> Option<Duration> {
+    match self.resume.take() {
+      Some((resumed, position)) if &resumed == location => Some(position),
+      _ => None,
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  const NOW: Duration = Duration::ZERO;
+
+  fn track(name: &str) -> Url {
+    Url::parse(&format!("file:///music/{name}.mp3")).expect("Track location")
+  }
+
+  fn at(seconds: u64) -> ClockTime {
+    ClockTime::from_seconds(seconds)
+  }
+
+  #[test]
+  fn moving_track() {
+    let mut watchdog = Watchdog::default();
+    for seconds in 0..5 {
+      assert_eq!(watchdog.check(&track("a"), at(seconds), NOW), None);
+    }
+  }
+
+  #[test]
+  fn waits_for_the_timeout() {
+    let mut watchdog = Watchdog::default();
+    let timeout = Duration::from_secs(60);
+    assert_eq!(watchdog.check(&track("a"), at(5), timeout), None);
+    assert_eq!(watchdog.check(&track("a"), at(5), timeout), None);
+  }
+
+  #[test]
+  fn seek_then_restart_then_give_up() {
+    let mut watchdog = Watchdog::default();
+    assert_eq!(watchdog.check(&track("a"), at(5), NOW), None);
+    assert_eq!(
+      watchdog.check(&track("a"), at(5), NOW),
+      Some(Recovery::Seek)
+    );
+    assert_eq!(
+      watchdog.check(&track("a"), at(5), NOW),
+      Some(Recovery::Restart)
+    );
+    assert_eq!(
+      watchdog.check(&track("a"), at(5), NOW),
+      Some(Recovery::GiveUp)
+    );
+  }
+
+  #[test]
+  fn progress_past_the_stall_resets() {
+    let mut watchdog = Watchdog::default();
+    watchdog.check(&track("a"), at(5), NOW);
+    assert_eq!(
+      watchdog.check(&track("a"), at(5), NOW),
+      Some(Recovery::Seek)
+    );
+    assert_eq!(watchdog.check(&track("a"), at(6), NOW), None);
+    assert_eq!(
+      watchdog.check(&track("a"), at(6), NOW),
+      Some(Recovery::Seek)
+    );
+  }
+
+  #[test]
+  fn restart_from_the_start_keeps_the_stall() {
+    let mut watchdog = Watchdog::default();
+    watchdog.check(&track("a"), at(5), NOW);
+    assert_eq!(
+      watchdog.check(&track("a"), at(5), NOW),
+      Some(Recovery::Seek)
+    );
+    assert_eq!(watchdog.check(&track("a"), at(0), NOW), None);
+    assert_eq!(
+      watchdog.check(&track("a"), at(0), NOW),
+      Some(Recovery::Restart)
+    );
+  }
+
+  #[test]
+  fn pause_keeps_the_stall() {
+    let mut watchdog = Watchdog::default();
+    watchdog.check(&track("a"), at(5), NOW);
+    assert_eq!(
+      watchdog.check(&track("a"), at(5), NOW),
+      Some(Recovery::Seek)
+    );
+    watchdog.reset();
+    assert_eq!(watchdog.check(&track("a"), at(5), NOW), None);
+    assert_eq!(
+      watchdog.check(&track("a"), at(5), NOW),
+      Some(Recovery::Restart)
+    );
+  }
+
+  #[test]
+  fn another_track_resets() {
+    let mut watchdog = Watchdog::default();
+    watchdog.check(&track("a"), at(5), NOW);
+    assert_eq!(
+      watchdog.check(&track("a"), at(5), NOW),
+      Some(Recovery::Seek)
+    );
+    assert_eq!(watchdog.check(&track("b"), at(5), NOW), None);
+    assert_eq!(
+      watchdog.check(&track("b"), at(5), NOW),
+      Some(Recovery::Seek)
+    );
+  }
+
+  #[test]
+  fn resume_once_on_the_restarted_track() {
+    let mut watchdog = Watchdog::default();
+    watchdog.resume_at(track("a"), Duration::from_secs(5));
+    assert_eq!(watchdog.take_resume(&track("b")), None);
+
+    watchdog.resume_at(track("a"), Duration::from_secs(5));
+    assert_eq!(
+      watchdog.take_resume(&track("a")),
+      Some(Duration::from_secs(5))
+    );
+    assert_eq!(watchdog.take_resume(&track("a")), None);
+  }
+}